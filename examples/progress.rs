@@ -0,0 +1,42 @@
+//! Transcodes a file and prints how far along it is.
+//!
+//! cargo run --example progress -- input.mov output.mp4
+
+use std::path::PathBuf;
+
+use ffflow::command::FfmpegCommand;
+use ffflow::event::FfmpegEvent;
+
+fn main() {
+    let mut args = std::env::args_os().skip(1);
+    let (Some(input), Some(output)) = (args.next(), args.next()) else {
+        eprintln!("usage: progress <input> <output>");
+        std::process::exit(2);
+    };
+
+    let command = FfmpegCommand {
+        inputs: vec![PathBuf::from(input)],
+        output: PathBuf::from(output),
+        video_codec: Some("libx264".to_string()),
+        audio_codec: Some("aac".to_string()),
+        preset: Some("veryfast".to_string()),
+        extra_args: vec!["-y".to_string()],
+        ..FfmpegCommand::default()
+    };
+
+    let mut total = None;
+    for event in ffflow::run(command) {
+        match event {
+            FfmpegEvent::Input(info) => total = total.max(info.duration),
+            FfmpegEvent::Progress(progress) => match total {
+                Some(total) if !total.is_zero() => {
+                    let percent = progress.time.as_secs_f64() / total.as_secs_f64() * 100.0;
+                    println!("{:5.1}%", percent.min(100.0));
+                }
+                _ => println!("frame {}", progress.frame),
+            },
+            FfmpegEvent::Error(message) => eprintln!("error: {message}"),
+            _ => {}
+        }
+    }
+}
@@ -1,13 +1,117 @@
-use clap::{Parser, Subcommand};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-use crate::core::command::FfmpegCommand;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::core::alias::validate_alias_name;
+use crate::core::config::Config;
+use crate::core::command::{escape_filter_value, parse_rate_kbps, take_audio_filters, FfmpegCommand, OutputSpec};
+use crate::core::color::ColorOptions;
+use crate::core::concat::ConcatList;
+use crate::core::container::{audio_copy_fits, required_subtitle_codec, suggested_audio_encoder};
+use crate::core::cropdetect::Crop;
+use crate::core::expand::{expand_input_globs, expand_line, expand_passthrough, expand_path, is_glob_pattern, SystemEnv};
+use crate::core::capabilities::{
+    build_configuration, encoders, ffmpeg_release, ffmpeg_version, filters, find_on_path, formats, has_filter,
+    hwaccels, Encoder, Filter, Format,
+};
+use crate::core::chapters::to_ffmetadata;
+use crate::core::formatter::{
+    format_bytes, format_chapter_lines, format_duration, format_encoder_lines, format_filter_lines, format_format_lines,
+    format_tag_lines,
+};
+use crate::core::hls::HlsJob;
+use crate::core::ladder::{fit_rungs, LadderPlan, PlannedRung, Rung, DEFAULT_RUNGS};
+use crate::core::loudnorm::{LoudnormJob, LoudnormTarget};
+use crate::core::sequence::{has_frame_counter, is_image_sequence, sequence_duration, DEFAULT_FRAMERATE};
+use crate::core::reverse::{boomerang_graph, DEFAULT_REVERSE_LIMIT};
+use crate::core::runner::{writes_to_stdout, JobTimeout};
+use crate::core::shell::{split_os, strip_prefix_os};
+use crate::core::probe::{
+    probe_audio_codec, probe_duration, probe_input, probe_tags, probe_video_streams,
+};
+use crate::core::profile::{find_profile, gif_filter, Profile, DEFAULT_GIF_FPS, PROFILES};
+use crate::core::preset::{translate_preset, PresetArgs, PRESET_NAMES};
+use crate::core::progress::parse_ffmpeg_time;
+use crate::core::quality::QualityJob;
+use crate::core::speed::{atempo_filters, scaled_duration, setpts_filter, MAX_FACTOR, MIN_FACTOR};
+use crate::core::split::{segment_pattern, parse_size, segment_time_for_size, SplitJob};
+use crate::core::stabilize::{StabilizeJob, TransformsFile};
+use crate::core::tags::{parse_tag, supports_cover, TagEdit};
+use crate::core::template::{expand_template, is_template, TemplateContext};
+use crate::core::verify::{StreamKind, VerifyMode};
+use crate::core::watermark::{Position, Watermark};
 
 #[derive(Debug, Parser)]
 #[command(name = "ffflow", version, about = "Professional ffmpeg wrapper")]
 pub struct SystemCli {
-    /// Path to a .flw file containing commands
+    /// Path to a .flw file containing commands, or `-` to read one from
+    /// stdin and run it without the TUI
     #[arg(value_name = "FILE")]
-    pub file: Option<std::path::PathBuf>,
+    pub file: Option<PathBuf>,
+    /// Read commands from stdin and run them without the TUI
+    #[arg(long)]
+    pub stdin: bool,
+    /// Run this command without the TUI, then exit; repeat to run several
+    /// in order. Takes anything the TUI does, `ffmpeg ...` lines included
+    #[arg(short = 'c', long = "command", value_name = "COMMAND",
+          conflicts_with_all = ["file", "stdin"])]
+    pub commands: Vec<String>,
+    /// Append a timestamped log of the session to this file
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// History lines kept on screen
+    #[arg(long, value_name = "N", default_value_t = 500,
+          value_parser = clap::value_parser!(u32).range(1..))]
+    pub scrollback: u32,
+    /// Run ffmpeg at lower CPU priority: niceness 1-19 on Unix; below-normal
+    /// (idle from 15) priority class on Windows
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(1..=19))]
+    pub nice: Option<i32>,
+    /// Record finished jobs in this file and skip the ones it lists, so an
+    /// interrupted batch can be run again where it stopped
+    #[arg(long, value_name = "FILE")]
+    pub checkpoint: Option<PathBuf>,
+    /// Refuse jobs whose `at`/`@at` start time has already passed, instead
+    /// of running them right away
+    #[arg(long)]
+    pub strict_schedule: bool,
+    /// Also show the ffmpeg lines normally hidden as noise: the version
+    /// banner, library versions, encoder stats
+    #[arg(long)]
+    pub verbose: bool,
+    /// Print each job's events as JSON lines instead of the plain session
+    /// log: on stdout, or stderr while a job writes its output there. Needs
+    /// `-c`, `--stdin` or `-`
+    #[arg(long)]
+    pub json_events: bool,
+    /// Let jobs replace existing outputs (`-y`) instead of refusing to
+    /// (`-n`). Needs `-c`, `--stdin` or `-`
+    #[arg(long)]
+    pub overwrite: bool,
+    /// Print a completion script for this shell and exit
+    #[arg(long, value_name = "SHELL")]
+    pub completions: Option<Shell>,
+}
+
+/// Writes the completion script for `ffflow` to stdout.
+pub fn print_completions(shell: Shell) {
+    write_completions(shell, &mut std::io::stdout());
+}
+
+/// The job commands (`encode`, `probe`, ...) come from `Cli`, not
+/// `SystemCli`; they are attached as subcommands so the script completes
+/// their names and flags too, for `-c` lines and `.flw` files.
+fn write_completions(shell: Shell, out: &mut dyn std::io::Write) {
+    let mut command = SystemCli::command()
+        .subcommands(Cli::command().get_subcommands().cloned())
+        .args_conflicts_with_subcommands(true);
+    clap_complete::generate(shell, &mut command, "ffflow", out);
 }
 
 #[derive(Debug, Parser)]
@@ -20,74 +124,2184 @@ pub struct Cli {
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     Encode(EncodeArgs),
+    Profile(ProfileArgs),
     Probe(ProbeArgs),
+    Info(InfoArgs),
+    /// Two-pass EBU R128 loudness normalization
+    Normalize(NormalizeArgs),
+    /// Save a single frame as an image
+    Thumbnail(ThumbnailArgs),
+    /// Make an animated GIF with an optimized palette
+    Gif(GifArgs),
+    /// Find the crop that removes black borders
+    Cropdetect(CropdetectArgs),
+    /// Pull the audio track out into its own file
+    ExtractAudio(ExtractAudioArgs),
+    /// Move the streams into another container without re-encoding
+    Remux(RemuxArgs),
+    /// Score an encode against its source (PSNR, SSIM, VMAF)
+    Compare(CompareArgs),
+    /// Encode several resolutions/bitrates for adaptive streaming
+    Ladder(LadderArgs),
+    /// Package for HLS streaming
+    Hls(HlsArgs),
+    /// Overlay a logo on a video
+    Watermark(WatermarkArgs),
+    /// Speed up (timelapse) or slow down (slow motion)
+    Speed(SpeedArgs),
+    /// Two-pass video stabilization (vidstab)
+    Stabilize(StabilizeArgs),
+    /// Cut a file into pieces by duration or size, without re-encoding
+    Split(SplitArgs),
+    /// Repeat a clip, play it backwards, or both ways (boomerang)
+    Loop(LoopArgs),
+    /// Show or edit container tags (title, artist, cover art, ...)
+    Tag(TagArgs),
+    /// List a file's chapters, or export them to an ffmetadata file
+    Chapters(ChaptersArgs),
+    /// Record a live stream or capture device for a set time
+    Record(RecordArgs),
+    /// List the encoders this ffmpeg build has
+    Encoders(EncodersArgs),
+    /// List the container formats this ffmpeg build reads and writes
+    Formats(FormatsArgs),
+    /// List the filters this ffmpeg build has
+    Filters(FiltersArgs),
+    /// Check ffmpeg, ffprobe and the optional parts ffflow can use
+    Doctor,
+    /// Show the ffflow and ffmpeg versions and how ffmpeg was built
+    Version,
     Presets,
 }
 
 #[derive(Debug, Parser)]
+#[command(after_help = "Stream selection examples:
+  ffflow encode -i in.mkv -o out.mp4 --map 0:v --map 0:a:1
+  ffflow encode -i in.mkv -o out.mp4 --audio-track 1 --no-subs
+  ffflow encode -i in.mov -o silent.mp4 --no-audio")]
 pub struct EncodeArgs {
     #[arg(short = 'i', long = "input", required = true)]
-    pub inputs: Vec<String>,
+    pub inputs: Vec<PathBuf>,
     #[arg(short = 'o', long = "output")]
-    pub output: String,
+    pub output: PathBuf,
     #[arg(long = "vcodec")]
     pub video_codec: Option<String>,
     #[arg(long = "acodec")]
     pub audio_codec: Option<String>,
     #[arg(long = "preset")]
     pub preset: Option<String>,
+    #[arg(long = "profile", value_parser = parse_profile)]
+    pub profile: Option<&'static Profile>,
+    /// Start at this offset (seconds or hh:mm:ss)
+    #[arg(long = "seek", value_name = "TIME", value_parser = parse_seek)]
+    pub seek: Option<Duration>,
+    /// Seek after the input for frame-accurate (but slower) starts
+    #[arg(long = "accurate-seek", requires = "seek")]
+    pub accurate_seek: bool,
+    /// Stream to keep, e.g. 0:v or 0:a:1 (repeatable)
+    #[arg(long = "map", value_name = "SPEC", value_parser = parse_map_spec)]
+    pub maps: Vec<String>,
+    /// Drop all audio streams
+    #[arg(long = "no-audio", conflicts_with_all = ["audio_codec", "audio_track", "af", "loudnorm"])]
+    pub no_audio: bool,
+    /// Drop all video streams, e.g. to keep only the audio
+    #[arg(long = "no-video", conflicts_with_all = [
+        "no_audio", "video_codec", "fps", "every", "crop", "burn_subs", "maxrate",
+    ])]
+    pub no_video: bool,
+    /// Drop all subtitle streams
+    #[arg(long = "no-subs")]
+    pub no_subs: bool,
+    /// Keep the video plus only this audio track (0-based)
+    #[arg(long = "audio-track", value_name = "N", conflicts_with = "maps")]
+    pub audio_track: Option<u32>,
+    /// Join the inputs back to back (stream copy unless codecs are given)
+    #[arg(long)]
+    pub concat: bool,
+    /// Read the input at its native frame rate, as a live source would
+    #[arg(long)]
+    pub realtime: bool,
+    /// Frame rate of an image-sequence input (frame_%05d.png or 'frames/*.png')
+    #[arg(long, value_name = "FPS", value_parser = parse_frame_rate)]
+    pub framerate: Option<f64>,
+    /// Input option placed before its -i, e.g. -hwaccel; prefix 1: for the
+    /// second input (repeatable; unprefixed ones follow the previous input)
+    #[arg(long = "in-opt", value_name = "[N:]ARG", allow_hyphen_values = true,
+          value_parser = parse_input_option)]
+    pub in_opt: Vec<InputOption>,
+    /// Several input options in one string, e.g. "1:-itsoffset 0.5"
+    #[arg(long = "in-opts", value_name = "[N:]ARGS", allow_hyphen_values = true,
+          value_parser = parse_input_options)]
+    pub in_opts: Vec<InputOption>,
+    /// Audio filters, e.g. "highpass=f=80" (joined with any -af after --)
+    #[arg(long, value_name = "FILTERS")]
+    pub af: Option<String>,
+    /// Normalize loudness in one pass (EBU R128; `normalize` does two)
+    #[arg(long)]
+    pub loudnorm: bool,
+    /// Integrated loudness target for --loudnorm (LUFS)
+    #[arg(long = "loudnorm-i", value_name = "LUFS", requires = "loudnorm",
+          allow_negative_numbers = true, value_parser = parse_lufs)]
+    pub loudnorm_i: Option<f32>,
+    /// Output frame rate
+    #[arg(long, value_name = "FPS", value_parser = parse_frame_rate, conflicts_with = "every")]
+    pub fps: Option<f64>,
+    /// Write one frame per interval to a numbered image output (frame_%04d.png)
+    #[arg(long, value_name = "TIME", value_parser = parse_split_time)]
+    pub every: Option<Duration>,
+    /// Output pixel format, e.g. yuv420p or yuv420p10le
+    #[arg(long = "pix-fmt", value_name = "FMT", help_heading = "Color")]
+    pub pix_fmt: Option<String>,
+    /// Tag the output's color matrix, e.g. bt709 or bt2020nc
+    #[arg(long, value_name = "SPACE", help_heading = "Color")]
+    pub colorspace: Option<String>,
+    /// Tag the output's color primaries, e.g. bt709 or bt2020
+    #[arg(long = "color-primaries", value_name = "PRIMARIES", help_heading = "Color")]
+    pub color_primaries: Option<String>,
+    /// Tag the output's transfer curve, e.g. bt709 or smpte2084 (PQ)
+    #[arg(long = "color-trc", value_name = "TRC", help_heading = "Color")]
+    pub color_trc: Option<String>,
+    /// Cap the video bitrate, e.g. 800k or 2M (buffer: twice the rate)
+    #[arg(long, value_name = "RATE", value_parser = parse_max_rate)]
+    pub maxrate: Option<u32>,
+    /// Crop to W:H:X:Y, or `auto` for the last cropdetect result
+    #[arg(long, value_name = "W:H:X:Y|auto", value_parser = parse_crop_choice)]
+    pub crop: Option<CropChoice>,
+    /// Burn in subtitles from a file, or the input's Nth subtitle track
+    #[arg(long = "burn-subs", value_name = "FILE|TRACK", value_parser = parse_burn_subs)]
+    pub burn_subs: Option<BurnSubs>,
+    /// Add a subtitle file as a selectable (soft) track
+    #[arg(long = "add-subs", value_name = "FILE", conflicts_with_all = ["no_subs", "concat"])]
+    pub add_subs: Option<PathBuf>,
+    /// Set a container tag, e.g. title="My Video" (repeatable)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_tag)]
+    pub meta: Vec<(String, String)>,
+    /// Copy the first input's container tags (`-map_metadata 0`); --meta
+    /// still sets its keys on top
+    #[arg(long = "copy-metadata")]
+    pub copy_metadata: bool,
+    /// Leave the inputs' container tags out (`-map_metadata -1`); --meta
+    /// still sets its keys
+    #[arg(long = "strip-metadata", conflicts_with = "copy_metadata")]
+    pub strip_metadata: bool,
+    /// Take the output's chapters from an ffmpeg metadata file
+    #[arg(long = "import-chapters", value_name = "FILE", conflicts_with = "concat")]
+    pub import_chapters: Option<PathBuf>,
+    /// Copy the first input's chapters (`-map_chapters 0`)
+    #[arg(long = "copy-chapters", conflicts_with_all = ["no_chapters", "import_chapters"])]
+    pub copy_chapters: bool,
+    /// Leave the input's chapters out (`-map_chapters -1`)
+    #[arg(long = "no-chapters", conflicts_with = "import_chapters")]
+    pub no_chapters: bool,
+    /// Kill ffmpeg after this long without progress (seconds or hh:mm:ss)
+    #[arg(long, value_name = "TIME", value_parser = parse_split_time)]
+    pub timeout: Option<Duration>,
+    /// Count --timeout from the start of the job, progress or not
+    #[arg(long = "timeout-wall-clock", requires = "timeout")]
+    pub timeout_wall_clock: bool,
+    /// Also write this output from the same decode: "[OPTIONS...] FILE",
+    /// e.g. "-c:v libvpx-vp9 -b:v 1M preview.webm" (repeatable)
+    #[arg(long = "also-output", value_name = "ARGS", allow_hyphen_values = true,
+          value_parser = parse_output_spec)]
+    pub also_output: Vec<OutputSpec>,
+    /// Check the output once the job succeeds; `full` also decodes all of it
+    #[arg(long, value_name = "MODE", num_args = 0..=1, default_missing_value = "quick")]
+    pub verify: Option<VerifyMode>,
+    /// Don't check --vcodec/--acodec against ffmpeg's encoder list
+    #[arg(long)]
+    pub no_validate: bool,
     #[arg(last = true)]
     pub extra_args: Vec<String>,
 }
 
+#[derive(Debug, Parser)]
+pub struct ProfileArgs {
+    #[arg(value_name = "PROFILE", value_parser = parse_profile)]
+    pub name: &'static Profile,
+    #[command(flatten)]
+    pub encode: EncodeArgs,
+}
+
 #[derive(Debug, Parser)]
 pub struct ProbeArgs {
     #[arg(short = 'i', long = "input")]
-    pub input: String,
+    pub input: PathBuf,
 }
 
-pub fn encode_args_to_command(args: EncodeArgs) -> FfmpegCommand {
-    FfmpegCommand {
-        inputs: args.inputs,
-        output: args.output,
+#[derive(Debug, Parser)]
+pub struct InfoArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+pub struct ThumbnailArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// Time of the frame (seconds or hh:mm:ss); defaults to the midpoint
+    #[arg(long, value_name = "TIME", value_parser = parse_seek)]
+    pub at: Option<Duration>,
+}
+
+#[derive(Debug, Parser)]
+pub struct GifArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// Frames per second [default: 10]
+    #[arg(long, value_name = "FPS", value_parser = parse_frame_rate)]
+    pub fps: Option<f64>,
+    /// Width in pixels, height follows; defaults to the input width
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CropdetectArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    /// Seconds of video to analyse
+    #[arg(long, default_value_t = 60)]
+    pub sample: u32,
+    /// Encode to --output with the detected crop right away
+    #[arg(long, requires = "output")]
+    pub apply: bool,
+    #[arg(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+    /// Extra encode arguments used with --apply
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct ExtractAudioArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// Audio encoder; defaults to copying when the output container allows
+    #[arg(long = "acodec")]
+    pub audio_codec: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct RemuxArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    /// The container is picked from the extension
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+pub struct CompareArgs {
+    /// The original
+    #[arg(long = "ref", value_name = "PATH")]
+    pub reference: PathBuf,
+    /// The encode to score
+    #[arg(long = "dist", value_name = "PATH")]
+    pub distorted: PathBuf,
+    /// Also write the scores to this file as JSON
+    #[arg(long, value_name = "PATH")]
+    pub json: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+pub struct LadderArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(long = "out-dir", value_name = "DIR")]
+    pub out_dir: PathBuf,
+    /// Comma-separated HEIGHTp:BITRATE rungs
+    #[arg(long, value_name = "SPEC", value_delimiter = ',', default_value = DEFAULT_RUNGS)]
+    pub rungs: Vec<Rung>,
+    #[arg(long = "vcodec", default_value = "libx264")]
+    pub video_codec: String,
+    #[arg(long = "acodec", default_value = "aac")]
+    pub audio_codec: String,
+    #[arg(long = "preset")]
+    pub preset: Option<String>,
+    /// Extra ffmpeg arguments for every rung
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct HlsArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(long = "out-dir", value_name = "DIR")]
+    pub out_dir: PathBuf,
+    /// Target segment length in seconds
+    #[arg(long = "segment-time", value_name = "SECS", default_value_t = 6,
+          value_parser = clap::value_parser!(u32).range(1..))]
+    pub segment_time: u32,
+    /// Playlist file name (the master playlist when variants are given)
+    #[arg(long = "playlist-name", value_name = "NAME", default_value = "index.m3u8")]
+    pub playlist_name: String,
+    /// HEIGHTp:BITRATE renditions, as for ladder (comma-separated or repeated)
+    #[arg(long = "variant", value_name = "SPEC", value_delimiter = ',')]
+    pub variants: Vec<Rung>,
+    #[arg(long = "vcodec", default_value = "libx264")]
+    pub video_codec: String,
+    #[arg(long = "acodec", default_value = "aac")]
+    pub audio_codec: String,
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct WatermarkArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    /// Image (or video) to lay over the input
+    #[arg(short = 'w', long = "watermark", value_name = "PATH")]
+    pub watermark: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// top-left, top-right, bottom-left, bottom-right or center
+    #[arg(long, default_value = "bottom-right")]
+    pub position: Position,
+    /// Distance from the edges, in pixels
+    #[arg(long, value_name = "PX", default_value_t = 20)]
+    pub margin: u32,
+    /// 0 (invisible) to 1 (opaque)
+    #[arg(long, default_value_t = 1.0, value_parser = parse_opacity)]
+    pub opacity: f32,
+    /// Watermark width as a fraction of the video width
+    #[arg(long, value_name = "FRACTION", value_parser = parse_scale)]
+    pub scale: Option<f32>,
+    #[arg(long = "vcodec")]
+    pub video_codec: Option<String>,
+    #[arg(long = "preset")]
+    pub preset: Option<String>,
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct SpeedArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// Playback speed: 2 is twice as fast, 0.5 half speed (0.25 to 100)
+    #[arg(long, value_parser = parse_speed_factor)]
+    pub factor: f64,
+    /// Drop the audio instead of re-timing it
+    #[arg(long)]
+    pub mute: bool,
+    #[arg(long = "vcodec")]
+    pub video_codec: Option<String>,
+    #[arg(long = "acodec")]
+    pub audio_codec: Option<String>,
+    #[arg(long = "preset")]
+    pub preset: Option<String>,
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+#[command(after_help = "Examples:
+  ffflow record --from rtmp://example.com/live/key --for 30:00 -o show.mkv
+  ffflow record --from :0.0 -f x11grab --for 60 -o screen.mkv --vcodec libx264")]
+pub struct RecordArgs {
+    /// Stream URL or capture device
+    #[arg(long, value_name = "URL|DEVICE")]
+    pub from: PathBuf,
+    /// Input format of a device, e.g. x11grab, v4l2, avfoundation, dshow
+    #[arg(short = 'f', long, value_name = "FORMAT")]
+    pub format: Option<String>,
+    /// How long to record (seconds or hh:mm:ss)
+    #[arg(long = "for", value_name = "TIME", value_parser = parse_split_time)]
+    pub length: Duration,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// Video encoder; a stream URL is copied as is unless one is given
+    #[arg(long = "vcodec")]
+    pub video_codec: Option<String>,
+    #[arg(long = "acodec")]
+    pub audio_codec: Option<String>,
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct StabilizeArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// How shaky the footage is, 1 to 10
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..=10))]
+    pub shakiness: u32,
+    /// Frames averaged on each side of the camera path; higher is smoother
+    #[arg(long, default_value_t = 10)]
+    pub smoothing: u32,
+    /// Extra arguments for the second pass, e.g. codecs
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct SplitArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    /// Output name with a counter, e.g. out_%03d.mkv
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// Length of each piece (seconds or hh:mm:ss)
+    #[arg(long, visible_alias = "segment-time", value_name = "TIME", value_parser = parse_split_time,
+          required_unless_present = "size", conflicts_with = "size")]
+    pub every: Option<Duration>,
+    /// Largest size of each piece, e.g. 1.9GB or 700MB
+    #[arg(long, value_name = "SIZE", value_parser = parse_split_size)]
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+pub struct LoopArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// How many times the clip plays in total
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub times: u32,
+    /// Play the clip backwards
+    #[arg(long, conflicts_with = "boomerang")]
+    pub reverse: bool,
+    /// Play the clip forwards, then backwards
+    #[arg(long)]
+    pub boomerang: bool,
+    /// Warn when reversing a clip longer than this (seconds or hh:mm:ss)
+    #[arg(long = "reverse-limit", value_name = "TIME", value_parser = parse_split_time)]
+    pub reverse_limit: Option<Duration>,
+    #[arg(long = "vcodec")]
+    pub video_codec: Option<String>,
+    #[arg(long = "acodec")]
+    pub audio_codec: Option<String>,
+    #[arg(long = "preset")]
+    pub preset: Option<String>,
+    #[arg(last = true)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct TagArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output", required_unless_present = "show")]
+    pub output: Option<PathBuf>,
+    /// Set a tag; an empty value removes it (repeatable)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_tag)]
+    pub set: Vec<(String, String)>,
+    /// Drop all existing tags first
+    #[arg(long = "clear-all")]
+    pub clear_all: bool,
+    /// Attach an image as cover art (mp4, m4a, mov)
+    #[arg(long, value_name = "IMAGE")]
+    pub cover: Option<PathBuf>,
+    /// Print the input's tags instead of writing a file
+    #[arg(long, conflicts_with_all = ["output", "set", "clear_all", "cover"])]
+    pub show: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct ChaptersArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    /// Also write them as ffmetadata, for encode --import-chapters
+    #[arg(long, value_name = "FILE")]
+    pub export: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+pub struct EncodersArgs {
+    /// Only encoders whose name or description contains this
+    pub filter: Option<String>,
+    /// Only video, audio or subtitle encoders
+    #[arg(long = "type", value_name = "TYPE")]
+    pub kind: Option<StreamKind>,
+}
+
+#[derive(Debug, Parser)]
+pub struct FormatsArgs {
+    /// Only formats whose name or description contains this
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct FiltersArgs {
+    /// Only filters whose name or description contains this
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BurnSubs {
+    File(PathBuf),
+    /// Subtitle stream index within the first input.
+    Track(u32),
+}
+
+/// Arguments given for one input with `--in-opt`/`--in-opts`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputOption {
+    /// `N:` prefix; None for no prefix.
+    pub input: Option<usize>,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CropChoice {
+    Auto,
+    Fixed(Crop),
+}
+
+#[derive(Debug, Parser)]
+pub struct NormalizeArgs {
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+    /// Integrated loudness target (LUFS)
+    #[arg(long, default_value_t = -16.0, allow_negative_numbers = true)]
+    pub target: f32,
+    /// Loudness range target (LU)
+    #[arg(long, default_value_t = 11.0)]
+    pub lra: f32,
+    /// Maximum true peak (dBTP)
+    #[arg(long, default_value_t = -1.5, allow_negative_numbers = true)]
+    pub tp: f32,
+}
+
+pub fn encode_args_to_command(mut args: EncodeArgs) -> Result<FfmpegCommand, String> {
+    fill_from_config(&mut args, &current_config());
+    if !args.no_validate {
+        check_encoder(args.video_codec.as_deref(), StreamKind::Video)?;
+        check_encoder(args.audio_codec.as_deref(), StreamKind::Audio)?;
+    }
+    args.inputs = args
+        .inputs
+        .into_iter()
+        .map(expand_path_buf)
+        .collect::<Result<_, _>>()?;
+    args.output = expand_path_buf(args.output)?;
+    // An image sequence is one input for ffmpeg to expand, not many.
+    let sequence = args.framerate.is_some() || args.inputs.first().is_some_and(|input| is_image_sequence(input));
+    let mut inputs = args.inputs;
+    let glob_input = sequence && is_glob_pattern(&inputs[0]);
+    if sequence {
+        let rest = expand_input_globs(inputs.split_off(1))?;
+        inputs.extend(rest);
+    } else {
+        inputs = expand_input_globs(inputs)?;
+    }
+    let output = match is_template(&args.output) {
+        true => {
+            let input = inputs.first().ok_or("an output template needs an input")?;
+            let context = TemplateContext {
+                input,
+                preset: args.preset.as_deref(),
+                codec: args.video_codec.as_deref(),
+                date: SystemTime::now(),
+            };
+            expand_template(&args.output, &context)?
+        }
+        false => args.output,
+    };
+    if args.every.is_some() && !has_frame_counter(&output) {
+        return Err("--every needs a numbered image output, e.g. frame_%04d.png".to_string());
+    }
+    let input_args = input_args(args.in_opt, args.in_opts, inputs.len())?;
+    if args.concat && input_args.len() > 1 {
+        return Err("--concat reads the inputs as one list; only input 0 takes input options".to_string());
+    }
+    let mut command = FfmpegCommand {
+        inputs,
+        input_args,
+        output,
         video_codec: args.video_codec,
         audio_codec: args.audio_codec,
+        subtitle_codec: None,
         preset: args.preset,
+        video_filters: Vec::new(),
+        audio_filters: Vec::new(),
+        filter_complex: None,
         extra_args: args.extra_args,
+        seek: args.seek,
+        accurate_seek: args.accurate_seek,
+        realtime: args.realtime,
+        stream_loop: None,
+        framerate: args.framerate,
+        glob_input,
+        max_rate_kbps: args.maxrate,
+        metadata: args.meta,
+        color: ColorOptions {
+            pix_fmt: args.pix_fmt,
+            colorspace: args.colorspace,
+            primaries: args.color_primaries,
+            trc: args.color_trc,
+        },
+        maps: args.maps,
+        no_video: args.no_video,
+        no_audio: args.no_audio,
+        no_subs: args.no_subs,
+        concat_list: None,
+        extra_outputs: args.also_output,
+    };
+    if let Some(track) = args.audio_track {
+        command.maps = vec!["0:v".to_string(), format!("0:a:{track}")];
+    }
+    // Loudness goes last, so it measures what the other filters made.
+    command.audio_filters.extend(args.af);
+    command.audio_filters.extend(take_audio_filters(&mut command.extra_args));
+    if args.loudnorm {
+        let target = LoudnormTarget {
+            integrated: args.loudnorm_i.unwrap_or(LoudnormTarget::default().integrated),
+            ..LoudnormTarget::default()
+        };
+        command.audio_filters.push(target.filter());
+    }
+    match (args.fps, args.every) {
+        (Some(fps), _) => command.video_filters.push(format!("fps={fps}")),
+        (None, Some(every)) => command
+            .video_filters
+            .push(format!("fps=1/{}", every.as_secs_f64())),
+        (None, None) => {}
+    }
+    // Crop first so a profile's scale works on the cropped picture.
+    match args.crop {
+        Some(CropChoice::Fixed(crop)) => command.video_filters.push(crop.filter()),
+        Some(CropChoice::Auto) => {
+            let crop = last_crop().ok_or("--crop auto needs a cropdetect run first")?;
+            command.video_filters.push(crop.filter());
+        }
+        None => {}
+    }
+    if let Some(profile) = args.profile {
+        profile.apply(&mut command);
+    }
+    if command.no_audio {
+        // A profile's audio codec means nothing once audio is dropped.
+        command.audio_codec = None;
+    }
+    if command.no_video {
+        // Nor do its video codec, preset and filters once video is.
+        command.video_codec = None;
+        command.preset = None;
+        command.video_filters.clear();
+    }
+    // Checked after the profile, which may copy the audio.
+    if !command.audio_filters.is_empty() && command.audio_codec.as_deref() == Some("copy") {
+        return Err("audio filters need the audio re-encoded; pick an --acodec other than copy".to_string());
+    }
+    // Burned in last, so subtitles render at the output size.
+    match args.burn_subs {
+        Some(BurnSubs::File(path)) => command.video_filters.push(format!(
+            "subtitles=filename={}",
+            escape_filter_value(&path.to_string_lossy())
+        )),
+        Some(BurnSubs::Track(track)) => {
+            if args.concat {
+                return Err("--burn-subs with a track index cannot be used with --concat".to_string());
+            }
+            let input = command.inputs.first().ok_or("--burn-subs needs an input")?;
+            command.video_filters.push(format!(
+                "subtitles=filename={}:si={track}",
+                escape_filter_value(&input.to_string_lossy())
+            ));
+        }
+        None => {}
+    }
+    if let Some(path) = args.add_subs {
+        let index = command.inputs.len();
+        command.inputs.push(path);
+        // Mapping the new track turns off ffmpeg's default stream choice,
+        // so the first input's video and audio are mapped explicitly.
+        if command.maps.is_empty() {
+            command.maps = vec!["0:v?".to_string(), "0:a?".to_string()];
+        }
+        command.maps.push(format!("{index}:s"));
+        let ext = command.output.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        command.subtitle_codec = Some(required_subtitle_codec(ext).unwrap_or("copy").to_string());
+    }
+    if args.copy_metadata || args.strip_metadata {
+        let source = if args.strip_metadata { "-1" } else { "0" };
+        command
+            .extra_args
+            .splice(0..0, ["-map_metadata".to_string(), source.to_string()]);
+    }
+    if args.copy_chapters || args.no_chapters {
+        let source = if args.no_chapters { "-1" } else { "0" };
+        command
+            .extra_args
+            .splice(0..0, ["-map_chapters".to_string(), source.to_string()]);
+    }
+    if let Some(path) = args.import_chapters {
+        let index = command.inputs.len();
+        command.inputs.push(path);
+        command
+            .extra_args
+            .splice(0..0, ["-map_chapters".to_string(), index.to_string()]);
+    }
+    if args.concat {
+        if command.inputs.len() < 2 {
+            return Err("--concat needs at least two inputs".to_string());
+        }
+        let list = ConcatList::create(&command.inputs)
+            .map_err(|err| format!("cannot write concat list: {err}"))?;
+        command.concat_list = Some(Arc::new(list));
+        if !command.no_video && command.video_filters.is_empty() {
+            command.video_codec.get_or_insert_with(|| "copy".to_string());
+        }
+        if !command.no_audio && command.audio_filters.is_empty() {
+            command.audio_codec.get_or_insert_with(|| "copy".to_string());
+        }
+    }
+    Ok(command)
+}
+
+pub fn profile_args_to_command(args: ProfileArgs) -> Result<FfmpegCommand, String> {
+    let mut encode = args.encode;
+    encode.profile = Some(args.name);
+    encode_args_to_command(encode)
+}
+
+/// `~` and environment variables in a typed path; one that is not valid
+/// UTF-8 is used as is.
+fn expand_path_buf(path: PathBuf) -> Result<PathBuf, String> {
+    match path.to_str() {
+        Some(text) => expand_path(text, &SystemEnv).map(PathBuf::from),
+        None => Ok(path),
+    }
+}
+
+/// Fills in the codecs and preset left unset from the config file's
+/// defaults. Flags come first, then a profile's own settings, then the
+/// config; anything still unset is left to ffmpeg.
+pub fn fill_from_config(args: &mut EncodeArgs, config: &Config) {
+    let profile = args.profile;
+    if !args.no_video && profile.is_none_or(|profile| profile.video_codec.is_none()) {
+        args.video_codec = args.video_codec.take().or_else(|| config.default_vcodec.clone());
+    }
+    if !args.no_audio && profile.is_none_or(|profile| profile.audio_codec.is_none()) {
+        args.audio_codec = args.audio_codec.take().or_else(|| config.default_acodec.clone());
+    }
+    if !args.no_video && profile.is_none_or(|profile| profile.preset.is_none()) {
+        args.preset = args.preset.take().or_else(|| config.default_preset.clone());
+    }
+}
+
+static CONFIG: Mutex<Option<Config>> = Mutex::new(None);
+
+/// Sets the config whose defaults later encodes fill in.
+pub fn use_config(config: Config) {
+    *CONFIG.lock().unwrap_or_else(|err| err.into_inner()) = Some(config);
+}
+
+fn current_config() -> Config {
+    CONFIG
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+static LAST_CROP: Mutex<Option<Crop>> = Mutex::new(None);
+
+/// Remembers a detected crop for later `--crop auto` encodes.
+pub fn remember_crop(crop: Crop) {
+    *LAST_CROP.lock().unwrap_or_else(|err| err.into_inner()) = Some(crop);
+}
+
+fn last_crop() -> Option<Crop> {
+    *LAST_CROP.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+static RE_INPUT_SCOPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(0|[1-9]\d*):").unwrap());
+
+/// Splits off an `N:` input prefix. A value that itself starts with a number
+/// and a colon, like `1:30`, needs the prefix written out: `0:1:30`.
+fn split_input_scope(value: &str) -> (Option<usize>, &str) {
+    match RE_INPUT_SCOPE.find(value) {
+        Some(scope) => (value[..scope.end() - 1].parse().ok(), &value[scope.end()..]),
+        None => (None, value),
+    }
+}
+
+fn parse_input_option(value: &str) -> Result<InputOption, String> {
+    let (input, arg) = split_input_scope(value);
+    Ok(InputOption { input, args: vec![arg.to_string()] })
+}
+
+fn parse_input_options(value: &str) -> Result<InputOption, String> {
+    let (input, text) = split_input_scope(value);
+    let args = shell_words::split(text).map_err(|err| format!("cannot split '{text}': {err}"))?;
+    Ok(InputOption { input, args })
+}
+
+/// Groups `--in-opt` and `--in-opts` by input index. An unprefixed
+/// `--in-opt` belongs to the same input as the one before it (the first
+/// input to begin with); an unprefixed `--in-opts` to the first input.
+fn input_args(in_opt: Vec<InputOption>, in_opts: Vec<InputOption>, inputs: usize) -> Result<Vec<Vec<String>>, String> {
+    let mut grouped: Vec<Vec<String>> = Vec::new();
+    let mut current = 0;
+    let scoped = in_opt
+        .into_iter()
+        .map(|option| {
+            current = option.input.unwrap_or(current);
+            (current, option.args)
+        })
+        .chain(in_opts.into_iter().map(|option| (option.input.unwrap_or(0), option.args)));
+    for (index, args) in scoped {
+        if index >= inputs {
+            return Err(format!(
+                "input option for input {index}, but the inputs are numbered 0 to {}",
+                inputs - 1
+            ));
+        }
+        if grouped.len() <= index {
+            grouped.resize(index + 1, Vec::new());
+        }
+        grouped[index].extend(args);
+    }
+    Ok(grouped)
+}
+
+/// `[OPTIONS...] FILE`, as it would follow the inputs on an ffmpeg line.
+fn parse_output_spec(value: &str) -> Result<OutputSpec, String> {
+    let mut words = shell_words::split(value).map_err(|err| format!("cannot split '{value}': {err}"))?;
+    let path = words.pop().ok_or("--also-output needs a file name")?;
+    Ok(OutputSpec {
+        args: words,
+        ..OutputSpec::new(path)
+    })
+}
+
+fn parse_burn_subs(value: &str) -> Result<BurnSubs, String> {
+    if value.is_empty() {
+        return Err("expected a subtitle file or track number".to_string());
+    }
+    Ok(match value.parse::<u32>() {
+        Ok(track) => BurnSubs::Track(track),
+        Err(_) => BurnSubs::File(PathBuf::from(value)),
+    })
+}
+
+/// Swaps `-c:s copy` for the codec the output container needs (text
+/// subtitles cannot be copied into mp4 or webm). Returns a note to show
+/// when it does.
+pub fn fix_subtitle_codec(command: &mut FfmpegCommand) -> Option<String> {
+    let ext = command.output.extension()?.to_str()?;
+    let required = required_subtitle_codec(ext)?;
+    let copied_in_extra = command
+        .extra_args
+        .windows(2)
+        .position(|pair| matches!(pair[0].as_str(), "-c:s" | "-scodec") && pair[1] == "copy");
+    if let Some(idx) = copied_in_extra {
+        command.extra_args[idx + 1] = required.to_string();
+    } else if command.subtitle_codec.as_deref() == Some("copy") {
+        command.subtitle_codec = Some(required.to_string());
+    } else {
+        return None;
+    }
+    Some(format!(
+        "note: .{ext} can't hold copied subtitles; using -c:s {required}"
+    ))
+}
+
+/// Turns `--preset` into the video encoder's own speed setting, or drops it
+/// when the encoder has none. Returns a note to show when it does either.
+pub fn fix_preset(command: &mut FfmpegCommand) -> Option<String> {
+    let preset = command.preset.clone()?;
+    // A codec given after `--` is the one ffmpeg ends up using.
+    let codec = command
+        .extra_args
+        .windows(2)
+        .rev()
+        .find(|pair| matches!(pair[0].as_str(), "-c:v" | "-vcodec" | "-codec:v"))
+        .map(|pair| pair[1].clone())
+        .or_else(|| command.video_codec.clone());
+    match translate_preset(codec.as_deref(), &preset) {
+        PresetArgs::Keep => None,
+        PresetArgs::Translated(args) => {
+            command.preset = None;
+            let note = format!(
+                "note: --preset {preset} for {} is {}",
+                codec.unwrap_or_default(),
+                args.join(" ")
+            );
+            command.extra_args.splice(0..0, args);
+            Some(note)
+        }
+        PresetArgs::Dropped(reason) => {
+            command.preset = None;
+            Some(format!(
+                "warning: {reason}; dropping --preset {preset} (put -preset after -- to force it)"
+            ))
+        }
+    }
+}
+
+fn parse_opacity(value: &str) -> Result<f32, String> {
+    let opacity: f32 = value
+        .parse()
+        .map_err(|_| format!("invalid opacity '{value}'"))?;
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(format!("opacity must be between 0 and 1, got {value}"));
+    }
+    Ok(opacity)
+}
+
+fn parse_scale(value: &str) -> Result<f32, String> {
+    let scale: f32 = value
+        .parse()
+        .map_err(|_| format!("invalid scale '{value}'"))?;
+    if !(scale > 0.0 && scale <= 1.0) {
+        return Err(format!("scale must be above 0 and at most 1, got {value}"));
+    }
+    Ok(scale)
+}
+
+fn parse_max_rate(value: &str) -> Result<u32, String> {
+    parse_rate_kbps(value).ok_or_else(|| format!("invalid rate '{value}' (expected e.g. 800k or 2M)"))
+}
+
+fn parse_frame_rate(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+        .ok_or_else(|| format!("invalid frame rate '{value}'"))
+}
+
+// loudnorm accepts -70 to -5 LUFS.
+fn parse_lufs(value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|lufs| (-70.0..=-5.0).contains(lufs))
+        .ok_or_else(|| format!("invalid loudness '{value}' (expected -70 to -5 LUFS)"))
+}
+
+fn parse_split_time(value: &str) -> Result<Duration, String> {
+    parse_ffmpeg_time(value)
+        .filter(|time| !time.is_zero())
+        .ok_or_else(|| format!("invalid duration '{value}'"))
+}
+
+fn parse_split_size(value: &str) -> Result<u64, String> {
+    parse_size(value).ok_or_else(|| format!("invalid size '{value}' (expected e.g. 1.9GB or 700MB)"))
+}
+
+fn parse_speed_factor(value: &str) -> Result<f64, String> {
+    let factor: f64 = value
+        .parse()
+        .map_err(|_| format!("invalid factor '{value}'"))?;
+    if !(MIN_FACTOR..=MAX_FACTOR).contains(&factor) {
+        return Err(format!(
+            "factor must be between {MIN_FACTOR} and {MAX_FACTOR}, got {value}"
+        ));
     }
+    Ok(factor)
+}
+
+fn parse_crop_choice(value: &str) -> Result<CropChoice, String> {
+    if value.eq_ignore_ascii_case("auto") {
+        return Ok(CropChoice::Auto);
+    }
+    value.parse().map(CropChoice::Fixed)
+}
+
+/// The encode that `cropdetect --apply` (or accepting the offer in the TUI)
+/// queues. Without `-o` the output goes next to the input as
+/// `<name>_cropped.<ext>`.
+pub fn cropped_encode_line(args: &CropdetectArgs, crop: Crop) -> OsString {
+    let output = args.output.clone().unwrap_or_else(|| {
+        let stem = args.input.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = args.input.extension().map_or("mp4".into(), |ext| ext.to_string_lossy());
+        args.input.with_file_name(format!("{stem}_cropped.{ext}"))
+    });
+    let mut words = vec![
+        "encode".to_string(),
+        "-i".to_string(),
+        args.input.to_string_lossy().into_owned(),
+        "-o".to_string(),
+        output.to_string_lossy().into_owned(),
+        "--crop".to_string(),
+        crop.to_string(),
+    ];
+    if !args.extra_args.is_empty() {
+        words.push("--".to_string());
+        words.extend(args.extra_args.iter().cloned());
+    }
+    shell_words::join(words).into()
+}
+
+fn parse_profile(name: &str) -> Result<&'static Profile, String> {
+    find_profile(name).ok_or_else(|| {
+        let names: Vec<&str> = PROFILES.iter().map(|profile| profile.name).collect();
+        format!("unknown profile '{name}' (available: {})", names.join(", "))
+    })
+}
+
+static RE_MAP_SPEC: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(-?\d+(:([vVasdt]|\d+|p:\d+|m:[^:]+(:[^:]*)?|#\w+|i:\w+|u|disp:\w+))*\??|\[[^\]]+\])$")
+        .unwrap()
+});
+
+/// Accepts `file[:stream specifier]` (`0`, `0:v`, `1:a:2`, `0:m:language:eng`,
+/// optionally ending in `?`) or a filtergraph label like `[out]`.
+fn parse_map_spec(spec: &str) -> Result<String, String> {
+    if RE_MAP_SPEC.is_match(spec) {
+        Ok(spec.to_string())
+    } else {
+        Err(format!("invalid stream spec '{spec}' (expected e.g. 0:v, 0:a:1 or [label])"))
+    }
+}
+
+fn parse_seek(value: &str) -> Result<Duration, String> {
+    parse_ffmpeg_time(value).ok_or_else(|| format!("invalid seek time '{value}'"))
 }
 
 pub fn probe_args_to_command(args: ProbeArgs) -> FfmpegCommand {
     FfmpegCommand {
         inputs: vec![args.input],
-        output: "-".to_string(),
+        output: PathBuf::from("-"),
         video_codec: None,
         audio_codec: None,
         preset: None,
         extra_args: vec!["-f".to_string(), "null".to_string()],
+        ..FfmpegCommand::default()
+    }
+}
+
+/// Used when the input's duration cannot be probed.
+const THUMBNAIL_FALLBACK_TIME: Duration = Duration::from_secs(1);
+
+/// Seeks to `--at` (or the input's midpoint) and writes one frame.
+pub fn thumbnail_args_to_command(args: ThumbnailArgs) -> FfmpegCommand {
+    let at = args.at.unwrap_or_else(|| {
+        probe_duration(&args.input)
+            .map(|duration| duration / 2)
+            .unwrap_or(THUMBNAIL_FALLBACK_TIME)
+    });
+    FfmpegCommand {
+        inputs: vec![args.input],
+        output: args.output,
+        seek: Some(at),
+        extra_args: vec!["-frames:v".to_string(), "1".to_string()],
+        ..FfmpegCommand::default()
     }
 }
 
-pub fn parse_line(line: &str) -> Result<Commands, String> {
+pub fn gif_args_to_command(args: GifArgs) -> FfmpegCommand {
+    let fps = args.fps.unwrap_or(DEFAULT_GIF_FPS);
+    FfmpegCommand {
+        inputs: vec![args.input],
+        output: args.output,
+        video_filters: vec![gif_filter(fps, args.width)],
+        no_audio: true,
+        extra_args: args.extra_args,
+        ..FfmpegCommand::default()
+    }
+}
+
+/// Drops the video and copies the audio, unless the output's container
+/// cannot hold the input's audio codec, in which case `--acodec` is required.
+pub fn extract_audio_args_to_command(args: ExtractAudioArgs) -> Result<FfmpegCommand, String> {
+    let audio_codec = match args.audio_codec {
+        Some(codec) => codec,
+        None => {
+            let ext = args
+                .output
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default();
+            let source = probe_audio_codec(&args.input);
+            match source {
+                Some(source) if audio_copy_fits(ext, &source) == Some(false) => {
+                    let hint = suggested_audio_encoder(ext).unwrap_or("<codec>");
+                    return Err(format!(
+                        "'{}' can't hold {source} audio as-is; pass --acodec {hint}",
+                        args.output.display()
+                    ));
+                }
+                _ => "copy".to_string(),
+            }
+        }
+    };
+    Ok(FfmpegCommand {
+        inputs: vec![args.input],
+        output: args.output,
+        audio_codec: Some(audio_codec),
+        extra_args: vec!["-vn".to_string()],
+        ..FfmpegCommand::default()
+    })
+}
+
+/// Copies every stream as-is (`-c copy`); ffmpeg picks the container from
+/// the output's extension.
+pub fn remux_args_to_command(args: RemuxArgs) -> FfmpegCommand {
+    FfmpegCommand {
+        inputs: vec![args.input],
+        output: args.output,
+        extra_args: vec!["-c".to_string(), "copy".to_string()],
+        ..FfmpegCommand::default()
+    }
+}
+
+/// Overlays the watermark on the input. Both files are probed first so a
+/// missing or undecodable logo fails here rather than mid-queue.
+pub fn watermark_args_to_command(args: WatermarkArgs) -> Result<FfmpegCommand, String> {
+    let video_width = |path: &std::path::Path| {
+        probe_input(path)
+            .ok()?
+            .into_iter()
+            .map(|info| info.width)
+            .find(|&width| width > 0)
+    };
+    if !args.watermark.exists() {
+        return Err(format!(
+            "watermark '{}' does not exist",
+            args.watermark.display()
+        ));
+    }
+    if video_width(&args.watermark).is_none() {
+        return Err(format!(
+            "ffmpeg can't decode '{}' as an image or video",
+            args.watermark.display()
+        ));
+    }
+    let width = match args.scale {
+        Some(scale) => {
+            let input_width = video_width(&args.input).ok_or_else(|| {
+                format!(
+                    "can't read the video size of '{}' to scale the watermark",
+                    args.input.display()
+                )
+            })?;
+            // Even widths keep 4:2:0 encoders happy once overlaid.
+            Some(((input_width as f32 * scale / 2.0).round() as u32).max(1) * 2)
+        }
+        None => None,
+    };
+    let watermark = Watermark {
+        position: args.position,
+        margin: args.margin,
+        opacity: args.opacity,
+        width,
+    };
+    Ok(FfmpegCommand {
+        inputs: vec![args.input, args.watermark],
+        output: args.output,
+        video_codec: args.video_codec,
+        audio_codec: Some("copy".to_string()),
+        preset: args.preset,
+        filter_complex: Some(watermark.filter_graph()),
+        maps: vec!["[vout]".to_string(), "0:a?".to_string()],
+        extra_args: args.extra_args,
+        ..FfmpegCommand::default()
+    })
+}
+
+/// Repeats with `-stream_loop`, and reverses with filters. Plain repeats
+/// are stream-copied unless codecs are given.
+pub fn loop_args_to_job(args: LoopArgs) -> JobArgs {
+    let input_duration = probe_duration(&args.input);
+    let mut notes = Vec::new();
+    let reversing = args.reverse || args.boomerang;
+    let limit = args.reverse_limit.unwrap_or(DEFAULT_REVERSE_LIMIT);
+    if let Some(duration) = input_duration.filter(|duration| reversing && *duration > limit) {
+        notes.push(format!(
+            "warning: '{}' is {} long; reversing holds all of it in memory",
+            args.input.display(),
+            format_duration(duration)
+        ));
+    }
+    let (video_codec, audio_codec) = match reversing {
+        true => (args.video_codec, args.audio_codec),
+        false => (
+            args.video_codec.or_else(|| Some("copy".to_string())),
+            args.audio_codec.or_else(|| Some("copy".to_string())),
+        ),
+    };
+    let mut command = FfmpegCommand {
+        inputs: vec![args.input.clone()],
+        output: args.output.clone(),
+        video_codec,
+        audio_codec,
+        preset: args.preset,
+        stream_loop: (args.times > 1).then(|| args.times - 1),
+        extra_args: args.extra_args,
+        ..FfmpegCommand::default()
+    };
+    if args.reverse {
+        command.video_filters.push("reverse".to_string());
+        command.extra_args.splice(0..0, ["-af".to_string(), "areverse".to_string()]);
+    }
+    if args.boomerang {
+        let audio = probe_audio_codec(&args.input).is_some();
+        command.filter_complex = Some(boomerang_graph(audio));
+        command.maps.push("[vout]".to_string());
+        if audio {
+            command.maps.push("[aout]".to_string());
+        }
+    }
+    let mut job = JobArgs::from(command);
+    job.report_output = Some(args.output);
+    job.notes.extend(notes);
+    let plays = args.times * if args.boomerang { 2 } else { 1 };
+    job.duration = input_duration.map(|duration| duration * plays);
+    job
+}
+
+pub fn tag_args_to_job(args: TagArgs) -> Result<JobArgs, String> {
+    let output = args
+        .output
+        .ok_or_else(|| "tag needs -o <output>, or --show".to_string())?;
+    if let Some(cover) = &args.cover {
+        if !cover.exists() {
+            return Err(format!("cover '{}' does not exist", cover.display()));
+        }
+        if !supports_cover(&output) {
+            return Err(format!(
+                "cover art needs an mp4, m4a or mov output, not '{}'",
+                output.display()
+            ));
+        }
+    }
+    let edit = TagEdit {
+        input: args.input,
+        output: output.clone(),
+        set: args.set,
+        clear_all: args.clear_all,
+        cover: args.cover,
+    };
+    let video_streams = match edit.cover {
+        Some(_) => probe_video_streams(&edit.input),
+        None => 0,
+    };
+    let mut job = JobArgs::from(edit.args(video_streams));
+    job.report_output = Some(output);
+    Ok(job)
+}
+
+/// `tag --show`: the input's tags, ready to print.
+pub fn tag_lines(input: &Path) -> Result<Vec<String>, String> {
+    let tags = probe_tags(input).map_err(|err| match err.reason() {
+        Some(reason) => format!("cannot read tags: {reason}"),
+        None => format!("cannot read tags: {err}"),
+    })?;
+    Ok(format_tag_lines(input, &tags))
+}
+
+/// `chapters`: the input's chapters, ready to print, after writing them to
+/// `--export` if asked.
+pub fn chapter_lines(args: &ChaptersArgs) -> Result<Vec<String>, String> {
+    let inputs = probe_input(&args.input).map_err(|err| match err.reason() {
+        Some(reason) => format!("cannot read chapters: {reason}"),
+        None => format!("cannot read chapters: {err}"),
+    })?;
+    let chapters = inputs
+        .into_iter()
+        .next()
+        .map(|info| info.chapters)
+        .unwrap_or_default();
+    let mut lines = format_chapter_lines(&args.input, &chapters);
+    if let Some(path) = &args.export {
+        if chapters.is_empty() {
+            return Err(format!("'{}' has no chapters to export", args.input.display()));
+        }
+        std::fs::write(path, to_ffmetadata(&chapters))
+            .map_err(|err| format!("cannot write '{}': {err}", path.display()))?;
+        lines.push(format!("Exported {} chapters to {}", chapters.len(), path.display()));
+    }
+    Ok(lines)
+}
+
+/// The `encoders` listing, filtered by type and text.
+pub fn encoder_lines(args: &EncodersArgs) -> Result<Vec<String>, String> {
+    let encoders = encoders().ok_or("cannot list encoders: ffmpeg did not run")?;
+    let matching: Vec<&Encoder> = encoders
+        .iter()
+        .filter(|encoder| args.kind.is_none_or(|kind| encoder.kind == kind))
+        .filter(|encoder| matches_filter(args.filter.as_deref(), &encoder.name, &encoder.description))
+        .collect();
+    Ok(format_encoder_lines(&matching))
+}
+
+// Only when ffmpeg can list its encoders; if it can't, the job fails
+// anyway.
+fn check_encoder(name: Option<&str>, kind: StreamKind) -> Result<(), String> {
+    match (name, encoders().filter(|encoders| !encoders.is_empty())) {
+        (Some(name), Some(encoders)) => validate_encoder(name, kind, encoders),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `encoders` can write `kind` with `name`, an encoder (`libx264`)
+/// or a codec ffmpeg picks an encoder for (`h264`). Otherwise says what
+/// was probably meant.
+pub fn validate_encoder(name: &str, kind: StreamKind, encoders: &[Encoder]) -> Result<(), String> {
+    let matches = |encoder: &&Encoder| encoder.name == name || encoder.codec == name;
+    if name == "copy" || encoders.iter().filter(|encoder| encoder.kind == kind).any(|encoder| matches(&encoder)) {
+        return Ok(());
+    }
+    if let Some(other) = encoders.iter().find(matches) {
+        return Err(format!("'{name}' encodes {}, not {}", other.kind.name(), kind.name()));
+    }
+    let known: Vec<&str> = encoders
+        .iter()
+        .filter(|encoder| encoder.kind == kind)
+        .map(|encoder| encoder.name.as_str())
+        .collect();
+    let unknown = match suggest_command(name, &known) {
+        Some(suggestion) => format!("unknown {} encoder '{name}', did you mean {suggestion}?", kind.name()),
+        None => format!("unknown {} encoder '{name}' (`encoders` lists them).", kind.name()),
+    };
+    Err(format!("{unknown} Use --no-validate to pass it to ffmpeg anyway."))
+}
+
+/// The `formats` listing, filtered by text.
+pub fn format_lines(args: &FormatsArgs) -> Result<Vec<String>, String> {
+    let formats = formats().ok_or("cannot list formats: ffmpeg did not run")?;
+    let matching: Vec<&Format> = formats
+        .iter()
+        .filter(|format| matches_filter(args.filter.as_deref(), &format.name, &format.description))
+        .collect();
+    Ok(format_format_lines(&matching))
+}
+
+/// The `filters` listing, filtered by text.
+pub fn filter_lines(args: &FiltersArgs) -> Result<Vec<String>, String> {
+    let filters = filters().ok_or("cannot list filters: ffmpeg did not run")?;
+    let matching: Vec<&Filter> = filters
+        .iter()
+        .filter(|filter| matches_filter(args.filter.as_deref(), &filter.name, &filter.description))
+        .collect();
+    Ok(format_filter_lines(&matching))
+}
+
+/// The `doctor` report: where ffmpeg and ffprobe are, and which of the
+/// optional parts of ffmpeg that ffflow can use this build has.
+pub fn doctor_lines() -> Vec<String> {
+    let mut lines = vec![match find_on_path("ffmpeg") {
+        Some(path) => format!(
+            "ffmpeg   {} (version {})",
+            path.display(),
+            ffmpeg_version().unwrap_or("unknown")
+        ),
+        None => "ffmpeg   not found on PATH; no job can run".to_string(),
+    }];
+    lines.push(match find_on_path("ffprobe") {
+        Some(path) => format!("ffprobe  {}", path.display()),
+        None => "ffprobe  not found on PATH".to_string(),
+    });
+    let has_encoder = |names: &[&str]| {
+        encoders().is_some_and(|encoders| encoders.iter().any(|encoder| names.contains(&encoder.name.as_str())))
+    };
+    let checks = [
+        ("libx265", "H.265 encoding", has_encoder(&["libx265"])),
+        ("libvpx", "VP8 and VP9 encoding", has_encoder(&["libvpx", "libvpx-vp9"])),
+        ("libaom", "AV1 encoding", has_encoder(&["libaom-av1"])),
+        ("nvenc", "NVIDIA hardware encoding", has_encoder(&["h264_nvenc", "hevc_nvenc", "av1_nvenc"])),
+        (
+            "vaapi",
+            "VA-API hardware acceleration",
+            hwaccels().is_some_and(|methods| methods.iter().any(|method| method == "vaapi")),
+        ),
+        ("libvmaf", "VMAF scores in compare", has_filter("libvmaf")),
+    ];
+    for (name, purpose, present) in checks {
+        let answer = if present { "yes" } else { "no " };
+        lines.push(format!("{name:<8} {answer}  {purpose}"));
+    }
+    lines
+}
+
+/// Build options worth knowing about when they are there.
+const BUILD_HIGHLIGHTS: &[&str] = &[
+    "gpl", "nonfree", "libx264", "libx265", "libvpx", "libaom", "libsvtav1", "libdav1d", "libopus",
+    "libmp3lame", "libfdk-aac", "libvmaf", "libvidstab", "libass", "libzimg", "nvenc", "cuda", "vaapi",
+    "videotoolbox",
+];
+
+/// The `version` report: ffflow's version, ffmpeg's, and the notable
+/// options ffmpeg was built with.
+pub fn version_lines() -> Vec<String> {
+    let mut lines = vec![format!("ffflow {}", env!("CARGO_PKG_VERSION"))];
+    let Some(text) = ffmpeg_version() else {
+        lines.push("ffmpeg   not found, or `ffmpeg -version` failed".to_string());
+        return lines;
+    };
+    lines.push(match ffmpeg_release() {
+        Some(release) if release.to_string() != text => format!("ffmpeg {release} ({text})"),
+        Some(release) => format!("ffmpeg {release}"),
+        None => format!("ffmpeg {text} (a build without a release number)"),
+    });
+    let enabled: Vec<&str> = build_configuration()
+        .unwrap_or_default()
+        .iter()
+        .map(String::as_str)
+        .filter(|option| BUILD_HIGHLIGHTS.contains(option))
+        .collect();
+    lines.push(match enabled.is_empty() {
+        true => "built with none of the usual extras".to_string(),
+        false => format!("built with {}", enabled.join(", ")),
+    });
+    lines
+}
+
+// Case-insensitive, on the name or the description.
+fn matches_filter(filter: Option<&str>, name: &str, description: &str) -> bool {
+    filter.is_none_or(|filter| {
+        let filter = filter.to_lowercase();
+        name.to_lowercase().contains(&filter) || description.to_lowercase().contains(&filter)
+    })
+}
+
+/// Re-times video and audio together. The output is shorter or longer than
+/// the input, so its predicted length is returned as the job's duration.
+pub fn speed_args_to_job(args: SpeedArgs) -> JobArgs {
+    let factor = args.factor;
+    let predicted = probe_duration(&args.input).map(|input| (input, scaled_duration(input, factor)));
+    let mut extra_args = if args.mute {
+        vec!["-an".to_string()]
+    } else {
+        vec!["-af".to_string(), atempo_filters(factor)]
+    };
+    extra_args.extend(args.extra_args);
+    let output = args.output.clone();
+    let command = FfmpegCommand {
+        inputs: vec![args.input],
+        output: args.output,
+        video_codec: args.video_codec,
+        audio_codec: args.audio_codec.filter(|_| !args.mute),
+        preset: args.preset,
+        video_filters: vec![setpts_filter(factor)],
+        extra_args,
+        ..FfmpegCommand::default()
+    };
+    let mut job = JobArgs::from(command);
+    job.report_output = Some(output);
+    if let Some((input, scaled)) = predicted {
+        job.notes.push(format!(
+            "speed {factor}x: {} becomes {}",
+            format_duration(input),
+            format_duration(scaled)
+        ));
+        job.duration = Some(scaled);
+    }
+    job
+}
+
+/// Stops after `--for` with `-t`, which also gives progress a length to
+/// measure against. A stream is copied unless codecs are given; raw frames
+/// from a device are left to the output container's default encoders.
+pub fn record_args_to_job(args: RecordArgs) -> JobArgs {
+    let copy = args.format.is_none().then(|| "copy".to_string());
+    let mut extra_args = vec!["-t".to_string(), args.length.as_secs_f64().to_string()];
+    extra_args.extend(args.extra_args);
+    let output = args.output.clone();
+    let command = FfmpegCommand {
+        inputs: vec![args.from],
+        input_args: args.format.map(|format| vec!["-f".to_string(), format]).into_iter().collect(),
+        output: args.output,
+        video_codec: args.video_codec.or_else(|| copy.clone()),
+        audio_codec: args.audio_codec.or(copy),
+        extra_args,
+        ..FfmpegCommand::default()
+    };
+    let mut job = JobArgs::from(command);
+    job.report_output = Some(output);
+    job.duration = Some(args.length);
+    job
+}
+
+/// Builds the comparison, scaling the encode to the reference's size when
+/// the two differ. Returns a warning to show when it does.
+pub fn compare_args_to_job(args: &CompareArgs) -> (QualityJob, Option<String>) {
+    let size = |path: &std::path::Path| {
+        probe_input(path)
+            .ok()?
+            .into_iter()
+            .map(|info| (info.width, info.height))
+            .find(|&(width, height)| width > 0 && height > 0)
+    };
+    let mut warning = None;
+    let mut scale_to = None;
+    if let (Some(reference), Some(distorted)) = (size(&args.reference), size(&args.distorted)) {
+        if reference != distorted {
+            warning = Some(format!(
+                "warning: '{}' is {}x{}, scaling it to the reference's {}x{}",
+                args.distorted.display(),
+                distorted.0,
+                distorted.1,
+                reference.0,
+                reference.1
+            ));
+            scale_to = Some(reference);
+        }
+    }
+    let job = QualityJob {
+        reference: args.reference.clone(),
+        distorted: args.distorted.clone(),
+        scale_to,
+        vmaf: has_filter("libvmaf"),
+    };
+    (job, warning)
+}
+
+/// Expands a ladder into one `ffmpeg` job line per rung, named
+/// `{stem}_{height}p.mp4` in the output directory. Rungs taller than the
+/// source are skipped.
+pub fn ladder_args_to_plan(args: LadderArgs) -> Result<LadderPlan, String> {
+    if args.rungs.is_empty() {
+        return Err("ladder needs at least one rung".to_string());
+    }
+    std::fs::create_dir_all(&args.out_dir)
+        .map_err(|err| format!("cannot create '{}': {err}", args.out_dir.display()))?;
+    let source = probe_input(&args.input).ok().and_then(|inputs| inputs.into_iter().next());
+    let source_height = source.as_ref().map(|info| info.height).filter(|&height| height > 0);
+    let stem = args
+        .input
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+
+    let (rungs, notes) = fit_rungs(args.rungs, source_height);
+    let mut plan = LadderPlan {
+        label: format!("ladder {stem}"),
+        rungs: Vec::new(),
+        notes,
+        duration: source.and_then(|info| info.duration),
+    };
+    for rung in rungs {
+        let output = args.out_dir.join(format!("{stem}_{}p.mp4", rung.height));
+        let mut command = FfmpegCommand {
+            inputs: vec![args.input.clone()],
+            output: output.clone(),
+            video_codec: Some(args.video_codec.clone()),
+            audio_codec: Some(args.audio_codec.clone()),
+            preset: args.preset.clone(),
+            extra_args: args.extra_args.clone(),
+            ..FfmpegCommand::default()
+        };
+        rung.apply(&mut command);
+        if let Some(note) = fix_preset(&mut command) {
+            if !plan.notes.contains(&note) {
+                plan.notes.push(note);
+            }
+        }
+        let words: Vec<String> = command
+            .to_args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        plan.rungs.push(PlannedRung {
+            rung,
+            output,
+            line: format!("ffmpeg {}", shell_words::join(words)).into(),
+        });
+    }
+    if plan.rungs.is_empty() {
+        return Err(format!("every rung is taller than '{}'", args.input.display()));
+    }
+    Ok(plan)
+}
+
+/// Creates the output directory and builds the packaging job. Variants
+/// taller than the source are dropped with a note, as in `ladder`.
+pub fn hls_args_to_job(args: HlsArgs) -> Result<(HlsJob, Vec<String>), String> {
+    std::fs::create_dir_all(&args.out_dir)
+        .map_err(|err| format!("cannot create '{}': {err}", args.out_dir.display()))?;
+    let mut notes = Vec::new();
+    let mut variants = Vec::new();
+    if !args.variants.is_empty() {
+        let source_height = probe_input(&args.input)
+            .ok()
+            .and_then(|inputs| inputs.into_iter().map(|info| info.height).find(|&height| height > 0));
+        (variants, notes) = fit_rungs(args.variants, source_height);
+        if variants.is_empty() {
+            return Err(format!("every variant is taller than '{}'", args.input.display()));
+        }
+    }
+    let audio = variants.is_empty() || probe_audio_codec(&args.input).is_some();
+    let job = HlsJob {
+        input: args.input,
+        out_dir: args.out_dir,
+        segment_time: args.segment_time,
+        playlist_name: args.playlist_name,
+        variants,
+        video_codec: args.video_codec,
+        audio_codec: args.audio_codec,
+        audio,
+        extra_args: args.extra_args,
+    };
+    Ok((job, notes))
+}
+
+/// Plans the split. For `--size`, the piece length comes from the input's
+/// average bitrate; the notes say what was picked.
+pub fn split_args_to_job(args: SplitArgs) -> Result<(SplitJob, Vec<String>), String> {
+    let mut notes = Vec::new();
+    let pattern = segment_pattern(&args.output)?;
+    let segment_time = match (args.every, args.size) {
+        (Some(every), _) => every,
+        (None, Some(size)) => {
+            let bitrate = probe_input(&args.input)
+                .ok()
+                .and_then(|inputs| inputs.into_iter().find_map(|info| info.bitrate_kbps))
+                .ok_or_else(|| format!("can't read the bitrate of '{}' to split by size", args.input.display()))?;
+            let every = segment_time_for_size(size, bitrate)
+                .ok_or_else(|| format!("{} is too small to split '{}' into", format_bytes(size), args.input.display()))?;
+            notes.push(format!(
+                "cutting every {} ({bitrate:.0} kb/s average)",
+                format_duration(every)
+            ));
+            every
+        }
+        (None, None) => return Err("split needs --every or --size".to_string()),
+    };
+    let job = SplitJob {
+        input: args.input,
+        pattern,
+        segment_time,
+    };
+    Ok((job, notes))
+}
+
+pub fn normalize_args_to_job(args: NormalizeArgs) -> LoudnormJob {
+    LoudnormJob {
+        input: args.input,
+        output: args.output,
+        target: LoudnormTarget {
+            integrated: args.target,
+            lra: args.lra,
+            true_peak: args.tp,
+        },
+    }
+}
+
+/// Fails early when ffmpeg lacks the vidstab filters, since pass one would
+/// only get as far as a cryptic "No such filter".
+pub fn stabilize_args_to_job(args: StabilizeArgs) -> Result<StabilizeJob, String> {
+    if !has_filter("vidstabdetect") || !has_filter("vidstabtransform") {
+        return Err(
+            "this ffmpeg was built without libvidstab; stabilize needs a build with --enable-libvidstab"
+                .to_string(),
+        );
+    }
+    Ok(StabilizeJob {
+        input: args.input,
+        output: args.output,
+        shakiness: args.shakiness,
+        smoothing: args.smoothing,
+        extra_args: args.extra_args,
+        transforms: TransformsFile::new(),
+    })
+}
+
+/// `ffmpeg -i <path>` with no output: ffmpeg reads the container header,
+/// prints the input section and stops without decoding anything.
+pub fn info_args_to_argv(args: InfoArgs) -> Vec<OsString> {
+    vec!["-hide_banner".into(), "-i".into(), args.input.into_os_string()]
+}
+
+pub fn parse_line(line: &OsStr) -> Result<Commands, String> {
     let mut argv = Vec::new();
-    argv.push("ffflow".to_string());
+    argv.push(OsString::from("ffflow"));
 
-    let tokens = shell_words::split(line).map_err(|err| err.to_string())?;
+    if let Some(message) = unknown_command(line, &["ffmpeg"]) {
+        return Err(message);
+    }
+    let tokens = split_os(line)?;
     argv.extend(tokens);
 
     let parsed = Cli::try_parse_from(argv).map_err(|err| err.to_string())?;
     Ok(parsed.command)
 }
 
-pub const PRESETS: [&str; 10] = [
-    "ultrafast",
-    "superfast",
-    "veryfast",
-    "faster",
-    "fast",
-    "medium",
-    "slow",
-    "slower",
-    "veryslow",
-    "placebo",
-];
+/// Farthest a typo may be from a command (in edits) to be suggested.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// An error for a line that starts with neither a subcommand nor one of
+/// `builtins`, naming the command it most likely meant.
+pub fn unknown_command(line: &OsStr, builtins: &[&str]) -> Option<String> {
+    let text = line.to_string_lossy();
+    let word = text.split_whitespace().next().filter(|word| !word.starts_with('-'))?;
+    let known = known_commands(builtins);
+    if known.iter().any(|name| name == word) {
+        return None;
+    }
+    let known: Vec<&str> = known.iter().map(String::as_str).collect();
+    Some(match suggest_command(word, &known) {
+        Some(name) => format!("unknown command '{word}'; did you mean '{name}'?"),
+        None => format!("unknown command '{word}'"),
+    })
+}
+
+/// Every subcommand name plus `help` and `builtins`.
+pub fn known_commands(builtins: &[&str]) -> Vec<String> {
+    let mut known: Vec<String> = Cli::command()
+        .get_subcommands()
+        .map(|command| command.get_name().to_string())
+        .collect();
+    known.extend(["help"].iter().chain(builtins).map(|name| name.to_string()));
+    known
+}
+
+/// Checks `name` can be defined as an alias: a valid name that no command
+/// already answers to.
+pub fn check_alias_name(name: &str, builtins: &[&str]) -> Result<(), String> {
+    validate_alias_name(name)?;
+    if known_commands(builtins)
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("'{name}' is a built-in command and cannot be an alias"));
+    }
+    Ok(())
+}
+
+/// The command `word` was probably meant to be: the only one it starts,
+/// else the nearest within `MAX_SUGGESTION_DISTANCE` edits (the first
+/// listed on a tie). Case is ignored.
+pub fn suggest_command<'a>(word: &str, known: &[&'a str]) -> Option<&'a str> {
+    let word = word.to_lowercase();
+    if let [only] = known
+        .iter()
+        .filter(|name| name.starts_with(word.as_str()))
+        .collect::<Vec<_>>()[..]
+    {
+        return Some(only);
+    }
+    known
+        .iter()
+        .map(|name| (edit_distance(&word, name), *name))
+        // A word no longer than the edits is no clue at all.
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE && *distance < word.chars().count())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// Levenshtein distance, counting characters rather than bytes.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The argv for one ffmpeg run, plus the concat list it reads from (if any),
+/// which must be kept until ffmpeg exits.
+#[derive(Debug)]
+pub struct JobArgs {
+    pub args: Vec<OsString>,
+    pub concat_list: Option<Arc<ConcatList>>,
+    /// File to confirm (path and size) once the job succeeds.
+    pub report_output: Option<PathBuf>,
+    /// Adjustments made while building the job, shown before it starts.
+    pub notes: Vec<String>,
+    /// Expected output length, when it is not the input's (or `-t`'s).
+    pub duration: Option<Duration>,
+    pub timeout: Option<JobTimeout>,
+    /// Check the output once the job succeeds.
+    pub verify: Option<VerifyMode>,
+}
+
+impl From<Vec<OsString>> for JobArgs {
+    fn from(args: Vec<OsString>) -> Self {
+        Self {
+            args,
+            concat_list: None,
+            report_output: None,
+            notes: Vec::new(),
+            duration: None,
+            timeout: None,
+            verify: None,
+        }
+    }
+}
+
+impl From<FfmpegCommand> for JobArgs {
+    fn from(mut command: FfmpegCommand) -> Self {
+        let notes = fix_subtitle_codec(&mut command)
+            .into_iter()
+            .chain(fix_preset(&mut command))
+            .collect();
+        Self {
+            args: command.to_args(),
+            concat_list: command.concat_list,
+            report_output: None,
+            notes,
+            duration: None,
+            timeout: None,
+            verify: None,
+        }
+    }
+}
+
+fn job_timeout(args: &EncodeArgs) -> Option<JobTimeout> {
+    args.timeout.map(|limit| JobTimeout {
+        limit,
+        wall_clock: args.timeout_wall_clock,
+    })
+}
+
+/// An encode line whose first input is a wildcard and whose output is a
+/// template becomes one line per matching file, each with its own output.
+/// Any other line comes back as is.
+pub fn fan_out_line(line: &OsStr) -> Vec<OsString> {
+    match parse_line(line) {
+        Ok(Commands::Encode(args)) => fan_out_encode(line, &args),
+        _ => vec![line.to_os_string()],
+    }
+}
+
+/// `fan_out_line` for a line already parsed as `encode`.
+pub fn fan_out_encode(line: &OsStr, args: &EncodeArgs) -> Vec<OsString> {
+    let single = || vec![line.to_os_string()];
+    let Some(pattern) = args.inputs.first() else {
+        return single();
+    };
+    if args.concat
+        || !is_template(&args.output)
+        || !is_glob_pattern(pattern)
+        || is_image_sequence(pattern)
+    {
+        return single();
+    }
+    // A pattern that matches nothing fails with a proper error once the
+    // line runs.
+    let Ok(files) = expand_input_globs(vec![pattern.clone()]) else {
+        return single();
+    };
+    let (Some(text), Some(files)) = (
+        line.to_str(),
+        files.iter().map(|file| file.to_str()).collect::<Option<Vec<_>>>(),
+    ) else {
+        return single();
+    };
+    let Ok(words) = shell_words::split(text) else {
+        return single();
+    };
+    let Some(index) = words
+        .windows(2)
+        .position(|pair| (pair[0] == "-i" || pair[0] == "--input") && Path::new(&pair[1]) == pattern)
+    else {
+        return single();
+    };
+    files
+        .into_iter()
+        .map(|file| {
+            let mut words = words.clone();
+            words[index + 1] = file.to_string();
+            shell_words::join(words).into()
+        })
+        .collect()
+}
+
+/// Whether a job line sends its output to stdout (`-o -`, `-o pipe:1`, or
+/// an `ffmpeg ...` line ending in one), without building the job.
+pub fn line_writes_stdout(line: &OsStr) -> bool {
+    if let Some(rest) = strip_prefix_os(line, "ffmpeg ") {
+        return split_os(&rest).is_ok_and(|args| writes_to_stdout(&args));
+    }
+    let Ok(words) = split_os(line) else {
+        return false;
+    };
+    let is_stdout = |value: &OsStr| ["-", "pipe:", "pipe:1"].iter().any(|name| value == *name);
+    words.windows(2).any(|pair| {
+        (pair[0] == "-o" || pair[0] == "--output") && is_stdout(&pair[1])
+    }) || words.iter().any(|word| {
+        strip_prefix_os(word, "--output=").is_some_and(|value| is_stdout(&value))
+    })
+}
+
+/// Resolves a job line (`encode ...`, `probe ...` or `ffmpeg ...`) to the
+/// argv passed to ffmpeg. Paths keep their raw bytes end to end.
+pub fn job_args_from_line(line: &OsStr) -> Result<JobArgs, String> {
+    match passthrough_job(line) {
+        Some(job) => job,
+        None => job_args_from_command(parse_line(line)?),
+    }
+}
+
+/// The job for an `ffmpeg ...` line, or `None` for any other line.
+pub fn passthrough_job(line: &OsStr) -> Option<Result<JobArgs, String>> {
+    strip_prefix_os(line, "ffmpeg ").map(passthrough_args)
+}
+
+fn passthrough_args(rest: OsString) -> Result<JobArgs, String> {
+    let rest = match rest.to_str().filter(|_| expand_passthrough()) {
+        Some(text) => OsString::from(expand_line(text, &SystemEnv)?),
+        None => rest,
+    };
+    let args = split_os(&rest)?;
+    if args.is_empty() {
+        return Err("ffmpeg requires arguments".to_string());
+    }
+    Ok(args.into())
+}
+
+/// `job_args_from_line` for a line already parsed into a subcommand.
+pub fn job_args_from_command(command: Commands) -> Result<JobArgs, String> {
+    match command {
+        Commands::Encode(args) => {
+            // Sequences have no container duration to show progress against.
+            let sequence = args
+                .inputs
+                .first()
+                .filter(|input| args.framerate.is_some() || is_image_sequence(input))
+                .map(|input| (input.clone(), args.framerate.unwrap_or(DEFAULT_FRAMERATE)));
+            let timeout = job_timeout(&args);
+            let verify = args.verify;
+            let command = encode_args_to_command(args)?;
+            let warnings = command.color.warnings();
+            let mut job = JobArgs::from(command);
+            job.notes.extend(warnings);
+            job.duration = sequence.and_then(|(pattern, rate)| sequence_duration(&pattern, rate));
+            job.timeout = timeout;
+            job.verify = verify;
+            Ok(job)
+        }
+        Commands::Profile(args) => {
+            let timeout = job_timeout(&args.encode);
+            let verify = args.encode.verify;
+            let command = profile_args_to_command(args)?;
+            let warnings = command.color.warnings();
+            let mut job = JobArgs::from(command);
+            job.notes.extend(warnings);
+            job.timeout = timeout;
+            job.verify = verify;
+            Ok(job)
+        }
+        Commands::Probe(args) => Ok(probe_args_to_command(args).into()),
+        Commands::Info(args) => Ok(info_args_to_argv(args).into()),
+        Commands::Thumbnail(args) => {
+            let output = args.output.clone();
+            let mut job = JobArgs::from(thumbnail_args_to_command(args));
+            job.report_output = Some(output);
+            Ok(job)
+        }
+        Commands::Gif(args) => {
+            let output = args.output.clone();
+            let mut job = JobArgs::from(gif_args_to_command(args));
+            job.report_output = Some(output);
+            Ok(job)
+        }
+        Commands::ExtractAudio(args) => {
+            let output = args.output.clone();
+            let mut job = JobArgs::from(extract_audio_args_to_command(args)?);
+            job.report_output = Some(output);
+            Ok(job)
+        }
+        Commands::Remux(args) => {
+            let output = args.output.clone();
+            let mut job = JobArgs::from(remux_args_to_command(args));
+            job.report_output = Some(output);
+            Ok(job)
+        }
+        Commands::Watermark(args) => {
+            let output = args.output.clone();
+            let mut job = JobArgs::from(watermark_args_to_command(args)?);
+            job.report_output = Some(output);
+            Ok(job)
+        }
+        Commands::Speed(args) => Ok(speed_args_to_job(args)),
+        Commands::Record(args) => Ok(record_args_to_job(args)),
+        Commands::Loop(args) => Ok(loop_args_to_job(args)),
+        Commands::Tag(args) if args.show => Err("tag --show only reads the file".to_string()),
+        Commands::Tag(args) => tag_args_to_job(args),
+        Commands::Chapters(_) => Err("chapters only reads the file".to_string()),
+        Commands::Encoders(_)
+        | Commands::Formats(_)
+        | Commands::Filters(_)
+        | Commands::Doctor
+        | Commands::Version => {
+            Err("this only lists what ffmpeg supports".to_string())
+        }
+        Commands::Normalize(_) => Err("normalize runs two ffmpeg passes".to_string()),
+        Commands::Stabilize(_) => Err("stabilize runs two ffmpeg passes".to_string()),
+        Commands::Cropdetect(_) => Err("cropdetect is not a single encode".to_string()),
+        Commands::Compare(_) => Err("compare is not an encode".to_string()),
+        Commands::Ladder(_) => Err("ladder queues one job per rung".to_string()),
+        Commands::Hls(args) => {
+            let (job, notes) = hls_args_to_job(args)?;
+            let mut job_args = JobArgs::from(job.args());
+            job_args.notes = notes;
+            Ok(job_args)
+        }
+        Commands::Split(args) => {
+            let (job, notes) = split_args_to_job(args)?;
+            let mut job_args = JobArgs::from(job.args());
+            job_args.notes = notes;
+            Ok(job_args)
+        }
+        Commands::Presets => Err("presets is not a job command".to_string()),
+    }
+}
+
+pub const PRESETS: [&str; 10] = PRESET_NAMES;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ffmpeg argv of one job line.
+    fn line_argv(line: &str) -> Vec<String> {
+        job_args_from_line(OsStr::new(line))
+            .unwrap()
+            .args
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    /// The value after each occurrence of `flag`, in order.
+    fn values_of<'a>(argv: &'a [String], flag: &str) -> Vec<&'a str> {
+        argv.windows(2)
+            .filter(|pair| pair[0] == flag)
+            .map(|pair| pair[1].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn meta_is_set_on_top_of_stripped_tags() {
+        let argv = line_argv(
+            "encode -i a.mkv -o b.mkv --meta title=Archive --strip-metadata -- -metadata comment=scan",
+        );
+        assert_eq!(values_of(&argv, "-map_metadata"), ["-1"]);
+        assert_eq!(values_of(&argv, "-metadata"), ["title=Archive", "comment=scan"]);
+        // ffmpeg applies -metadata after -map_metadata wherever they are,
+        // but every one of them has to come before the output.
+        let output = argv.iter().position(|arg| arg == "b.mkv").unwrap();
+        assert_eq!(output, argv.len() - 1);
+    }
+
+    #[test]
+    fn meta_is_set_on_top_of_copied_tags() {
+        let argv = line_argv("encode -i a.mkv -i b.mkv -o c.mkv --copy-metadata --meta title=Joined --copy-chapters");
+        assert_eq!(values_of(&argv, "-map_metadata"), ["0"]);
+        assert_eq!(values_of(&argv, "-map_chapters"), ["0"]);
+        assert_eq!(values_of(&argv, "-metadata"), ["title=Joined"]);
+        assert!(!argv.iter().any(|arg| arg == "-1"), "{argv:?}");
+    }
+
+    #[test]
+    fn metadata_flags_are_left_out_by_default() {
+        let argv = line_argv("encode -i a.mkv -o b.mkv -- -metadata title=Raw");
+        assert!(values_of(&argv, "-map_metadata").is_empty(), "{argv:?}");
+        assert!(values_of(&argv, "-map_chapters").is_empty(), "{argv:?}");
+        assert_eq!(values_of(&argv, "-metadata"), ["title=Raw"]);
+    }
+
+    #[test]
+    fn strip_and_copy_metadata_conflict() {
+        let err = parse_line(OsStr::new("encode -i a.mkv -o b.mkv --copy-metadata --strip-metadata")).unwrap_err();
+        assert!(err.contains("--strip-metadata") || err.contains("--copy-metadata"), "{err}");
+    }
+
+    #[test]
+    fn fix_preset_translates_for_vp9() {
+        let mut command = FfmpegCommand {
+            video_codec: Some("libvpx-vp9".to_string()),
+            preset: Some("slow".to_string()),
+            ..FfmpegCommand::default()
+        };
+        let note = fix_preset(&mut command).expect("a note");
+        assert!(note.contains("libvpx-vp9"), "{note}");
+        assert_eq!(command.preset, None);
+        assert_eq!(command.extra_args, ["-deadline", "good", "-cpu-used", "1"]);
+    }
+
+    fn sample_encoders() -> Vec<Encoder> {
+        let encoder = |name: &str, kind, codec: &str| Encoder {
+            name: name.to_string(),
+            kind,
+            codec: codec.to_string(),
+            description: String::new(),
+        };
+        vec![
+            encoder("libx264", StreamKind::Video, "h264"),
+            encoder("libx265", StreamKind::Video, "hevc"),
+            encoder("libvpx-vp9", StreamKind::Video, "vp9"),
+            encoder("aac", StreamKind::Audio, "aac"),
+            encoder("libopus", StreamKind::Audio, "opus"),
+        ]
+    }
+
+    #[test]
+    fn validate_encoder_takes_names_and_codecs() {
+        let encoders = sample_encoders();
+        assert_eq!(validate_encoder("libx264", StreamKind::Video, &encoders), Ok(()));
+        assert_eq!(validate_encoder("hevc", StreamKind::Video, &encoders), Ok(()));
+        assert_eq!(validate_encoder("opus", StreamKind::Audio, &encoders), Ok(()));
+    }
+
+    #[test]
+    fn validate_encoder_always_takes_copy() {
+        assert_eq!(validate_encoder("copy", StreamKind::Video, &[]), Ok(()));
+        assert_eq!(validate_encoder("copy", StreamKind::Audio, &sample_encoders()), Ok(()));
+    }
+
+    #[test]
+    fn validate_encoder_suggests_a_close_name() {
+        let err = validate_encoder("libx624", StreamKind::Video, &sample_encoders()).unwrap_err();
+        assert!(err.starts_with("unknown video encoder 'libx624', did you mean libx264?"), "{err}");
+        assert!(err.contains("--no-validate"), "{err}");
+
+        let err = validate_encoder("prores", StreamKind::Video, &sample_encoders()).unwrap_err();
+        assert!(err.contains("`encoders` lists them"), "{err}");
+    }
+
+    #[test]
+    fn validate_encoder_rejects_the_wrong_stream_type() {
+        let err = validate_encoder("aac", StreamKind::Video, &sample_encoders()).unwrap_err();
+        assert_eq!(err, "'aac' encodes audio, not video");
+        let err = validate_encoder("libx265", StreamKind::Audio, &sample_encoders()).unwrap_err();
+        assert_eq!(err, "'libx265' encodes video, not audio");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn nice_takes_unix_niceness() {
+        let parsed = SystemCli::try_parse_from(["ffflow", "--nice", "12"]).unwrap();
+        assert_eq!(parsed.nice, Some(12));
+        assert!(SystemCli::try_parse_from(["ffflow", "--nice", "19"]).is_ok());
+        assert!(SystemCli::try_parse_from(["ffflow", "--nice", "25"]).is_err());
+        assert!(SystemCli::try_parse_from(["ffflow", "--nice", "0"]).is_err());
+    }
+
+    #[test]
+    fn bash_completions_cover_both_grammars() {
+        let mut script = Vec::new();
+        write_completions(Shell::Bash, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        for option in ["--command", "--stdin", "--checkpoint", "--nice", "--completions"] {
+            assert!(script.contains(option), "missing {option}");
+        }
+        for command in ["encode", "probe", "hls", "split", "ladder", "compare"] {
+            assert!(script.contains(&format!("ffflow,{command})")), "missing {command}");
+        }
+        for option in ["--vcodec", "--preset", "--segment-time"] {
+            assert!(script.contains(option), "missing {option}");
+        }
+    }
+}
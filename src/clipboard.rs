@@ -0,0 +1,26 @@
+//! System clipboard access, compiled in with the `clipboard` feature.
+
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<(), String> {
+    use std::cell::RefCell;
+
+    // On X11 and Wayland the copied text is served by the clipboard handle
+    // itself, so it has to outlive this call.
+    thread_local! {
+        static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+    }
+
+    CLIPBOARD.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        if slot.is_none() {
+            *slot = Some(arboard::Clipboard::new().map_err(|err| err.to_string())?);
+        }
+        let clipboard = slot.as_mut().expect("clipboard initialised above");
+        clipboard.set_text(text).map_err(|err| err.to_string())
+    })
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<(), String> {
+    Err("built without the `clipboard` feature".to_string())
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+
+use crate::core::shell::{strip_prefix_os, trim_os};
+
+/// Splits `NAME = COMMAND`, the text after `alias`.
+pub fn parse_alias_definition(text: &str) -> Result<(String, String), String> {
+    let Some((name, expansion)) = text.split_once('=') else {
+        return Err("expected alias <name> = <command>".to_string());
+    };
+    let (name, expansion) = (name.trim(), expansion.trim());
+    validate_alias_name(name)?;
+    if expansion.is_empty() {
+        return Err(format!("alias '{name}' needs a command after '='"));
+    }
+    Ok((name.to_string(), expansion.to_string()))
+}
+
+/// One word of letters, digits, `-` and `_`, not starting with `-` so it
+/// can't be mistaken for a flag. Built-in names are the caller's to reject.
+pub fn validate_alias_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid alias name '{name}' (use letters, digits, '-' and '_')"
+        ))
+    }
+}
+
+/// Replaces a leading alias in `line` with the command it stands for, and
+/// again while the result starts with one, so aliases can build on each
+/// other. The rest of the line follows the expansion untouched. An alias
+/// that leads back to itself is an error instead of a loop.
+pub fn expand_alias(line: &OsStr, aliases: &BTreeMap<String, String>) -> Result<OsString, String> {
+    let mut line = trim_os(line);
+    let mut chain: Vec<String> = Vec::new();
+    loop {
+        let Some(word) = line.to_string_lossy().split_whitespace().next().map(str::to_string) else {
+            return Ok(line);
+        };
+        let Some(expansion) = aliases.get(&word) else {
+            return Ok(line);
+        };
+        if chain.contains(&word) {
+            chain.push(word.clone());
+            return Err(format!(
+                "alias '{word}' refers back to itself ({})",
+                chain.join(" -> ")
+            ));
+        }
+        let Some(rest) = strip_prefix_os(&line, &word) else {
+            return Ok(line);
+        };
+        let mut expanded = OsString::from(expansion);
+        expanded.push(rest);
+        chain.push(word);
+        line = expanded;
+    }
+}
@@ -1,40 +1,107 @@
+use std::ffi::OsString;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::Path;
 
-pub fn parse_flw_file(path: &Path) -> Result<Vec<String>, io::Error> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut commands = Vec::new();
-    let mut current_command = String::new();
+use crate::core::depends::{order_jobs, split_job_header};
+use crate::core::expand::{expand_line, SystemEnv};
+use crate::core::queue::split_priority;
+use crate::core::shell::os_from_bytes;
+
+/// Incremental `.flw` parser: skips blank lines and `#` comments and joins
+/// lines ending in `\` with the next one. Works on raw bytes so paths that
+/// are not valid UTF-8 survive untouched.
+#[derive(Debug, Default)]
+pub struct FlwParser {
+    current_command: Vec<u8>,
+}
+
+impl FlwParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    for line in reader.lines() {
-        let line = line?;
-        let trimmed = line.trim();
+    /// Feeds one raw line and returns a command once it is complete.
+    pub fn push_line(&mut self, line: &[u8]) -> Option<OsString> {
+        let trimmed = line.trim_ascii();
 
-        if trimmed.is_empty() && current_command.is_empty() {
-            continue;
+        if trimmed.is_empty() && self.current_command.is_empty() {
+            return None;
         }
 
-        if trimmed.starts_with('#') {
-            continue;
+        if trimmed.starts_with(b"#") {
+            return None;
         }
 
-        if let Some(stripped) = trimmed.strip_suffix('\\') {
-            current_command.push_str(stripped.trim());
-            current_command.push(' ');
+        if let Some(stripped) = trimmed.strip_suffix(b"\\") {
+            self.current_command.extend_from_slice(stripped.trim_ascii());
+            self.current_command.push(b' ');
+            return None;
+        }
+
+        self.current_command.extend_from_slice(trimmed);
+        if self.current_command.is_empty() {
+            return None;
+        }
+        Some(os_from_bytes(std::mem::take(&mut self.current_command)))
+    }
+
+    /// Returns a trailing command left open by a final `\`.
+    pub fn finish(self) -> Option<OsString> {
+        if self.current_command.is_empty() {
+            None
         } else {
-            current_command.push_str(trimmed);
-            if !current_command.is_empty() {
-                commands.push(current_command.clone());
-                current_command.clear();
-            }
+            Some(os_from_bytes(self.current_command))
         }
     }
+}
 
-    if !current_command.is_empty() {
-        commands.push(current_command);
+/// Expands `~` and environment variables in a `.flw` command. Raw `ffmpeg`
+/// lines are left for the caller, which only expands them when asked to,
+/// and so are lines that are not valid UTF-8.
+pub fn expand_command(command: OsString) -> Result<OsString, String> {
+    let Some(text) = command.to_str() else {
+        return Ok(command);
+    };
+    let body = split_priority(&command).map_or(command.clone(), |(_, body)| body);
+    let body = match split_job_header(&body) {
+        Ok(Some((_, body))) => body,
+        _ => body,
+    };
+    if body == "ffmpeg" || body.to_string_lossy().starts_with("ffmpeg ") {
+        return Ok(command);
     }
+    expand_line(text, &SystemEnv)
+        .map(OsString::from)
+        .map_err(|err| format!("{err} in '{text}'"))
+}
+
+pub fn parse_flw<R: BufRead>(mut reader: R) -> Result<Vec<OsString>, io::Error> {
+    let mut parser = FlwParser::new();
+    let mut commands = Vec::new();
+    let mut line = Vec::new();
 
-    Ok(commands)
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if let Some(command) = parser.push_line(&line) {
+            commands.push(command);
+        }
+    }
+
+    commands.extend(parser.finish());
+    let commands = commands
+        .into_iter()
+        .map(|command| {
+            expand_command(command).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    order_jobs(commands).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn parse_flw_file(path: &Path) -> Result<Vec<OsString>, io::Error> {
+    let file = File::open(path)?;
+    parse_flw(BufReader::new(file))
 }
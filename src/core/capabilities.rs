@@ -0,0 +1,281 @@
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::core::verify::StreamKind;
+
+// ` V....D libx264   H.264 ...`: a type letter, five more flags, the name.
+static RE_ENCODER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ ([VAS])[A-Z.]{5} (\S+)\s+(.*)$").unwrap());
+static RE_CODEC: Lazy<Regex> = Lazy::new(|| Regex::new(r"\(codec (\S+)\)$").unwrap());
+// ` DE matroska,webm  Matroska / WebM`; ffmpeg 6.1 added a third flag
+// column for devices.
+static RE_FORMAT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ ([D ])([E ])[d ]? (\S+)\s+(.*)$").unwrap());
+
+// ` TSC scale  V->V  Scale the input video size ...`; ffmpeg 4 had only
+// two flag columns.
+static RE_FILTER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ [A-Z.]{2,3} (\S+)\s+(\S*->\S*)\s+(.*)$").unwrap());
+
+static ENCODERS: Lazy<Option<Vec<Encoder>>> =
+    Lazy::new(|| Some(list("-encoders")?.lines().filter_map(parse_encoder_line).collect()));
+// Probed with the rest, though no command lists decoders yet.
+#[allow(dead_code)]
+static DECODERS: Lazy<Option<Vec<Encoder>>> =
+    Lazy::new(|| Some(list("-decoders")?.lines().filter_map(parse_encoder_line).collect()));
+static FORMATS: Lazy<Option<Vec<Format>>> =
+    Lazy::new(|| Some(list("-formats")?.lines().filter_map(parse_format_line).collect()));
+static FILTERS: Lazy<Option<Vec<Filter>>> =
+    Lazy::new(|| Some(list("-filters")?.lines().filter_map(parse_filter_line).collect()));
+static HWACCELS: Lazy<Option<Vec<String>>> = Lazy::new(|| Some(parse_hwaccels(&list("-hwaccels")?)));
+static VERSION: Lazy<Option<VersionInfo>> = Lazy::new(|| {
+    let output = Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    Some(VersionInfo {
+        text: parse_version_line(text.lines().next()?)?,
+        configuration: parse_configuration(&text),
+    })
+});
+
+struct VersionInfo {
+    text: String,
+    configuration: Vec<String>,
+}
+
+/// An ffmpeg release number. They compare in release order, so feature
+/// code can check it against a minimum release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FfmpegVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FfmpegVersion {
+    /// Reads `6.1.1` and the forms packagers wrap it in: `n6.1.1`,
+    /// `6.1.1-3ubuntu5`, `n6.1.1-7+b1`. Git snapshots (`N-113000-g...`)
+    /// carry no release number and give `None`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.strip_prefix('n').unwrap_or(text);
+        let release = text
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?;
+        let mut parts = release.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let mut next = || parts.next().map_or(Some(0), |part| part.parse().ok());
+        let minor = next()?;
+        let patch = next()?;
+        Some(Self { major, minor, patch })
+    }
+}
+
+impl fmt::Display for FfmpegVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if self.patch != 0 {
+            write!(f, ".{}", self.patch)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoder {
+    pub name: String,
+    pub kind: StreamKind,
+    /// The codec it writes, e.g. `h264` for libx264. `-c:v h264` also
+    /// works and picks an encoder for it.
+    pub codec: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub name: String,
+    /// What it takes and gives, e.g. `V->V` or `A->N` (`N`: any number,
+    /// `|`: none).
+    pub pads: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Format {
+    /// One or more comma-separated names, e.g. `matroska,webm`.
+    pub name: String,
+    pub demux: bool,
+    pub mux: bool,
+    pub description: String,
+}
+
+/// One row of `ffmpeg -encoders`; the legend and header give `None`.
+pub fn parse_encoder_line(line: &str) -> Option<Encoder> {
+    let capture = RE_ENCODER.captures(line)?;
+    if &capture[2] == "=" {
+        return None;
+    }
+    let description = capture[3].trim();
+    Some(Encoder {
+        name: capture[2].to_string(),
+        kind: match &capture[1] {
+            "V" => StreamKind::Video,
+            "A" => StreamKind::Audio,
+            _ => StreamKind::Subtitle,
+        },
+        codec: RE_CODEC
+            .captures(description)
+            .map_or(&capture[2], |codec| codec.get(1).unwrap().as_str())
+            .to_string(),
+        description: description.to_string(),
+    })
+}
+
+/// One row of `ffmpeg -formats`; the legend and header give `None`.
+pub fn parse_format_line(line: &str) -> Option<Format> {
+    let capture = RE_FORMAT.captures(line)?;
+    if &capture[3] == "=" || (&capture[1] == " " && &capture[2] == " ") {
+        return None;
+    }
+    Some(Format {
+        name: capture[3].to_string(),
+        demux: &capture[1] == "D",
+        mux: &capture[2] == "E",
+        description: capture[4].trim().to_string(),
+    })
+}
+
+/// One row of `ffmpeg -filters`; the legend gives `None`.
+pub fn parse_filter_line(line: &str) -> Option<Filter> {
+    let capture = RE_FILTER.captures(line)?;
+    Some(Filter {
+        name: capture[1].to_string(),
+        pads: capture[2].to_string(),
+        description: capture[3].trim().to_string(),
+    })
+}
+
+/// The methods `ffmpeg -hwaccels` lists under its heading.
+pub fn parse_hwaccels(text: &str) -> Vec<String> {
+    text.lines()
+        .skip_while(|line| !line.ends_with(':'))
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The version from `ffmpeg version 6.1.1-3ubuntu5 Copyright ...`.
+pub fn parse_version_line(line: &str) -> Option<String> {
+    let mut words = line.split_whitespace();
+    words.find(|word| *word == "version")?;
+    words.next().map(str::to_string)
+}
+
+/// The `--enable-...` options from the `configuration:` line of
+/// `ffmpeg -version`, without the prefix, e.g. `libx264`.
+pub fn parse_configuration(text: &str) -> Vec<String> {
+    text.lines()
+        .find_map(|line| line.trim().strip_prefix("configuration:"))
+        .map(|line| {
+            line.split_whitespace()
+                .filter_map(|option| option.strip_prefix("--enable-"))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Encoders of the ffmpeg on `PATH`, asked once per process. `None` when
+/// ffmpeg could not be run.
+pub fn encoders() -> Option<&'static [Encoder]> {
+    ENCODERS.as_deref()
+}
+
+/// Decoders of the ffmpeg on `PATH`, asked once per process. Their rows
+/// look like encoder rows, so they share the type.
+#[allow(dead_code)]
+pub fn decoders() -> Option<&'static [Encoder]> {
+    DECODERS.as_deref()
+}
+
+/// Container formats of the ffmpeg on `PATH`, asked once per process.
+pub fn formats() -> Option<&'static [Format]> {
+    FORMATS.as_deref()
+}
+
+/// Filters of the ffmpeg on `PATH`, asked once per process.
+pub fn filters() -> Option<&'static [Filter]> {
+    FILTERS.as_deref()
+}
+
+/// Whether the ffmpeg on `PATH` was built with this filter. A missing
+/// ffmpeg has no filters.
+pub fn has_filter(name: &str) -> bool {
+    filters().is_some_and(|filters| filters.iter().any(|filter| filter.name == name))
+}
+
+/// Hardware acceleration methods (`cuda`, `vaapi`, ...) of the ffmpeg on
+/// `PATH`, asked once per process.
+pub fn hwaccels() -> Option<&'static [String]> {
+    HWACCELS.as_deref()
+}
+
+/// The version the ffmpeg on `PATH` reports, as written, e.g.
+/// `6.1.1-3ubuntu5`. Runs `ffmpeg -version` the first time.
+pub fn ffmpeg_version() -> Option<&'static str> {
+    VERSION.as_ref().map(|info| info.text.as_str())
+}
+
+/// The release of the ffmpeg on `PATH`; `None` when ffmpeg did not run or
+/// is a git snapshot.
+pub fn ffmpeg_release() -> Option<FfmpegVersion> {
+    ffmpeg_version().and_then(FfmpegVersion::parse)
+}
+
+/// `ffmpeg_version` if it is already known, without waiting for ffmpeg.
+pub fn known_ffmpeg_version() -> Option<&'static str> {
+    Lazy::get(&VERSION)?.as_ref().map(|info| info.text.as_str())
+}
+
+/// Asks for the ffmpeg version on another thread, so it is known by the
+/// time something needs it.
+pub fn detect_version_in_background() {
+    thread::spawn(|| Lazy::force(&VERSION));
+}
+
+/// The `--enable-...` options ffmpeg was built with, without the prefix.
+pub fn build_configuration() -> Option<&'static [String]> {
+    VERSION.as_ref().map(|info| info.configuration.as_slice())
+}
+
+/// Where `program` would be found on `PATH`.
+pub fn find_on_path(program: &str) -> Option<PathBuf> {
+    let program = format!("{program}{}", env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&program))
+        .find(|path| path.is_file())
+}
+
+fn list(flag: &str) -> Option<String> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", flag])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
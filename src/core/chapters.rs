@@ -0,0 +1,28 @@
+use crate::core::metadata::Chapter;
+
+/// Chapters as an ffmetadata file, which ffmpeg reads back as an input
+/// (`-i chapters.txt -map_chapters N`). Times are in milliseconds.
+pub fn to_ffmetadata(chapters: &[Chapter]) -> String {
+    let mut text = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        text.push_str("\n[CHAPTER]\nTIMEBASE=1/1000\n");
+        text.push_str(&format!("START={}\n", chapter.start.as_millis()));
+        text.push_str(&format!("END={}\n", chapter.end.as_millis()));
+        if let Some(title) = &chapter.title {
+            text.push_str(&format!("title={}\n", escape_ffmetadata(title)));
+        }
+    }
+    text
+}
+
+// `=`, `;`, `#`, `\` and newlines are special in ffmetadata values.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
@@ -0,0 +1,73 @@
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::core::shell::{os_bytes, os_from_bytes, trim_os};
+
+/// Queued commands that have already finished, one per line in a file, so
+/// a batch that was cut short can be run again without redoing them.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    done: HashSet<OsString>,
+    skipped: Vec<OsString>,
+}
+
+impl Checkpoint {
+    /// Reads the commands recorded so far; a file that does not exist yet
+    /// is an empty checkpoint.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let done = match fs::read(path) {
+            Ok(bytes) => bytes
+                .split(|byte| *byte == b'\n')
+                .map(|line| trim_os(&os_from_bytes(line.to_vec())))
+                .filter(|line| !line.is_empty())
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            done,
+            skipped: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_done(&self, line: &OsStr) -> bool {
+        self.done.contains(&trim_os(line))
+    }
+
+    /// Drops the commands that are already done, keeping the rest in order.
+    pub fn filter(&mut self, queue: Vec<OsString>) -> Vec<OsString> {
+        let (done, queue): (Vec<OsString>, Vec<OsString>) =
+            queue.into_iter().partition(|line| self.is_done(line));
+        self.skipped.extend(done);
+        queue
+    }
+
+    /// The commands `filter` has dropped.
+    pub fn skipped(&self) -> &[OsString] {
+        &self.skipped
+    }
+
+    /// Appends `line` to the file as done.
+    pub fn record(&mut self, line: &OsStr) -> io::Result<()> {
+        let line = trim_os(line);
+        if !self.done.insert(line.clone()) {
+            return Ok(());
+        }
+        let mut entry = os_bytes(&line).into_owned();
+        entry.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&entry)
+    }
+}
@@ -0,0 +1,101 @@
+use std::ffi::OsString;
+
+/// Common values, so a typo gets a warning before ffmpeg rejects it. ffmpeg
+/// knows more than these, so anything else is still passed through.
+pub const PIX_FMTS: &[&str] = &[
+    "yuv420p",
+    "yuvj420p",
+    "yuv422p",
+    "yuv444p",
+    "yuv420p10le",
+    "yuv422p10le",
+    "yuv444p10le",
+    "yuv420p12le",
+    "nv12",
+    "p010le",
+    "rgb24",
+    "rgba",
+    "gbrp",
+    "gray",
+];
+pub const COLORSPACES: &[&str] = &[
+    "bt709",
+    "bt2020nc",
+    "bt2020c",
+    "bt470bg",
+    "smpte170m",
+    "smpte240m",
+    "fcc",
+    "ycgco",
+    "gbr",
+];
+pub const COLOR_PRIMARIES: &[&str] = &[
+    "bt709",
+    "bt2020",
+    "bt470m",
+    "bt470bg",
+    "smpte170m",
+    "smpte240m",
+    "film",
+    "smpte428",
+    "smpte431",
+    "smpte432",
+];
+pub const COLOR_TRCS: &[&str] = &[
+    "bt709",
+    "smpte2084",
+    "arib-std-b67",
+    "linear",
+    "gamma22",
+    "gamma28",
+    "smpte170m",
+    "smpte240m",
+    "iec61966-2-1",
+    "bt2020-10",
+    "bt2020-12",
+];
+
+/// Output pixel format and color tags. The tags only label the stream;
+/// converting between color spaces takes a filter such as `zscale`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorOptions {
+    pub pix_fmt: Option<String>,
+    pub colorspace: Option<String>,
+    pub primaries: Option<String>,
+    pub trc: Option<String>,
+}
+
+impl ColorOptions {
+    pub fn args(&self) -> Vec<OsString> {
+        let options = [
+            ("-pix_fmt", &self.pix_fmt),
+            ("-colorspace", &self.colorspace),
+            ("-color_primaries", &self.primaries),
+            ("-color_trc", &self.trc),
+        ];
+        options
+            .into_iter()
+            .filter_map(|(flag, value)| value.as_ref().map(|value| [flag.into(), value.into()]))
+            .flatten()
+            .collect()
+    }
+
+    /// One warning per value not in the lists above.
+    pub fn warnings(&self) -> Vec<String> {
+        let checks = [
+            ("--pix-fmt", &self.pix_fmt, PIX_FMTS),
+            ("--colorspace", &self.colorspace, COLORSPACES),
+            ("--color-primaries", &self.primaries, COLOR_PRIMARIES),
+            ("--color-trc", &self.trc, COLOR_TRCS),
+        ];
+        checks
+            .into_iter()
+            .filter_map(|(flag, value, known)| {
+                let value = value.as_deref()?;
+                (!known.contains(&value)).then(|| {
+                    format!("warning: {flag} '{value}' is not a common value; passing it to ffmpeg as is")
+                })
+            })
+            .collect()
+    }
+}
@@ -1,40 +1,509 @@
-#[derive(Debug, Clone)]
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::color::ColorOptions;
+use crate::core::concat::ConcatList;
+
+#[derive(Debug, Clone, Default)]
 pub struct FfmpegCommand {
-    pub inputs: Vec<String>,
-    pub output: String,
+    pub inputs: Vec<PathBuf>,
+    /// Options placed right before each input's `-i`, by input index; may be
+    /// shorter than `inputs`.
+    pub input_args: Vec<Vec<String>>,
+    pub output: PathBuf,
     pub video_codec: Option<String>,
     pub audio_codec: Option<String>,
+    pub subtitle_codec: Option<String>,
     pub preset: Option<String>,
+    /// Chained into a single `-vf`, in order.
+    pub video_filters: Vec<String>,
+    /// Chained into a single `-af`, in order.
+    pub audio_filters: Vec<String>,
+    /// A graph over several inputs; its output pads are picked with `maps`.
+    pub filter_complex: Option<String>,
     pub extra_args: Vec<String>,
+    /// Start offset. Placed before the first `-i` (fast keyframe seek)
+    /// unless `accurate_seek` is set, which decodes up to the exact frame.
+    pub seek: Option<Duration>,
+    pub accurate_seek: bool,
+    /// Read the first input at its native frame rate (`-re`).
+    pub realtime: bool,
+    /// Extra times to play the first input (`-stream_loop`).
+    pub stream_loop: Option<u32>,
+    /// Frame rate of an image-sequence first input (`-framerate`).
+    pub framerate: Option<f64>,
+    /// The first input is a `*` wildcard for the image2 demuxer to expand
+    /// (`-pattern_type glob`), not a file name.
+    pub glob_input: bool,
+    /// Caps the video bitrate (`-maxrate`), with a two-second buffer.
+    pub max_rate_kbps: Option<u32>,
+    pub color: ColorOptions,
+    /// Container tags (`-metadata key=value`), in order.
+    pub metadata: Vec<(String, String)>,
+    /// `-map` specs, in order. Empty means ffmpeg's default stream choice.
+    pub maps: Vec<String>,
+    pub no_video: bool,
+    pub no_audio: bool,
+    pub no_subs: bool,
+    /// Reads the inputs through this concat-demuxer list instead of one
+    /// `-i` each, joining them back to back.
+    pub concat_list: Option<Arc<ConcatList>>,
+    /// Written from the same decode after `output`, each with its own
+    /// options; the fields above only apply to `output`.
+    pub extra_outputs: Vec<OutputSpec>,
+}
+
+/// One more output of a command and the options that go before it.
+#[derive(Debug, Clone, Default)]
+pub struct OutputSpec {
+    pub path: PathBuf,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub video_filters: Vec<String>,
+    pub audio_filters: Vec<String>,
+    pub maps: Vec<String>,
+    pub args: Vec<String>,
+}
+
+impl OutputSpec {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+
+    fn push_args(&self, args: &mut Vec<OsString>) {
+        let flags = [
+            ("-c:v", self.video_codec.clone()),
+            ("-c:a", self.audio_codec.clone()),
+            ("-vf", (!self.video_filters.is_empty()).then(|| self.video_filters.join(","))),
+            ("-af", (!self.audio_filters.is_empty()).then(|| self.audio_filters.join(","))),
+        ];
+        for (flag, value) in flags {
+            if let Some(value) = value {
+                args.push(flag.into());
+                args.push(value.into());
+            }
+        }
+        for spec in &self.maps {
+            args.push("-map".into());
+            args.push(spec.into());
+        }
+        args.extend(self.args.iter().map(OsString::from));
+        args.push(self.path.clone().into_os_string());
+    }
 }
 
 impl FfmpegCommand {
-    pub fn to_args(&self) -> Vec<String> {
-        let mut args = Vec::new();
+    pub fn to_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = Vec::new();
+        let seek = self.seek.map(|offset| format!("{:.3}", offset.as_secs_f64()));
+
+        if let (Some(offset), false) = (&seek, self.accurate_seek) {
+            args.push("-ss".into());
+            args.push(offset.into());
+        }
+
+        if self.realtime {
+            args.push("-re".into());
+        }
+
+        if let Some(count) = self.stream_loop {
+            args.push("-stream_loop".into());
+            args.push(count.to_string().into());
+        }
+
+        if let Some(rate) = self.framerate {
+            args.push("-framerate".into());
+            args.push(rate.to_string().into());
+        }
+
+        if self.glob_input {
+            args.extend(["-pattern_type", "glob"].map(OsString::from));
+        }
 
-        for input in &self.inputs {
-            args.push("-i".to_string());
-            args.push(input.clone());
+        let input_args = |index: usize| self.input_args.get(index).into_iter().flatten();
+        if let Some(list) = &self.concat_list {
+            args.extend(input_args(0).map(OsString::from));
+            args.extend(["-f", "concat", "-safe", "0", "-i"].map(OsString::from));
+            args.push(list.path().into());
+        } else {
+            for (index, input) in self.inputs.iter().enumerate() {
+                args.extend(input_args(index).map(OsString::from));
+                args.push("-i".into());
+                args.push(input.clone().into_os_string());
+            }
+        }
+
+        if let (Some(offset), true) = (&seek, self.accurate_seek) {
+            args.push("-ss".into());
+            args.push(offset.into());
+        }
+
+        if let Some(graph) = &self.filter_complex {
+            args.push("-filter_complex".into());
+            args.push(graph.into());
         }
 
         if let Some(codec) = &self.video_codec {
-            args.push("-c:v".to_string());
-            args.push(codec.clone());
+            args.push("-c:v".into());
+            args.push(codec.into());
         }
 
         if let Some(codec) = &self.audio_codec {
-            args.push("-c:a".to_string());
-            args.push(codec.clone());
+            args.push("-c:a".into());
+            args.push(codec.into());
+        }
+
+        if let Some(codec) = &self.subtitle_codec {
+            args.push("-c:s".into());
+            args.push(codec.into());
         }
 
         if let Some(preset) = &self.preset {
-            args.push("-preset".to_string());
-            args.push(preset.clone());
+            args.push("-preset".into());
+            args.push(preset.into());
+        }
+
+        if !self.video_filters.is_empty() {
+            args.push("-vf".into());
+            args.push(self.video_filters.join(",").into());
+        }
+
+        if !self.audio_filters.is_empty() {
+            args.push("-af".into());
+            args.push(self.audio_filters.join(",").into());
+        }
+
+        args.extend(self.color.args());
+
+        for spec in &self.maps {
+            args.push("-map".into());
+            args.push(spec.into());
+        }
+
+        if self.no_video {
+            args.push("-vn".into());
+        }
+
+        if self.no_audio {
+            args.push("-an".into());
+        }
+
+        if self.no_subs {
+            args.push("-sn".into());
+        }
+
+        if let Some(kbps) = self.max_rate_kbps {
+            args.push("-maxrate".into());
+            args.push(format!("{kbps}k").into());
+            args.push("-bufsize".into());
+            args.push(format!("{}k", kbps * 2).into());
         }
 
-        args.extend(self.extra_args.iter().cloned());
-        args.push(self.output.clone());
+        for (key, value) in &self.metadata {
+            args.push("-metadata".into());
+            args.push(format!("{key}={value}").into());
+        }
+
+        args.extend(self.extra_args.iter().map(OsString::from));
+        args.push(self.output.clone().into_os_string());
+
+        for output in &self.extra_outputs {
+            output.push_args(&mut args);
+        }
 
         args
     }
 }
+
+/// Parses a bitrate such as `800k`, `2M` or `1.5M` into kb/s. A unit is
+/// required, since a bare number means bits to ffmpeg but is easily meant
+/// as kilobits.
+pub fn parse_rate_kbps(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let (number, multiplier) = if let Some(mbps) = value.strip_suffix(['M', 'm']) {
+        (mbps, 1000.0)
+    } else {
+        (value.strip_suffix(['k', 'K'])?, 1.0)
+    };
+    let kbps = (number.parse::<f64>().ok()? * multiplier).round();
+    (kbps.is_finite() && kbps >= 1.0 && kbps <= f64::from(u32::MAX / 2)).then_some(kbps as u32)
+}
+
+/// Removes every `-af`/`-filter:a` pair from pass-through arguments and
+/// returns their filters, so they can join one chain instead of the last
+/// one winning.
+pub fn take_audio_filters(extra_args: &mut Vec<String>) -> Vec<String> {
+    let mut filters = Vec::new();
+    let mut kept = Vec::with_capacity(extra_args.len());
+    let mut args = std::mem::take(extra_args).into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "-af" || arg == "-filter:a" {
+            if let Some(filter) = args.next() {
+                filters.push(filter);
+                continue;
+            }
+        }
+        kept.push(arg);
+    }
+    *extra_args = kept;
+    filters
+}
+
+/// Escapes a value for use as a filter option inside a filtergraph, e.g. a
+/// path for `subtitles=filename=...`. ffmpeg unescapes twice: once for the
+/// graph (`,` `;` `[` `]` end filters) and once for the option list (`:`
+/// ends an option), and `\` and `'` are special at both levels.
+pub fn escape_filter_value(value: &str) -> String {
+    let mut option = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(ch);
+    }
+    let mut graph = String::with_capacity(option.len());
+    for ch in option.chars() {
+        if matches!(ch, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(ch);
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(command: &FfmpegCommand) -> Vec<String> {
+        command
+            .to_args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn command(inputs: &[&str]) -> FfmpegCommand {
+        FfmpegCommand {
+            inputs: inputs.iter().map(PathBuf::from).collect(),
+            output: PathBuf::from("out.mp4"),
+            ..FfmpegCommand::default()
+        }
+    }
+
+    #[test]
+    fn plain_transcode() {
+        assert_eq!(argv(&command(&["in.mov"])), ["-i", "in.mov", "out.mp4"]);
+    }
+
+    #[test]
+    fn first_input_options_come_before_any_input() {
+        let command = FfmpegCommand {
+            seek: Some(Duration::from_millis(1500)),
+            realtime: true,
+            stream_loop: Some(2),
+            framerate: Some(24.0),
+            glob_input: true,
+            input_args: vec![vec!["-f".into(), "image2".into()]],
+            ..command(&["frames/*.png"])
+        };
+        assert_eq!(
+            argv(&command),
+            [
+                "-ss", "1.500", "-re", "-stream_loop", "2", "-framerate", "24", "-pattern_type",
+                "glob", "-f", "image2", "-i", "frames/*.png", "out.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn input_args_go_before_their_own_input() {
+        let command = FfmpegCommand {
+            input_args: vec![Vec::new(), vec!["-itsoffset".into(), "0.5".into()]],
+            ..command(&["video.mp4", "voice.wav", "logo.png"])
+        };
+        assert_eq!(
+            argv(&command),
+            [
+                "-i", "video.mp4", "-itsoffset", "0.5", "-i", "voice.wav", "-i", "logo.png",
+                "out.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn accurate_seek_goes_after_the_inputs() {
+        let command = FfmpegCommand {
+            seek: Some(Duration::from_secs(90)),
+            accurate_seek: true,
+            ..command(&["in.mov"])
+        };
+        assert_eq!(argv(&command), ["-i", "in.mov", "-ss", "90.000", "out.mp4"]);
+    }
+
+    #[test]
+    fn output_options_in_order() {
+        let command = FfmpegCommand {
+            filter_complex: Some("[0:v]split[a][b]".into()),
+            video_codec: Some("libx264".into()),
+            audio_codec: Some("aac".into()),
+            subtitle_codec: Some("mov_text".into()),
+            preset: Some("slow".into()),
+            video_filters: vec!["scale=1280:-2".into(), "fps=30".into()],
+            audio_filters: vec!["loudnorm".into()],
+            color: ColorOptions {
+                pix_fmt: Some("yuv420p".into()),
+                trc: Some("bt709".into()),
+                ..ColorOptions::default()
+            },
+            maps: vec!["[a]".into(), "0:a".into()],
+            no_video: true,
+            no_audio: true,
+            no_subs: true,
+            max_rate_kbps: Some(2500),
+            metadata: vec![("title".into(), "My Film".into())],
+            extra_args: vec!["-movflags".into(), "+faststart".into()],
+            ..command(&["in.mov"])
+        };
+        assert_eq!(
+            argv(&command),
+            [
+                "-i", "in.mov",
+                "-filter_complex", "[0:v]split[a][b]",
+                "-c:v", "libx264", "-c:a", "aac", "-c:s", "mov_text", "-preset", "slow",
+                "-vf", "scale=1280:-2,fps=30", "-af", "loudnorm",
+                "-pix_fmt", "yuv420p", "-color_trc", "bt709",
+                "-map", "[a]", "-map", "0:a",
+                "-vn", "-an", "-sn",
+                "-maxrate", "2500k", "-bufsize", "5000k",
+                "-metadata", "title=My Film",
+                "-movflags", "+faststart",
+                "out.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn audio_filters_are_taken_out_of_pass_through_args() {
+        let mut extra: Vec<String> = [
+            "-af", "volume=2", "-movflags", "+faststart", "-filter:a", "aresample=48000", "-af",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(take_audio_filters(&mut extra), ["volume=2", "aresample=48000"]);
+        // A trailing flag without a value is left for ffmpeg to reject.
+        assert_eq!(extra, ["-movflags", "+faststart", "-af"]);
+
+        let mut none = vec!["-shortest".to_string()];
+        assert!(take_audio_filters(&mut none).is_empty());
+        assert_eq!(none, ["-shortest"]);
+    }
+
+    #[test]
+    fn rates_with_units() {
+        assert_eq!(parse_rate_kbps("800k"), Some(800));
+        assert_eq!(parse_rate_kbps("800K"), Some(800));
+        assert_eq!(parse_rate_kbps(" 64k "), Some(64));
+        assert_eq!(parse_rate_kbps("2M"), Some(2000));
+        assert_eq!(parse_rate_kbps("2m"), Some(2000));
+        assert_eq!(parse_rate_kbps("1.5M"), Some(1500));
+        assert_eq!(parse_rate_kbps("0.75k"), Some(1));
+    }
+
+    #[test]
+    fn rates_without_a_known_unit_are_rejected() {
+        // Bits to ffmpeg, so almost certainly not what was meant.
+        assert_eq!(parse_rate_kbps("800"), None);
+        assert_eq!(parse_rate_kbps("800kb"), None);
+        assert_eq!(parse_rate_kbps("2G"), None);
+        assert_eq!(parse_rate_kbps("k"), None);
+        assert_eq!(parse_rate_kbps(""), None);
+    }
+
+    #[test]
+    fn rates_out_of_range_are_rejected() {
+        assert_eq!(parse_rate_kbps("0k"), None);
+        assert_eq!(parse_rate_kbps("0.2k"), None);
+        assert_eq!(parse_rate_kbps("-5M"), None);
+        assert_eq!(parse_rate_kbps("NaNk"), None);
+        assert_eq!(parse_rate_kbps("infM"), None);
+        assert_eq!(parse_rate_kbps("9999999M"), None);
+    }
+
+    #[test]
+    fn filter_values_are_escaped_for_both_levels() {
+        assert_eq!(escape_filter_value("movie subs.srt"), "movie subs.srt");
+        // `'` becomes `\'` for the option, then both are escaped for the graph.
+        assert_eq!(escape_filter_value("My Film's subs.srt"), r"My Film\\\'s subs.srt");
+        assert_eq!(escape_filter_value(r"C:\subs\a,b.srt"), r"C\\:\\\\subs\\\\a\,b.srt");
+        assert_eq!(escape_filter_value("[x];y"), r"\[x\]\;y");
+    }
+
+    #[test]
+    fn extra_outputs_carry_their_own_options() {
+        let command = FfmpegCommand {
+            video_codec: Some("libx264".into()),
+            maps: vec!["0:v".into()],
+            extra_outputs: vec![
+                OutputSpec {
+                    video_codec: Some("libvpx-vp9".into()),
+                    audio_codec: Some("libopus".into()),
+                    video_filters: vec!["scale=-2:480".into()],
+                    maps: vec!["0:v".into(), "0:a".into()],
+                    args: vec!["-b:v".into(), "1M".into()],
+                    ..OutputSpec::new("small.webm")
+                },
+                OutputSpec {
+                    audio_filters: vec!["aresample=44100".into()],
+                    maps: vec!["0:a".into()],
+                    ..OutputSpec::new("audio.m4a")
+                },
+            ],
+            ..command(&["in.mov"])
+        };
+        assert_eq!(
+            argv(&command),
+            [
+                "-i", "in.mov", "-c:v", "libx264", "-map", "0:v", "out.mp4",
+                "-c:v", "libvpx-vp9", "-c:a", "libopus", "-vf", "scale=-2:480",
+                "-map", "0:v", "-map", "0:a", "-b:v", "1M", "small.webm",
+                "-af", "aresample=44100", "-map", "0:a", "audio.m4a",
+            ]
+        );
+    }
+
+    #[test]
+    fn non_ascii_paths_pass_through_unchanged() {
+        let command = FfmpegCommand {
+            extra_outputs: vec![OutputSpec::new("Überblick/résumé 日本.webm")],
+            ..command(&["Übung/Café – 東京.mov"])
+        };
+        let args = command.to_args();
+        assert_eq!(args[1], OsString::from("Übung/Café – 東京.mov"));
+        assert_eq!(args[3], OsString::from("Überblick/résumé 日本.webm"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_keep_their_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        // Latin-1 `café.mov` and `naïve.mp4`, as left behind by old tools.
+        let input = OsStr::from_bytes(b"caf\xe9.mov");
+        let output = OsStr::from_bytes(b"na\xefve.mp4");
+        assert!(input.to_str().is_none());
+        let command = FfmpegCommand {
+            inputs: vec![PathBuf::from(input)],
+            output: PathBuf::from(output),
+            ..FfmpegCommand::default()
+        };
+        let args: Vec<Vec<u8>> = command.to_args().into_iter().map(OsString::into_vec).collect();
+        assert_eq!(args, [b"-i".to_vec(), b"caf\xe9.mov".to_vec(), b"na\xefve.mp4".to_vec()]);
+    }
+}
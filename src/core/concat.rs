@@ -0,0 +1,114 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_LIST: AtomicUsize = AtomicUsize::new(0);
+
+/// A concat-demuxer list file in the temp dir. The file is removed when the
+/// list is dropped, so keep it alive until ffmpeg has exited.
+#[derive(Debug)]
+pub struct ConcatList {
+    path: PathBuf,
+}
+
+impl ConcatList {
+    pub fn create(inputs: &[PathBuf]) -> io::Result<Self> {
+        let contents = concat_list_contents(inputs)?;
+        let path = std::env::temp_dir().join(format!(
+            "ffflow-concat-{}-{}.txt",
+            std::process::id(),
+            NEXT_LIST.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, contents)?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ConcatList {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Renders one `file '<path>'` line per input. Relative paths would resolve
+/// against the list's own directory, so they are made absolute first.
+pub fn concat_list_contents(inputs: &[PathBuf]) -> io::Result<Vec<u8>> {
+    let cwd = std::env::current_dir()?;
+    let mut contents = Vec::new();
+    for input in inputs {
+        let path = cwd.join(input);
+        contents.extend_from_slice(b"file '");
+        for &byte in path.as_os_str().as_encoded_bytes() {
+            if byte == b'\'' {
+                contents.extend_from_slice(b"'\\''");
+            } else {
+                contents.push(byte);
+            }
+        }
+        contents.extend_from_slice(b"'\n");
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::core::command::FfmpegCommand;
+
+    #[test]
+    fn list_quotes_each_path() {
+        let inputs = [PathBuf::from("/clips/part 1.mp4"), PathBuf::from("/clips/Bob's cut.mp4")];
+        let contents = concat_list_contents(&inputs).unwrap();
+        assert_eq!(
+            String::from_utf8(contents).unwrap(),
+            "file '/clips/part 1.mp4'\nfile '/clips/Bob'\\''s cut.mp4'\n"
+        );
+    }
+
+    #[test]
+    fn relative_paths_are_made_absolute() {
+        let contents = concat_list_contents(&[PathBuf::from("a.mp4")]).unwrap();
+        let absolute = std::env::current_dir().unwrap().join("a.mp4");
+        let expected = format!("file '{}'\n", absolute.display());
+        assert_eq!(String::from_utf8(contents).unwrap(), expected);
+    }
+
+    #[test]
+    fn list_file_lives_as_long_as_the_list() {
+        let inputs = [PathBuf::from("/clips/a.mp4"), PathBuf::from("/clips/b.mp4")];
+        let list = ConcatList::create(&inputs).unwrap();
+        let path = list.path().to_path_buf();
+        assert!(path.starts_with(std::env::temp_dir()));
+        assert_eq!(fs::read(&path).unwrap(), concat_list_contents(&inputs).unwrap());
+        drop(list);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn command_reads_inputs_through_the_list() {
+        let inputs = vec![PathBuf::from("/clips/a.mp4"), PathBuf::from("/clips/b.mp4")];
+        let list = Arc::new(ConcatList::create(&inputs).unwrap());
+        let command = FfmpegCommand {
+            inputs,
+            input_args: vec![vec!["-auto_convert".into(), "1".into()]],
+            output: PathBuf::from("joined.mp4"),
+            video_codec: Some("copy".into()),
+            concat_list: Some(Arc::clone(&list)),
+            ..FfmpegCommand::default()
+        };
+        let expected: Vec<OsString> = ["-auto_convert", "1", "-f", "concat", "-safe", "0", "-i"]
+            .iter()
+            .map(OsString::from)
+            .chain([list.path().into(), "-c:v".into(), "copy".into(), "joined.mp4".into()])
+            .collect();
+        assert_eq!(command.to_args(), expected);
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Overrides where the config file is read from and written to.
+pub const CONFIG_ENV: &str = "FFFLOW_CONFIG";
+
+/// Settings kept between sessions in `config.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Used by `encode` when no `--vcodec` (or profile) picks one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_vcodec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_acodec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_preset: Option<String>,
+    /// Name to the command line it stands for, e.g.
+    /// `x265 = "encode --vcodec libx265 --preset slow"`.
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// `$FFFLOW_CONFIG`, else `ffflow/config.toml` under the user's config
+/// directory. `None` when no home directory is known.
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(CONFIG_ENV).filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("ffflow").join("config.toml"))
+}
+
+impl Config {
+    /// Reads the file at `path`; a file that does not exist yet gives the
+    /// defaults.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(format!("cannot read '{}': {err}", path.display())),
+        };
+        toml::from_str(&text).map_err(|err| format!("invalid config '{}': {err}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|err| err.to_string())?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|err| format!("cannot create '{}': {err}", dir.display()))?;
+        }
+        fs::write(path, text).map_err(|err| format!("cannot write '{}': {err}", path.display()))
+    }
+}
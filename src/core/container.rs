@@ -0,0 +1,46 @@
+/// Whether audio in `codec` can be stream-copied into a file with this
+/// extension. `None` for containers this table does not know.
+pub fn audio_copy_fits(ext: &str, codec: &str) -> Option<bool> {
+    let ext = ext.to_ascii_lowercase();
+    let fits = match ext.as_str() {
+        "mka" | "mkv" | "nut" => true,
+        "m4a" | "mp4" | "mov" => matches!(
+            codec,
+            "aac" | "alac" | "mp3" | "ac3" | "eac3" | "opus" | "flac"
+        ),
+        "mp3" => codec == "mp3",
+        "aac" | "adts" => codec == "aac",
+        "flac" => codec == "flac",
+        "opus" => codec == "opus",
+        "ogg" | "oga" => matches!(codec, "vorbis" | "opus" | "flac"),
+        "wav" => codec.starts_with("pcm_"),
+        "ac3" => codec == "ac3",
+        _ => return None,
+    };
+    Some(fits)
+}
+
+/// A sensible encoder for an audio-only file with this extension.
+pub fn suggested_audio_encoder(ext: &str) -> Option<&'static str> {
+    let encoder = match ext.to_ascii_lowercase().as_str() {
+        "mp3" => "libmp3lame",
+        "m4a" | "mp4" | "mov" | "aac" | "adts" => "aac",
+        "flac" => "flac",
+        "opus" => "libopus",
+        "ogg" | "oga" => "libvorbis",
+        "wav" => "pcm_s16le",
+        "ac3" => "ac3",
+        _ => return None,
+    };
+    Some(encoder)
+}
+
+/// Subtitle codec for soft subs in a container that cannot take text
+/// subtitles as they are. `None` means copying works.
+pub fn required_subtitle_codec(ext: &str) -> Option<&'static str> {
+    match ext.to_ascii_lowercase().as_str() {
+        "mp4" | "m4v" | "mov" => Some("mov_text"),
+        "webm" => Some("webvtt"),
+        _ => None,
+    }
+}
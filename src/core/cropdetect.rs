@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// A crop rectangle as `cropdetect` reports it: size first, then offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Crop {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Crop {
+    /// The `crop=W:H:X:Y` filter for this rectangle.
+    pub fn filter(&self) -> String {
+        format!("crop={self}")
+    }
+}
+
+impl fmt::Display for Crop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    /// Accepts `W:H:X:Y`, with or without a leading `crop=`.
+    fn from_str(value: &str) -> Result<Self, String> {
+        let spec = value.strip_prefix("crop=").unwrap_or(value);
+        let parts: Vec<u32> = spec
+            .split(':')
+            .map(|part| part.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid crop '{value}' (expected W:H:X:Y)"))?;
+        match parts[..] {
+            [width, height, x, y] if width > 0 && height > 0 => Ok(Self {
+                width,
+                height,
+                x,
+                y,
+            }),
+            _ => Err(format!("invalid crop '{value}' (expected W:H:X:Y)")),
+        }
+    }
+}
+
+/// Decodes the first `sample` of the input through `cropdetect` and
+/// discards the result.
+pub fn cropdetect_args(input: &Path, sample: Duration) -> Vec<OsString> {
+    vec![
+        "-hide_banner".into(),
+        "-i".into(),
+        input.into(),
+        "-t".into(),
+        format!("{:.3}", sample.as_secs_f64()).into(),
+        "-vf".into(),
+        "cropdetect".into(),
+        "-an".into(),
+        "-f".into(),
+        "null".into(),
+        "-".into(),
+    ]
+}
+
+/// Suggestions before this point are often fades from black or intros and
+/// are only used when nothing later was seen (very short inputs).
+const SETTLE_TIME: f64 = 2.0;
+
+static RE_CROP_LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\bt:\s*(-?[0-9.]+).*\bcrop=(\d+):(\d+):(\d+):(\d+)").unwrap());
+
+/// Tallies the `crop=` suggestions `cropdetect` prints once per frame.
+#[derive(Debug, Default)]
+pub struct CropDetector {
+    settled: HashMap<Crop, usize>,
+    early: HashMap<Crop, usize>,
+}
+
+impl CropDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        let Some(capture) = RE_CROP_LINE.captures(line) else {
+            return;
+        };
+        let number = |idx: usize| capture[idx].parse::<u32>().ok();
+        let (Some(width), Some(height), Some(x), Some(y)) =
+            (number(2), number(3), number(4), number(5))
+        else {
+            return;
+        };
+        if width == 0 || height == 0 {
+            return;
+        }
+        let crop = Crop {
+            width,
+            height,
+            x,
+            y,
+        };
+        let time = capture[1].parse::<f64>().unwrap_or(0.0);
+        let tally = if time < SETTLE_TIME {
+            &mut self.early
+        } else {
+            &mut self.settled
+        };
+        *tally.entry(crop).or_default() += 1;
+    }
+
+    /// The most frequent suggestion with its count and the number of
+    /// samples it was picked from. Ties go to the larger rectangle, so the
+    /// crop never eats into picture that some frames showed.
+    pub fn best(&self) -> Option<(Crop, usize, usize)> {
+        let tally = if self.settled.is_empty() {
+            &self.early
+        } else {
+            &self.settled
+        };
+        let total = tally.values().sum();
+        tally
+            .iter()
+            .max_by_key(|(crop, count)| (**count, crop.width * crop.height))
+            .map(|(crop, count)| (*crop, *count, total))
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+
+use crate::core::queue::split_priority;
+use crate::core::shell::{os_bytes, os_from_bytes, strip_prefix_os, trim_os};
+
+/// The `@job <name> [after <job>, ...]:` part of a `.flw` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobHeader {
+    pub name: String,
+    /// Jobs that must have succeeded before this one starts.
+    pub after: Vec<String>,
+}
+
+/// How a named job ended, for the jobs that come after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+    /// Not run, because a job it comes after didn't succeed.
+    Skipped,
+}
+
+impl Outcome {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::Skipped => "was skipped",
+        }
+    }
+}
+
+/// Splits `@job <name> [after <job>, ...]: <command>` into its header and
+/// command; `None` for a line without one.
+pub fn split_job_header(line: &OsStr) -> Result<Option<(JobHeader, OsString)>, String> {
+    let Some(rest) = strip_prefix_os(line, "@job")
+        .filter(|rest| rest.to_string_lossy().starts_with(char::is_whitespace))
+    else {
+        return Ok(None);
+    };
+    let usage = || "expected @job <name> [after <job>, ...]: <command>".to_string();
+    let bytes = os_bytes(&rest);
+    let colon = bytes.iter().position(|byte| *byte == b':').ok_or_else(usage)?;
+    let header = String::from_utf8_lossy(&bytes[..colon]).into_owned();
+    let command = trim_os(&os_from_bytes(bytes[colon + 1..].to_vec()));
+    if command.is_empty() {
+        return Err(usage());
+    }
+    let mut words = header.split_whitespace();
+    let name = words.next().ok_or_else(usage)?.to_string();
+    validate_job_name(&name)?;
+    let after: Vec<String> = match words.next() {
+        None => Vec::new(),
+        Some("after") => words
+            .flat_map(|word| word.split(','))
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Some(_) => return Err(usage()),
+    };
+    if header.split_whitespace().nth(1).is_some() && after.is_empty() {
+        return Err(usage());
+    }
+    for job in &after {
+        validate_job_name(job)?;
+    }
+    Ok(Some((JobHeader { name, after }, command)))
+}
+
+fn validate_job_name(name: &str) -> Result<(), String> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid && !name.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid job name '{name}' (use letters, digits, '-', '_' and '.')"
+        ))
+    }
+}
+
+/// Orders a `.flw` file's lines so every job comes after the ones it
+/// names, keeping file order otherwise. A line without `@job` comes after
+/// the line above it, as it always has. Unknown names, names used twice
+/// and jobs that wait on each other are errors.
+pub fn order_jobs(lines: Vec<OsString>) -> Result<Vec<OsString>, String> {
+    let mut headers = Vec::with_capacity(lines.len());
+    for line in &lines {
+        let body = split_priority(line).map_or_else(|_| line.clone(), |(_, body)| body);
+        headers.push(split_job_header(&body)?.map(|(header, _)| header));
+    }
+    if headers.iter().all(Option::is_none) {
+        return Ok(lines);
+    }
+
+    let mut index_of: HashMap<&str, usize> = HashMap::new();
+    for (index, header) in headers.iter().enumerate() {
+        if let Some(header) = header {
+            if index_of.insert(header.name.as_str(), index).is_some() {
+                return Err(format!("job '{}' is defined twice", header.name));
+            }
+        }
+    }
+    let mut needs: Vec<Vec<usize>> = Vec::with_capacity(lines.len());
+    for (index, header) in headers.iter().enumerate() {
+        needs.push(match header {
+            Some(header) => header
+                .after
+                .iter()
+                .map(|job| {
+                    index_of.get(job.as_str()).copied().ok_or_else(|| {
+                        format!("job '{}' comes after '{job}', which is not defined", header.name)
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            None => index.checked_sub(1).into_iter().collect(),
+        });
+    }
+    if let Some(cycle) = find_cycle(&needs) {
+        let names: Vec<&str> = cycle
+            .iter()
+            .filter_map(|index| headers[*index].as_ref().map(|header| header.name.as_str()))
+            .collect();
+        return Err(format!("jobs wait on each other: {}", names.join(" -> ")));
+    }
+
+    // Always take the earliest line that is free to run.
+    let mut placed = vec![false; lines.len()];
+    let mut order = Vec::with_capacity(lines.len());
+    while order.len() < lines.len() {
+        let next = (0..lines.len())
+            .find(|index| !placed[*index] && needs[*index].iter().all(|need| placed[*need]))
+            .expect("no cycles left");
+        placed[next] = true;
+        order.push(next);
+    }
+    let mut lines: Vec<Option<OsString>> = lines.into_iter().map(Some).collect();
+    Ok(order.into_iter().filter_map(|index| lines[index].take()).collect())
+}
+
+/// A chain of lines that leads back to its start, e.g. `[a, b, a]`.
+fn find_cycle(needs: &[Vec<usize>]) -> Option<Vec<usize>> {
+    // 0: not seen, 1: on the current path, 2: done.
+    fn visit(index: usize, needs: &[Vec<usize>], state: &mut [u8], path: &mut Vec<usize>) -> Option<Vec<usize>> {
+        state[index] = 1;
+        path.push(index);
+        for &need in &needs[index] {
+            match state[need] {
+                1 => {
+                    let start = path.iter().position(|seen| *seen == need).unwrap_or(0);
+                    let mut cycle = path[start..].to_vec();
+                    cycle.push(need);
+                    return Some(cycle);
+                }
+                0 => {
+                    if let Some(cycle) = visit(need, needs, state, path) {
+                        return Some(cycle);
+                    }
+                }
+                _ => {}
+            }
+        }
+        path.pop();
+        state[index] = 2;
+        None
+    }
+
+    let mut state = vec![0u8; needs.len()];
+    (0..needs.len()).find_map(|index| {
+        (state[index] == 0)
+            .then(|| visit(index, needs, &mut state, &mut Vec::new()))
+            .flatten()
+    })
+}
+
+/// How the named jobs run so far have ended.
+#[derive(Debug, Clone, Default)]
+pub struct JobOutcomes {
+    outcomes: HashMap<String, Outcome>,
+}
+
+impl JobOutcomes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, name: &str, outcome: Outcome) {
+        self.outcomes.insert(name.to_string(), outcome);
+    }
+
+    /// Records how `line` ended if it is a named job.
+    pub fn record_line(&mut self, line: &OsStr, outcome: Outcome) {
+        let body = split_priority(line).map_or_else(|_| line.to_owned(), |(_, body)| body);
+        if let Ok(Some((header, _))) = split_job_header(&body) {
+            self.record(&header.name, outcome);
+        }
+    }
+
+    /// The first job `header` comes after that hasn't succeeded, with how
+    /// it ended (`None` when it hasn't run).
+    pub fn blocker<'a>(&self, header: &'a JobHeader) -> Option<(&'a str, Option<Outcome>)> {
+        header.after.iter().find_map(|job| match self.outcomes.get(job) {
+            Some(Outcome::Succeeded) => None,
+            outcome => Some((job.as_str(), outcome.copied())),
+        })
+    }
+}
+
+/// Why a job can't run, e.g. `needs 'extract', which failed`.
+pub fn describe_blocker(job: &str, outcome: Option<Outcome>) -> String {
+    match outcome {
+        Some(outcome) => format!("needs '{job}', which {}", outcome.describe()),
+        None => format!("needs '{job}', which hasn't run"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(lines: &[&str]) -> Result<Vec<String>, String> {
+        let lines = lines.iter().map(OsString::from).collect();
+        order_jobs(lines).map(|lines| lines.iter().map(|line| line.to_string_lossy().into_owned()).collect())
+    }
+
+    #[test]
+    fn header_splits_name_after_and_command() {
+        let (header, command) = split_job_header(OsStr::new("@job enc after a, b: encode -i x"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.name, "enc");
+        assert_eq!(header.after, ["a", "b"]);
+        assert_eq!(command, "encode -i x");
+        assert_eq!(split_job_header(OsStr::new("encode -i x")), Ok(None));
+    }
+
+    #[test]
+    fn self_dependency_is_a_cycle() {
+        assert_eq!(
+            order(&["@job a after a: probe a.mov"]),
+            Err("jobs wait on each other: a -> a".to_string())
+        );
+    }
+
+    #[test]
+    fn three_job_cycle_is_named() {
+        let err = order(&[
+            "@job a after c: probe a.mov",
+            "@job b after a: probe b.mov",
+            "@job c after b: probe c.mov",
+        ])
+        .unwrap_err();
+        assert_eq!(err, "jobs wait on each other: a -> c -> b -> a");
+    }
+
+    #[test]
+    fn unknown_after_name_is_an_error() {
+        assert_eq!(
+            order(&["@job enc after prep: probe a.mov"]),
+            Err("job 'enc' comes after 'prep', which is not defined".to_string())
+        );
+    }
+
+    #[test]
+    fn unnamed_lines_follow_the_line_above() {
+        let ordered = order(&[
+            "@job enc after prep: probe enc.mov",
+            "probe after-enc.mov",
+            "@job prep: probe prep.mov",
+            "probe after-prep.mov",
+        ])
+        .unwrap();
+        assert_eq!(
+            ordered,
+            [
+                "@job prep: probe prep.mov",
+                "@job enc after prep: probe enc.mov",
+                "probe after-enc.mov",
+                "probe after-prep.mov",
+            ]
+        );
+    }
+
+    #[test]
+    fn files_without_headers_keep_their_order() {
+        assert_eq!(order(&["probe b.mov", "probe a.mov"]).unwrap(), ["probe b.mov", "probe a.mov"]);
+    }
+}
@@ -1,6 +1,11 @@
+use std::fmt;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum FfxError {
     #[error("ffmpeg binary not found in PATH")]
     BinaryNotFound,
@@ -11,4 +16,294 @@ pub enum FfxError {
     },
     #[error("invalid command: {message}")]
     InvalidCommand { message: String },
+    #[error("interrupted")]
+    Interrupted,
+    #[error("ffflow hit an internal error and stopped (details above)")]
+    Panicked,
+}
+
+impl FfxError {
+    /// Why ffmpeg failed, read from its stderr. `None` for errors that did
+    /// not come from a running ffmpeg.
+    pub fn reason(&self) -> Option<FailureReason> {
+        match self {
+            FfxError::ProcessFailed { stderr, .. } => Some(classify_stderr(stderr)),
+            _ => None,
+        }
+    }
+}
+
+/// Common ffmpeg failures, so callers can react without grepping stderr.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FailureReason {
+    NoSuchFile { path: String },
+    UnknownEncoder { name: String },
+    InvalidArgument { detail: String },
+    PermissionDenied { path: String },
+    /// The output container has no way to store this codec, so the stream
+    /// can't be copied into it.
+    CodecNotSupportedInContainer { codec: String, stream: String },
+    Unknown,
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureReason::NoSuchFile { path } => write!(f, "no such file '{path}'"),
+            FailureReason::UnknownEncoder { name } => write!(f, "unknown encoder '{name}'"),
+            FailureReason::InvalidArgument { detail } => write!(f, "invalid argument: {detail}"),
+            FailureReason::PermissionDenied { path } => write!(f, "permission denied for '{path}'"),
+            FailureReason::CodecNotSupportedInContainer { codec, stream } => write!(
+                f,
+                "the output container can't hold {codec} (stream {stream}) as-is; \
+                 re-encode it instead, e.g. encode with --vcodec or --acodec"
+            ),
+            FailureReason::Unknown => write!(f, "ffmpeg failed"),
+        }
+    }
+}
+
+static RE_LOG_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[[^\]]*\]\s*").unwrap());
+static RE_OPENING_FILE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^Error opening (?:input|output) file (.+?)\.?$").unwrap());
+static RE_UNKNOWN_ENCODER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Unknown encoder '([^']+)'").unwrap());
+static RE_NO_CODEC_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"Could not find tag for codec (\S+) in stream (#\S+?),").unwrap()
+});
+static RE_UNRECOGNIZED_OPTION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Unrecognized option '([^']+)'").unwrap());
+
+const NO_SUCH_FILE: &str = ": No such file or directory";
+const PERMISSION_DENIED: &str = ": Permission denied";
+const INVALID_ARGUMENT: &str = ": Invalid argument";
+
+/// How many of the last stderr lines `reported_failure` looks at.
+pub const FAILURE_TAIL_LINES: usize = 5;
+
+/// Some failures still end with exit status 0; ffmpeg then says so in its
+/// last lines instead. Returns the line that gives it away. Only the tail is
+/// checked, since `Error while decoding` mid-run is usually recovered from.
+pub fn reported_failure<S: AsRef<str>>(tail: &[S]) -> Option<&str> {
+    let start = tail.len().saturating_sub(FAILURE_TAIL_LINES);
+    tail[start..]
+        .iter()
+        .map(|line| RE_LOG_TAG.replace(line.as_ref().trim(), ""))
+        .position(|line| line == "Conversion failed!" || line.starts_with("Error while "))
+        .map(|idx| tail[start + idx].as_ref().trim())
+}
+
+/// Scans ffmpeg's stderr for the first line that explains the failure.
+///
+/// ffmpeg 6 and older name the file in the error line (`in.mov: No such
+/// file or directory`); newer builds say `Error opening input: ...` and name
+/// the file on a separate `Error opening input file in.mov.` line.
+pub fn classify_stderr(stderr: &str) -> FailureReason {
+    let lines: Vec<String> = stderr
+        .lines()
+        .map(|line| RE_LOG_TAG.replace(line.trim(), "").into_owned())
+        .collect();
+    let opened_file = lines
+        .iter()
+        .find_map(|line| RE_OPENING_FILE.captures(line))
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str().to_string());
+    let path_of = |subject: &str| {
+        if subject.starts_with("Error") {
+            opened_file.clone().unwrap_or_default()
+        } else {
+            subject.to_string()
+        }
+    };
+
+    for line in &lines {
+        if let Some(name) = RE_UNKNOWN_ENCODER.captures(line).and_then(|cap| cap.get(1)) {
+            return FailureReason::UnknownEncoder {
+                name: name.as_str().to_string(),
+            };
+        }
+        if let Some(cap) = RE_NO_CODEC_TAG.captures(line) {
+            return FailureReason::CodecNotSupportedInContainer {
+                codec: cap[1].to_string(),
+                stream: cap[2].to_string(),
+            };
+        }
+        if let Some(subject) = line.strip_suffix(NO_SUCH_FILE) {
+            return FailureReason::NoSuchFile {
+                path: path_of(subject),
+            };
+        }
+        if let Some(subject) = line.strip_suffix(PERMISSION_DENIED) {
+            return FailureReason::PermissionDenied {
+                path: path_of(subject),
+            };
+        }
+        if let Some(option) = RE_UNRECOGNIZED_OPTION.captures(line).and_then(|cap| cap.get(1)) {
+            return FailureReason::InvalidArgument {
+                detail: format!("unrecognized option '{}'", option.as_str()),
+            };
+        }
+        if line.ends_with(INVALID_ARGUMENT) {
+            return FailureReason::InvalidArgument {
+                detail: line.to_string(),
+            };
+        }
+    }
+    FailureReason::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_input_in_ffmpeg6_form() {
+        let stderr = "ffmpeg version 6.1.1 Copyright (c) 2000-2023 the FFmpeg developers\n\
+                      missing.mp4: No such file or directory\n";
+        assert_eq!(
+            classify_stderr(stderr),
+            FailureReason::NoSuchFile {
+                path: "missing.mp4".into()
+            }
+        );
+    }
+
+    #[test]
+    fn missing_input_in_ffmpeg7_form_takes_path_from_split_line() {
+        let stderr = "ffmpeg version 7.0.1 Copyright (c) 2000-2024 the FFmpeg developers\n\
+                      [in#0 @ 0x55d5c8e0a740] Error opening input: No such file or directory\n\
+                      Error opening input file clips/missing take.mp4.\n\
+                      Error opening input files: No such file or directory\n";
+        assert_eq!(
+            classify_stderr(stderr),
+            FailureReason::NoSuchFile {
+                path: "clips/missing take.mp4".into()
+            }
+        );
+    }
+
+    #[test]
+    fn permission_denied_in_both_forms() {
+        let v6 = "/srv/out.mp4: Permission denied\n";
+        let v7 = "[out#0/mp4 @ 0x5581f2c1c9c0] Error opening output /srv/out.mp4: Permission denied\n\
+                  Error opening output file /srv/out.mp4.\n\
+                  Error opening output files: Permission denied\n";
+        let expected = FailureReason::PermissionDenied {
+            path: "/srv/out.mp4".into(),
+        };
+        assert_eq!(classify_stderr(v6), expected);
+        assert_eq!(classify_stderr(v7), expected);
+    }
+
+    #[test]
+    fn unknown_encoder_behind_log_tag() {
+        let stderr = "Stream mapping:\n  Stream #0:0 -> #0:0 (h264 (native) -> ? (libfoo))\n\
+                      [vost#0:0 @ 0x55b1c6a3f2c0] Unknown encoder 'libfoo'\n\
+                      [vost#0:0 @ 0x55b1c6a3f2c0] Error selecting an encoder\n\
+                      Error opening output file out.mp4.\n\
+                      Error opening output files: Encoder not found\n";
+        assert_eq!(
+            classify_stderr(stderr),
+            FailureReason::UnknownEncoder {
+                name: "libfoo".into()
+            }
+        );
+    }
+
+    #[test]
+    fn codec_tag_missing_wins_over_later_invalid_argument() {
+        let stderr = "[mp4 @ 0x5633f43c3a00] Could not find tag for codec pcm_s16le in stream #1, \
+                      codec not currently supported in container\n\
+                      [out#0/mp4 @ 0x5633f43c2e40] Could not write header (incorrect codec parameters ?): Invalid argument\n\
+                      Conversion failed!\n";
+        assert_eq!(
+            classify_stderr(stderr),
+            FailureReason::CodecNotSupportedInContainer {
+                codec: "pcm_s16le".into(),
+                stream: "#1".into()
+            }
+        );
+    }
+
+    #[test]
+    fn unrecognized_option() {
+        let stderr = "Unrecognized option 'fastseek'.\n\
+                      Error splitting the argument list: Option not found\n";
+        assert_eq!(
+            classify_stderr(stderr),
+            FailureReason::InvalidArgument {
+                detail: "unrecognized option 'fastseek'".into()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_argument_keeps_line_without_tag() {
+        let stderr = "[fc#-1 @ 0x55e3a4b0e580] Error initializing complex filters: Invalid argument\n";
+        assert_eq!(
+            classify_stderr(stderr),
+            FailureReason::InvalidArgument {
+                detail: "Error initializing complex filters: Invalid argument".into()
+            }
+        );
+    }
+
+    #[test]
+    fn unrecognised_stderr_is_unknown() {
+        let stderr = "[h264 @ 0x55d0] decode_slice_header error\nConversion failed!\n";
+        assert_eq!(classify_stderr(stderr), FailureReason::Unknown);
+        assert_eq!(classify_stderr(""), FailureReason::Unknown);
+    }
+
+    #[test]
+    fn reason_only_for_process_failures() {
+        let failed = FfxError::ProcessFailed {
+            exit_code: Some(1),
+            stderr: "in.mov: No such file or directory".into(),
+        };
+        assert_eq!(
+            failed.reason(),
+            Some(FailureReason::NoSuchFile {
+                path: "in.mov".into()
+            })
+        );
+        assert_eq!(FfxError::BinaryNotFound.reason(), None);
+    }
+
+    #[test]
+    fn conversion_failed_in_tail_is_reported() {
+        let tail = [
+            "frame=  240 fps=0.0 q=-1.0 Lsize=N/A time=00:00:08.00 bitrate=N/A speed=  16x",
+            "[aac @ 0x5619c5b3e680] Too many bits 8832.000000 > 6144 per frame requested",
+            "Conversion failed!",
+        ];
+        assert_eq!(reported_failure(&tail), Some("Conversion failed!"));
+    }
+
+    #[test]
+    fn error_while_line_keeps_its_tag() {
+        let tail = ["[out#0/mp4 @ 0x55] Error while filtering: Cannot allocate memory  "];
+        assert_eq!(
+            reported_failure(&tail),
+            Some("[out#0/mp4 @ 0x55] Error while filtering: Cannot allocate memory")
+        );
+    }
+
+    #[test]
+    fn decode_error_before_the_tail_is_ignored() {
+        let mut tail = vec!["[h264 @ 0x55] Error while decoding MB 12 30, bytestream -5".to_string()];
+        tail.extend((0..FAILURE_TAIL_LINES).map(|i| format!("frame={i} fps=25 speed=1x")));
+        assert_eq!(reported_failure(&tail), None);
+
+        tail.remove(1);
+        assert!(reported_failure(&tail).is_some());
+    }
+
+    #[test]
+    fn short_and_empty_tails() {
+        assert_eq!(reported_failure(&["Conversion failed!"]), Some("Conversion failed!"));
+        assert_eq!(reported_failure(&["video:1kB audio:0kB"]), None);
+        assert_eq!(reported_failure::<&str>(&[]), None);
+    }
 }
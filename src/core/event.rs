@@ -1,4 +1,4 @@
-use crate::core::metadata::{InputInfo, OutputInfo};
+use crate::core::metadata::{InputInfo, OutputInfo, StreamMapping};
 use crate::core::progress::FfmpegProgress;
 use crate::core::summary::EncodeSummary;
 
@@ -15,13 +15,29 @@ pub enum LogLevel {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum FfmpegEvent {
     Progress(FfmpegProgress),
     Input(InputInfo),
+    /// ffmpeg opened this output file and is about to write it. Its
+    /// stream details follow as `Output`.
+    OutputStarted(String),
     Output(OutputInfo),
+    /// The `Stream mapping:` block: which input streams feed which outputs.
+    Mapping(Vec<StreamMapping>),
     Summary(EncodeSummary),
+    Warning(String),
     Error(String),
     Prompt(String),
+    /// A segmenting muxer (HLS, DASH) opened this segment file, so the one
+    /// before it is complete.
+    SegmentOpened(String),
+    /// A line classified as `LogLevel::Noise`, sent only with
+    /// `runner::set_verbose(true)`. It also arrives as `Log`.
+    Noise(String),
+    /// A raw stderr line, sent for everything except the periodic
+    /// `frame=... time=...` stats, which arrive as `Progress` instead.
+    Log(String),
 }
 
 pub fn classify_log_line(line: &str) -> LogLevel {
@@ -0,0 +1,295 @@
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static RE_PROTOCOL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]+:").unwrap());
+
+/// Expands wildcard inputs (`*.mov`, `clip_??.mp4`, `take[12].wav`) to the
+/// files they match, sorted. Paths without wildcards pass through as-is.
+pub fn expand_input_globs(inputs: Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
+    let mut expanded = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let Some(pattern) = input.to_str().filter(|_| is_glob_pattern(&input)) else {
+            expanded.push(input);
+            continue;
+        };
+        let mut matches: Vec<PathBuf> = glob::glob(pattern)
+            .map_err(|err| format!("invalid input pattern '{pattern}': {err}"))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect();
+        if matches.is_empty() {
+            return Err(format!("no files match '{pattern}'"));
+        }
+        matches.sort();
+        expanded.extend(matches);
+    }
+    Ok(expanded)
+}
+
+/// A path is treated as a pattern only if it has wildcard characters, is
+/// not a protocol URL (`http://`, `pipe:0`, ...) and does not name an
+/// existing file, so a literal `take[1].mov` still works.
+pub fn is_glob_pattern(path: &Path) -> bool {
+    let Some(text) = path.to_str() else {
+        return false;
+    };
+    if !text.contains(['*', '?', '[']) || RE_PROTOCOL.is_match(text) {
+        return false;
+    }
+    !path.exists()
+}
+
+static EXPAND_PASSTHROUGH: AtomicBool = AtomicBool::new(false);
+
+/// Whether raw `ffmpeg ...` lines get `~` and variable expansion too
+/// (`set expand on`). Off by default, since ffmpeg options may hold a
+/// literal `$`.
+pub fn set_expand_passthrough(on: bool) {
+    EXPAND_PASSTHROUGH.store(on, Ordering::SeqCst);
+}
+
+pub fn expand_passthrough() -> bool {
+    EXPAND_PASSTHROUGH.load(Ordering::SeqCst)
+}
+
+/// Where `~` and variables are looked up. A trait so expansion can be tried
+/// against a fixed set of names.
+pub trait Environment {
+    fn var(&self, name: &str) -> Option<String>;
+    /// Home directory of `user`, or of the current user for `None`.
+    fn home_dir(&self, user: Option<&str>) -> Option<String>;
+}
+
+/// The process environment and the system's user database.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemEnv;
+
+impl Environment for SystemEnv {
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn home_dir(&self, user: Option<&str>) -> Option<String> {
+        match user {
+            None => self.var("HOME").or_else(|| self.var("USERPROFILE")),
+            Some(user) => user_home(user),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<String> {
+    use std::ffi::{CStr, CString};
+
+    let name = CString::new(user).ok()?;
+    // SAFETY: passwd is plain old data, so all zeroes is a valid value.
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut found: *mut libc::passwd = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 4096];
+    // SAFETY: every pointer is valid for the call, and `buf.len()` is the
+    // size of the buffer the strings in `entry` are written to.
+    let status = unsafe {
+        libc::getpwnam_r(name.as_ptr(), &mut entry, buf.as_mut_ptr(), buf.len(), &mut found)
+    };
+    if status != 0 || found.is_null() || entry.pw_dir.is_null() {
+        return None;
+    }
+    // SAFETY: on success `pw_dir` points to a NUL-terminated string in `buf`.
+    let dir = unsafe { CStr::from_ptr(entry.pw_dir) };
+    dir.to_str().ok().map(str::to_string)
+}
+
+#[cfg(not(unix))]
+fn user_home(_user: &str) -> Option<String> {
+    None
+}
+
+/// Expands `~`, `~user`, `$VAR` and `${VAR}` in a command line as a shell
+/// would: not inside single quotes or after a backslash, and `~` only at
+/// the start of a word. On Windows `%VAR%` works too. A variable that is
+/// not set is an error naming it.
+pub fn expand_line(line: &str, env: &impl Environment) -> Result<String, String> {
+    expand(line, env, true)
+}
+
+/// Like `expand_line` for one path whose quotes were already removed, so
+/// `'` and `\` in it are plain characters.
+pub fn expand_path(path: &str, env: &impl Environment) -> Result<String, String> {
+    expand(path, env, false)
+}
+
+fn expand(text: &str, env: &impl Environment, quoting: bool) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let (mut single, mut double) = (false, false);
+    let mut word_start = true;
+    while let Some(c) = chars.next() {
+        let at_word_start = std::mem::replace(&mut word_start, false);
+        match c {
+            '\'' if quoting && !double => {
+                single = !single;
+                out.push(c);
+            }
+            _ if single => out.push(c),
+            '"' if quoting => {
+                double = !double;
+                out.push(c);
+            }
+            '\\' if quoting => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '~' if at_word_start && !double => {
+                let user = take_while(&mut chars, |c| c.is_alphanumeric() || "._-".contains(c));
+                let ends_word = chars.peek().is_none_or(|c| *c == '/' || c.is_whitespace());
+                if !ends_word {
+                    out.push('~');
+                    out.push_str(&user);
+                    continue;
+                }
+                let user = (!user.is_empty()).then_some(user);
+                let home = env.home_dir(user.as_deref()).ok_or_else(|| match &user {
+                    Some(user) => format!("no home directory for user '{user}' in '~{user}'"),
+                    None => "'~' used but the home directory is not known".to_string(),
+                })?;
+                out.push_str(&home);
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let name = take_while(&mut chars, |c| c != '}');
+                if chars.next() != Some('}') {
+                    return Err(format!("unclosed '${{{name}' in '{text}'"));
+                }
+                out.push_str(&lookup(env, &name)?);
+            }
+            '$' if chars.peek().is_some_and(|c| c.is_ascii_alphabetic() || *c == '_') => {
+                let name = take_while(&mut chars, |c| c.is_ascii_alphanumeric() || c == '_');
+                out.push_str(&lookup(env, &name)?);
+            }
+            '%' if cfg!(windows) => {
+                let rest: String = chars.clone().collect();
+                match rest.split_once('%') {
+                    Some((name, _)) if is_var_name(name) => {
+                        chars.nth(name.chars().count());
+                        out.push_str(&lookup(env, name)?);
+                    }
+                    _ => out.push(c),
+                }
+            }
+            c => {
+                word_start = c.is_whitespace() && !double;
+                out.push(c);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn take_while(chars: &mut Peekable<Chars<'_>>, keep: impl Fn(char) -> bool) -> String {
+    let mut taken = String::new();
+    while let Some(c) = chars.next_if(|c| keep(*c)) {
+        taken.push(c);
+    }
+    taken
+}
+
+fn is_var_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn lookup(env: &impl Environment, name: &str) -> Result<String, String> {
+    if !is_var_name(name) {
+        return Err(format!("invalid variable name '{name}'"));
+    }
+    env.var(name)
+        .ok_or_else(|| format!("environment variable '{name}' is not set"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    struct FakeEnv {
+        vars: HashMap<&'static str, &'static str>,
+        homes: HashMap<Option<&'static str>, &'static str>,
+    }
+
+    impl Environment for FakeEnv {
+        fn var(&self, name: &str) -> Option<String> {
+            self.vars.get(name).map(|value| value.to_string())
+        }
+
+        fn home_dir(&self, user: Option<&str>) -> Option<String> {
+            self.homes
+                .iter()
+                .find(|(key, _)| **key == user)
+                .map(|(_, home)| home.to_string())
+        }
+    }
+
+    fn env() -> FakeEnv {
+        FakeEnv {
+            vars: HashMap::from([("CLIPS", "/media/clips"), ("NAME", "take1")]),
+            homes: HashMap::from([(None, "/home/me"), (Some("ana"), "/home/ana")]),
+        }
+    }
+
+    #[test]
+    fn tilde_is_the_home_directory() {
+        assert_eq!(expand_line("-i ~/in.mov -o ~", &env()).unwrap(), "-i /home/me/in.mov -o /home/me");
+        // Only at the start of a word.
+        assert_eq!(expand_line("-o a~b.mp4", &env()).unwrap(), "-o a~b.mp4");
+    }
+
+    #[test]
+    fn tilde_user_is_their_home_directory() {
+        assert_eq!(expand_line("-i ~ana/in.mov", &env()).unwrap(), "-i /home/ana/in.mov");
+        let err = expand_line("-i ~bob/in.mov", &env()).unwrap_err();
+        assert!(err.contains("'bob'"), "{err}");
+    }
+
+    #[test]
+    fn dollar_variables_expand() {
+        assert_eq!(
+            expand_line("-i $CLIPS/$NAME.mov -o ${CLIPS}/${NAME}_out.mp4", &env()).unwrap(),
+            "-i /media/clips/take1.mov -o /media/clips/take1_out.mp4"
+        );
+    }
+
+    #[test]
+    fn single_quotes_and_backslashes_keep_text_literal() {
+        assert_eq!(expand_line("-o '$NAME ~' \\$NAME", &env()).unwrap(), "-o '$NAME ~' \\$NAME");
+        assert_eq!(expand_line("-o \"$NAME\"", &env()).unwrap(), "-o \"take1\"");
+    }
+
+    #[test]
+    fn undefined_variable_is_named_in_the_error() {
+        let err = expand_line("-i $MISSING/in.mov", &env()).unwrap_err();
+        assert!(err.contains("'MISSING'"), "{err}");
+        let err = expand_line("-i ${ALSO_MISSING}", &env()).unwrap_err();
+        assert!(err.contains("'ALSO_MISSING'"), "{err}");
+    }
+
+    #[test]
+    fn paths_have_no_quoting() {
+        assert_eq!(expand_path("~/it's/$NAME", &env()).unwrap(), "/home/me/it's/take1");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn percent_variables_expand_on_windows() {
+        assert_eq!(expand_line("-i %CLIPS%\\in.mov", &env()).unwrap(), "-i /media/clips\\in.mov");
+        assert_eq!(expand_line("-vf scale=50%", &env()).unwrap(), "-vf scale=50%");
+        let err = expand_line("-i %MISSING%", &env()).unwrap_err();
+        assert!(err.contains("'MISSING'"), "{err}");
+    }
+}
@@ -1,8 +1,19 @@
+use std::path::Path;
 use std::time::Duration;
 
-use crate::core::metadata::{InputInfo, OutputInfo};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::core::capabilities::{Encoder, Filter, Format};
+use crate::core::event::FfmpegEvent;
+use crate::core::hls::PlaylistStats;
+use crate::core::job::JobStatus;
+use crate::core::ladder::Rung;
+use crate::core::metadata::{Chapter, InputInfo, OutputInfo, StreamMapping};
 use crate::core::progress::FfmpegProgress;
-use crate::core::summary::EncodeSummary;
+use crate::core::quality::QualityScores;
+use crate::core::summary::{average_speed, EncodeSummary, SessionStats};
+use crate::core::verify::StreamKind;
 
 pub fn format_input_line(info: &InputInfo) -> String {
     let resolution = if info.width > 0 && info.height > 0 {
@@ -33,9 +44,33 @@ pub fn format_input_line(info: &InputInfo) -> String {
         .bitrate_kbps
         .map(|kbps| format!("{:.1} kb/s", kbps))
         .unwrap_or_else(|| "unknown".to_string());
-    format!(
-        "Input  : {path} ({container}/{codec} {resolution} @ {fps}, duration={duration}, bitrate={bitrate})"
-    )
+    let mut line = format!(
+        "Input {}: {path} ({container}/{codec} {resolution} @ {fps}, duration={duration}, bitrate={bitrate}",
+        info.index
+    );
+    if !info.chapters.is_empty() {
+        line.push_str(&format!(", chapters={}", info.chapters.len()));
+    }
+    line.push(')');
+    line
+}
+
+/// Short form for the header, e.g. `a.mov h264 1920x1080 00:01:30`.
+pub fn format_input_brief(info: &InputInfo) -> String {
+    let mut parts = Vec::new();
+    if let Some(path) = &info.path {
+        parts.push(path.clone());
+    }
+    if !info.codec.is_empty() {
+        parts.push(info.codec.clone());
+    }
+    if info.width > 0 && info.height > 0 {
+        parts.push(format!("{}x{}", info.width, info.height));
+    }
+    if let Some(duration) = info.duration {
+        parts.push(format_duration(duration));
+    }
+    parts.join(" ")
 }
 
 pub fn format_output_line(info: &OutputInfo) -> String {
@@ -56,10 +91,40 @@ pub fn format_output_line(info: &OutputInfo) -> String {
     };
     let path = if info.path.is_empty() {
         "output".to_string()
+    } else if info.container == "tee" {
+        // tee writes to every `|`-separated slave, each optionally prefixed
+        // with `[opts]`; show the targets rather than the raw spec.
+        info.path
+            .split('|')
+            .map(|slave| match slave.rfind(']') {
+                Some(pos) if slave.starts_with('[') => &slave[pos + 1..],
+                _ => slave,
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
     } else {
         info.path.clone()
     };
-    format!("Output : {path} ({container}/{codec} {resolution})")
+    format!(
+        "Output {}: {path} ({container}/{codec} {resolution})",
+        info.index
+    )
+}
+
+/// e.g. `maps: 0:0→0:0 h264→libx264, 0:1→0:1 copy`.
+pub fn format_mapping_line(mappings: &[StreamMapping]) -> String {
+    let entries: Vec<String> = mappings
+        .iter()
+        .map(|mapping| {
+            let codecs = match (&mapping.decoder, &mapping.encoder) {
+                (Some(decoder), Some(encoder)) => format!(" {decoder}→{encoder}"),
+                (Some(codec), None) | (None, Some(codec)) => format!(" {codec}"),
+                (None, None) => String::new(),
+            };
+            format!("{}→{}{codecs}", mapping.source, mapping.target)
+        })
+        .collect();
+    format!("maps: {}", entries.join(", "))
 }
 
 pub fn format_summary_line(summary: &EncodeSummary) -> String {
@@ -70,26 +135,205 @@ pub fn format_summary_line(summary: &EncodeSummary) -> String {
         "unknown".to_string()
     };
     let duration = format_duration(summary.duration);
-    format!("Final  : size={size} avg_bitrate={bitrate} duration={duration}")
+    let mut line = format!("Final  : size={size} avg_bitrate={bitrate} duration={duration}");
+    if let Some(elapsed) = summary.encode_elapsed {
+        line.push_str(&format!(" took {}", format_duration(elapsed)));
+        if let Some(ratio) = format_realtime_ratio(summary.duration, elapsed) {
+            line.push_str(&format!(" ({ratio})"));
+        }
+    }
+    line
 }
 
-pub fn format_progress_line(update: &FfmpegProgress, total: Option<Duration>) -> Option<String> {
-    if update.frame == 0 && update.speed == 0.0 && update.time == Duration::from_secs(0) {
+pub fn format_realtime_ratio(media: Duration, elapsed: Duration) -> Option<String> {
+    average_speed(media, elapsed).map(|speed| format!("{speed:.1}x realtime"))
+}
+
+/// e.g. `completed in 00:07:45 (avg 3.1x)`; just the time when how much
+/// media was written is unknown.
+pub fn format_completion_line(elapsed: Duration, media: Option<Duration>) -> String {
+    let line = format!("completed in {}", format_duration(elapsed));
+    match media.and_then(|media| average_speed(media, elapsed)) {
+        Some(speed) => format!("{line} (avg {speed:.1}x)"),
+        None => line,
+    }
+}
+
+/// The `stats` listing.
+pub fn format_session_stats(stats: &SessionStats) -> Vec<String> {
+    let speed = match stats.average_speed() {
+        Some(speed) => format!(" (avg {speed:.1}x)"),
+        None => String::new(),
+    };
+    vec![
+        format!("Jobs run       {}", stats.jobs),
+        format!("Written        {}", format_bytes(stats.bytes_written)),
+        format!("Media          {}", format_duration(stats.media)),
+        format!("Time spent     {}{speed}", format_duration(stats.elapsed)),
+    ]
+}
+
+/// e.g. `session 3 jobs, 1.20 GB, 00:12:04`, for the header.
+pub fn format_session_brief(stats: &SessionStats) -> String {
+    let jobs = match stats.jobs {
+        1 => "1 job".to_string(),
+        n => format!("{n} jobs"),
+    };
+    format!(
+        "session {jobs}, {}, {}",
+        format_bytes(stats.bytes_written),
+        format_duration(stats.elapsed)
+    )
+}
+
+pub fn format_progress_line(
+    update: &FfmpegProgress,
+    total: Option<Duration>,
+    total_frames: Option<u64>,
+) -> Option<String> {
+    if update.frame == 0 && update.speed.is_none_or(|speed| speed == 0.0) && update.time.is_zero() {
         return None;
     }
 
+    let percent = update_ratio(update, total, total_frames)
+        .map(|ratio| format!("{:.0}% ", ratio * 100.0))
+        .unwrap_or_default();
     let elapsed = format_duration(update.time);
     let total = total
         .map(format_duration)
         .unwrap_or_else(|| "--:--:--".to_string());
+    let frames = match total_frames {
+        Some(frames) => format!("{}/{frames}", update.frame),
+        None => update.frame.to_string(),
+    };
 
     Some(format!(
-        "progress: time={elapsed}/{total} frame={} speed={}x",
-        update.frame,
-        update.speed
+        "progress: {percent}time={elapsed}/{total} frame={frames} speed={}{}",
+        format_speed(update.speed),
+        format_frame_stats(update)
     ))
 }
 
+/// How far along a job is: by frames when the total is known (image
+/// sequences), else by time.
+pub fn update_ratio(
+    update: &FfmpegProgress,
+    total: Option<Duration>,
+    total_frames: Option<u64>,
+) -> Option<f64> {
+    match total_frames.filter(|frames| *frames > 0) {
+        Some(frames) => Some((update.frame as f64 / frames as f64).clamp(0.0, 1.0)),
+        None => progress_ratio(update.time, total?),
+    }
+}
+
+/// How far `time` is into `total`, from 0 to 1.
+pub fn progress_ratio(time: Duration, total: Duration) -> Option<f64> {
+    let total = total.as_secs_f64();
+    (total > 0.0).then(|| (time.as_secs_f64() / total).clamp(0.0, 1.0))
+}
+
+/// A `[=====>    ]` bar with `width` cells inside the brackets, `ratio`
+/// (0 to 1) of them filled.
+pub fn progress_bar_string(ratio: f64, width: usize) -> String {
+    let filled = ((ratio.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    let mut bar = String::with_capacity(width + 2);
+    bar.push('[');
+    for idx in 0..width {
+        if idx < filled {
+            bar.push('=');
+        } else if idx == filled {
+            bar.push('>');
+        } else {
+            bar.push(' ');
+        }
+    }
+    bar.push(']');
+    bar
+}
+
+/// Terminal columns `text` takes up: wide characters (CJK, most emoji)
+/// count two, combining marks none.
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Cuts `text` to at most `width` columns, ending in `…` when anything was
+/// cut. Grapheme clusters are never split.
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut used = 0;
+    let mut cut = String::new();
+    for grapheme in text.graphemes(true) {
+        let columns = grapheme.width();
+        if used + columns + 1 > width {
+            break;
+        }
+        used += columns;
+        cut.push_str(grapheme);
+    }
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+/// The longest end of `text` that fits in `width` columns, for a one-line
+/// box that scrolls sideways to keep the end in view.
+pub fn tail_to_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    let mut start = text.len();
+    for (index, grapheme) in text.grapheme_indices(true).rev() {
+        used += grapheme.width();
+        if used > width {
+            break;
+        }
+        start = index;
+    }
+    &text[start..]
+}
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One block character per value, its height relative to the largest (the
+/// floor is zero, so a flat rate draws a flat line). Only the last `width`
+/// values are drawn.
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    let values = &values[values.len().saturating_sub(width)..];
+    let max = values.iter().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|&value| {
+            let level = match max > 0.0 {
+                true => (value.max(0.0) / max * (SPARK_LEVELS.len() - 1) as f64).round() as usize,
+                false => 0,
+            };
+            SPARK_LEVELS[level.min(SPARK_LEVELS.len() - 1)]
+        })
+        .collect()
+}
+
+/// Renders `1.5x`, or `--` while ffmpeg reports `speed=N/A`.
+pub fn format_speed(speed: Option<f32>) -> String {
+    speed
+        .map(|speed| format!("{speed}x"))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+/// Renders ` dup=N drop=M`, leaving out counters that are zero or unknown.
+pub fn format_frame_stats(update: &FfmpegProgress) -> String {
+    let mut stats = String::new();
+    if let Some(dup) = update.dup.filter(|&dup| dup > 0) {
+        stats.push_str(&format!(" dup={dup}"));
+    }
+    if let Some(drop) = update.drop.filter(|&drop| drop > 0) {
+        stats.push_str(&format!(" drop={drop}"));
+    }
+    stats
+}
+
 pub fn format_duration(duration: Duration) -> String {
     let total_secs = duration.as_secs();
     let hours = total_secs / 3600;
@@ -98,6 +342,255 @@ pub fn format_duration(duration: Duration) -> String {
     format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
 }
 
+/// Confirms a file the job wrote, e.g. `Saved frame.jpg (84.21 KB)`.
+pub fn format_saved_file(path: &Path, bytes: u64) -> String {
+    format!("Saved {} ({})", path.display(), format_bytes(bytes))
+}
+
+/// The `compare` result table; metrics that were not measured show `n/a`.
+pub fn format_quality_lines(scores: &QualityScores) -> Vec<String> {
+    let value = |score: Option<f64>, precision: usize| match score {
+        Some(score) => format!("{score:.precision$}"),
+        None => "n/a".to_string(),
+    };
+    vec![
+        "Metric     Score".to_string(),
+        format!("PSNR avg   {} dB", value(scores.psnr, 2)),
+        format!("SSIM all   {}", value(scores.ssim, 4)),
+        format!("VMAF mean  {}", value(scores.vmaf, 2)),
+    ]
+}
+
+/// `tag --show` output: keys padded to one column, continuation lines of a
+/// multi-line value indented under its first line.
+pub fn format_tag_lines(path: &Path, tags: &[(String, String)]) -> Vec<String> {
+    if tags.is_empty() {
+        return vec![format!("No tags in {}", path.display())];
+    }
+    let width = tags.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+    let mut lines = vec![format!("Tags in {}:", path.display())];
+    for (key, value) in tags {
+        let mut values = value.split('\n');
+        lines.push(format!("  {key:<width$}  {}", values.next().unwrap_or("")));
+        lines.extend(values.map(|more| format!("  {:width$}  {more}", "")));
+    }
+    lines
+}
+
+/// The `chapters` listing, one numbered row per chapter with its span.
+pub fn format_chapter_lines(path: &Path, chapters: &[Chapter]) -> Vec<String> {
+    if chapters.is_empty() {
+        return vec![format!("No chapters in {}", path.display())];
+    }
+    let precise = |time: Duration| format!("{}.{:03}", format_duration(time), time.subsec_millis());
+    let width = chapters.len().to_string().len();
+    let mut lines = vec![format!("{} chapters in {}:", chapters.len(), path.display())];
+    lines.extend(chapters.iter().enumerate().map(|(index, chapter)| {
+        format!(
+            "  {:>width$}  {} - {}  {}",
+            index + 1,
+            precise(chapter.start),
+            precise(chapter.end),
+            chapter.title.as_deref().unwrap_or("")
+        )
+        .trim_end()
+        .to_string()
+    }));
+    lines
+}
+
+/// The `encoders` listing, grouped by stream type, one name and
+/// description per row.
+pub fn format_encoder_lines(encoders: &[&Encoder]) -> Vec<String> {
+    if encoders.is_empty() {
+        return vec!["No matching encoders".to_string()];
+    }
+    let width = encoders.iter().map(|encoder| encoder.name.len()).max().unwrap_or(0);
+    let mut lines = Vec::new();
+    for kind in [StreamKind::Video, StreamKind::Audio, StreamKind::Subtitle] {
+        let group: Vec<&&Encoder> = encoders.iter().filter(|encoder| encoder.kind == kind).collect();
+        if group.is_empty() {
+            continue;
+        }
+        lines.push(format!("{} {} encoders:", group.len(), kind.name()));
+        lines.extend(
+            group
+                .iter()
+                .map(|encoder| format!("  {:<width$}  {}", encoder.name, encoder.description)),
+        );
+    }
+    lines
+}
+
+/// The `formats` listing, with ffmpeg's D (read) and E (write) flags.
+pub fn format_format_lines(formats: &[&Format]) -> Vec<String> {
+    if formats.is_empty() {
+        return vec!["No matching formats".to_string()];
+    }
+    let width = formats.iter().map(|format| format.name.len()).max().unwrap_or(0);
+    let mut lines = vec![format!("{} formats (D = read, E = write):", formats.len())];
+    lines.extend(formats.iter().map(|format| {
+        format!(
+            "  {}{}  {:<width$}  {}",
+            if format.demux { 'D' } else { ' ' },
+            if format.mux { 'E' } else { ' ' },
+            format.name,
+            format.description
+        )
+    }));
+    lines
+}
+
+/// The `filters` listing, with what each one takes and gives.
+pub fn format_filter_lines(filters: &[&Filter]) -> Vec<String> {
+    if filters.is_empty() {
+        return vec!["No matching filters".to_string()];
+    }
+    let width = filters.iter().map(|filter| filter.name.len()).max().unwrap_or(0);
+    let mut lines = vec![format!("{} filters (V = video, A = audio, N = any number):", filters.len())];
+    lines.extend(filters.iter().map(|filter| {
+        format!("  {:<width$}  {:<6}  {}", filter.name, filter.pads, filter.description)
+    }));
+    lines
+}
+
+/// One row of the ladder summary, e.g.
+/// `720p  out/a_720p.mp4  21.40 MB  2988 kb/s avg`.
+pub fn format_rung_result(rung: &Rung, path: &Path, bytes: u64, duration: Option<Duration>) -> String {
+    let mut line = format!("{:>5}p  {}  {}", rung.height, path.display(), format_bytes(bytes));
+    if let Some(secs) = duration.map(|duration| duration.as_secs_f64()).filter(|&secs| secs > 0.0) {
+        let kbps = bytes as f64 * 8.0 / 1000.0 / secs;
+        line.push_str(&format!("  {kbps:.0} kb/s avg"));
+    }
+    line
+}
+
+/// e.g. `HLS: 42 segments, 00:04:12 in out/index.m3u8`.
+pub fn format_playlist_stats(path: &Path, stats: &PlaylistStats) -> String {
+    format!(
+        "HLS: {} segments, {} in {}",
+        stats.segments,
+        format_duration(stats.duration),
+        path.display()
+    )
+}
+
+/// `value` as a quoted JSON string.
+pub fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if u32::from(ch) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(ch))),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_number(value: Option<f64>) -> String {
+    match value {
+        Some(value) if value.is_finite() => value.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+fn json_secs(value: Option<Duration>) -> String {
+    json_number(value.map(|duration| duration.as_secs_f64()))
+}
+
+/// One `--json-events` line for an ffmpeg event, or `None` for the raw
+/// stderr and noise lines it leaves out. Progress carries `percent` only
+/// when the job's length is known.
+pub fn format_event_json(event: &FfmpegEvent, ratio: Option<f64>) -> Option<String> {
+    let json = match event {
+        FfmpegEvent::Progress(update) => {
+            let percent = ratio
+                .map(|ratio| format!(", \"percent\": {}", json_number(Some((ratio * 1000.0).round() / 10.0))))
+                .unwrap_or_default();
+            format!(
+                "{{\"event\": \"progress\", \"frame\": {}, \"time_secs\": {}, \"fps\": {}, \
+                 \"speed\": {}, \"size_bytes\": {}{percent}}}",
+                update.frame,
+                json_secs(Some(update.time)),
+                json_number(update.fps.map(f64::from)),
+                json_number(update.speed.map(f64::from)),
+                update.size_bytes,
+            )
+        }
+        FfmpegEvent::Input(info) => format!(
+            "{{\"event\": \"input\", \"index\": {}, \"path\": {}, \"codec\": {}, \"width\": {}, \
+             \"height\": {}, \"duration_secs\": {}}}",
+            info.index,
+            info.path.as_deref().map_or("null".to_string(), json_string),
+            json_string(&info.codec),
+            info.width,
+            info.height,
+            json_secs(info.duration),
+        ),
+        FfmpegEvent::Output(info) => format!(
+            "{{\"event\": \"output\", \"index\": {}, \"path\": {}, \"container\": {}, \
+             \"codec\": {}, \"width\": {}, \"height\": {}}}",
+            info.index,
+            json_string(&info.path),
+            json_string(&info.container),
+            json_string(&info.codec),
+            info.width,
+            info.height,
+        ),
+        FfmpegEvent::Summary(summary) => format!(
+            "{{\"event\": \"summary\", \"size_bytes\": {}, \"duration_secs\": {}, \
+             \"avg_bitrate_kbps\": {}, \"elapsed_secs\": {}}}",
+            summary.final_size_bytes,
+            json_secs(Some(summary.duration)),
+            json_number(Some(f64::from(summary.avg_bitrate_kbps))),
+            json_secs(summary.encode_elapsed),
+        ),
+        FfmpegEvent::SegmentOpened(path) => {
+            format!("{{\"event\": \"segment\", \"path\": {}}}", json_string(path))
+        }
+        FfmpegEvent::Warning(message) => {
+            format!("{{\"event\": \"warning\", \"message\": {}}}", json_string(message))
+        }
+        FfmpegEvent::Error(message) => {
+            format!("{{\"event\": \"error\", \"message\": {}}}", json_string(message))
+        }
+        FfmpegEvent::Prompt(message) => {
+            format!("{{\"event\": \"prompt\", \"message\": {}}}", json_string(message))
+        }
+        // The `output` event that follows names the file too.
+        FfmpegEvent::OutputStarted(_)
+        | FfmpegEvent::Mapping(_)
+        | FfmpegEvent::Log(_)
+        | FfmpegEvent::Noise(_) => return None,
+    };
+    Some(json)
+}
+
+/// `--json-events` line for a job about to start.
+pub fn format_start_json(command: &str) -> String {
+    format!("{{\"event\": \"start\", \"command\": {}}}", json_string(command))
+}
+
+/// `--json-events` line for a job that ended.
+pub fn format_finish_json(status: JobStatus) -> String {
+    format!(
+        "{{\"event\": \"finish\", \"status\": {}}}",
+        json_string(&format!("{status:?}"))
+    )
+}
+
+/// `--json-events` line for anything else the session says.
+pub fn format_message_json(text: &str) -> String {
+    format!("{{\"event\": \"message\", \"text\": {}}}", json_string(text))
+}
+
 pub fn format_bytes(bytes: u64) -> String {
     const KB: f64 = 1024.0;
     const MB: f64 = KB * 1024.0;
@@ -113,3 +606,62 @@ pub fn format_bytes(bytes: u64) -> String {
         format!("{} B", bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::summary::parse_summary_line;
+
+    #[test]
+    fn realtime_ratio_below_at_and_above_realtime() {
+        let ratio = |media, elapsed| {
+            format_realtime_ratio(Duration::from_secs(media), Duration::from_secs(elapsed))
+        };
+        assert_eq!(ratio(30, 60).as_deref(), Some("0.5x realtime"));
+        assert_eq!(ratio(60, 60).as_deref(), Some("1.0x realtime"));
+        assert_eq!(ratio(465, 150).as_deref(), Some("3.1x realtime"));
+        assert_eq!(ratio(60, 0), None);
+    }
+
+    #[test]
+    fn completion_line_leaves_out_an_unknown_speed() {
+        let elapsed = Duration::from_secs(465);
+        assert_eq!(
+            format_completion_line(elapsed, Some(Duration::from_secs(1441))),
+            "completed in 00:07:45 (avg 3.1x)"
+        );
+        assert_eq!(format_completion_line(elapsed, None), "completed in 00:07:45");
+        assert_eq!(format_completion_line(Duration::ZERO, Some(elapsed)), "completed in 00:00:00");
+    }
+
+    #[test]
+    fn summary_line_shows_the_ratio_once_timed() {
+        let mut summary = parse_summary_line("Lsize=    1000kB time=00:00:30.00 bitrate= 273.1kbits/s").unwrap();
+        assert!(!format_summary_line(&summary).contains("realtime"));
+        summary.encode_elapsed = Some(Duration::from_secs(60));
+        assert!(format_summary_line(&summary).ends_with(" took 00:01:00 (0.5x realtime)"));
+    }
+
+    #[test]
+    fn progress_bar_for_several_ratios() {
+        assert_eq!(progress_bar_string(0.0, 10), "[>         ]");
+        assert_eq!(progress_bar_string(0.5, 10), "[=====>    ]");
+        assert_eq!(progress_bar_string(1.0, 10), "[==========]");
+        assert_eq!(progress_bar_string(-1.0, 4), progress_bar_string(0.0, 4));
+        assert_eq!(progress_bar_string(7.0, 4), progress_bar_string(1.0, 4));
+    }
+
+    #[test]
+    fn event_json_escapes_messages() {
+        let event = FfmpegEvent::Error("in \"a\".mov:\tNo such file".into());
+        assert_eq!(
+            format_event_json(&event, None).as_deref(),
+            Some(r#"{"event": "error", "message": "in \"a\".mov:\tNo such file"}"#)
+        );
+        assert_eq!(format_event_json(&FfmpegEvent::Log("frame=".into()), None), None);
+        assert_eq!(
+            format_finish_json(JobStatus::VerificationFailed),
+            r#"{"event": "finish", "status": "VerificationFailed"}"#
+        );
+    }
+}
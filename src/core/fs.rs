@@ -0,0 +1,40 @@
+use std::io;
+use std::path::Path;
+
+/// Free-space lookup. A trait so callers can be tried against a full disk
+/// without having one.
+pub trait FreeSpace {
+    /// Bytes an unprivileged user can still write on the filesystem that
+    /// holds `path`.
+    fn available(&self, path: &Path) -> io::Result<u64>;
+}
+
+/// Asks the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemFs;
+
+impl FreeSpace for SystemFs {
+    #[cfg(unix)]
+    fn available(&self, path: &Path) -> io::Result<u64> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+        // SAFETY: statvfs is plain old data, so all zeroes is a valid value.
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: `path` is NUL-terminated and `stat` is a valid out-pointer.
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+    }
+
+    #[cfg(not(unix))]
+    fn available(&self, _path: &Path) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "free space is only checked on unix",
+        ))
+    }
+}
@@ -0,0 +1,158 @@
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::core::ladder::Rung;
+
+/// Name of each variant's media playlist when there is a master playlist.
+const VARIANT_PLAYLIST: &str = "index.m3u8";
+
+/// An HLS packaging run: one stream, or one per variant plus a master
+/// playlist that lists them.
+#[derive(Debug, Clone)]
+pub struct HlsJob {
+    pub input: PathBuf,
+    pub out_dir: PathBuf,
+    /// Target segment length, seconds.
+    pub segment_time: u32,
+    pub playlist_name: String,
+    /// Empty for a single rendition at the source size.
+    pub variants: Vec<Rung>,
+    pub video_codec: String,
+    pub audio_codec: String,
+    /// Whether the input has audio to carry into each variant.
+    pub audio: bool,
+    pub extra_args: Vec<String>,
+}
+
+impl HlsJob {
+    pub fn args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["-i".into(), self.input.clone().into_os_string()];
+        if !self.variants.is_empty() {
+            args.extend(self.variant_args());
+        }
+        for arg in [
+            "-c:v",
+            &self.video_codec,
+            "-c:a",
+            &self.audio_codec,
+            // ffmpeg only cuts on keyframes; force one at every boundary so
+            // segments come out the requested length.
+            "-force_key_frames",
+            &format!("expr:gte(t,n_forced*{})", self.segment_time),
+        ] {
+            args.push(arg.into());
+        }
+        args.extend(self.extra_args.iter().map(OsString::from));
+        for arg in [
+            "-f",
+            "hls",
+            "-hls_time",
+            &self.segment_time.to_string(),
+            "-hls_playlist_type",
+            "vod",
+        ] {
+            args.push(arg.into());
+        }
+
+        if self.variants.is_empty() {
+            args.push("-hls_segment_filename".into());
+            args.push(self.out_dir.join("segment_%05d.ts").into_os_string());
+            args.push(self.out_dir.join(&self.playlist_name).into_os_string());
+        } else {
+            args.push("-master_pl_name".into());
+            args.push(self.playlist_name.clone().into());
+            args.push("-hls_segment_filename".into());
+            args.push(
+                self.out_dir
+                    .join("%v")
+                    .join("segment_%05d.ts")
+                    .into_os_string(),
+            );
+            args.push(
+                self.out_dir
+                    .join("%v")
+                    .join(VARIANT_PLAYLIST)
+                    .into_os_string(),
+            );
+        }
+        args
+    }
+
+    /// Splits the video once per variant, scales each copy and maps it with
+    /// the audio into its own numbered stream group, named by height.
+    fn variant_args(&self) -> Vec<OsString> {
+        let count = self.variants.len();
+        let labels: String = (0..count).map(|idx| format!("[s{idx}]")).collect();
+        let mut graph = format!("[0:v]split={count}{labels}");
+        let mut maps = Vec::new();
+        let mut rates = Vec::new();
+        let mut groups = Vec::new();
+        for (idx, rung) in self.variants.iter().enumerate() {
+            graph.push_str(&format!(";[s{idx}]scale=-2:{}[v{idx}]", rung.height));
+            maps.extend(["-map".to_string(), format!("[v{idx}]")]);
+            let mut group = format!("v:{idx}");
+            if self.audio {
+                maps.extend(["-map".to_string(), "0:a:0".to_string()]);
+                group.push_str(&format!(",a:{idx}"));
+            }
+            groups.push(format!("{group},name:{}p", rung.height));
+            let bitrate = format!("{}k", rung.bitrate_kbps);
+            rates.extend([
+                format!("-b:v:{idx}"),
+                bitrate.clone(),
+                format!("-maxrate:v:{idx}"),
+                bitrate,
+                format!("-bufsize:v:{idx}"),
+                format!("{}k", rung.bitrate_kbps * 2),
+            ]);
+        }
+
+        let mut args: Vec<OsString> = vec!["-filter_complex".into(), graph.into()];
+        args.extend(maps.into_iter().map(OsString::from));
+        args.extend(rates.into_iter().map(OsString::from));
+        args.push("-var_stream_map".into());
+        args.push(groups.join(" ").into());
+        args
+    }
+
+    /// The playlist that lists segments: the only one, or the first
+    /// variant's.
+    pub fn media_playlist(&self) -> PathBuf {
+        match self.variants.first() {
+            Some(rung) => self
+                .out_dir
+                .join(format!("{}p", rung.height))
+                .join(VARIANT_PLAYLIST),
+            None => self.out_dir.join(&self.playlist_name),
+        }
+    }
+}
+
+/// What a finished media playlist contains.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlaylistStats {
+    pub segments: usize,
+    pub duration: Duration,
+}
+
+pub fn read_playlist(path: &Path) -> io::Result<PlaylistStats> {
+    Ok(parse_playlist(&std::fs::read_to_string(path)?))
+}
+
+/// Counts the `#EXTINF:<seconds>,` entries and adds up their lengths.
+pub fn parse_playlist(text: &str) -> PlaylistStats {
+    let mut stats = PlaylistStats::default();
+    for line in text.lines() {
+        let Some(info) = line.trim().strip_prefix("#EXTINF:") else {
+            continue;
+        };
+        stats.segments += 1;
+        let seconds = info.split(',').next().unwrap_or_default();
+        if let Ok(seconds) = seconds.trim().parse::<f64>() {
+            stats.duration += Duration::from_secs_f64(seconds.max(0.0));
+        }
+    }
+    stats
+}
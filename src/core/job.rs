@@ -1,18 +1,41 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JobStatus {
+    // Only `ffflow::run_blocking` starts jobs out pending.
+    #[allow(dead_code)]
     Pending,
     Running,
+    Paused,
     Finished,
     Failed,
+    /// ffmpeg succeeded but `--verify` found a problem with the output.
+    VerificationFailed,
+    /// Stopped by Ctrl-C before ffmpeg finished.
+    Interrupted,
+    /// Not run, because a job it comes after in the `.flw` file failed.
+    Skipped,
     AwaitingConfirmation,
 }
 
 #[derive(Debug, Clone)]
 pub struct Job {
+    // Read by library users; the binary tracks status per queue entry.
+    #[allow(dead_code)]
     pub id: u64,
+    #[allow(dead_code)]
     pub status: JobStatus,
     pub started_at: Option<Instant>,
     pub ended_at: Option<Instant>,
 }
+
+impl Job {
+    /// Wall-clock time between start and end, or up to now while running.
+    pub fn elapsed(&self) -> Option<Duration> {
+        let started = self.started_at?;
+        Some(match self.ended_at {
+            Some(ended) => ended.saturating_duration_since(started),
+            None => started.elapsed(),
+        })
+    }
+}
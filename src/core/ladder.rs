@@ -0,0 +1,106 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::core::command::{parse_rate_kbps, FfmpegCommand};
+
+/// Used when `ladder` is given no `--rungs`.
+pub const DEFAULT_RUNGS: &str = "1080p:5000k,720p:3000k,480p:1200k,360p:800k";
+
+/// One rendition of a bitrate ladder: output height and video bitrate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rung {
+    pub height: u32,
+    pub bitrate_kbps: u32,
+}
+
+impl Rung {
+    /// Scales to the rung's height (keeping the aspect ratio, even width)
+    /// and caps the bitrate, with a two-second VBV buffer.
+    pub fn apply(&self, command: &mut FfmpegCommand) {
+        command
+            .video_filters
+            .push(format!("scale=-2:{}", self.height));
+        let bitrate = format!("{}k", self.bitrate_kbps);
+        let mut args = vec![
+            "-b:v".to_string(),
+            bitrate.clone(),
+            "-maxrate".to_string(),
+            bitrate,
+            "-bufsize".to_string(),
+            format!("{}k", self.bitrate_kbps * 2),
+        ];
+        args.append(&mut command.extra_args);
+        command.extra_args = args;
+    }
+}
+
+impl fmt::Display for Rung {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}p:{}k", self.height, self.bitrate_kbps)
+    }
+}
+
+impl FromStr for Rung {
+    type Err = String;
+
+    /// Accepts `720p:3000k`; the bitrate may also be given in `M`.
+    fn from_str(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid rung '{value}' (expected e.g. 720p:3000k)");
+        let (height, bitrate) = value.trim().split_once(':').ok_or_else(invalid)?;
+        let height = height
+            .strip_suffix(['p', 'P'])
+            .unwrap_or(height)
+            .parse::<u32>()
+            .map_err(|_| invalid())?;
+        // A bare number is taken as kb/s here, unlike ffmpeg.
+        let bitrate_kbps = parse_rate_kbps(bitrate)
+            .or_else(|| bitrate.parse::<f64>().ok().map(|kbps| kbps.round() as u32))
+            .ok_or_else(invalid)?;
+        if height == 0 || bitrate_kbps == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            height,
+            bitrate_kbps,
+        })
+    }
+}
+
+/// Drops rungs taller than the source, since upscaling only costs bits.
+/// Returns the rungs to encode and a note for each one skipped.
+pub fn fit_rungs(rungs: Vec<Rung>, source_height: Option<u32>) -> (Vec<Rung>, Vec<String>) {
+    let mut notes = Vec::new();
+    let fitting = rungs
+        .into_iter()
+        .filter(|rung| match source_height {
+            Some(height) if rung.height > height => {
+                notes.push(format!("skipping {rung}: taller than the {height}p source"));
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    (fitting, notes)
+}
+
+/// A ladder expanded into one queued job line per rung.
+#[derive(Debug, Clone)]
+pub struct LadderPlan {
+    /// Groups the rungs in the UI, e.g. `ladder movie`.
+    pub label: String,
+    pub rungs: Vec<PlannedRung>,
+    /// Rungs that were skipped, and why.
+    pub notes: Vec<String>,
+    /// Source duration, for the average bitrate of each output.
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedRung {
+    pub rung: Rung,
+    pub output: PathBuf,
+    pub line: OsString,
+}
@@ -0,0 +1,67 @@
+use std::collections::VecDeque;
+use std::ffi::OsString;
+
+use crate::core::progress::FfmpegProgress;
+
+/// Bitrate readings kept for the sparkline.
+pub const LIVE_SAMPLES: usize = 60;
+
+/// URL schemes that carry a stream with no end.
+const LIVE_SCHEMES: [&str; 8] = ["rtmp", "rtmps", "rtsp", "srt", "udp", "rtp", "tcp", "rist"];
+/// `-f` formats that grab from a device or the screen.
+const CAPTURE_FORMATS: [&str; 11] = [
+    "x11grab",
+    "kmsgrab",
+    "gdigrab",
+    "dshow",
+    "avfoundation",
+    "v4l2",
+    "video4linux2",
+    "alsa",
+    "pulse",
+    "jack",
+    "decklink",
+];
+
+/// Whether a job reads a live source: a streaming URL, a capture device,
+/// or an input read at its native rate with `-re`.
+pub fn is_live_source(args: &[OsString]) -> bool {
+    args.iter().any(|arg| arg == "-re")
+        || args.windows(2).any(|pair| {
+            let value = pair[1].to_string_lossy();
+            match pair[0].to_str() {
+                Some("-f") => CAPTURE_FORMATS.contains(&value.as_ref()),
+                Some("-i") => value
+                    .split_once("://")
+                    .is_some_and(|(scheme, _)| LIVE_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str())),
+                _ => false,
+            }
+        })
+}
+
+/// Recent bitrate of a live job, for the sparkline.
+#[derive(Debug, Clone, Default)]
+pub struct LiveStats {
+    bitrates: VecDeque<f64>,
+}
+
+impl LiveStats {
+    pub fn push(&mut self, update: &FfmpegProgress) {
+        if let Some(kbps) = update.bitrate_kbps {
+            if self.bitrates.len() == LIVE_SAMPLES {
+                self.bitrates.pop_front();
+            }
+            self.bitrates.push_back(f64::from(kbps));
+        }
+    }
+
+    /// Bitrate readings in kb/s, oldest first.
+    pub fn bitrates(&self) -> Vec<f64> {
+        self.bitrates.iter().copied().collect()
+    }
+
+    pub fn current_bitrate(&self) -> Option<f64> {
+        self.bitrates.back().copied()
+    }
+
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// EBU R128 targets for the `loudnorm` filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnormTarget {
+    /// Integrated loudness, LUFS.
+    pub integrated: f32,
+    /// Loudness range, LU.
+    pub lra: f32,
+    /// Maximum true peak, dBTP.
+    pub true_peak: f32,
+}
+
+impl Default for LoudnormTarget {
+    fn default() -> Self {
+        Self {
+            integrated: -16.0,
+            lra: 11.0,
+            true_peak: -1.5,
+        }
+    }
+}
+
+impl LoudnormTarget {
+    /// The `loudnorm` filter aiming at this target. On its own it runs in
+    /// one pass, adjusting on the fly.
+    pub fn filter(&self) -> String {
+        format!(
+            "loudnorm=I={}:LRA={}:TP={}",
+            self.integrated, self.lra, self.true_peak
+        )
+    }
+}
+
+/// What pass one measured; pass two feeds these back into `loudnorm` so it
+/// can normalize linearly instead of guessing on the fly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnormMeasurement {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub target_offset: f64,
+}
+
+/// A two-pass loudness normalization: measure, then apply.
+#[derive(Debug, Clone)]
+pub struct LoudnormJob {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub target: LoudnormTarget,
+}
+
+impl LoudnormJob {
+    /// Pass one: decode the audio only and print the measurement as JSON.
+    pub fn measure_args(&self) -> Vec<OsString> {
+        vec![
+            "-hide_banner".into(),
+            "-i".into(),
+            self.input.clone().into_os_string(),
+            "-vn".into(),
+            "-sn".into(),
+            "-dn".into(),
+            "-af".into(),
+            format!("{}:print_format=json", self.target_filter()).into(),
+            "-f".into(),
+            "null".into(),
+            "-".into(),
+        ]
+    }
+
+    /// Pass two: apply the measured correction. Video is copied untouched.
+    pub fn apply_args(&self, measured: &LoudnormMeasurement) -> Vec<OsString> {
+        let filter = format!(
+            "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true:print_format=summary",
+            self.target_filter(),
+            measured.input_i,
+            measured.input_tp,
+            measured.input_lra,
+            measured.input_thresh,
+            measured.target_offset,
+        );
+        vec![
+            "-i".into(),
+            self.input.clone().into_os_string(),
+            "-af".into(),
+            filter.into(),
+            "-c:v".into(),
+            "copy".into(),
+            self.output.clone().into_os_string(),
+        ]
+    }
+
+    fn target_filter(&self) -> String {
+        self.target.filter()
+    }
+}
+
+static RE_JSON_FIELD: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^"(\w+)"\s*:\s*"([^"]*)",?$"#).unwrap());
+
+/// Collects the JSON block `loudnorm` prints to stderr at the end of pass
+/// one. The block spans several lines after a `[Parsed_loudnorm_N @ ...]`
+/// tag, so lines are fed one at a time.
+#[derive(Debug, Default)]
+pub struct LoudnormParser {
+    seen_tag: bool,
+    in_block: bool,
+    complete: bool,
+    fields: HashMap<String, String>,
+}
+
+impl LoudnormParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        if self.complete {
+            return;
+        }
+        let mut line = line.trim();
+        if line.starts_with("[Parsed_loudnorm") {
+            self.seen_tag = true;
+            line = line.split_once(']').map_or("", |(_, rest)| rest.trim());
+        }
+        if !self.in_block {
+            self.in_block = self.seen_tag && line == "{";
+            return;
+        }
+        if line == "}" {
+            self.in_block = false;
+            self.complete = true;
+            return;
+        }
+        if let Some(capture) = RE_JSON_FIELD.captures(line) {
+            self.fields
+                .insert(capture[1].to_string(), capture[2].to_string());
+        }
+    }
+
+    /// Returns the measurement, or why it cannot be trusted. A wrong second
+    /// pass is worse than none, so every field must be present and finite.
+    pub fn finish(&self) -> Result<LoudnormMeasurement, String> {
+        if !self.complete {
+            return Err("loudnorm measurement not found in ffmpeg output".to_string());
+        }
+        let field = |name: &str| -> Result<f64, String> {
+            let raw = self
+                .fields
+                .get(name)
+                .ok_or_else(|| format!("loudnorm measurement is missing '{name}'"))?;
+            let value = raw
+                .parse::<f64>()
+                .map_err(|_| format!("loudnorm measurement has a bad '{name}': '{raw}'"))?;
+            if !value.is_finite() {
+                return Err(format!(
+                    "loudnorm measured {name} = {raw}; is the audio silent?"
+                ));
+            }
+            Ok(value)
+        };
+        Ok(LoudnormMeasurement {
+            input_i: field("input_i")?,
+            input_tp: field("input_tp")?,
+            input_lra: field("input_lra")?,
+            input_thresh: field("input_thresh")?,
+            target_offset: field("target_offset")?,
+        })
+    }
+}
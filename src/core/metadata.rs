@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use once_cell::sync::Lazy;
@@ -6,7 +7,9 @@ use regex::Regex;
 use crate::core::progress::parse_ffmpeg_time;
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct InputInfo {
+    pub index: usize,
     pub width: u32,
     pub height: u32,
     pub fps: f32,
@@ -15,10 +18,20 @@ pub struct InputInfo {
     pub container: Option<String>,
     pub path: Option<String>,
     pub bitrate_kbps: Option<f32>,
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub start: Duration,
+    pub end: Duration,
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct OutputInfo {
+    pub index: usize,
     pub container: String,
     pub codec: String,
     pub width: u32,
@@ -26,197 +39,487 @@ pub struct OutputInfo {
     pub path: String,
 }
 
+/// One line of ffmpeg's `Stream mapping:` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StreamMapping {
+    /// `input:stream`, or the filter pad a graph output comes from.
+    pub source: String,
+    /// `output:stream`, or the filter pad a stream feeds into.
+    pub target: String,
+    /// Codec of the source stream; `None` for copies and graph outputs.
+    pub decoder: Option<String>,
+    /// Encoder name, `copy`, or `None` when the stream feeds a graph.
+    pub encoder: Option<String>,
+}
+
 static RE_INPUT_HEADER: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^Input #\d+,\s*(.+),\s*from '([^']+)'").unwrap());
+    Lazy::new(|| Regex::new(r"^Input #(\d+),\s*(.+),\s*from '([^']+)'").unwrap());
 static RE_OUTPUT_HEADER: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^Output #\d+,\s*([^,]+),\s*to '([^']+)'").unwrap());
+    Lazy::new(|| Regex::new(r"^Output #(\d+),\s*([^,]+),\s*to '([^']+)'").unwrap());
 static RE_DURATION: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"Duration:\s*([0-9:\.]+)").unwrap());
 static RE_BITRATE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"bitrate:\s*([0-9]*\.?[0-9]+)\s*kb/s").unwrap());
 static RE_STREAM_VIDEO: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"Stream #\d+:\d+.*Video:\s*([^,]+)").unwrap());
+    Lazy::new(|| Regex::new(r"Stream #(\d+):\d+.*Video:\s*([^,]+)").unwrap());
+static RE_STREAM_AUDIO: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Stream #\d+:\d+.*Audio:\s*([^,]+)").unwrap());
 static RE_RESOLUTION: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(\d{2,5})x(\d{2,5})").unwrap());
+static RE_OPENING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Opening '([^']+)' for writing").unwrap());
+static RE_MAP_DIRECT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^Stream #(\d+:\d+)(?:\[[^\]]*\])? -> #(\d+:\d+) \((.+)\)$").unwrap()
+});
+static RE_MAP_TRANSCODE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(\S+)(?: \(([^)]+)\))? -> (\S+)(?: \(([^)]+)\))?$").unwrap()
+});
+static RE_MAP_TO_GRAPH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^Stream #(\d+:\d+)(?:\[[^\]]*\])? \(([^)]+)\) -> (.+)$").unwrap()
+});
+static RE_MAP_FROM_GRAPH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+?) -> Stream #(\d+:\d+) \(([^)]+)\)$").unwrap());
+static RE_GRAPH_SUFFIX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s*\(graph \d+\)$").unwrap());
+static RE_CHAPTER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*Chapter #(\d+):\d+: start (-?[0-9.]+), end (-?[0-9.]+)").unwrap()
+});
+// Only the first colon after the padded key separates it from the value.
+static RE_CHAPTER_TITLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s+title\s*: (.*)$").unwrap());
 static RE_FPS: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"([0-9]*\.?[0-9]+)\s*fps").unwrap());
 
 #[derive(Default)]
 pub struct MetadataParser {
-    pending_input_duration: Option<Duration>,
-    pending_input_container: Option<String>,
-    pending_input_path: Option<String>,
-    pending_input_bitrate_kbps: Option<f32>,
-    input_emitted: bool,
-    pending_output_container: Option<String>,
-    pending_output_path: Option<String>,
+    inputs: BTreeMap<usize, PendingInput>,
+    current_input: Option<usize>,
+    outputs: BTreeMap<usize, PendingOutput>,
+    current_output: Option<usize>,
     section: MetadataSection,
+    mappings: Option<Vec<StreamMapping>>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default)]
+struct PendingInput {
+    duration: Option<Duration>,
+    container: Option<String>,
+    path: Option<String>,
+    bitrate_kbps: Option<f32>,
+    chapters: Vec<Chapter>,
+    /// Lines belong to the last chapter until a stream or program starts.
+    in_chapter: bool,
+    emitted: bool,
+}
+
+#[derive(Debug, Default)]
+struct PendingOutput {
+    container: String,
+    path: String,
+    audio_codec: Option<String>,
+    emitted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 enum MetadataSection {
     Input,
     Output,
+    #[default]
     Other,
 }
 
-impl Default for MetadataSection {
-    fn default() -> Self {
-        MetadataSection::Other
-    }
-}
-
 impl MetadataParser {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn parse_input_line(&mut self, line: &str) -> Option<InputInfo> {
+    /// Feeds one stderr line and returns every input that became complete.
+    ///
+    /// An input is reported on its first video stream line, matched by the
+    /// `#N` in `Stream #N:M`; inputs without one (audio-only) are flushed
+    /// when the first `Output #N` header shows up.
+    pub fn parse_input_line(&mut self, line: &str) -> Vec<InputInfo> {
         if let Some(capture) = RE_INPUT_HEADER.captures(line) {
-            let container = capture.get(1).map(|m| m.as_str().trim().to_string());
-            let path = capture.get(2).map(|m| m.as_str().trim().to_string());
-            self.pending_input_container = container;
-            self.pending_input_path = path;
-            self.pending_input_duration = None;
-            self.pending_input_bitrate_kbps = None;
-            self.input_emitted = false;
+            let index = capture
+                .get(1)
+                .and_then(|m| m.as_str().parse::<usize>().ok())
+                .unwrap_or(0);
+            self.inputs.insert(
+                index,
+                PendingInput {
+                    container: capture.get(2).map(|m| m.as_str().trim().to_string()),
+                    path: capture.get(3).map(|m| m.as_str().trim().to_string()),
+                    ..PendingInput::default()
+                },
+            );
+            self.current_input = Some(index);
             self.section = MetadataSection::Input;
-            return None;
+            return Vec::new();
         }
 
         if RE_OUTPUT_HEADER.is_match(line) {
             self.section = MetadataSection::Output;
-            if !self.input_emitted {
-                if let Some(info) = self.build_input_info(None, 0, 0, 0.0) {
-                    self.input_emitted = true;
-                    return Some(info);
-                }
-            }
-            return None;
+            self.current_input = None;
+            return self.flush_inputs();
         }
 
         if self.section != MetadataSection::Input {
-            return None;
+            return Vec::new();
         }
 
         if let Some(capture) = RE_DURATION.captures(line) {
-            if let Some(value) = capture.get(1) {
-                self.pending_input_duration = parse_ffmpeg_time(value.as_str());
-            }
-            if let Some(bitrate_cap) = RE_BITRATE.captures(line) {
-                if let Some(value) = bitrate_cap.get(1) {
-                    self.pending_input_bitrate_kbps = value.as_str().parse::<f32>().ok();
+            if let Some(pending) = self
+                .current_input
+                .and_then(|index| self.inputs.get_mut(&index))
+            {
+                if let Some(value) = capture.get(1) {
+                    pending.duration = parse_ffmpeg_time(value.as_str());
+                }
+                if let Some(value) = RE_BITRATE.captures(line).and_then(|cap| cap.get(1)) {
+                    pending.bitrate_kbps = value.as_str().parse::<f32>().ok();
                 }
             }
-            return None;
+            return Vec::new();
         }
 
-        if self.input_emitted {
-            return None;
+        if let Some(capture) = RE_CHAPTER.captures(line) {
+            let index = capture[1].parse::<usize>().ok().or(self.current_input);
+            if let Some(pending) = index.and_then(|index| self.inputs.get_mut(&index)) {
+                let seconds = |value: &str| {
+                    Duration::from_secs_f64(value.parse::<f64>().unwrap_or(0.0).max(0.0))
+                };
+                pending.chapters.push(Chapter {
+                    start: seconds(&capture[2]),
+                    end: seconds(&capture[3]),
+                    title: None,
+                });
+                pending.in_chapter = true;
+            }
+            return Vec::new();
         }
 
-        let codec = RE_STREAM_VIDEO
-            .captures(line)
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str().trim().to_string());
+        if let Some(pending) = self
+            .current_input
+            .and_then(|index| self.inputs.get_mut(&index))
+            .filter(|pending| pending.in_chapter)
+        {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("Stream #") || trimmed.starts_with("Program ") {
+                pending.in_chapter = false;
+            } else if let Some(capture) = RE_CHAPTER_TITLE.captures(line) {
+                if let Some(chapter) = pending.chapters.last_mut() {
+                    chapter.title = Some(capture[1].to_string());
+                }
+                return Vec::new();
+            }
+        }
 
-        let (width, height) = RE_RESOLUTION
-            .captures(line)
-            .and_then(|cap| {
-                let w = cap.get(1)?.as_str().parse::<u32>().ok()?;
-                let h = cap.get(2)?.as_str().parse::<u32>().ok()?;
-                Some((w, h))
-            })
-            .unwrap_or((0, 0));
+        let Some(capture) = RE_STREAM_VIDEO.captures(line) else {
+            return Vec::new();
+        };
+        let Some(index) = capture
+            .get(1)
+            .and_then(|m| m.as_str().parse::<usize>().ok())
+            .or(self.current_input)
+        else {
+            return Vec::new();
+        };
+        let codec = capture.get(2).map(|m| m.as_str().trim().to_string());
 
+        let (width, height) = parse_resolution(line);
         let fps = RE_FPS
             .captures(line)
             .and_then(|cap| cap.get(1))
             .and_then(|m| m.as_str().parse::<f32>().ok())
             .unwrap_or(0.0);
 
-        if codec.is_none() && width == 0 && height == 0 && fps == 0.0 {
+        let Some(pending) = self.inputs.get_mut(&index) else {
+            return Vec::new();
+        };
+        if pending.emitted {
+            return Vec::new();
+        }
+        pending.emitted = true;
+        vec![pending.to_info(index, codec, width, height, fps)]
+    }
+
+    /// Reports inputs that never showed a video stream, e.g. when ffmpeg
+    /// stops before any `Output #N` header.
+    pub fn flush_inputs(&mut self) -> Vec<InputInfo> {
+        self.inputs
+            .iter_mut()
+            .filter(|(_, pending)| !pending.emitted)
+            .map(|(index, pending)| {
+                pending.emitted = true;
+                pending.to_info(*index, None, 0, 0, 0.0)
+            })
+            .collect()
+    }
+
+    /// Feeds one stderr line and returns every output that became complete.
+    ///
+    /// Each `Output #N` section yields exactly one `OutputInfo`, reported on
+    /// its first video stream line. Sections without video (audio-only, or a
+    /// muxer that only lists audio) are reported when the section ends: at
+    /// the first unindented line after it, the next `Output #N` header, or
+    /// `flush_outputs`.
+    pub fn parse_output_line(&mut self, line: &str) -> Vec<OutputInfo> {
+        if let Some(capture) = RE_OUTPUT_HEADER.captures(line) {
+            let index = capture
+                .get(1)
+                .and_then(|m| m.as_str().parse::<usize>().ok())
+                .unwrap_or(0);
+            let flushed = self.flush_outputs();
+            self.outputs.insert(
+                index,
+                PendingOutput {
+                    container: capture
+                        .get(2)
+                        .map(|m| m.as_str().trim().to_string())
+                        .unwrap_or_default(),
+                    path: capture
+                        .get(3)
+                        .map(|m| m.as_str().trim().to_string())
+                        .unwrap_or_default(),
+                    ..PendingOutput::default()
+                },
+            );
+            self.current_output = Some(index);
+            self.section = MetadataSection::Output;
+            return flushed;
+        }
+
+        if self.section != MetadataSection::Output {
+            return Vec::new();
+        }
+        // Stream and metadata lines are indented; `[muxer @ 0x...]` notes
+        // can come in between.
+        if !line.starts_with(char::is_whitespace) && !line.starts_with('[') {
+            self.section = MetadataSection::Other;
+            return self.flush_outputs();
+        }
+
+        let Some(pending) = self
+            .current_output
+            .and_then(|index| self.outputs.get_mut(&index))
+        else {
+            return Vec::new();
+        };
+        if pending.emitted {
+            return Vec::new();
+        }
+
+        if let Some(codec) = RE_STREAM_AUDIO.captures(line).and_then(|cap| cap.get(1)) {
+            pending
+                .audio_codec
+                .get_or_insert_with(|| codec.as_str().trim().to_string());
+            return Vec::new();
+        }
+
+        let Some(codec) = RE_STREAM_VIDEO.captures(line).and_then(|cap| cap.get(2)) else {
+            return Vec::new();
+        };
+        let codec = codec.as_str().trim().to_string();
+        let (width, height) = parse_resolution(line);
+        let index = self.current_output.unwrap_or(0);
+
+        pending.emitted = true;
+        vec![pending.to_info(index, codec, width, height)]
+    }
+
+    /// Reports output sections that never showed a video stream.
+    pub fn flush_outputs(&mut self) -> Vec<OutputInfo> {
+        self.outputs
+            .iter_mut()
+            .filter(|(_, pending)| !pending.emitted)
+            .map(|(index, pending)| {
+                pending.emitted = true;
+                let codec = pending.audio_codec.clone().unwrap_or_default();
+                pending.to_info(*index, codec, 0, 0)
+            })
+            .collect()
+    }
+
+    /// Feeds one stderr line and returns the `Stream mapping:` block once
+    /// the first line after it arrives. That line is not consumed; it still
+    /// needs the other parsers.
+    pub fn parse_mapping_line(&mut self, line: &str) -> Option<Vec<StreamMapping>> {
+        let trimmed = line.trim();
+        if trimmed == "Stream mapping:" {
+            self.mappings = Some(Vec::new());
             return None;
         }
+        let mappings = self.mappings.as_mut()?;
+        match parse_stream_mapping(trimmed) {
+            Some(mapping) => {
+                mappings.push(mapping);
+                None
+            }
+            None => self.mappings.take().filter(|found| !found.is_empty()),
+        }
+    }
+}
+
+/// Parses `Stream #0:0 -> #0:0 (h264 (native) -> h264 (libx264))`, `Stream
+/// #0:1 -> #0:1 (copy)`, and the filter graph forms `Stream #0:0 (h264) ->
+/// scale:default (graph 0)` and `overlay (graph 0) -> Stream #0:0 (libx264)`.
+fn parse_stream_mapping(line: &str) -> Option<StreamMapping> {
+    let without_graph = |pad: &str| RE_GRAPH_SUFFIX.replace(pad.trim(), "").into_owned();
+
+    if let Some(capture) = RE_MAP_DIRECT.captures(line) {
+        let detail = &capture[3];
+        let (decoder, encoder) = match RE_MAP_TRANSCODE.captures(detail) {
+            Some(codecs) => {
+                // `native` names ffmpeg's built-in coder; the codec says more.
+                let encoder = codecs
+                    .get(4)
+                    .map(|m| m.as_str())
+                    .filter(|name| *name != "native")
+                    .unwrap_or(&codecs[3]);
+                (Some(codecs[1].to_string()), Some(encoder.to_string()))
+            }
+            None => (None, Some(detail.to_string())),
+        };
+        return Some(StreamMapping {
+            source: capture[1].to_string(),
+            target: capture[2].to_string(),
+            decoder,
+            encoder,
+        });
+    }
+    if let Some(capture) = RE_MAP_TO_GRAPH.captures(line) {
+        return Some(StreamMapping {
+            source: capture[1].to_string(),
+            target: without_graph(&capture[3]),
+            decoder: Some(capture[2].to_string()),
+            encoder: None,
+        });
+    }
+    let capture = RE_MAP_FROM_GRAPH.captures(line)?;
+    Some(StreamMapping {
+        source: without_graph(&capture[1]),
+        target: capture[2].to_string(),
+        decoder: None,
+        encoder: Some(capture[3].to_string()),
+    })
+}
+
+/// Segment extensions written by the hls, dash and segment muxers.
+const SEGMENT_EXTENSIONS: [&str; 5] = ["ts", "m4s", "aac", "mp4", "vtt"];
+
+/// Path of a media segment the muxer just opened, from its `Opening '...'
+/// for writing` line. Playlists and manifests (also "opened") are skipped.
+pub fn parse_segment_opened(line: &str) -> Option<String> {
+    let path = RE_OPENING.captures(line)?.get(1)?.as_str();
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    SEGMENT_EXTENSIONS
+        .contains(&ext.as_str())
+        .then(|| path.to_string())
+}
+
+/// Path of the output file from its `Output #N, mp4, to '...'` header.
+pub fn parse_output_started(line: &str) -> Option<String> {
+    Some(RE_OUTPUT_HEADER.captures(line)?.get(3)?.as_str().trim().to_string())
+}
 
-        let info = self.build_input_info(codec, width, height, fps);
-        if info.is_some() {
-            self.input_emitted = true;
+impl PendingOutput {
+    fn to_info(&self, index: usize, codec: String, width: u32, height: u32) -> OutputInfo {
+        OutputInfo {
+            index,
+            container: self.container.clone(),
+            codec,
+            width,
+            height,
+            path: self.path.clone(),
         }
-        info
     }
+}
 
-    fn build_input_info(
+impl PendingInput {
+    fn to_info(
         &self,
+        index: usize,
         codec: Option<String>,
         width: u32,
         height: u32,
         fps: f32,
-    ) -> Option<InputInfo> {
-        if codec.is_none()
-            && width == 0
-            && height == 0
-            && fps == 0.0
-            && self.pending_input_container.is_none()
-            && self.pending_input_path.is_none()
-            && self.pending_input_duration.is_none()
-            && self.pending_input_bitrate_kbps.is_none()
-        {
-            return None;
-        }
-
-        Some(InputInfo {
+    ) -> InputInfo {
+        InputInfo {
+            index,
             width,
             height,
             fps,
             codec: codec.unwrap_or_default(),
-            duration: self.pending_input_duration,
-            container: self.pending_input_container.clone(),
-            path: self.pending_input_path.clone(),
-            bitrate_kbps: self.pending_input_bitrate_kbps,
-        })
+            duration: self.duration,
+            container: self.container.clone(),
+            path: self.path.clone(),
+            bitrate_kbps: self.bitrate_kbps,
+            chapters: self.chapters.clone(),
+        }
     }
+}
 
-    pub fn parse_output_line(&mut self, line: &str) -> Option<OutputInfo> {
-        if let Some(capture) = RE_OUTPUT_HEADER.captures(line) {
-            let container = capture.get(1).map(|m| m.as_str().trim().to_string());
-            let path = capture.get(2).map(|m| m.as_str().trim().to_string());
-            self.pending_output_container = container;
-            self.pending_output_path = path;
-            self.section = MetadataSection::Output;
-            return None;
-        }
+fn parse_resolution(line: &str) -> (u32, u32) {
+    RE_RESOLUTION
+        .captures(line)
+        .and_then(|cap| {
+            let w = cap.get(1)?.as_str().parse::<u32>().ok()?;
+            let h = cap.get(2)?.as_str().parse::<u32>().ok()?;
+            Some((w, h))
+        })
+        .unwrap_or((0, 0))
+}
 
-        if self.section != MetadataSection::Output && self.pending_output_container.is_none() {
-            return None;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let codec = RE_STREAM_VIDEO
-            .captures(line)
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str().trim().to_string());
-        if codec.is_none() {
-            return None;
+    #[test]
+    fn three_inputs_with_interleaved_stream_lines() {
+        let stderr = [
+            "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'main.mp4':",
+            "  Duration: 00:01:00.00, start: 0.000000, bitrate: 4000 kb/s",
+            "Input #1, png_pipe, from 'logo.png':",
+            "  Duration: N/A, bitrate: N/A",
+            "  Stream #0:0[0x1](und): Video: h264 (High), yuv420p(progressive), 1920x1080, 3800 kb/s, 30 fps, 30 tbr",
+            "Input #2, wav, from 'voice.wav':",
+            "  Duration: 00:01:05.50, bitrate: 1411 kb/s",
+            "  Stream #2:0: Audio: pcm_s16le, 44100 Hz, 2 channels, s16, 1411 kb/s",
+            "  Stream #1:0: Video: png, rgba(pc), 320x120, 25 fps, 25 tbr, 25 tbn",
+            "Output #0, mp4, to 'out.mp4':",
+        ];
+        let mut parser = MetadataParser::new();
+        let mut inputs = Vec::new();
+        let mut emitted_at = Vec::new();
+        for (line_no, line) in stderr.iter().enumerate() {
+            for input in parser.parse_input_line(line) {
+                emitted_at.push((input.index, line_no));
+                inputs.push(input);
+            }
         }
 
-        let (width, height) = RE_RESOLUTION
-            .captures(line)
-            .and_then(|cap| {
-                let w = cap.get(1)?.as_str().parse::<u32>().ok()?;
-                let h = cap.get(2)?.as_str().parse::<u32>().ok()?;
-                Some((w, h))
-            })
-            .unwrap_or((0, 0));
+        // Each input is reported on its own stream line, whichever header
+        // came last; the audio-only one waits for the output header.
+        assert_eq!(emitted_at, [(0, 4), (1, 8), (2, 9)]);
 
-        let container = self.pending_output_container.take().unwrap_or_default();
-        let path = self.pending_output_path.take().unwrap_or_default();
+        let main = &inputs[0];
+        assert_eq!(main.path.as_deref(), Some("main.mp4"));
+        assert_eq!((main.width, main.height, main.fps), (1920, 1080, 30.0));
+        assert_eq!(main.codec, "h264 (High)");
+        assert_eq!(main.duration, Some(Duration::from_secs(60)));
+        assert_eq!(main.bitrate_kbps, Some(4000.0));
 
-        Some(OutputInfo {
-            container,
-            codec: codec.unwrap_or_default(),
-            width,
-            height,
-            path,
-        })
+        let logo = &inputs[1];
+        assert_eq!(logo.path.as_deref(), Some("logo.png"));
+        assert_eq!(logo.container.as_deref(), Some("png_pipe"));
+        assert_eq!((logo.width, logo.height, logo.fps), (320, 120, 25.0));
+        assert_eq!(logo.duration, None);
+
+        let voice = &inputs[2];
+        assert_eq!(voice.path.as_deref(), Some("voice.wav"));
+        assert_eq!((voice.width, voice.height), (0, 0));
+        assert_eq!(voice.duration, Some(Duration::from_millis(65_500)));
+        assert_eq!(voice.bitrate_kbps, Some(1411.0));
+
+        assert!(parser.flush_inputs().is_empty());
     }
 }
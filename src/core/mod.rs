@@ -1,64 +1,47 @@
-use std::process::{Command, Stdio};
-use std::time::Instant;
-
+pub mod alias;
+pub mod capabilities;
 pub mod command;
+pub mod chapters;
+pub mod checkpoint;
+pub mod color;
+pub mod concat;
+pub mod config;
+pub mod container;
+pub mod cropdetect;
+pub mod depends;
 pub mod error;
 pub mod batch;
 pub mod job;
+pub mod ladder;
+pub mod live;
+pub mod loudnorm;
 pub mod progress;
 pub mod metadata;
 pub mod summary;
+pub mod tags;
+pub mod template;
+pub mod verify;
 pub mod event;
+pub mod expand;
 pub mod runner;
+pub mod sequence;
+pub mod session_log;
 pub mod formatter;
-
-use command::FfmpegCommand;
-use error::FfxError;
-use job::{Job, JobStatus};
-
-pub fn run(command: FfmpegCommand) -> Result<Job, FfxError> {
-    let mut job = Job {
-        id: 1,
-        status: JobStatus::Pending,
-        started_at: None,
-        ended_at: None,
-    };
-
-    job.status = JobStatus::Running;
-    job.started_at = Some(Instant::now());
-
-    let mut cmd = Command::new("ffmpeg");
-    cmd.args(command.to_args()).stderr(Stdio::piped());
-
-    let child = cmd.spawn().map_err(|e| {
-        if e.kind() == std::io::ErrorKind::NotFound {
-            FfxError::BinaryNotFound
-        } else {
-            FfxError::ProcessFailed {
-                exit_code: None,
-                stderr: e.to_string(),
-            }
-        }
-    })?;
-
-    let output = child.wait_with_output().map_err(|e| FfxError::ProcessFailed {
-        exit_code: None,
-        stderr: e.to_string(),
-    })?;
-
-    job.ended_at = Some(Instant::now());
-
-    if output.status.success() {
-        job.status = JobStatus::Finished;
-        Ok(job)
-    } else {
-        Err(FfxError::ProcessFailed {
-            exit_code: output.status.code(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-        })
-    }
-}
-
-pub fn run_with_events(command: FfmpegCommand) -> (std::sync::mpsc::Receiver<event::FfmpegEvent>, std::sync::mpsc::Sender<String>) {
-    runner::run_with_events(command)
-}
+pub mod fs;
+pub mod hls;
+pub mod probe;
+pub mod preflight;
+pub mod preset;
+pub mod process;
+pub mod profile;
+pub mod quality;
+pub mod queue;
+pub mod report;
+pub mod reverse;
+pub mod schedule;
+pub mod shell;
+pub mod speed;
+pub mod split;
+pub mod stabilize;
+pub mod watch;
+pub mod watermark;
@@ -0,0 +1,157 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::core::formatter::format_bytes;
+use crate::core::fs::FreeSpace;
+use crate::core::probe::probe_duration;
+use crate::core::progress::parse_duration_from_args;
+use crate::core::runner::writes_to_stdout;
+
+/// Spare room, as a fraction of the projected size, below which a job
+/// gets a warning. Estimates are rough; containers and VBR add overhead.
+pub const HEADROOM: f64 = 0.2;
+
+/// Assumed audio bitrate when only the video's is given, in bits/s.
+const DEFAULT_AUDIO_BITRATE: f64 = 128_000.0;
+
+/// How the projected output compares with the free space where it goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpaceCheck {
+    Enough,
+    /// It fits, but with less than `HEADROOM` to spare.
+    Low {
+        projected: u64,
+        free: u64,
+        dir: PathBuf,
+    },
+    /// It does not fit.
+    Short {
+        projected: u64,
+        free: u64,
+        dir: PathBuf,
+    },
+}
+
+impl SpaceCheck {
+    pub fn message(&self) -> Option<String> {
+        let (projected, free, dir) = match self {
+            SpaceCheck::Enough => return None,
+            SpaceCheck::Low {
+                projected,
+                free,
+                dir,
+            }
+            | SpaceCheck::Short {
+                projected,
+                free,
+                dir,
+            } => (projected, free, dir),
+        };
+        Some(format!(
+            "output may need about {}; only {} free on '{}'",
+            format_bytes(*projected),
+            format_bytes(*free),
+            dir.display()
+        ))
+    }
+}
+
+/// Estimates a job's output size and compares it with the free space in
+/// the output's directory. `None` when either is unknown: a re-encode
+/// without a target bitrate, an unknown duration, a non-file output.
+pub fn preflight(
+    fs: &impl FreeSpace,
+    args: &[OsString],
+    expected: Option<Duration>,
+) -> Option<SpaceCheck> {
+    let output = args.last().map(Path::new)?;
+    let name = output.to_str().unwrap_or_default();
+    if writes_to_stdout(args) || name.starts_with("pipe:") || name.contains("://") {
+        return None;
+    }
+    let inputs = input_paths(args);
+    let duration = || {
+        expected
+            .or_else(|| parse_duration_from_args(args))
+            .or_else(|| inputs.first().and_then(|input| probe_duration(input)))
+    };
+    let projected = estimate_output_bytes(args, duration)?;
+    let dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let free = fs.available(&dir).ok()?;
+    Some(classify(projected, free, dir))
+}
+
+fn classify(projected: u64, free: u64, dir: PathBuf) -> SpaceCheck {
+    if projected > free {
+        SpaceCheck::Short {
+            projected,
+            free,
+            dir,
+        }
+    } else if ((free - projected) as f64) < projected as f64 * HEADROOM {
+        SpaceCheck::Low {
+            projected,
+            free,
+            dir,
+        }
+    } else {
+        SpaceCheck::Enough
+    }
+}
+
+/// Stream copies are about as big as their inputs; encodes with a target
+/// bitrate are bitrate × duration. `duration` is only asked for the latter.
+pub fn estimate_output_bytes(
+    args: &[OsString],
+    duration: impl FnOnce() -> Option<Duration>,
+) -> Option<u64> {
+    let value = |flags: &[&str]| {
+        args.windows(2)
+            .rev()
+            .find(|pair| flags.iter().any(|flag| pair[0] == *flag))
+            .and_then(|pair| pair[1].to_str())
+    };
+    let has = |flag: &str| args.iter().any(|arg| arg == flag);
+    let video_copy = value(&["-c:v", "-vcodec"]) == Some("copy") || has("-vn");
+    let audio_copy = value(&["-c:a", "-acodec"]) == Some("copy") || has("-an");
+    if value(&["-c", "-codec"]) == Some("copy") || (video_copy && audio_copy) {
+        let sizes = input_paths(args)
+            .iter()
+            .map(|input| std::fs::metadata(input).ok().map(|meta| meta.len()))
+            .collect::<Option<Vec<u64>>>()?;
+        return Some(sizes.iter().sum());
+    }
+    let video = value(&["-b:v"])
+        .or_else(|| value(&["-maxrate"]))
+        .and_then(parse_bitrate)?;
+    let audio = match has("-an") {
+        true => 0.0,
+        false => value(&["-b:a"])
+            .and_then(parse_bitrate)
+            .unwrap_or(DEFAULT_AUDIO_BITRATE),
+    };
+    let secs = duration()?.as_secs_f64();
+    Some(((video + audio) * secs / 8.0).round() as u64)
+}
+
+// ffmpeg's bitrate syntax: bits per second, with an optional k or M.
+fn parse_bitrate(value: &str) -> Option<f64> {
+    let (number, multiplier) = match value.as_bytes().last()? {
+        b'k' | b'K' => (&value[..value.len() - 1], 1e3),
+        b'M' | b'm' => (&value[..value.len() - 1], 1e6),
+        _ => (value, 1.0),
+    };
+    let bits = number.parse::<f64>().ok()? * multiplier;
+    (bits.is_finite() && bits > 0.0).then_some(bits)
+}
+
+fn input_paths(args: &[OsString]) -> Vec<PathBuf> {
+    args.windows(2)
+        .filter(|pair| pair[0] == "-i")
+        .map(|pair| PathBuf::from(&pair[1]))
+        .collect()
+}
@@ -0,0 +1,163 @@
+/// x264's speed presets, fastest first. `--preset` takes these names for
+/// any encoder and they are translated to its own speed setting.
+pub const PRESET_NAMES: [&str; 10] = [
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+    "placebo",
+];
+
+// One row per encoder family, one entry per name in `PRESET_NAMES`.
+const VPX: [(&str, u8); 10] = [
+    ("realtime", 8),
+    ("realtime", 6),
+    ("good", 5),
+    ("good", 4),
+    ("good", 3),
+    ("good", 2),
+    ("good", 1),
+    ("good", 0),
+    ("best", 0),
+    ("best", 0),
+];
+const AOM_CPU_USED: [u8; 10] = [8, 7, 6, 5, 4, 3, 2, 1, 0, 0];
+const SVT_PRESET: [u8; 10] = [13, 12, 10, 9, 8, 6, 5, 4, 2, 0];
+const NVENC_PRESET: [&str; 10] = ["p1", "p1", "p2", "p3", "p3", "p4", "p5", "p6", "p7", "p7"];
+const QSV_PRESET: [&str; 10] = [
+    "veryfast", "veryfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow",
+    "veryslow",
+];
+
+/// What `--preset` turns into for one encoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetArgs {
+    /// `-preset NAME` as given.
+    Keep,
+    /// The encoder's own options instead, with a note saying so.
+    Translated(Vec<String>),
+    /// The encoder has nothing the preset maps to; says why.
+    Dropped(String),
+}
+
+/// Maps a preset name onto `codec`'s own speed setting. No codec means
+/// ffmpeg picks one for the container, so the name is passed as is.
+pub fn translate_preset(codec: Option<&str>, preset: &str) -> PresetArgs {
+    let Some(codec) = codec else {
+        return PresetArgs::Keep;
+    };
+    let index = PRESET_NAMES
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(preset));
+    let args = |pairs: &[(&str, String)]| {
+        PresetArgs::Translated(
+            pairs
+                .iter()
+                .flat_map(|(flag, value)| [flag.to_string(), value.clone()])
+                .collect(),
+        )
+    };
+    match (codec, index) {
+        ("libx264" | "libx264rgb" | "libx265", Some(_)) => PresetArgs::Keep,
+        ("libvpx" | "libvpx-vp9", Some(i)) => args(&[
+            ("-deadline", VPX[i].0.to_string()),
+            ("-cpu-used", VPX[i].1.to_string()),
+        ]),
+        ("libaom-av1", Some(i)) => args(&[("-cpu-used", AOM_CPU_USED[i].to_string())]),
+        ("libsvtav1", Some(i)) => args(&[("-preset", SVT_PRESET[i].to_string())]),
+        ("libsvtav1", None) if preset.parse::<u8>().is_ok_and(|n| n <= 13) => PresetArgs::Keep,
+        (nvenc, Some(i)) if nvenc.ends_with("_nvenc") => {
+            args(&[("-preset", NVENC_PRESET[i].to_string())])
+        }
+        (nvenc, None) if nvenc.ends_with("_nvenc") && is_nvenc_preset(preset) => PresetArgs::Keep,
+        (qsv, Some(i)) if qsv.ends_with("_qsv") => args(&[("-preset", QSV_PRESET[i].to_string())]),
+        ("copy", _) => PresetArgs::Dropped("--preset does nothing when the video is copied".into()),
+        (vt, Some(_)) if vt.ends_with("_videotoolbox") => {
+            PresetArgs::Dropped(format!("{vt} has no speed presets"))
+        }
+        (codec, Some(_)) => {
+            PresetArgs::Dropped(format!("ffflow doesn't know {codec}'s speed presets"))
+        }
+        (codec, None) => PresetArgs::Dropped(format!(
+            "'{preset}' is not a preset ffflow knows for {codec} (expected one of {})",
+            PRESET_NAMES.join(", ")
+        )),
+    }
+}
+
+fn is_nvenc_preset(preset: &str) -> bool {
+    preset
+        .strip_prefix('p')
+        .and_then(|level| level.parse::<u8>().ok())
+        .is_some_and(|level| (1..=7).contains(&level))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translated(args: &[&str]) -> PresetArgs {
+        PresetArgs::Translated(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn x264_and_x265_keep_the_preset() {
+        assert_eq!(translate_preset(Some("libx264"), "slow"), PresetArgs::Keep);
+        assert_eq!(translate_preset(Some("libx265"), "veryfast"), PresetArgs::Keep);
+    }
+
+    #[test]
+    fn vp9_maps_to_deadline_and_cpu_used() {
+        assert_eq!(
+            translate_preset(Some("libvpx-vp9"), "slow"),
+            translated(&["-deadline", "good", "-cpu-used", "1"])
+        );
+        assert_eq!(
+            translate_preset(Some("libvpx-vp9"), "ultrafast"),
+            translated(&["-deadline", "realtime", "-cpu-used", "8"])
+        );
+    }
+
+    #[test]
+    fn aom_maps_to_cpu_used() {
+        assert_eq!(
+            translate_preset(Some("libaom-av1"), "medium"),
+            translated(&["-cpu-used", "3"])
+        );
+        assert_eq!(
+            translate_preset(Some("libaom-av1"), "VerySlow"),
+            translated(&["-cpu-used", "0"])
+        );
+    }
+
+    #[test]
+    fn nvenc_maps_to_p_levels() {
+        assert_eq!(
+            translate_preset(Some("h264_nvenc"), "slow"),
+            translated(&["-preset", "p5"])
+        );
+        assert_eq!(translate_preset(Some("h264_nvenc"), "p3"), PresetArgs::Keep);
+        assert!(matches!(
+            translate_preset(Some("h264_nvenc"), "p9"),
+            PresetArgs::Dropped(_)
+        ));
+    }
+
+    #[test]
+    fn videotoolbox_drops_the_preset() {
+        assert_eq!(
+            translate_preset(Some("hevc_videotoolbox"), "fast"),
+            PresetArgs::Dropped("hevc_videotoolbox has no speed presets".to_string())
+        );
+    }
+
+    #[test]
+    fn no_codec_passes_the_name_through() {
+        assert_eq!(translate_preset(None, "whatever"), PresetArgs::Keep);
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::core::error::FfxError;
+use crate::core::metadata::{InputInfo, MetadataParser};
+use crate::core::runner::strip_ansi;
+use crate::core::tags::parse_tags;
+use crate::core::verify::{decode_args, stream_kinds, MediaLayout};
+
+static RE_AUDIO_STREAM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Stream #\d+:\d+.*Audio:\s*([^,\s]+)").unwrap());
+static RE_VIDEO_STREAM: Lazy<Regex> = Lazy::new(|| Regex::new(r"Stream #0:\d+.*Video:").unwrap());
+
+/// Reads an input's container header with `ffmpeg -i` and returns what the
+/// metadata parser found. Nothing is decoded, so this is quick.
+pub fn probe_input(path: &Path) -> Result<Vec<InputInfo>, FfxError> {
+    let (stderr, exit_code) = probe_stderr(path)?;
+    let mut parser = MetadataParser::new();
+    let mut inputs = Vec::new();
+    for line in stderr.lines() {
+        inputs.extend(parser.parse_input_line(line));
+    }
+    inputs.extend(parser.flush_inputs());
+    if inputs.is_empty() {
+        // With no output given ffmpeg always exits 1, so the exit code
+        // says nothing; a missing input section does.
+        return Err(FfxError::ProcessFailed { exit_code, stderr });
+    }
+    Ok(inputs)
+}
+
+/// Duration of the first input that reports one.
+pub fn probe_duration(path: &Path) -> Option<Duration> {
+    probe_input(path)
+        .ok()?
+        .into_iter()
+        .find_map(|info| info.duration)
+}
+
+/// Codec of the input's first audio stream, e.g. `aac`.
+pub fn probe_audio_codec(path: &Path) -> Option<String> {
+    let (stderr, _) = probe_stderr(path).ok()?;
+    stderr
+        .lines()
+        .find_map(|line| RE_AUDIO_STREAM.captures(line))
+        .map(|capture| capture[1].to_string())
+}
+
+/// Number of video streams in the input, cover art included.
+pub fn probe_video_streams(path: &Path) -> usize {
+    probe_stderr(path).map_or(0, |(stderr, _)| {
+        stderr
+            .lines()
+            .filter(|line| RE_VIDEO_STREAM.is_match(line))
+            .count()
+    })
+}
+
+/// Container-level tags (`title`, `artist`, ...) of the input.
+pub fn probe_tags(path: &Path) -> Result<Vec<(String, String)>, FfxError> {
+    let (stderr, exit_code) = probe_stderr(path)?;
+    if !stderr.lines().any(|line| line.starts_with("Input #")) {
+        return Err(FfxError::ProcessFailed { exit_code, stderr });
+    }
+    Ok(parse_tags(&stderr))
+}
+
+/// Duration and stream types of the file, as its header gives them.
+pub fn probe_layout(path: &Path) -> Result<MediaLayout, FfxError> {
+    let (stderr, exit_code) = probe_stderr(path)?;
+    let mut parser = MetadataParser::new();
+    let mut inputs = Vec::new();
+    for line in stderr.lines() {
+        inputs.extend(parser.parse_input_line(line));
+    }
+    inputs.extend(parser.flush_inputs());
+    let Some(input) = inputs.first() else {
+        return Err(FfxError::ProcessFailed { exit_code, stderr });
+    };
+    Ok(MediaLayout {
+        duration: input.duration,
+        streams: stream_kinds(stderr.lines()),
+    })
+}
+
+/// Decodes all of `path` (see `decode_args`) and returns the errors ffmpeg
+/// logged, plus whether it exited cleanly.
+pub fn decode_log(path: &Path) -> Result<(Vec<String>, bool), FfxError> {
+    let output = Command::new("ffmpeg")
+        .args(decode_args(path))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| FfxError::ProcessFailed {
+            exit_code: None,
+            stderr: err.to_string(),
+        })?;
+    let lines = strip_ansi(&String::from_utf8_lossy(&output.stderr))
+        .lines()
+        .map(str::to_string)
+        .collect();
+    Ok((lines, output.status.success()))
+}
+
+fn probe_stderr(path: &Path) -> Result<(String, Option<i32>), FfxError> {
+    let output = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                FfxError::BinaryNotFound
+            } else {
+                FfxError::ProcessFailed {
+                    exit_code: None,
+                    stderr: err.to_string(),
+                }
+            }
+        })?;
+
+    Ok((
+        strip_ansi(&String::from_utf8_lossy(&output.stderr)).into_owned(),
+        output.status.code(),
+    ))
+}
@@ -0,0 +1,142 @@
+use std::io;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static NICENESS: AtomicI32 = AtomicI32::new(0);
+
+/// Highest niceness `set_niceness` takes, as on Unix.
+pub const MAX_NICENESS: i32 = 19;
+
+/// Makes Ctrl-C (SIGINT) set a flag instead of exiting, so the caller can
+/// stop ffmpeg and exit on its own terms. Check it with `interrupted`.
+#[cfg(unix)]
+pub fn catch_interrupt() -> io::Result<()> {
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+    // SAFETY: the handler only stores to an atomic, which is
+    // async-signal-safe.
+    let handler: extern "C" fn(libc::c_int) = on_interrupt;
+    let previous = unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+    if previous == libc::SIG_ERR {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether Ctrl-C was pressed since `catch_interrupt`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Asks a process to stop the way Ctrl-C would (SIGINT); ffmpeg finishes
+/// writing the output it has so far. Unix only.
+#[cfg(unix)]
+pub fn interrupt(pid: u32) -> io::Result<()> {
+    send_signal(pid, libc::SIGINT)
+}
+
+/// Suspends a running process (SIGSTOP). Unix only.
+#[cfg(unix)]
+pub fn pause(pid: u32) -> io::Result<()> {
+    send_signal(pid, libc::SIGSTOP)
+}
+
+/// Continues a process suspended by `pause` (SIGCONT). Unix only.
+#[cfg(unix)]
+pub fn resume(pid: u32) -> io::Result<()> {
+    send_signal(pid, libc::SIGCONT)
+}
+
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: libc::c_int) -> io::Result<()> {
+    let pid = libc::pid_t::try_from(pid)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "pid out of range"))?;
+    // SAFETY: kill(2) has no memory-safety preconditions.
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Sets how much lower than ffflow's own the priority of ffmpeg processes
+/// started from now on is: 0 (unchanged) to `MAX_NICENESS`.
+pub fn set_niceness(nice: i32) {
+    NICENESS.store(nice.clamp(0, MAX_NICENESS), Ordering::SeqCst);
+}
+
+pub fn niceness() -> i32 {
+    NICENESS.load(Ordering::SeqCst)
+}
+
+/// Makes `command` start at the niceness from `set_niceness`.
+#[cfg(unix)]
+pub fn apply_niceness(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    let nice = niceness();
+    if nice == 0 {
+        return;
+    }
+    // SAFETY: nice(2) is async-signal-safe and touches no memory. Raising
+    // the niceness never needs privileges, so its result is not checked.
+    unsafe {
+        command.pre_exec(move || {
+            libc::nice(nice);
+            Ok(())
+        });
+    }
+}
+
+/// Windows has priority classes instead of niceness: any niceness starts
+/// the process below normal, 15 and up at idle priority.
+#[cfg(windows)]
+pub fn apply_niceness(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+
+    const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+    const IDLE_PRIORITY_CLASS: u32 = 0x0000_0040;
+    match niceness() {
+        0 => {}
+        1..=14 => {
+            command.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+        }
+        _ => {
+            command.creation_flags(IDLE_PRIORITY_CLASS);
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn apply_niceness(_command: &mut Command) {}
+
+#[cfg(not(unix))]
+pub fn catch_interrupt() -> io::Result<()> {
+    Err(unsupported("catching Ctrl-C"))
+}
+
+#[cfg(not(unix))]
+pub fn interrupt(_pid: u32) -> io::Result<()> {
+    Err(unsupported("interrupt"))
+}
+
+#[cfg(not(unix))]
+pub fn pause(_pid: u32) -> io::Result<()> {
+    Err(unsupported("pause"))
+}
+
+#[cfg(not(unix))]
+pub fn resume(_pid: u32) -> io::Result<()> {
+    Err(unsupported("pause"))
+}
+
+#[cfg(not(unix))]
+fn unsupported(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{what} unsupported on this platform"),
+    )
+}
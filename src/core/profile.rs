@@ -0,0 +1,95 @@
+use crate::core::command::FfmpegCommand;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub video_codec: Option<&'static str>,
+    pub audio_codec: Option<&'static str>,
+    pub preset: Option<&'static str>,
+    pub crf: Option<u8>,
+    pub scale: Option<&'static str>,
+    pub extra_args: &'static [&'static str],
+}
+
+pub const PROFILES: [Profile; 3] = [
+    Profile {
+        name: "web-720p",
+        description: "H.264/AAC at 720p, streamable mp4",
+        video_codec: Some("libx264"),
+        audio_codec: Some("aac"),
+        preset: Some("medium"),
+        crf: Some(23),
+        scale: Some("-2:720"),
+        extra_args: &["-pix_fmt", "yuv420p", "-movflags", "+faststart"],
+    },
+    Profile {
+        name: "archive",
+        description: "near-lossless H.264, audio copied untouched",
+        video_codec: Some("libx264"),
+        audio_codec: Some("copy"),
+        preset: Some("slow"),
+        crf: Some(18),
+        scale: None,
+        extra_args: &[],
+    },
+    Profile {
+        name: "mp4-fast",
+        description: "quick H.264/AAC mp4 at source resolution",
+        video_codec: Some("libx264"),
+        audio_codec: Some("aac"),
+        preset: Some("veryfast"),
+        crf: Some(23),
+        scale: None,
+        extra_args: &["-movflags", "+faststart"],
+    },
+];
+
+pub fn find_profile(name: &str) -> Option<&'static Profile> {
+    PROFILES
+        .iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(name))
+}
+
+impl Profile {
+    /// Fills in the profile's settings. Codecs and preset only apply where
+    /// the command leaves them unset, and the profile's own arguments go
+    /// ahead of the user's extra args so those still win in ffmpeg.
+    pub fn apply(&self, command: &mut FfmpegCommand) {
+        if command.video_codec.is_none() {
+            command.video_codec = self.video_codec.map(str::to_string);
+        }
+        if command.audio_codec.is_none() {
+            command.audio_codec = self.audio_codec.map(str::to_string);
+        }
+        if command.preset.is_none() {
+            command.preset = self.preset.map(str::to_string);
+        }
+
+        let mut args = Vec::new();
+        if let Some(crf) = self.crf {
+            args.push("-crf".to_string());
+            args.push(crf.to_string());
+        }
+        if let Some(scale) = self.scale {
+            command.video_filters.push(format!("scale={scale}"));
+        }
+        args.extend(self.extra_args.iter().map(|arg| arg.to_string()));
+        args.append(&mut command.extra_args);
+        command.extra_args = args;
+    }
+}
+
+pub const DEFAULT_GIF_FPS: f64 = 10.0;
+
+/// One-pass GIF filter: `palettegen` builds a palette from the clip and
+/// `paletteuse` maps the frames onto it, which looks far better than
+/// ffmpeg's fixed default palette. No `width` keeps the input width.
+pub fn gif_filter(fps: f64, width: Option<u32>) -> String {
+    let mut filters = vec![format!("fps={fps}")];
+    if let Some(width) = width {
+        filters.push(format!("scale={width}:-1:flags=lanczos"));
+    }
+    filters.push("split[a][b];[a]palettegen[p];[b][p]paletteuse".to_string());
+    filters.join(",")
+}
@@ -1,16 +1,25 @@
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::time::{Duration, Instant};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::core::metadata::InputInfo;
+
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct FfmpegProgress {
     pub frame: u64,
-    pub fps: f32,
+    /// `None` while ffmpeg still reports `N/A`.
+    pub fps: Option<f32>,
     pub time: Duration,
-    pub bitrate_kbps: f32,
-    pub speed: f32,
+    pub bitrate_kbps: Option<f32>,
+    pub speed: Option<f32>,
     pub size_bytes: u64,
+    pub dup: Option<u64>,
+    pub drop: Option<u64>,
+    pub q: Option<f32>,
 }
 
 static RE_FRAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"frame=\s*(\d+)").unwrap());
@@ -18,7 +27,12 @@ static RE_FPS: Lazy<Regex> = Lazy::new(|| Regex::new(r"fps=\s*([0-9]*\.?[0-9]+)"
 static RE_TIME: Lazy<Regex> = Lazy::new(|| Regex::new(r"time=\s*([0-9:\.]+)").unwrap());
 static RE_BITRATE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"bitrate=\s*([0-9]*\.?[0-9]+)\s*([A-Za-z/]+)").unwrap());
+static RE_TIME_UNKNOWN: Lazy<Regex> = Lazy::new(|| Regex::new(r"time=\s*(N/A|--)").unwrap());
 static RE_SPEED: Lazy<Regex> = Lazy::new(|| Regex::new(r"speed=\s*([0-9]*\.?[0-9]+)x").unwrap());
+static RE_DUP: Lazy<Regex> = Lazy::new(|| Regex::new(r"dup=\s*(\d+)").unwrap());
+static RE_DROP: Lazy<Regex> = Lazy::new(|| Regex::new(r"drop=\s*(\d+)").unwrap());
+static RE_Q: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|\s)q=\s*(-?[0-9]*\.?[0-9]+)").unwrap());
 static RE_SIZE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"size=\s*([0-9]*\.?[0-9]+)\s*([A-Za-z]+)").unwrap());
 
@@ -49,27 +63,345 @@ pub fn parse_progress_line(line: &str) -> Option<FfmpegProgress> {
         let unit = cap.get(2)?.as_str();
         parse_size_to_bytes(value, unit)
     });
+    let dup = RE_DUP
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .and_then(|m| m.as_str().parse::<u64>().ok());
+    let drop = RE_DROP
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .and_then(|m| m.as_str().parse::<u64>().ok());
+    let q = RE_Q
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .and_then(|m| m.as_str().parse::<f32>().ok());
 
+    // Before anything is written, ffmpeg 7 may report every value as
+    // `N/A`; that is still a stats line, not a log message.
     if frame.is_none()
         && fps.is_none()
         && time.is_none()
         && bitrate.is_none()
         && speed.is_none()
         && size_bytes.is_none()
+        && !RE_TIME_UNKNOWN.is_match(line)
     {
         return None;
     }
 
     Some(FfmpegProgress {
         frame: frame.unwrap_or(0),
-        fps: fps.unwrap_or(0.0),
+        fps,
         time: time.unwrap_or(Duration::from_secs(0)),
-        bitrate_kbps: bitrate.unwrap_or(0.0),
-        speed: speed.unwrap_or(0.0),
+        bitrate_kbps: bitrate,
+        speed,
         size_bytes: size_bytes.unwrap_or(0),
+        dup,
+        drop,
+        q,
     })
 }
 
+/// Time going backwards by more than this between updates means ffmpeg
+/// started another pass inside the same process.
+const PASS_RESET_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Follows which pass of a multi-pass encode is running, so a bar that
+/// restarts from zero each pass can be shown as one overall bar.
+#[derive(Debug, Clone)]
+pub struct PassTracker {
+    current: u32,
+    total: u32,
+    pass_started: Instant,
+    last_time: Duration,
+}
+
+impl PassTracker {
+    pub fn new(current: u32, total: u32) -> Self {
+        let total = total.max(1);
+        Self {
+            current: current.clamp(1, total),
+            total,
+            pass_started: Instant::now(),
+            last_time: Duration::ZERO,
+        }
+    }
+
+    /// Builds a tracker from `-pass N` in an ffmpeg argv. Two-pass encodes
+    /// run as two invocations, so `-pass 1` is pass 1/2 and `-pass 2` is 2/2.
+    pub fn from_args<S: AsRef<OsStr>>(args: &[S]) -> Option<Self> {
+        let args: Vec<&str> = args.iter().filter_map(|arg| arg.as_ref().to_str()).collect();
+        let pass = args
+            .windows(2)
+            .find(|pair| pair[0] == "-pass" || pair[0].starts_with("-pass:"))
+            .and_then(|pair| pair[1].parse::<u32>().ok())?;
+        match pass {
+            1 | 2 => Some(Self::new(pass, 2)),
+            _ => None,
+        }
+    }
+
+    // Library API; the binary only shows `label`.
+    #[allow(dead_code)]
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    #[allow(dead_code)]
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Feeds a progress update. Returns the finished pass number and how long
+    /// it took when the media time jumps back, i.e. a new pass has begun.
+    pub fn observe(&mut self, update: &FfmpegProgress) -> Option<(u32, Duration)> {
+        let reset = update.time + PASS_RESET_THRESHOLD < self.last_time;
+        self.last_time = update.time;
+        if !reset || self.current >= self.total {
+            return None;
+        }
+        let finished = self.finish();
+        self.current += 1;
+        self.pass_started = Instant::now();
+        Some(finished)
+    }
+
+    /// Reports the pass that is running now as complete.
+    pub fn finish(&self) -> (u32, Duration) {
+        (self.current, self.pass_started.elapsed())
+    }
+
+    /// Scales a 0..=1 ratio within the current pass to the whole job.
+    pub fn overall_ratio(&self, pass_ratio: f64) -> f64 {
+        let done = (self.current - 1) as f64 + pass_ratio.clamp(0.0, 1.0);
+        done / self.total as f64
+    }
+
+    /// Renders e.g. `pass 1/2 37%`.
+    pub fn label(&self, pass_ratio: Option<f64>) -> String {
+        match pass_ratio {
+            Some(ratio) => format!(
+                "pass {}/{} {:.0}%",
+                self.current,
+                self.total,
+                ratio.clamp(0.0, 1.0) * 100.0
+            ),
+            None => format!("pass {}/{}", self.current, self.total),
+        }
+    }
+}
+
+/// Speed samples the trend is judged over.
+const SPEED_WINDOW: usize = 8;
+
+/// Fewer samples than this are too noisy to call a trend.
+const SPEED_TREND_MIN_SAMPLES: usize = 4;
+
+/// Change per sample, relative to the mean speed, that counts as a trend.
+const SPEED_TREND_THRESHOLD: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Trend {
+    pub fn arrow(self) -> char {
+        match self {
+            Trend::Rising => '↑',
+            Trend::Falling => '↓',
+            Trend::Steady => '→',
+        }
+    }
+}
+
+/// The last few `speed=` readings in a fixed ring buffer.
+#[derive(Debug, Clone, Default)]
+pub struct SpeedTrend {
+    samples: [f32; SPEED_WINDOW],
+    len: usize,
+    next: usize,
+}
+
+impl SpeedTrend {
+    pub fn push(&mut self, speed: f32) {
+        self.samples[self.next] = speed;
+        self.next = (self.next + 1) % SPEED_WINDOW;
+        self.len = (self.len + 1).min(SPEED_WINDOW);
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.next = 0;
+    }
+
+    pub fn trend(&self) -> Trend {
+        let start = if self.len < SPEED_WINDOW { 0 } else { self.next };
+        let mut ordered = [0.0; SPEED_WINDOW];
+        for (idx, slot) in ordered.iter_mut().take(self.len).enumerate() {
+            *slot = self.samples[(start + idx) % SPEED_WINDOW];
+        }
+        classify_trend(&ordered[..self.len])
+    }
+}
+
+/// Classifies speeds (oldest first) by the least-squares slope, relative to
+/// their mean so a 0.1x drift means the same at 0.5x and at 20x.
+pub fn classify_trend(speeds: &[f32]) -> Trend {
+    if speeds.len() < SPEED_TREND_MIN_SAMPLES {
+        return Trend::Steady;
+    }
+    let n = speeds.len() as f32;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = speeds.iter().sum::<f32>() / n;
+    if mean_y <= 0.0 {
+        return Trend::Steady;
+    }
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (idx, speed) in speeds.iter().enumerate() {
+        let dx = idx as f32 - mean_x;
+        covariance += dx * (speed - mean_y);
+        variance += dx * dx;
+    }
+    let relative_slope = covariance / variance / mean_y;
+    if relative_slope > SPEED_TREND_THRESHOLD {
+        Trend::Rising
+    } else if relative_slope < -SPEED_TREND_THRESHOLD {
+        Trend::Falling
+    } else {
+        Trend::Steady
+    }
+}
+
+/// Progress updates looked back over to tell whether `dup=`/`drop=` still
+/// grow.
+const FRAME_COUNT_WINDOW: usize = 10;
+
+/// Recent `dup=` and `drop=` counters, to tell a job that is still
+/// duplicating or dropping frames from one that did so once.
+#[derive(Debug, Clone, Default)]
+pub struct FrameCounts {
+    dups: VecDeque<u64>,
+    drops: VecDeque<u64>,
+}
+
+impl FrameCounts {
+    pub fn push(&mut self, update: &FfmpegProgress) {
+        for (counts, value) in [(&mut self.dups, update.dup), (&mut self.drops, update.drop)] {
+            if counts.len() == FRAME_COUNT_WINDOW {
+                counts.pop_front();
+            }
+            counts.push_back(value.unwrap_or(0));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.dups.clear();
+        self.drops.clear();
+    }
+
+    /// Frames duplicated over the last few updates.
+    pub fn recent_dups(&self) -> u64 {
+        growth(&self.dups)
+    }
+
+    /// Frames dropped over the last few updates; above zero means the
+    /// input arrives faster than it is processed.
+    pub fn recent_drops(&self) -> u64 {
+        growth(&self.drops)
+    }
+}
+
+fn growth(counts: &VecDeque<u64>) -> u64 {
+    match (counts.front(), counts.back()) {
+        (Some(first), Some(last)) => last.saturating_sub(*first),
+        _ => 0,
+    }
+}
+
+/// Finds the job length from `-t <secs|hh:mm:ss>`, `-to` minus any `-ss`
+/// offset, or a `duration=` filter option, so progress can show a
+/// percentage before ffmpeg reports one.
+pub fn parse_duration_from_args<S: AsRef<OsStr>>(args: &[S]) -> Option<Duration> {
+    let args: Vec<&str> = args.iter().filter_map(|arg| arg.as_ref().to_str()).collect();
+    let end = args
+        .windows(2)
+        .find(|pair| pair[0] == "-to")
+        .and_then(|pair| parse_ffmpeg_time(pair[1]));
+    let mut idx = 0;
+    while idx < args.len() {
+        if args[idx] == "-t" {
+            if let Some(value) = args.get(idx + 1) {
+                if let Ok(seconds) = value.parse::<f64>() {
+                    let micros = (seconds * 1_000_000.0).round().max(0.0) as u64;
+                    return Some(Duration::from_micros(micros));
+                }
+                if let Some(duration) = parse_ffmpeg_time(value) {
+                    return Some(duration);
+                }
+            }
+        }
+        if let Some(pos) = args[idx].find("duration=") {
+            let value = &args[idx][pos + "duration=".len()..];
+            let value = value.split(':').next().unwrap_or(value);
+            if let Ok(seconds) = value.parse::<f64>() {
+                let micros = (seconds * 1_000_000.0).round().max(0.0) as u64;
+                return Some(Duration::from_micros(micros));
+            }
+        }
+        idx += 1;
+    }
+    end.map(|end| end.saturating_sub(parse_seek_from_args(&args).unwrap_or_default()))
+}
+
+/// Which input decides how long a job runs when its arguments don't say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputDuration {
+    /// `-shortest`: the output stops with the shortest input.
+    Shortest,
+    /// The first input a `-map` picks, or input 0 without one.
+    Input(usize),
+}
+
+impl InputDuration {
+    pub fn from_args<S: AsRef<OsStr>>(args: &[S]) -> Self {
+        let args: Vec<&str> = args.iter().filter_map(|arg| arg.as_ref().to_str()).collect();
+        if args.contains(&"-shortest") {
+            return Self::Shortest;
+        }
+        let mapped = args
+            .windows(2)
+            .filter(|pair| pair[0] == "-map")
+            .find_map(|pair| {
+                let digits = pair[1].split(':').next()?.trim_end_matches('?');
+                digits.parse::<usize>().ok()
+            });
+        Self::Input(mapped.unwrap_or(0))
+    }
+
+    /// The duration from the inputs reported so far; `None` until the one
+    /// that counts has shown up with a length.
+    pub fn pick(self, inputs: &[InputInfo]) -> Option<Duration> {
+        match self {
+            Self::Shortest => inputs.iter().filter_map(|info| info.duration).min(),
+            Self::Input(index) => inputs
+                .iter()
+                .find(|info| info.index == index)
+                .and_then(|info| info.duration),
+        }
+    }
+}
+
+/// Returns the `-ss` start offset, wherever it sits in the argv.
+pub fn parse_seek_from_args<S: AsRef<OsStr>>(args: &[S]) -> Option<Duration> {
+    let args: Vec<&str> = args.iter().filter_map(|arg| arg.as_ref().to_str()).collect();
+    args.windows(2)
+        .find(|pair| pair[0] == "-ss")
+        .and_then(|pair| parse_ffmpeg_time(pair[1]))
+}
+
 pub fn parse_ffmpeg_time(value: &str) -> Option<Duration> {
     let parts: Vec<&str> = value.split(':').collect();
     if parts.is_empty() {
@@ -113,15 +445,75 @@ pub fn parse_size_to_bytes(value: f32, unit: &str) -> Option<u64> {
 }
 
 pub fn parse_bitrate_to_kbps(value: f32, unit: &str) -> Option<f32> {
-    let unit = unit.trim().to_ascii_lowercase();
-    let multiplier = if unit.starts_with("kbit") {
+    let unit = unit.trim();
+    let rate = unit.strip_suffix("/s").unwrap_or(unit);
+
+    // ffmpeg always writes bits: `kbits/s` in stats, `kb/s` in headers. An
+    // upper-case `B` is the one place bytes show up (`kB/s`, `MB/s`).
+    let bytes_multiplier = match rate {
+        "B" => Some(0.008),
+        "kB" | "KB" => Some(8.0),
+        "MB" => Some(8000.0),
+        "GB" => Some(8_000_000.0),
+        _ => None,
+    };
+    if let Some(multiplier) = bytes_multiplier {
+        return Some(value * multiplier);
+    }
+
+    let rate = rate.to_ascii_lowercase();
+    let multiplier = if rate.starts_with("kbit") || rate == "kb" {
         1.0
-    } else if unit.starts_with("mbit") {
+    } else if rate.starts_with("mbit") || rate == "mb" {
         1000.0
-    } else if unit.starts_with("gbit") {
+    } else if rate.starts_with("gbit") || rate == "gb" {
         1_000_000.0
+    } else if rate.starts_with("bit") || rate == "b" {
+        0.001
     } else {
         return None;
     };
     Some(value * multiplier)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_unknown_values_stay_unknown() {
+        for line in [
+            "frame=    0 fps=N/A q=N/A size=N/A time=N/A bitrate=N/A speed=N/A",
+            "size=N/A time=N/A bitrate=N/A speed=N/A",
+            "frame=    0 fps=-- q=-- size=-- time=-- bitrate=-- speed=--",
+        ] {
+            let progress = parse_progress_line(line).expect(line);
+            assert_eq!(progress.frame, 0, "{line}");
+            assert_eq!(progress.time, Duration::ZERO, "{line}");
+            assert_eq!(progress.size_bytes, 0, "{line}");
+            assert_eq!(progress.fps, None, "{line}");
+            assert_eq!(progress.bitrate_kbps, None, "{line}");
+            assert_eq!(progress.speed, None, "{line}");
+            assert_eq!(progress.q, None, "{line}");
+            assert_eq!((progress.dup, progress.drop), (None, None), "{line}");
+        }
+    }
+
+    #[test]
+    fn known_values_are_kept_next_to_unknown_ones() {
+        let progress =
+            parse_progress_line("frame=   12 fps=N/A q=28.0 size=     256kB time=00:00:00.48 bitrate=N/A speed=N/A")
+                .unwrap();
+        assert_eq!(progress.frame, 12);
+        assert_eq!(progress.q, Some(28.0));
+        assert_eq!(progress.size_bytes, 256_000);
+        assert_eq!(progress.time, Duration::from_millis(480));
+        assert_eq!((progress.fps, progress.speed, progress.bitrate_kbps), (None, None, None));
+    }
+
+    #[test]
+    fn log_lines_are_not_progress() {
+        assert!(parse_progress_line("Press [q] to stop, [?] for help").is_none());
+        assert!(parse_progress_line("  Duration: N/A, bitrate: N/A").is_none());
+    }
+}
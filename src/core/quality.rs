@@ -0,0 +1,123 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::core::formatter::json_string;
+
+/// Compares an encode against its source with `psnr`, `ssim` and, when the
+/// ffmpeg build has it, `libvmaf`.
+#[derive(Debug, Clone)]
+pub struct QualityJob {
+    pub reference: PathBuf,
+    pub distorted: PathBuf,
+    /// Scale the distorted video to this size first (the reference's).
+    pub scale_to: Option<(u32, u32)>,
+    pub vmaf: bool,
+}
+
+impl QualityJob {
+    /// The distorted video goes first: these filters treat their first
+    /// input as the one being measured and the second as the reference.
+    pub fn args(&self) -> Vec<OsString> {
+        let mut metrics = vec!["psnr", "ssim"];
+        if self.vmaf {
+            metrics.push("libvmaf");
+        }
+        let labels = |prefix: &str| {
+            (0..metrics.len())
+                .map(|idx| format!("[{prefix}{idx}]"))
+                .collect::<String>()
+        };
+        let scale = self
+            .scale_to
+            .map(|(width, height)| format!("scale={width}:{height}:flags=bicubic,"))
+            .unwrap_or_default();
+        let mut graph = format!(
+            "[0:v]{scale}split={n}{d};[1:v]split={n}{r}",
+            n = metrics.len(),
+            d = labels("d"),
+            r = labels("r"),
+        );
+        for (idx, metric) in metrics.iter().enumerate() {
+            graph.push_str(&format!(";[d{idx}][r{idx}]{metric}"));
+        }
+
+        vec![
+            "-hide_banner".into(),
+            "-i".into(),
+            self.distorted.clone().into_os_string(),
+            "-i".into(),
+            self.reference.clone().into_os_string(),
+            "-lavfi".into(),
+            graph.into(),
+            "-f".into(),
+            "null".into(),
+            "-".into(),
+        ]
+    }
+}
+
+/// Final averages; a metric that was not run (or not printed) is `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QualityScores {
+    pub psnr: Option<f64>,
+    pub ssim: Option<f64>,
+    pub vmaf: Option<f64>,
+}
+
+impl QualityScores {
+    pub fn is_empty(&self) -> bool {
+        self.psnr.is_none() && self.ssim.is_none() && self.vmaf.is_none()
+    }
+
+    /// One JSON object with both paths and the three scores. Scores that
+    /// are missing or infinite (PSNR of identical files) become `null`.
+    pub fn to_json(self, job: &QualityJob) -> String {
+        let number = |value: Option<f64>| match value {
+            Some(value) if value.is_finite() => value.to_string(),
+            _ => "null".to_string(),
+        };
+        format!(
+            "{{\"reference\": {}, \"distorted\": {}, \"psnr\": {}, \"ssim\": {}, \"vmaf\": {}}}\n",
+            json_string(&job.reference.to_string_lossy()),
+            json_string(&job.distorted.to_string_lossy()),
+            number(self.psnr),
+            number(self.ssim),
+            number(self.vmaf),
+        )
+    }
+}
+
+static RE_PSNR: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bPSNR .*\baverage:(\S+)").unwrap());
+static RE_SSIM: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bSSIM .*\bAll:([0-9.]+)").unwrap());
+// libvmaf 2.x prints `VMAF score: 93.1`, the 1.x wrapper `VMAF score = 93.1`.
+static RE_VMAF: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bVMAF score\s*[:=]\s*([0-9.]+)").unwrap());
+
+/// Picks the summary lines the metric filters print when they close.
+#[derive(Debug, Default)]
+pub struct QualityParser {
+    scores: QualityScores,
+}
+
+impl QualityParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        let score = |re: &Regex| re.captures(line).and_then(|cap| cap[1].parse::<f64>().ok());
+        if let Some(value) = score(&RE_PSNR) {
+            self.scores.psnr = Some(value);
+        } else if let Some(value) = score(&RE_SSIM) {
+            self.scores.ssim = Some(value);
+        } else if let Some(value) = score(&RE_VMAF) {
+            self.scores.vmaf = Some(value);
+        }
+    }
+
+    pub fn scores(&self) -> QualityScores {
+        self.scores
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::VecDeque;
+use std::ffi::{OsStr, OsString};
+use std::str::FromStr;
+
+use crate::core::shell::{strip_prefix_os, trim_os};
+
+/// A command waiting in the queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedJob {
+    pub line: OsString,
+    /// Higher runs sooner; 0 unless the line asked for another.
+    pub priority: i32,
+}
+
+/// Commands waiting to run. Higher priorities go first and equal ones keep
+/// the order they were added in, so without priorities it is plain FIFO.
+/// Only waiting jobs are ordered; the running one is never in here.
+#[derive(Debug, Clone, Default)]
+pub struct JobQueue {
+    jobs: VecDeque<QueuedJob>,
+}
+
+/// What happens to the jobs still waiting when one fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// Run the next job anyway.
+    #[default]
+    Continue,
+    /// Drop the rest of the queue.
+    Stop,
+    /// Hold the queue until the user resumes it.
+    Pause,
+}
+
+impl OnError {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Continue => "continue",
+            Self::Stop => "stop",
+            Self::Pause => "pause",
+        }
+    }
+}
+
+impl FromStr for OnError {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "continue" => Ok(Self::Continue),
+            "stop" => Ok(Self::Stop),
+            "pause" => Ok(Self::Pause),
+            _ => Err(format!(
+                "invalid on-error policy '{value}' (expected continue, stop or pause)"
+            )),
+        }
+    }
+}
+
+/// The policy an `@on_error <policy>` line in a `.flw` file sets.
+pub fn parse_on_error_directive(line: &str) -> Option<Result<OnError, String>> {
+    let value = line.strip_prefix("@on_error")?;
+    if !value.is_empty() && !value.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(value.trim().parse())
+}
+
+/// Splits a leading `@priority N` off a line, as `.flw` files may use.
+pub fn split_priority(line: &OsStr) -> Result<(i32, OsString), String> {
+    let Some(rest) = strip_prefix_os(line, "@priority")
+        .filter(|rest| rest.is_empty() || rest.to_string_lossy().starts_with(char::is_whitespace))
+    else {
+        return Ok((0, line.to_owned()));
+    };
+    let rest = trim_os(&rest);
+    let text = rest.to_string_lossy();
+    let number = text.split_whitespace().next().unwrap_or_default();
+    let priority = number
+        .parse::<i32>()
+        .map_err(|_| format!("invalid priority '{number}' (expected a whole number)"))?;
+    let command = strip_prefix_os(&rest, number).unwrap_or_default();
+    Ok((priority, trim_os(&command)))
+}
+
+/// The line without its "run this next" marker, when it has one: a `!`
+/// on the command (`encode! ...`) or a `--next` word before any `--`.
+pub fn strip_next_marker(line: &OsStr) -> Option<OsString> {
+    let text = line.to_str()?.trim_start();
+    let first = text.split_whitespace().next()?;
+    if let Some(command) = first
+        .strip_suffix('!')
+        .filter(|command| !command.is_empty() && !command.starts_with('!'))
+    {
+        return Some(OsString::from(format!("{command}{}", &text[first.len()..])));
+    }
+    let mut words = shell_words::split(text).ok()?;
+    let end = words.iter().position(|word| word == "--").unwrap_or(words.len());
+    let at = words[..end].iter().position(|word| word == "--next")?;
+    words.remove(at);
+    Some(OsString::from(shell_words::join(&words)))
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &QueuedJob> {
+        self.jobs.iter()
+    }
+
+    /// Adds `line` behind every job of the same or higher priority. A line
+    /// starting with `@priority N` gets that priority; one whose number
+    /// doesn't parse is queued as it is, so running it reports the error.
+    pub fn push_back(&mut self, line: OsString) {
+        let (priority, line) = match split_priority(&line) {
+            Ok(split) => split,
+            Err(_) => (0, line),
+        };
+        self.push_with_priority(line, priority);
+    }
+
+    pub fn push_with_priority(&mut self, line: OsString, priority: i32) {
+        let at = self
+            .jobs
+            .iter()
+            .position(|queued| queued.priority < priority)
+            .unwrap_or(self.jobs.len());
+        self.jobs.insert(at, QueuedJob { line, priority });
+    }
+
+    /// Puts `line` next in line, ahead of every other waiting job.
+    pub fn push_front(&mut self, line: OsString) {
+        let priority = self.jobs.front().map_or(0, |job| job.priority).max(0);
+        self.jobs.push_front(QueuedJob { line, priority });
+    }
+
+    pub fn pop_front(&mut self) -> Option<OsString> {
+        self.jobs.pop_front().map(|job| job.line)
+    }
+
+    /// Moves the job at `index` (0 is next) to the front. It takes on the
+    /// front job's priority if that is higher, so it stays in front.
+    pub fn promote(&mut self, index: usize) -> Option<&QueuedJob> {
+        self.move_to(index, 0)
+    }
+
+    /// Moves the job at `from` so it sits at `to`, counting after it has
+    /// been taken out. Its priority is raised or lowered just enough to
+    /// keep it there between its new neighbours.
+    pub fn move_to(&mut self, from: usize, to: usize) -> Option<&QueuedJob> {
+        if to >= self.jobs.len() {
+            return None;
+        }
+        let mut job = self.jobs.remove(from)?;
+        let ahead = to.checked_sub(1).and_then(|index| self.jobs.get(index));
+        if let Some(ahead) = ahead {
+            job.priority = job.priority.min(ahead.priority);
+        }
+        if let Some(behind) = self.jobs.get(to) {
+            job.priority = job.priority.max(behind.priority);
+        }
+        self.jobs.insert(to, job);
+        self.jobs.get(to)
+    }
+
+    pub fn clear(&mut self) {
+        self.jobs.clear();
+    }
+
+    /// Takes the job at `index` out of the queue.
+    pub fn remove(&mut self, index: usize) -> Option<QueuedJob> {
+        self.jobs.remove(index)
+    }
+}
+
+impl Extend<OsString> for JobQueue {
+    fn extend<I: IntoIterator<Item = OsString>>(&mut self, lines: I) {
+        for line in lines {
+            self.push_back(line);
+        }
+    }
+}
+
+impl From<Vec<OsString>> for JobQueue {
+    fn from(lines: Vec<OsString>) -> Self {
+        let mut queue = Self::new();
+        queue.extend(lines);
+        queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(lines: &[&str]) -> JobQueue {
+        JobQueue::from(lines.iter().map(OsString::from).collect::<Vec<_>>())
+    }
+
+    /// The waiting lines, next first.
+    fn lines(queue: &JobQueue) -> Vec<String> {
+        queue.iter().map(|job| job.line.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn without_priorities_the_queue_is_fifo() {
+        let mut queue = queue(&["probe -i a.mp4", "probe -i b.mp4"]);
+        queue.push_back("probe -i c.mp4".into());
+        assert_eq!(queue.pop_front().unwrap(), "probe -i a.mp4");
+        assert_eq!(queue.pop_front().unwrap(), "probe -i b.mp4");
+        assert_eq!(queue.pop_front().unwrap(), "probe -i c.mp4");
+        assert!(queue.pop_front().is_none());
+    }
+
+    #[test]
+    fn higher_priority_goes_first_and_ties_keep_their_order() {
+        let queue = queue(&[
+            "probe -i a.mp4",
+            "@priority 5 probe -i b.mp4",
+            "@priority -1 probe -i c.mp4",
+            "@priority 5 probe -i d.mp4",
+            "probe -i e.mp4",
+        ]);
+        assert_eq!(
+            lines(&queue),
+            ["probe -i b.mp4", "probe -i d.mp4", "probe -i a.mp4", "probe -i e.mp4", "probe -i c.mp4"]
+        );
+        let priorities: Vec<i32> = queue.iter().map(|job| job.priority).collect();
+        assert_eq!(priorities, [5, 5, 0, 0, -1]);
+    }
+
+    #[test]
+    fn bad_priority_is_queued_as_typed() {
+        let mut queue = JobQueue::new();
+        queue.push_back("@priority high probe -i a.mp4".into());
+        let job = queue.iter().next().unwrap();
+        assert_eq!((job.line.to_str().unwrap(), job.priority), ("@priority high probe -i a.mp4", 0));
+    }
+
+    #[test]
+    fn push_front_jumps_every_priority() {
+        let mut queue = queue(&["@priority 3 probe -i a.mp4", "probe -i b.mp4"]);
+        queue.push_front("probe -i now.mp4".into());
+        assert_eq!(lines(&queue), ["probe -i now.mp4", "probe -i a.mp4", "probe -i b.mp4"]);
+        // It keeps its place when another job of that priority comes in.
+        queue.push_with_priority("probe -i c.mp4".into(), 3);
+        assert_eq!(queue.pop_front().unwrap(), "probe -i now.mp4");
+    }
+
+    #[test]
+    fn promoted_job_stays_in_front() {
+        let mut queue = queue(&["@priority 2 probe -i a.mp4", "probe -i b.mp4", "probe -i c.mp4"]);
+        let promoted = queue.promote(2).unwrap();
+        assert_eq!((promoted.line.to_str().unwrap(), promoted.priority), ("probe -i c.mp4", 2));
+        queue.push_with_priority("probe -i d.mp4".into(), 2);
+        assert_eq!(
+            lines(&queue),
+            ["probe -i c.mp4", "probe -i a.mp4", "probe -i d.mp4", "probe -i b.mp4"]
+        );
+        assert!(queue.promote(4).is_none());
+    }
+
+    #[test]
+    fn next_markers_are_stripped() {
+        let strip = |line: &str| strip_next_marker(OsStr::new(line)).map(|line| line.into_string().unwrap());
+        assert_eq!(strip("encode! -i a.mov -o a.mp4").as_deref(), Some("encode -i a.mov -o a.mp4"));
+        assert_eq!(strip("encode -i a.mov --next -o a.mp4").as_deref(), Some("encode -i a.mov -o a.mp4"));
+        // After `--` it belongs to ffmpeg.
+        assert_eq!(strip("encode -i a.mov -o a.mp4 -- --next"), None);
+        assert_eq!(strip("encode -i a.mov -o a.mp4"), None);
+        assert_eq!(strip("! -i a.mov"), None);
+    }
+
+    #[test]
+    fn priority_prefix_is_split_off() {
+        let split = |line: &str| {
+            split_priority(OsStr::new(line)).map(|(priority, line)| (priority, line.into_string().unwrap()))
+        };
+        assert_eq!(split("@priority 10 probe -i a.mp4"), Ok((10, "probe -i a.mp4".into())));
+        assert_eq!(split("@priority -2   probe -i a.mp4"), Ok((-2, "probe -i a.mp4".into())));
+        assert_eq!(split("probe -i a.mp4"), Ok((0, "probe -i a.mp4".into())));
+        assert_eq!(split("@priorityx probe"), Ok((0, "@priorityx probe".into())));
+        assert!(split("@priority soon probe -i a.mp4").is_err());
+    }
+
+    #[test]
+    fn moved_job_keeps_its_new_place() {
+        let mut queue = queue(&["probe -i a.mp4", "probe -i b.mp4", "probe -i c.mp4", "probe -i d.mp4"]);
+        let moved = queue.move_to(0, 2).unwrap();
+        assert_eq!(moved.line, "probe -i a.mp4");
+        assert_eq!(lines(&queue), ["probe -i b.mp4", "probe -i c.mp4", "probe -i a.mp4", "probe -i d.mp4"]);
+        queue.move_to(3, 0);
+        assert_eq!(lines(&queue), ["probe -i d.mp4", "probe -i b.mp4", "probe -i c.mp4", "probe -i a.mp4"]);
+        queue.push_back("probe -i e.mp4".into());
+        assert_eq!(lines(&queue).last().unwrap(), "probe -i e.mp4");
+    }
+
+    #[test]
+    fn moving_between_priorities_takes_the_neighbours_priority() {
+        let mut queue = queue(&["@priority 9 probe -i a.mp4", "@priority 5 probe -i b.mp4", "probe -i c.mp4"]);
+        // Moved up among the 9s, it is a 9 too.
+        assert_eq!(queue.move_to(2, 0).unwrap().priority, 9);
+        queue.push_with_priority("probe -i d.mp4".into(), 9);
+        assert_eq!(lines(&queue), ["probe -i c.mp4", "probe -i a.mp4", "probe -i d.mp4", "probe -i b.mp4"]);
+        // Moved down behind the 5, it drops to 5, so a new 5 lines up behind it.
+        assert_eq!(queue.move_to(0, 3).unwrap().priority, 5);
+        queue.push_with_priority("probe -i e.mp4".into(), 5);
+        assert_eq!(
+            lines(&queue),
+            ["probe -i a.mp4", "probe -i d.mp4", "probe -i b.mp4", "probe -i c.mp4", "probe -i e.mp4"]
+        );
+    }
+
+    #[test]
+    fn out_of_range_moves_and_removals_change_nothing() {
+        let mut queue = queue(&["probe -i a.mp4", "probe -i b.mp4"]);
+        assert!(queue.move_to(2, 0).is_none());
+        assert!(queue.move_to(0, 2).is_none());
+        assert!(queue.remove(2).is_none());
+        assert_eq!(lines(&queue), ["probe -i a.mp4", "probe -i b.mp4"]);
+    }
+
+    #[test]
+    fn removed_job_is_returned() {
+        let mut queue = queue(&["probe -i a.mp4", "@priority 1 probe -i b.mp4", "probe -i c.mp4"]);
+        let removed = queue.remove(0).unwrap();
+        assert_eq!((removed.line.to_str().unwrap(), removed.priority), ("probe -i b.mp4", 1));
+        assert_eq!(lines(&queue), ["probe -i a.mp4", "probe -i c.mp4"]);
+        assert_eq!(queue.len(), 2);
+    }
+}
@@ -0,0 +1,124 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::core::formatter::json_string;
+use crate::core::job::JobStatus;
+
+/// What one finished job did, for `report`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobReport {
+    pub command: String,
+    pub status: JobStatus,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub size_bytes: Option<u64>,
+    pub avg_bitrate_kbps: Option<f32>,
+    /// Media duration ffmpeg wrote.
+    pub duration: Option<Duration>,
+    /// Wall-clock time the job took.
+    pub elapsed: Duration,
+    /// Problems `--verify` found with the output.
+    pub verification: Vec<String>,
+}
+
+/// Writes the reports as JSON or CSV, picked by the file extension.
+pub fn write_reports(path: &Path, reports: &[JobReport]) -> io::Result<()> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let contents = match ext.as_deref() {
+        Some("json") => reports_to_json(reports),
+        Some("csv") => reports_to_csv(reports),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "report file must end in .json or .csv",
+            ))
+        }
+    };
+    std::fs::write(path, contents)
+}
+
+/// A JSON array with one object per job; missing values are `null`.
+pub fn reports_to_json(reports: &[JobReport]) -> String {
+    let text = |value: &Option<String>| value.as_deref().map_or("null".to_string(), json_string);
+    let number = |value: Option<f64>| match value {
+        Some(value) if value.is_finite() => value.to_string(),
+        _ => "null".to_string(),
+    };
+    let objects: Vec<String> = reports
+        .iter()
+        .map(|report| {
+            format!(
+                "  {{\"command\": {}, \"status\": {}, \"input\": {}, \"output\": {}, \
+                 \"size_bytes\": {}, \"avg_bitrate_kbps\": {}, \"duration_secs\": {}, \
+                 \"elapsed_secs\": {}, \"verification\": [{}]}}",
+                json_string(&report.command),
+                json_string(&format!("{:?}", report.status)),
+                text(&report.input),
+                text(&report.output),
+                report
+                    .size_bytes
+                    .map_or("null".to_string(), |bytes| bytes.to_string()),
+                number(report.avg_bitrate_kbps.map(f64::from)),
+                number(report.duration.map(|duration| duration.as_secs_f64())),
+                number(Some(report.elapsed.as_secs_f64())),
+                report
+                    .verification
+                    .iter()
+                    .map(|finding| json_string(finding))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+const CSV_HEADER: &str =
+    "command,status,input,output,size_bytes,avg_bitrate_kbps,duration_secs,elapsed_secs,verification";
+
+/// One header row, then one row per job; missing values are empty.
+pub fn reports_to_csv(reports: &[JobReport]) -> String {
+    let mut out = format!("{CSV_HEADER}\n");
+    for report in reports {
+        let fields = [
+            csv_field(&report.command),
+            format!("{:?}", report.status),
+            csv_field(report.input.as_deref().unwrap_or_default()),
+            csv_field(report.output.as_deref().unwrap_or_default()),
+            report
+                .size_bytes
+                .map(|bytes| bytes.to_string())
+                .unwrap_or_default(),
+            report
+                .avg_bitrate_kbps
+                .map(|kbps| kbps.to_string())
+                .unwrap_or_default(),
+            report
+                .duration
+                .map(|duration| format!("{:.3}", duration.as_secs_f64()))
+                .unwrap_or_default(),
+            format!("{:.3}", report.elapsed.as_secs_f64()),
+            csv_field(&report.verification.join("; ")),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+// RFC 4180: quote fields holding a comma, quote or line break, doubling
+// any quotes inside.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,17 @@
+use std::time::Duration;
+
+/// Above this length `loop --reverse`/`--boomerang` warn before starting:
+/// `reverse` and `areverse` hold every decoded frame in memory.
+pub const DEFAULT_REVERSE_LIMIT: Duration = Duration::from_secs(60);
+
+/// `-filter_complex` graph that plays the clip forwards then backwards.
+/// Outputs `[vout]`, plus `[aout]` when `audio` is set.
+pub fn boomerang_graph(audio: bool) -> String {
+    let video = "[0:v]split[fwd][back];[back]reverse[rev];[fwd][rev]concat=n=2:v=1:a=0[vout]";
+    if !audio {
+        return video.to_string();
+    }
+    format!(
+        "{video};[0:a]asplit[afwd][aback];[aback]areverse[arev];[afwd][arev]concat=n=2:v=0:a=1[aout]"
+    )
+}
@@ -1,14 +1,28 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::ffi::OsString;
 use std::io::{BufReader, Read};
 use std::process::{Command, Stdio};
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
 
 use crate::core::command::FfmpegCommand;
+use crate::core::error::{reported_failure, FAILURE_TAIL_LINES};
 use crate::core::event::{classify_log_line, FfmpegEvent, LogLevel};
-use crate::core::metadata::MetadataParser;
+use crate::core::job::{Job, JobStatus};
+use crate::core::metadata::{parse_output_started, parse_segment_opened, MetadataParser};
+use crate::core::process;
 use crate::core::progress::{parse_bitrate_to_kbps, parse_ffmpeg_time, parse_progress_line, FfmpegProgress};
-use crate::core::summary::parse_summary_line;
+use crate::core::summary::{parse_summary_line, EncodeSummary};
+
+// Parameter bytes, intermediate bytes, then one final byte.
+static RE_CSI: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]").unwrap());
 
 #[derive(Debug, Clone, Copy)]
 enum StreamKind {
@@ -24,6 +38,9 @@ struct ProgressAccumulator {
     bitrate_kbps: Option<f32>,
     speed: Option<f32>,
     size_bytes: Option<u64>,
+    dup: Option<u64>,
+    drop: Option<u64>,
+    q: Option<f32>,
 }
 
 impl ProgressAccumulator {
@@ -62,6 +79,16 @@ impl ProgressAccumulator {
                     self.time = Some(Duration::from_micros(parsed));
                 }
             }
+            "dup_frames" => {
+                self.dup = value.trim().parse::<u64>().ok();
+            }
+            "drop_frames" => {
+                self.drop = value.trim().parse::<u64>().ok();
+            }
+            // Per-stream quantizer, e.g. `stream_0_0_q`; the first one wins.
+            key if key.starts_with("stream_") && key.ends_with("_q") && self.q.is_none() => {
+                self.q = value.trim().parse::<f32>().ok();
+            }
             _ => {}
         }
     }
@@ -73,23 +100,105 @@ impl ProgressAccumulator {
             && self.bitrate_kbps.is_none()
             && self.speed.is_none()
             && self.size_bytes.is_none()
+            && self.dup.is_none()
+            && self.drop.is_none()
         {
             return None;
         }
 
         Some(FfmpegProgress {
             frame: self.frame.unwrap_or(0),
-            fps: self.fps.unwrap_or(0.0),
+            fps: self.fps,
             time: self.time.unwrap_or(Duration::from_secs(0)),
-            bitrate_kbps: self.bitrate_kbps.unwrap_or(0.0),
-            speed: self.speed.unwrap_or(0.0),
+            bitrate_kbps: self.bitrate_kbps,
+            speed: self.speed,
             size_bytes: self.size_bytes.unwrap_or(0),
+            dup: self.dup,
+            drop: self.drop,
+            q: self.q,
         })
     }
 
     fn reset(&mut self) {
         *self = Self::default();
     }
+
+    /// Whatever arrived after the last `progress=` line. ffmpeg normally
+    /// closes every group, but one cut short at exit would be lost.
+    fn finish(&mut self) -> Option<FfmpegProgress> {
+        let progress = self.to_progress();
+        self.reset();
+        progress
+    }
+}
+
+/// Frames dropped between two consecutive progress updates that count as a
+/// sudden rise worth telling the user about.
+const DROP_SPIKE_FRAMES: u64 = 25;
+
+/// Watches `drop=` across progress updates and warns once on a sharp rise.
+#[derive(Default)]
+struct DropWatch {
+    last: u64,
+    warned: bool,
+}
+
+impl DropWatch {
+    fn observe(&mut self, progress: &FfmpegProgress) -> Option<String> {
+        let drop = progress.drop?;
+        let delta = drop.saturating_sub(self.last);
+        self.last = drop;
+        if self.warned || delta < DROP_SPIKE_FRAMES {
+            return None;
+        }
+        self.warned = true;
+        Some(format!(
+            "ffmpeg dropped {delta} frames in one update ({drop} total); output may stutter or drift out of sync"
+        ))
+    }
+}
+
+/// When a job counts as stuck and is killed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobTimeout {
+    pub limit: Duration,
+    /// Count from the start of the job instead of from the last progress
+    /// update.
+    pub wall_clock: bool,
+}
+
+/// Deadline for a running job. Unless it is a wall-clock limit, every
+/// progress update pushes the deadline back, so only a stalled job expires.
+struct Watchdog {
+    timeout: JobTimeout,
+    deadline: Instant,
+}
+
+impl Watchdog {
+    fn start(timeout: JobTimeout, now: Instant) -> Self {
+        Self {
+            timeout,
+            deadline: now + timeout.limit,
+        }
+    }
+
+    fn progressed(&mut self, now: Instant) {
+        if !self.timeout.wall_clock {
+            self.deadline = now + self.timeout.limit;
+        }
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        now >= self.deadline
+    }
+
+    fn message(&self) -> String {
+        let secs = self.timeout.limit.as_secs_f64();
+        match self.timeout.wall_clock {
+            true => format!("timeout: job still running after {secs}s"),
+            false => format!("timeout: no progress for {secs}s"),
+        }
+    }
 }
 
 fn split_number_unit(value: &str) -> Option<(&str, &str)> {
@@ -107,7 +216,188 @@ fn split_number_unit(value: &str) -> Option<(&str, &str)> {
     Some((&trimmed[..idx], trimmed[idx..].trim()))
 }
 
-fn has_progress_stdout(args: &[String]) -> bool {
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Whether jobs started from now on also send the lines that count as
+/// noise (banners, encoder stats) as `FfmpegEvent::Noise`.
+pub fn set_verbose(on: bool) {
+    VERBOSE.store(on, Ordering::SeqCst);
+}
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::SeqCst)
+}
+
+const NO_OUTPUT_MESSAGE: &str = "At least one output file must be specified";
+
+/// Lines buffered between the pipe readers and the parser before ffmpeg's
+/// own writes start to block.
+const LINE_BUFFER: usize = 1024;
+
+/// Latest progress snapshot of a job. The runner overwrites it on every
+/// update, so a consumer that polls once per frame never falls behind, no
+/// matter how fast ffmpeg reports. Each snapshot also records how many
+/// events had been sent before it, which is where it belongs in the stream.
+#[derive(Debug, Clone, Default)]
+pub struct LatestProgress(Arc<Mutex<Option<(u64, FfmpegProgress)>>>);
+
+impl LatestProgress {
+    pub(crate) fn set(&self, progress: FfmpegProgress, sent_before: u64) {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner()) = Some((sent_before, progress));
+    }
+
+    /// Returns the newest update since the last call, if any.
+    pub fn take(&self) -> Option<FfmpegProgress> {
+        self.0
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take()
+            .map(|(_, progress)| progress)
+    }
+
+    /// `take`, but only once `received` events cover every event sent
+    /// before the snapshot, so it can be shown in its place among them.
+    pub fn take_after(&self, received: u64) -> Option<FfmpegProgress> {
+        let mut slot = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        match *slot {
+            Some((sent_before, _)) if sent_before <= received => {
+                slot.take().map(|(_, progress)| progress)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The runner's end of a job's event channel. Counts what it sends, so
+/// progress snapshots can be placed among the events.
+struct EventSender {
+    tx: Sender<FfmpegEvent>,
+    sent: u64,
+}
+
+impl EventSender {
+    /// Sends `event`; one the consumer already dropped is lost.
+    fn send(&mut self, event: FfmpegEvent) {
+        self.sent += 1;
+        let _ = self.tx.send(event);
+    }
+}
+
+/// How long the runner waits for output before checking for cancellation.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// Shared flag that asks the runner to kill its ffmpeg child.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Pid of a job's ffmpeg process while it runs; unset before spawn and once
+/// the process has been reaped, so a recycled pid is never signalled.
+#[derive(Debug, Clone, Default)]
+pub struct ChildPid(Arc<AtomicU32>);
+
+impl ChildPid {
+    fn set(&self, pid: u32) {
+        self.0.store(pid, Ordering::SeqCst);
+    }
+
+    fn clear(&self) {
+        self.0.store(0, Ordering::SeqCst);
+    }
+
+    pub fn get(&self) -> Option<u32> {
+        match self.0.load(Ordering::SeqCst) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
+}
+
+/// The exit code ffmpeg ended with, set once the process has been reaped.
+/// Stays unset for a job killed by a signal or one that never started.
+#[derive(Debug, Clone, Default)]
+pub struct ChildExit(Arc<OnceLock<i32>>);
+
+impl ChildExit {
+    pub fn get(&self) -> Option<i32> {
+        self.0.get().copied()
+    }
+}
+
+/// A running ffmpeg job. Progress is coalesced into `progress`; every other
+/// event arrives losslessly on `events`, which closes when the job ends.
+pub struct JobHandle {
+    pub events: Receiver<FfmpegEvent>,
+    pub stdin: Sender<String>,
+    pub progress: LatestProgress,
+    pub cancel: CancelToken,
+    pub pid: ChildPid,
+    pub exit: ChildExit,
+}
+
+/// Iterator over every event of a job, progress included, in the order the
+/// runner produced them. Progress updates that came in faster than they were
+/// read are coalesced into the newest one. Ends when ffmpeg exits.
+// Library API; the binary reads `JobHandle` directly.
+#[allow(dead_code)]
+pub struct EventStream {
+    handle: JobHandle,
+    received: u64,
+}
+
+#[allow(dead_code)]
+impl EventStream {
+    /// Kills the ffmpeg process; the stream then ends with an error event.
+    pub fn cancel(&self) {
+        self.handle.cancel.cancel();
+    }
+
+    /// Writes to ffmpeg's stdin, e.g. `"y\n"` to answer an overwrite prompt.
+    pub fn stdin(&self) -> &Sender<String> {
+        &self.handle.stdin
+    }
+}
+
+impl From<JobHandle> for EventStream {
+    fn from(handle: JobHandle) -> Self {
+        Self { handle, received: 0 }
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = FfmpegEvent;
+
+    fn next(&mut self) -> Option<FfmpegEvent> {
+        loop {
+            // Events sent before the current snapshot go first.
+            if let Some(progress) = self.handle.progress.take_after(self.received) {
+                return Some(FfmpegEvent::Progress(progress));
+            }
+            match self.handle.events.recv_timeout(CANCEL_POLL) {
+                Ok(event) => {
+                    self.received += 1;
+                    return Some(event);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return self.handle.progress.take().map(FfmpegEvent::Progress);
+                }
+            }
+        }
+    }
+}
+
+fn has_progress_stdout(args: &[OsString]) -> bool {
+    let args: Vec<&str> = args.iter().filter_map(|arg| arg.to_str()).collect();
     if args.iter().any(|arg| arg.starts_with("-progress=") && arg.contains("pipe:1")) {
         return true;
     }
@@ -116,40 +406,115 @@ fn has_progress_stdout(args: &[String]) -> bool {
         .any(|pair| pair[0] == "-progress" && pair[1].starts_with("pipe:1"))
 }
 
-pub fn run_with_events(command: FfmpegCommand) -> (Receiver<FfmpegEvent>, Sender<String>) {
+/// Whether ffmpeg writes its output to stdout (`-`, `pipe:` or `pipe:1`
+/// last). `-f null -` writes nothing, so it does not count.
+pub fn writes_to_stdout(args: &[OsString]) -> bool {
+    let Some((output, rest)) = args.split_last() else {
+        return false;
+    };
+    if !["-", "pipe:", "pipe:1"].iter().any(|name| output == name) {
+        return false;
+    }
+    !matches!(rest, [.., format, muxer] if format == "-f" && muxer == "null")
+}
+
+// Drops `-progress pipe:1`, which would mix key=value lines into media
+// that is also going to stdout.
+fn strip_progress_stdout(args: Vec<OsString>) -> Vec<OsString> {
+    let mut kept = Vec::with_capacity(args.len());
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        let to_stdout = args
+            .peek()
+            .and_then(|next| next.to_str())
+            .is_some_and(|next| next.starts_with("pipe:1"));
+        if arg == "-progress" && to_stdout {
+            args.next();
+            continue;
+        }
+        if arg.to_str().is_some_and(|arg| arg.starts_with("-progress=") && arg.contains("pipe:1")) {
+            continue;
+        }
+        kept.push(arg);
+    }
+    kept
+}
+
+#[allow(dead_code)]
+pub fn run_with_events(command: FfmpegCommand) -> JobHandle {
     run_args_with_events(command.to_args())
 }
 
-pub fn run_args_with_events(args: Vec<String>) -> (Receiver<FfmpegEvent>, Sender<String>) {
+#[allow(dead_code)]
+pub fn run_args_with_events(args: Vec<OsString>) -> JobHandle {
+    run_args_with_timeout(args, None)
+}
+
+/// `run_args_with_events`, but ffmpeg is killed and the job fails once
+/// `timeout` runs out.
+pub fn run_args_with_timeout(args: Vec<OsString>, timeout: Option<JobTimeout>) -> JobHandle {
     let (event_tx, event_rx) = mpsc::channel::<FfmpegEvent>();
+    let mut event_tx = EventSender { tx: event_tx, sent: 0 };
     let (stdin_tx, stdin_rx) = mpsc::channel::<String>();
+    let progress = LatestProgress::default();
+    let latest = progress.clone();
+    let cancel = CancelToken::default();
+    let cancel_flag = cancel.clone();
+    let pid = ChildPid::default();
+    let child_pid = pid.clone();
+    let exit = ChildExit::default();
+    let child_exit = exit.clone();
+    let verbose = verbose();
 
     thread::spawn(move || {
+        // Media on stdout goes straight to ours; progress then comes from
+        // the stats lines on stderr only.
+        let to_stdout = writes_to_stdout(&args);
+        let mut args = args;
+        if to_stdout && has_progress_stdout(&args) {
+            args = strip_progress_stdout(args);
+            event_tx.send(FfmpegEvent::Warning(
+                "ignoring -progress pipe:1: the output itself goes to stdout".to_string(),
+            ));
+        }
+        let progress_stdout = has_progress_stdout(&args);
+
         let mut cmd = Command::new("ffmpeg");
         cmd.args(&args).stderr(Stdio::piped()).stdin(Stdio::piped());
+        process::apply_niceness(&mut cmd);
 
-        if has_progress_stdout(&args) {
+        if to_stdout {
+            cmd.stdout(Stdio::inherit());
+        } else if progress_stdout {
             cmd.stdout(Stdio::piped());
         } else {
             cmd.stdout(Stdio::null());
         }
 
+        let job = Job {
+            id: 0,
+            status: JobStatus::Running,
+            started_at: Some(Instant::now()),
+            ended_at: None,
+        };
+
         let mut child = match cmd.spawn() {
             Ok(child) => child,
             Err(err) => {
-                let _ = event_tx.send(FfmpegEvent::Error(err.to_string()));
+                event_tx.send(FfmpegEvent::Error(err.to_string()));
                 return;
             }
         };
+        child_pid.set(child.id());
 
         if let Some(mut stdin) = child.stdin.take() {
             thread::spawn(move || {
                 use std::io::Write;
                 for input in stdin_rx {
-                    if let Err(_) = stdin.write_all(input.as_bytes()) {
+                    if stdin.write_all(input.as_bytes()).is_err() {
                         break;
                     }
-                    if let Err(_) = stdin.flush() {
+                    if stdin.flush().is_err() {
                         break;
                     }
                 }
@@ -159,89 +524,272 @@ pub fn run_args_with_events(args: Vec<String>) -> (Receiver<FfmpegEvent>, Sender
         let stderr = match child.stderr.take() {
             Some(stderr) => stderr,
             None => {
-                let _ = event_tx.send(FfmpegEvent::Error("failed to capture ffmpeg stderr".to_string()));
+                event_tx.send(FfmpegEvent::Error("failed to capture ffmpeg stderr".to_string()));
                 let _ = child.wait();
                 return;
             }
         };
 
-        let (line_tx, line_rx) = mpsc::channel::<(StreamKind, String)>();
+        let (line_tx, line_rx) = mpsc::sync_channel::<(StreamKind, String)>(LINE_BUFFER);
         let stderr_tx = line_tx.clone();
         let stderr_handle = spawn_line_reader(StreamKind::Stderr, stderr, stderr_tx);
 
-        let stdout_handle = if has_progress_stdout(&args) {
-            if let Some(stdout) = child.stdout.take() {
-                Some(spawn_line_reader(StreamKind::Stdout, stdout, line_tx.clone()))
-            } else {
-                None
-            }
+        let stdout_handle = if progress_stdout {
+            child
+                .stdout
+                .take()
+                .map(|stdout| spawn_line_reader(StreamKind::Stdout, stdout, line_tx.clone()))
         } else {
             None
         };
 
         drop(line_tx);
 
-        let mut metadata = MetadataParser::new();
-        let mut progress_acc = ProgressAccumulator::default();
-
-        for (stream, line) in line_rx {
-            match stream {
-                StreamKind::Stdout => {
-                    if let Some(progress) = parse_progress_kv_line(&line, &mut progress_acc) {
-                        let _ = event_tx.send(FfmpegEvent::Progress(progress));
-                    }
-                }
-                StreamKind::Stderr => {
-                    if let Some(progress) = parse_progress_line(&line) {
-                        let _ = event_tx.send(FfmpegEvent::Progress(progress));
-                        continue;
-                    }
-
-                    if let Some(input) = metadata.parse_input_line(&line) {
-                        let _ = event_tx.send(FfmpegEvent::Input(input));
-                        continue;
-                    }
-
-                    if let Some(output) = metadata.parse_output_line(&line) {
-                        let _ = event_tx.send(FfmpegEvent::Output(output));
-                        continue;
-                    }
+        let watchdog = timeout.map(|timeout| Watchdog::start(timeout, Instant::now()));
+        let mut parser = LineParser::new(event_tx, latest, job, watchdog, verbose);
+        let mut timed_out = false;
 
-                    if let Some(summary) = parse_summary_line(&line) {
-                        let _ = event_tx.send(FfmpegEvent::Summary(summary));
-                        continue;
-                    }
-
-                    let level = classify_log_line(&line);
-                    if matches!(level, LogLevel::Error) {
-                        let _ = event_tx.send(FfmpegEvent::Error(line.clone()));
-                    } else if matches!(level, LogLevel::Prompt) {
-                        let _ = event_tx.send(FfmpegEvent::Prompt(line));
+        loop {
+            let received = line_rx.recv_timeout(CANCEL_POLL);
+            // A job that just ended on its own has not timed out.
+            let running = !matches!(received, Err(RecvTimeoutError::Disconnected));
+            if running && parser.expired(Instant::now()) {
+                timed_out = true;
+                cancel_flag.cancel();
+            }
+            let (stream, line) = match received {
+                Ok(item) => item,
+                Err(RecvTimeoutError::Timeout) => {
+                    if cancel_flag.is_cancelled() {
+                        let _ = child.kill();
+                        break;
                     }
+                    continue;
                 }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if cancel_flag.is_cancelled() {
+                let _ = child.kill();
+                break;
+            }
+            match stream {
+                StreamKind::Stdout => parser.stdout_line(&line, Instant::now()),
+                StreamKind::Stderr => parser.stderr_line(line, Instant::now()),
             }
         }
 
+        // Unblocks the readers if we stopped early on cancel.
+        drop(line_rx);
+
+        parser.finish(!cancel_flag.is_cancelled());
+        let LineParser {
+            events: mut event_tx,
+            mut tail,
+            probe_only,
+            watchdog,
+            ..
+        } = parser;
+
         let _ = stderr_handle.join();
         if let Some(handle) = stdout_handle {
             let _ = handle.join();
         }
 
+        child_pid.clear();
+        if cancel_flag.is_cancelled() {
+            let _ = child.wait();
+            let message = match watchdog.filter(|_| timed_out) {
+                Some(watchdog) => watchdog.message(),
+                None => "job cancelled".to_string(),
+            };
+            event_tx.send(FfmpegEvent::Error(message));
+            return;
+        }
+
         if let Ok(status) = child.wait() {
-            if !status.success() {
+            if let Some(code) = status.code() {
+                let _ = child_exit.0.set(code);
+            }
+            // `ffmpeg -i <file>` on its own prints the input metadata and then
+            // exits 1 for lack of an output; for a probe that is success.
+            if !status.success() && !probe_only {
                 let message = format!("ffmpeg exited with status {status}");
-                let _ = event_tx.send(FfmpegEvent::Error(message));
+                event_tx.send(FfmpegEvent::Error(message));
+            } else if let Some(line) = reported_failure(tail.make_contiguous()) {
+                let message = format!("ffmpeg exited 0 but reported: {line}");
+                event_tx.send(FfmpegEvent::Error(message));
             }
         }
     });
 
-    (event_rx, stdin_tx)
+    JobHandle {
+        events: event_rx,
+        stdin: stdin_tx,
+        progress,
+        cancel,
+        pid,
+        exit,
+    }
+}
+
+/// Turns the lines ffmpeg writes into events, in the order they come.
+struct LineParser {
+    events: EventSender,
+    latest: LatestProgress,
+    job: Job,
+    metadata: MetadataParser,
+    /// The last lines of the log, where ffmpeg reports why it failed.
+    tail: VecDeque<String>,
+    progress_acc: ProgressAccumulator,
+    drop_watch: DropWatch,
+    /// ffmpeg ran without an output, to print what it knows of the inputs.
+    probe_only: bool,
+    watchdog: Option<Watchdog>,
+    verbose: bool,
+}
+
+impl LineParser {
+    fn new(
+        events: EventSender,
+        latest: LatestProgress,
+        job: Job,
+        watchdog: Option<Watchdog>,
+        verbose: bool,
+    ) -> Self {
+        Self {
+            events,
+            latest,
+            job,
+            metadata: MetadataParser::new(),
+            tail: VecDeque::with_capacity(FAILURE_TAIL_LINES),
+            progress_acc: ProgressAccumulator::default(),
+            drop_watch: DropWatch::default(),
+            probe_only: false,
+            watchdog,
+            verbose,
+        }
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        self.watchdog.as_ref().is_some_and(|watchdog| watchdog.expired(now))
+    }
+
+    fn progressed(&mut self, progress: FfmpegProgress, now: Instant) {
+        if let Some(watchdog) = self.watchdog.as_mut() {
+            watchdog.progressed(now);
+        }
+        if let Some(warning) = self.drop_watch.observe(&progress) {
+            self.events.send(FfmpegEvent::Warning(warning));
+        }
+        self.latest.set(progress, self.events.sent);
+    }
+
+    /// A line of `-progress pipe:1` output.
+    fn stdout_line(&mut self, line: &str, now: Instant) {
+        if let Some(progress) = parse_progress_kv_line(line, &mut self.progress_acc) {
+            self.progressed(progress, now);
+        }
+    }
+
+    fn stderr_line(&mut self, line: String, now: Instant) {
+        if let Some(progress) = parse_progress_line(&line) {
+            for output in self.metadata.flush_outputs() {
+                self.events.send(FfmpegEvent::Output(output));
+            }
+            self.progressed(progress, now);
+            // The last stats line, `Lsize=`, is also the summary.
+            let last = line.contains("Lsize=");
+            if let Some(summary) = last.then(|| parse_summary_line(&line)).flatten() {
+                self.summary(summary);
+            }
+            return;
+        }
+
+        self.events.send(FfmpegEvent::Log(line.clone()));
+        if self.tail.len() == FAILURE_TAIL_LINES {
+            self.tail.pop_front();
+        }
+        self.tail.push_back(line.clone());
+
+        if let Some(path) = parse_segment_opened(&line) {
+            self.events.send(FfmpegEvent::SegmentOpened(path));
+            return;
+        }
+
+        if let Some(mappings) = self.metadata.parse_mapping_line(&line) {
+            self.events.send(FfmpegEvent::Mapping(mappings));
+        }
+
+        let inputs = self.metadata.parse_input_line(&line);
+        let had_inputs = !inputs.is_empty();
+        for input in inputs {
+            self.events.send(FfmpegEvent::Input(input));
+        }
+
+        // An `Output #N` header can flush pending inputs and open
+        // the output section on the same line.
+        let outputs = self.metadata.parse_output_line(&line);
+        let started = parse_output_started(&line);
+        // The line that ends an audio-only section reports it,
+        // but isn't part of it.
+        let had_outputs =
+            started.is_some() || (!outputs.is_empty() && line.starts_with(char::is_whitespace));
+        for output in outputs {
+            self.events.send(FfmpegEvent::Output(output));
+        }
+        if let Some(path) = started {
+            self.events.send(FfmpegEvent::OutputStarted(path));
+        }
+
+        if had_inputs || had_outputs {
+            return;
+        }
+
+        if let Some(summary) = parse_summary_line(&line) {
+            self.summary(summary);
+            return;
+        }
+
+        if line.contains(NO_OUTPUT_MESSAGE) {
+            self.probe_only = true;
+            return;
+        }
+
+        let level = classify_log_line(&line);
+        if matches!(level, LogLevel::Error) {
+            self.events.send(FfmpegEvent::Error(line));
+        } else if matches!(level, LogLevel::Prompt) {
+            self.events.send(FfmpegEvent::Prompt(line));
+        } else if matches!(level, LogLevel::Noise) && self.verbose {
+            self.events.send(FfmpegEvent::Noise(line));
+        }
+    }
+
+    fn summary(&mut self, mut summary: EncodeSummary) {
+        self.job.ended_at = Some(Instant::now());
+        summary.encode_elapsed = self.job.elapsed();
+        self.events.send(FfmpegEvent::Summary(summary));
+    }
+
+    /// Sends what is still pending once ffmpeg's output has ended. A
+    /// progress group cut short is kept only if the job ran to the end.
+    fn finish(&mut self, ran_to_end: bool) {
+        if let Some(progress) = self.progress_acc.finish().filter(|_| ran_to_end) {
+            self.latest.set(progress, self.events.sent);
+        }
+        for input in self.metadata.flush_inputs() {
+            self.events.send(FfmpegEvent::Input(input));
+        }
+        for output in self.metadata.flush_outputs() {
+            self.events.send(FfmpegEvent::Output(output));
+        }
+    }
 }
 
 fn spawn_line_reader<R: Read + Send + 'static>(
     stream: StreamKind,
     reader: R,
-    sender: Sender<(StreamKind, String)>,
+    sender: SyncSender<(StreamKind, String)>,
 ) -> thread::JoinHandle<()> {
     thread::spawn(move || {
         let mut reader = BufReader::new(reader);
@@ -264,9 +812,7 @@ fn spawn_line_reader<R: Read + Send + 'static>(
                     if line_buf.is_empty() {
                         continue;
                     }
-                    let line = String::from_utf8_lossy(&line_buf)
-                        .trim_matches(&['\r', '\n'][..])
-                        .to_string();
+                    let line = clean_line(&line_buf);
                     line_buf.clear();
                     if !line.is_empty() {
                         let _ = sender.send((stream, line));
@@ -279,9 +825,7 @@ fn spawn_line_reader<R: Read + Send + 'static>(
         }
 
         if !line_buf.is_empty() {
-            let line = String::from_utf8_lossy(&line_buf)
-                .trim_matches(&['\r', '\n'][..])
-                .to_string();
+            let line = clean_line(&line_buf);
             if !line.is_empty() {
                 let _ = sender.send((stream, line));
             }
@@ -289,6 +833,19 @@ fn spawn_line_reader<R: Read + Send + 'static>(
     })
 }
 
+fn clean_line(bytes: &[u8]) -> String {
+    strip_ansi(&String::from_utf8_lossy(bytes))
+        .trim_matches(&['\r', '\n'][..])
+        .to_string()
+}
+
+/// Removes ANSI CSI sequences (`ESC[1;31m` colors, `ESC[K` and the like),
+/// which some ffmpeg builds put in their log even when it is piped. Any
+/// other text, a lone escape character included, is kept.
+pub fn strip_ansi(line: &str) -> Cow<'_, str> {
+    RE_CSI.replace_all(line, "")
+}
+
 fn parse_progress_kv_line(line: &str, acc: &mut ProgressAccumulator) -> Option<FfmpegProgress> {
     let trimmed = line.trim();
     if trimmed.is_empty() {
@@ -308,3 +865,408 @@ fn parse_progress_kv_line(line: &str, acc: &mut ProgressAccumulator) -> Option<F
 
     parse_progress_line(trimmed)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    /// Counts the bytes the line reader has pulled from ffmpeg's stderr.
+    struct CountingReader<R> {
+        inner: R,
+        read: Arc<AtomicUsize>,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.read.fetch_add(read, Ordering::SeqCst);
+            Ok(read)
+        }
+    }
+
+    fn stats_line(frame: u64) -> String {
+        let centis = frame * 100 / 30;
+        format!(
+            "frame={frame:6} fps=24000 q=-1.0 size={:8}kB time=00:{:02}:{:02}.{:02} bitrate=1000.0kbits/s speed= 800x",
+            frame * 4,
+            centis / 6000 % 60,
+            centis / 100 % 60,
+            centis % 100,
+        )
+    }
+
+    #[test]
+    fn progress_flood_is_coalesced_to_the_latest_update() {
+        const LINES: u64 = 100_000;
+        const FRAME_EVERY: u64 = 1_000;
+
+        // ffmpeg ends stats lines with `\r` while it runs.
+        let stderr: String = (1..=LINES).map(|frame| stats_line(frame) + "\r").collect();
+        let longest = stats_line(LINES).len() + 1;
+        let read = Arc::new(AtomicUsize::new(0));
+        let reader = CountingReader {
+            inner: Cursor::new(stderr.into_bytes()),
+            read: Arc::clone(&read),
+        };
+        let (line_tx, line_rx) = mpsc::sync_channel(LINE_BUFFER);
+        let reader = spawn_line_reader(StreamKind::Stderr, reader, line_tx);
+
+        let latest = LatestProgress::default();
+        let mut consumed = 0;
+        let mut most_ahead = 0;
+        let mut lines = 0;
+        for (_, line) in line_rx {
+            lines += 1;
+            consumed += line.len() + 1;
+            most_ahead = most_ahead.max(read.load(Ordering::SeqCst) - consumed);
+            latest.set(parse_progress_line(&line).expect("a stats line"), 0);
+            // A UI frame sees only the newest update, never a backlog.
+            if lines % FRAME_EVERY == 0 {
+                let shown = latest.take().expect("an update since the last frame");
+                assert_eq!(shown.frame, lines);
+                assert!(latest.take().is_none());
+            }
+        }
+        reader.join().unwrap();
+
+        assert_eq!(lines, LINES);
+        // The reader stays at most one full line buffer (plus BufReader's
+        // own) ahead, however many lines ffmpeg writes.
+        let bound = (LINE_BUFFER + 2) * longest + 8 * 1024;
+        assert!(most_ahead <= bound, "reader ran {most_ahead} bytes ahead (bound {bound})");
+    }
+
+    #[test]
+    fn last_update_is_kept_until_taken() {
+        let latest = LatestProgress::default();
+        for frame in 1..=10 {
+            latest.set(parse_progress_line(&stats_line(frame)).unwrap(), 0);
+        }
+        assert_eq!(latest.take().map(|progress| progress.frame), Some(10));
+        assert!(latest.take().is_none());
+    }
+
+    fn handle(events: Receiver<FfmpegEvent>, progress: LatestProgress) -> JobHandle {
+        JobHandle {
+            events,
+            stdin: mpsc::channel().0,
+            progress,
+            cancel: CancelToken::default(),
+            pid: ChildPid::default(),
+            exit: ChildExit::default(),
+        }
+    }
+
+    #[test]
+    fn event_stream_keeps_progress_in_place() {
+        let label = |event: Option<FfmpegEvent>| match event {
+            Some(FfmpegEvent::Progress(progress)) => format!("progress {}", progress.frame),
+            Some(FfmpegEvent::Log(line) | FfmpegEvent::Warning(line)) => line,
+            other => panic!("unexpected {other:?}"),
+        };
+        let (tx, rx) = mpsc::channel();
+        let mut events = EventSender { tx, sent: 0 };
+        let latest = LatestProgress::default();
+        let mut stream = EventStream::from(handle(rx, latest.clone()));
+
+        events.send(FfmpegEvent::Log("Press [q] to stop".into()));
+        latest.set(parse_progress_line(&stats_line(1)).unwrap(), events.sent);
+        events.send(FfmpegEvent::Warning("drop spike".into()));
+        // The warning is already queued, but came after the snapshot.
+        assert_eq!(label(stream.next()), "Press [q] to stop");
+        assert_eq!(label(stream.next()), "progress 1");
+        assert_eq!(label(stream.next()), "drop spike");
+
+        // Updates the stream didn't get to yet collapse into the newest.
+        latest.set(parse_progress_line(&stats_line(2)).unwrap(), events.sent);
+        events.send(FfmpegEvent::Log("video:1kB".into()));
+        latest.set(parse_progress_line(&stats_line(3)).unwrap(), events.sent);
+        drop(events);
+        assert_eq!(label(stream.next()), "video:1kB");
+        assert_eq!(label(stream.next()), "progress 3");
+        assert!(stream.next().is_none());
+    }
+
+    /// A parser for a job started just now, and what it sends.
+    fn parser(
+        watchdog: Option<Watchdog>,
+        verbose: bool,
+    ) -> (LineParser, Receiver<FfmpegEvent>, LatestProgress) {
+        let (tx, rx) = mpsc::channel();
+        let latest = LatestProgress::default();
+        let job = Job {
+            id: 0,
+            status: JobStatus::Running,
+            started_at: Some(Instant::now()),
+            ended_at: None,
+        };
+        let events = EventSender { tx, sent: 0 };
+        (LineParser::new(events, latest.clone(), job, watchdog, verbose), rx, latest)
+    }
+
+    /// Feeds `stderr` line by line, as ffmpeg would write it.
+    fn feed_stderr(parser: &mut LineParser, stderr: &str) {
+        for line in stderr.lines() {
+            parser.stderr_line(line.to_string(), Instant::now());
+        }
+    }
+
+    // `ffmpeg -i in.mp4 -map 0 -c:v libx264 -c:a aac -f tee
+    // "[f=mp4]out.mp4|[f=mpegts]out.ts" -map 0:a -c:a libmp3lame audio.mp3`
+    const TEE_STDERR: &str = "\
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':
+  Metadata:
+    major_brand     : isom
+  Duration: 00:00:10.00, start: 0.000000, bitrate: 1205 kb/s
+  Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p(progressive), 1280x720, 1072 kb/s, 30 fps, 30 tbr, 15360 tbn (default)
+  Stream #0:1[0x2](und): Audio: aac (LC) (mp4a / 0x6134706D), 48000 Hz, stereo, fltp, 128 kb/s (default)
+Stream mapping:
+  Stream #0:0 -> #0:0 (h264 (native) -> h264 (libx264))
+  Stream #0:1 -> #0:1 (aac (native) -> aac (native))
+  Stream #0:1 -> #1:0 (aac (native) -> mp3 (libmp3lame))
+Press [q] to stop, [?] for help
+[libx264 @ 0x55d0c1a4e2c0] profile High, level 3.1, 4:2:0, 8-bit
+Output #0, tee, to '[f=mp4]out.mp4|[f=mpegts]out.ts':
+  Metadata:
+    encoder         : Lavf60.16.100
+  Stream #0:0(und): Video: h264, yuv420p(progressive), 1280x720, q=2-31, 30 fps, 30 tbn (default)
+    Metadata:
+      encoder         : Lavc60.31.102 libx264
+  Stream #0:1(und): Audio: aac (LC), 48000 Hz, stereo, fltp, 128 kb/s (default)
+Output #1, mp3, to 'audio.mp3':
+  Metadata:
+    TSSE            : Lavf60.16.100
+  Stream #1:0(und): Audio: mp3, 48000 Hz, stereo, fltp (default)
+frame=  120 fps= 60 q=28.0 size=N/A time=00:00:03.96 bitrate=N/A speed=1.98x
+[mp4 @ 0x55d0c1b07a80] Starting second pass: moving the moov atom to the beginning of the file
+frame=  300 fps= 62 q=-1.0 Lsize=N/A time=00:00:10.00 bitrate=N/A speed=2.05x
+video:1300kB audio:157kB subtitle:0kB other streams:0kB global headers:0kB muxing overhead: unknown
+";
+
+    #[test]
+    fn tee_job_reports_each_output_and_keeps_its_log() {
+        let (mut parser, events, latest) = parser(None, false);
+        feed_stderr(&mut parser, TEE_STDERR);
+        parser.finish(true);
+        drop(parser);
+        let events: Vec<FfmpegEvent> = events.into_iter().collect();
+
+        let outputs: Vec<(usize, &str, &str, &str)> = events
+            .iter()
+            .filter_map(|event| match event {
+                FfmpegEvent::Output(output) => Some((
+                    output.index,
+                    output.container.as_str(),
+                    output.codec.as_str(),
+                    output.path.as_str(),
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                (0, "tee", "h264", "[f=mp4]out.mp4|[f=mpegts]out.ts"),
+                (1, "mp3", "mp3", "audio.mp3"),
+            ]
+        );
+
+        // Every line but the stats lines is still passed on, in order,
+        // around the progress updates.
+        let logged: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                FfmpegEvent::Log(line) => Some(line.as_str()),
+                _ => None,
+            })
+            .collect();
+        let expected: Vec<&str> = TEE_STDERR
+            .lines()
+            .filter(|line| !line.starts_with("frame="))
+            .collect();
+        assert_eq!(logged, expected);
+        assert_eq!(latest.take().map(|progress| progress.frame), Some(300));
+        assert!(events.iter().any(|event| matches!(event, FfmpegEvent::Summary(_))));
+    }
+
+    /// One `-progress pipe:1` group, without its closing `progress=` line.
+    fn progress_group(frame: u64, out_time_us: u64) -> Vec<String> {
+        vec![
+            format!("frame={frame}"),
+            "fps=25.00".to_string(),
+            "stream_0_0_q=28.0".to_string(),
+            "bitrate=N/A".to_string(),
+            "total_size=48".to_string(),
+            format!("out_time_us={out_time_us}"),
+            "dup_frames=0".to_string(),
+            "drop_frames=0".to_string(),
+            "speed=1.5x".to_string(),
+        ]
+    }
+
+    #[test]
+    fn progress_groups_end_at_their_progress_line() {
+        let (mut parser, _events, latest) = parser(None, false);
+        for line in progress_group(50, 2_000_000) {
+            parser.stdout_line(&line, Instant::now());
+        }
+        assert!(latest.take().is_none());
+        parser.stdout_line("progress=continue", Instant::now());
+        let progress = latest.take().unwrap();
+        assert_eq!((progress.frame, progress.time), (50, Duration::from_secs(2)));
+        assert_eq!((progress.fps, progress.speed, progress.q), (Some(25.0), Some(1.5), Some(28.0)));
+        assert_eq!(progress.bitrate_kbps, None);
+    }
+
+    #[test]
+    fn partial_trailing_group_is_kept_at_the_end() {
+        let (mut parser, _events, latest) = parser(None, false);
+        for line in progress_group(50, 2_000_000) {
+            parser.stdout_line(&line, Instant::now());
+        }
+        parser.stdout_line("progress=continue", Instant::now());
+        latest.take();
+        // ffmpeg exited before closing the last group.
+        for line in &progress_group(75, 3_000_000)[..6] {
+            parser.stdout_line(line, Instant::now());
+        }
+        assert!(latest.take().is_none());
+        parser.finish(true);
+        let progress = latest.take().unwrap();
+        assert_eq!((progress.frame, progress.time), (75, Duration::from_secs(3)));
+        assert_eq!((progress.dup, progress.speed), (None, None));
+    }
+
+    #[test]
+    fn partial_trailing_group_of_a_cancelled_job_is_dropped() {
+        let (mut parser, _events, latest) = parser(None, false);
+        for line in &progress_group(75, 3_000_000)[..2] {
+            parser.stdout_line(line, Instant::now());
+        }
+        parser.finish(false);
+        assert!(latest.take().is_none());
+    }
+
+    #[test]
+    fn watchdog_is_reset_by_progress_and_fires_after_silence() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let timeout = JobTimeout {
+            limit: Duration::from_secs(2),
+            wall_clock: false,
+        };
+        let (mut parser, _events, _latest) = parser(Some(Watchdog::start(timeout, start)), false);
+
+        parser.stderr_line(stats_line(10), at(1_500));
+        assert!(!parser.expired(at(3_000)));
+        // Log lines aren't progress; they don't keep a stuck job alive.
+        parser.stderr_line("[h264 @ 0x5581] error while decoding MB 3 7".into(), at(3_000));
+        parser.stdout_line("frame=90", at(3_000));
+        assert!(!parser.expired(at(3_499)));
+        assert!(parser.expired(at(3_500)));
+
+        parser.stdout_line("progress=continue", at(3_600));
+        assert!(!parser.expired(at(5_000)));
+        assert!(parser.expired(at(5_600)));
+        assert_eq!(parser.watchdog.unwrap().message(), "timeout: no progress for 2s");
+    }
+
+    #[test]
+    fn wall_clock_watchdog_ignores_progress() {
+        let start = Instant::now();
+        let timeout = JobTimeout {
+            limit: Duration::from_secs(2),
+            wall_clock: true,
+        };
+        let (mut parser, _events, _latest) = parser(Some(Watchdog::start(timeout, start)), false);
+        parser.stderr_line(stats_line(10), start + Duration::from_millis(1_900));
+        assert!(parser.expired(start + Duration::from_secs(2)));
+        assert_eq!(parser.watchdog.unwrap().message(), "timeout: job still running after 2s");
+    }
+
+    #[test]
+    fn no_watchdog_never_expires() {
+        let (parser, _events, _latest) = parser(None, false);
+        assert!(!parser.expired(Instant::now() + Duration::from_secs(86_400)));
+    }
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(Into::into).collect()
+    }
+
+    #[test]
+    fn progress_pipe_is_not_a_stdout_output() {
+        let progress_only = os_args(&["-i", "in.mov", "-progress", "pipe:1", "out.mp4"]);
+        assert!(!writes_to_stdout(&progress_only));
+        assert!(has_progress_stdout(&progress_only));
+        assert!(has_progress_stdout(&os_args(&["-i", "in.mov", "-progress=pipe:1", "out.mp4"])));
+
+        for output in ["-", "pipe:", "pipe:1"] {
+            let media = os_args(&["-i", "in.mov", "-f", "mpegts", output]);
+            assert!(writes_to_stdout(&media), "{output}");
+            assert!(!has_progress_stdout(&media), "{output}");
+        }
+        // Nothing is written for `-f null -`, and `pipe:2` is stderr.
+        assert!(!writes_to_stdout(&os_args(&["-i", "in.mov", "-f", "null", "-"])));
+        assert!(!writes_to_stdout(&os_args(&["-i", "in.mov", "-f", "mpegts", "pipe:2"])));
+    }
+
+    #[test]
+    fn progress_pipe_is_stripped_only_from_stdout() {
+        let both = os_args(&["-progress", "pipe:1", "-i", "in.mov", "-f", "mpegts", "pipe:1"]);
+        assert!(writes_to_stdout(&both));
+        assert!(has_progress_stdout(&both));
+        let stripped = strip_progress_stdout(both);
+        assert_eq!(stripped, os_args(&["-i", "in.mov", "-f", "mpegts", "pipe:1"]));
+        assert!(writes_to_stdout(&stripped));
+        assert!(!has_progress_stdout(&stripped));
+
+        let joined = os_args(&["-progress=pipe:1", "-i", "in.mov", "-f", "mpegts", "-"]);
+        assert!(!has_progress_stdout(&strip_progress_stdout(joined)));
+
+        let to_file = os_args(&["-progress", "progress.txt", "-i", "in.mov", "-f", "mpegts", "-"]);
+        assert_eq!(strip_progress_stdout(to_file.clone()), to_file);
+    }
+
+    const BANNER: &str = "\
+ffmpeg version 6.1.1 Copyright (c) 2000-2023 the FFmpeg developers
+  built with gcc 13.2.0
+  libavutil      58. 29.100 / 58. 29.100
+[libx264 @ 0x55d0c1a4e2c0] frame I:1     Avg QP:20.31  size: 41280
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':
+";
+
+    /// The `Noise` and `Log` lines sent for `stderr`.
+    fn noise_and_log(verbose: bool, stderr: &str) -> (Vec<String>, Vec<String>) {
+        let (mut parser, events, _latest) = parser(None, verbose);
+        feed_stderr(&mut parser, stderr);
+        drop(parser);
+        let (mut noise, mut log) = (Vec::new(), Vec::new());
+        for event in events {
+            match event {
+                FfmpegEvent::Noise(line) => noise.push(line),
+                FfmpegEvent::Log(line) => log.push(line),
+                _ => {}
+            }
+        }
+        (noise, log)
+    }
+
+    #[test]
+    fn verbose_jobs_send_noise_lines() {
+        let (noise, log) = noise_and_log(true, BANNER);
+        assert_eq!(noise, BANNER.lines().take(4).collect::<Vec<_>>());
+        assert_eq!(log, BANNER.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn quiet_jobs_send_no_noise() {
+        let (noise, log) = noise_and_log(false, BANNER);
+        assert!(noise.is_empty(), "{noise:?}");
+        // The raw log is the same either way.
+        assert_eq!(log, BANNER.lines().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,235 @@
+use std::ffi::{OsStr, OsString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use crate::core::shell::{strip_prefix_os, trim_os};
+
+static STRICT_SCHEDULE: AtomicBool = AtomicBool::new(false);
+
+/// Makes a start time that has already passed an error; by default the
+/// job runs right away with a note.
+pub fn set_strict_schedule(strict: bool) {
+    STRICT_SCHEDULE.store(strict, Ordering::Relaxed);
+}
+
+pub fn strict_schedule() -> bool {
+    STRICT_SCHEDULE.load(Ordering::Relaxed)
+}
+
+/// A command waiting for its start time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledJob {
+    pub at: SystemTime,
+    pub line: OsString,
+}
+
+/// Jobs waiting for their start time, soonest first. They are not in the
+/// job queue until it comes.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    jobs: Vec<ScheduledJob>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScheduledJob> {
+        self.jobs.iter()
+    }
+
+    /// Adds `job` after every job due at the same time or sooner.
+    pub fn add(&mut self, job: ScheduledJob) {
+        let at = self.jobs.partition_point(|waiting| waiting.at <= job.at);
+        self.jobs.insert(at, job);
+    }
+
+    /// Removes the job at `index` (0 is the soonest).
+    pub fn cancel(&mut self, index: usize) -> Option<ScheduledJob> {
+        (index < self.jobs.len()).then(|| self.jobs.remove(index))
+    }
+
+    /// Removes and returns the jobs whose time has come, soonest first.
+    pub fn take_due(&mut self, now: SystemTime) -> Vec<ScheduledJob> {
+        let due = self.jobs.partition_point(|job| job.at <= now);
+        self.jobs.drain(..due).collect()
+    }
+}
+
+/// What follows `at ` (as typed) or `@at ` (as in `.flw` files).
+pub fn strip_at_prefix(line: &OsStr) -> Option<OsString> {
+    ["at ", "@at "]
+        .into_iter()
+        .find_map(|prefix| strip_prefix_os(line, prefix))
+        .map(|rest| trim_os(&rest))
+}
+
+/// Reads `<time> <command>`. `HH:MM[:SS]` is the next time the clock shows
+/// it, so never in the past; `YYYY-MM-DDTHH:MM[:SS]` is that moment and
+/// may be. Both are local time.
+pub fn parse_scheduled(text: &OsStr, now: SystemTime) -> Result<ScheduledJob, String> {
+    let lossy = text.to_string_lossy();
+    let when = lossy.split_whitespace().next().unwrap_or_default();
+    let line = strip_prefix_os(text, when)
+        .map(|rest| trim_os(&rest))
+        .filter(|line| !line.is_empty())
+        .ok_or("expected at <time> <command>")?;
+    let invalid = || format!("invalid start time '{when}' (expected HH:MM or YYYY-MM-DDTHH:MM)");
+    let at = match when.split_once('T') {
+        Some((date, time)) => {
+            let (year, month, day) = parse_date(date).ok_or_else(invalid)?;
+            let (hour, minute, second) = parse_clock(time).ok_or_else(invalid)?;
+            from_local(&LocalTime { year, month, day, hour, minute, second }).ok_or_else(invalid)?
+        }
+        None => {
+            let (hour, minute, second) = parse_clock(when).ok_or_else(invalid)?;
+            let today = LocalTime { hour, minute, second, ..to_local(now) };
+            match from_local(&today).ok_or_else(invalid)? {
+                at if at > now => at,
+                _ => from_local(&LocalTime { day: today.day + 1, ..today }).ok_or_else(invalid)?,
+            }
+        }
+    };
+    Ok(ScheduledJob { at, line })
+}
+
+/// `2024-07-01 02:30`, in local time.
+pub fn format_start_time(time: SystemTime) -> String {
+    let local = to_local(time);
+    let mut text = format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        local.year, local.month, local.day, local.hour, local.minute
+    );
+    if local.second != 0 {
+        text.push_str(&format!(":{:02}", local.second));
+    }
+    text
+}
+
+fn parse_clock(text: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = text.split(':');
+    let hour = parse_field(parts.next()?, 0..=23)?;
+    let minute = parse_field(parts.next()?, 0..=59)?;
+    let second = parts.next().map_or(Some(0), |part| parse_field(part, 0..=59))?;
+    parts.next().is_none().then_some((hour, minute, second))
+}
+
+fn parse_date(text: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = text.split('-');
+    let year = parts.next().filter(|part| part.len() == 4)?.parse().ok()?;
+    let month = parse_field(parts.next()?, 1..=12)?;
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    let day = parse_field(parts.next()?, 1..=days)?;
+    parts.next().is_none().then_some((year, month, day))
+}
+
+fn parse_field(text: &str, range: std::ops::RangeInclusive<u32>) -> Option<u32> {
+    if !(1..=2).contains(&text.len()) || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok().filter(|value| range.contains(value))
+}
+
+/// A wall-clock reading. `day` may run past the end of the month; it is
+/// carried into the next one.
+#[derive(Debug, Clone, Copy)]
+struct LocalTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+#[cfg(unix)]
+fn to_local(time: SystemTime) -> LocalTime {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs()) as libc::time_t;
+    // SAFETY: tm is plain old data, so all zeroes is a valid value.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the duration of the call.
+    unsafe { libc::localtime_r(&secs, &mut tm) };
+    LocalTime {
+        year: i64::from(tm.tm_year) + 1900,
+        month: (tm.tm_mon + 1) as u32,
+        day: tm.tm_mday as u32,
+        hour: tm.tm_hour as u32,
+        minute: tm.tm_min as u32,
+        second: tm.tm_sec as u32,
+    }
+}
+
+/// `None` for a time the clocks skipped when changing to summer time.
+#[cfg(unix)]
+fn from_local(local: &LocalTime) -> Option<SystemTime> {
+    // SAFETY: tm is plain old data, so all zeroes is a valid value.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    tm.tm_year = i32::try_from(local.year - 1900).ok()?;
+    tm.tm_mon = local.month as i32 - 1;
+    tm.tm_mday = local.day as i32;
+    tm.tm_hour = local.hour as i32;
+    tm.tm_min = local.minute as i32;
+    tm.tm_sec = local.second as i32;
+    // Let mktime work out whether daylight saving time applies.
+    tm.tm_isdst = -1;
+    // SAFETY: `tm` is a valid, initialized struct.
+    let secs = unsafe { libc::mktime(&mut tm) };
+    // A changed clock means the time was skipped by a clock change.
+    if secs == -1 || tm.tm_hour as u32 != local.hour || tm.tm_min as u32 != local.minute {
+        return None;
+    }
+    let secs = u64::try_from(secs).ok()?;
+    Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+}
+
+// Without a portable way to read the local zone elsewhere, times are UTC.
+#[cfg(not(unix))]
+fn to_local(time: SystemTime) -> LocalTime {
+    use crate::core::session_log::civil_from_days;
+
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    LocalTime {
+        year,
+        month,
+        day,
+        hour: (secs_of_day / 3600) as u32,
+        minute: (secs_of_day % 3600 / 60) as u32,
+        second: (secs_of_day % 60) as u32,
+    }
+}
+
+#[cfg(not(unix))]
+fn from_local(local: &LocalTime) -> Option<SystemTime> {
+    // Howard Hinnant's `days_from_civil`; the day is only added, so one
+    // past the end of the month lands on the next.
+    let year = local.year - i64::from(local.month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((local.month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(local.day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86_400
+        + i64::from(local.hour) * 3600
+        + i64::from(local.minute) * 60
+        + i64::from(local.second);
+    let secs = u64::try_from(secs).ok()?;
+    Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+}
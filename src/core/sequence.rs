@@ -0,0 +1,131 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::core::expand::is_glob_pattern;
+
+static RE_COUNTER: Lazy<Regex> = Lazy::new(|| Regex::new(r"%0?(\d*)d").unwrap());
+
+const IMAGE_EXTENSIONS: [&str; 10] = [
+    "png", "jpg", "jpeg", "tif", "tiff", "exr", "dpx", "bmp", "webp", "tga",
+];
+
+/// How far past `-start_number` ffmpeg looks for the first image, as its
+/// `-start_number_range` default.
+const START_NUMBER_RANGE: u64 = 5;
+
+/// ffmpeg's own frame rate for image sequences without `-framerate`.
+pub const DEFAULT_FRAMERATE: f64 = 25.0;
+
+/// Whether the path has a `%d`/`%05d` counter, as image sequences and
+/// segment outputs do.
+pub fn has_frame_counter(path: &Path) -> bool {
+    RE_COUNTER.is_match(&path.to_string_lossy())
+}
+
+/// Whether the path names a run of numbered images rather than one file:
+/// `frame_%05d.png`, or a wildcard such as `frames/*.png`.
+pub fn is_image_sequence(path: &Path) -> bool {
+    if has_frame_counter(path) {
+        return true;
+    }
+    let is_image = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    is_image && is_glob_pattern(path)
+}
+
+/// The files on disk a counter or wildcard pattern matches, sorted.
+pub fn sequence_files(pattern: &Path) -> Vec<PathBuf> {
+    let text = pattern.to_string_lossy();
+    let glob_pattern = match RE_COUNTER.captures(&text) {
+        Some(found) => {
+            let whole = found.get(0).expect("capture 0 is the match");
+            let digits = match found[1].parse::<usize>() {
+                Ok(width) if width > 0 => "[0-9]".repeat(width),
+                _ => "[0-9]*".to_string(),
+            };
+            format!(
+                "{}{digits}{}",
+                glob::Pattern::escape(&text[..whole.start()]),
+                glob::Pattern::escape(&text[whole.end()..])
+            )
+        }
+        None => text.into_owned(),
+    };
+    let mut files: Vec<PathBuf> = glob::glob(&glob_pattern)
+        .map(|paths| paths.filter_map(Result::ok).collect())
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// How long the sequence plays at `framerate`: one frame per file.
+pub fn sequence_duration(pattern: &Path, framerate: f64) -> Option<Duration> {
+    let frames = sequence_frame_count(pattern, 0);
+    (frames > 0 && framerate > 0.0).then(|| Duration::from_secs_f64(frames as f64 / framerate))
+}
+
+/// How many images ffmpeg reads for a counter pattern. Like ffmpeg, it
+/// starts at the first file within `START_NUMBER_RANGE` numbers of
+/// `start` (`-start_number`) and stops at the first gap. A wildcard reads
+/// every file it matches.
+pub fn sequence_frame_count(pattern: &Path, start: u64) -> u64 {
+    let text = pattern.to_string_lossy();
+    let Some(found) = RE_COUNTER.captures(&text) else {
+        return sequence_files(pattern).len() as u64;
+    };
+    let whole = found.get(0).expect("capture 0 is the match");
+    let width = found[1].parse::<usize>().unwrap_or(0);
+    let zero_pad = whole.as_str().starts_with("%0");
+    let frame = |number: u64| {
+        let number = match zero_pad {
+            true => format!("{number:0width$}"),
+            false => format!("{number:width$}"),
+        };
+        PathBuf::from(format!("{}{number}{}", &text[..whole.start()], &text[whole.end()..]))
+    };
+    let Some(first) = (start..start + START_NUMBER_RANGE).find(|number| frame(*number).is_file()) else {
+        return 0;
+    };
+    (first..).take_while(|number| frame(*number).is_file()).count() as u64
+}
+
+/// Frames the job's output gets from its image-sequence input: one per
+/// file read. `None` without such an input, or when the output options
+/// change how many frames come out (`-t`, `-frames:v`, `-r`, an `fps` or
+/// `select` filter).
+pub fn sequence_total_frames(args: &[OsString]) -> Option<u64> {
+    let last_input = args.iter().rposition(|arg| arg == "-i")?;
+    let retimed = args[last_input..].windows(2).any(|pair| {
+        let value = pair[1].to_string_lossy();
+        match pair[0].to_str() {
+            Some("-t" | "-to" | "-frames:v" | "-vframes" | "-r" | "-r:v") => true,
+            Some("-vf" | "-filter:v" | "-filter_complex") => {
+                ["fps=", "framestep", "select="].iter().any(|name| value.contains(name))
+            }
+            _ => false,
+        }
+    });
+    if retimed {
+        return None;
+    }
+    let mut start = 0;
+    for pair in args.windows(2) {
+        match pair[0].to_str() {
+            Some("-start_number") => start = pair[1].to_str()?.parse().ok()?,
+            Some("-i") if is_image_sequence(Path::new(&pair[1])) => {
+                let frames = sequence_frame_count(Path::new(&pair[1]), start);
+                return (frames > 0).then_some(frames);
+            }
+            // Input options only last until their `-i`.
+            Some("-i") => start = 0,
+            _ => {}
+        }
+    }
+    None
+}
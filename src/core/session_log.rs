@@ -0,0 +1,94 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Append-only session log. Lines are written and flushed one by one on a
+/// background thread, so a slow disk never holds up the caller.
+#[derive(Debug)]
+pub struct SessionLog {
+    tx: Option<Sender<String>>,
+    errors: Receiver<io::Error>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl SessionLog {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (tx, rx) = mpsc::channel::<String>();
+        let (error_tx, errors) = mpsc::channel::<io::Error>();
+        let writer = thread::spawn(move || write_lines(file, rx, error_tx));
+        Ok(Self {
+            tx: Some(tx),
+            errors,
+            writer: Some(writer),
+        })
+    }
+
+    /// Queues `line` under a `[kind]` tag with the current time. Returns
+    /// false once the writer has stopped.
+    pub fn record(&self, kind: &str, line: &str) -> bool {
+        let stamped = format!("{} [{kind}] {line}\n", format_timestamp(SystemTime::now()));
+        self.tx
+            .as_ref()
+            .is_some_and(|tx| tx.send(stamped).is_ok())
+    }
+
+    /// Returns the write error that stopped the writer, if any. The writer
+    /// gives up after the first failure, so this reports at most once.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.errors.try_recv().ok()
+    }
+}
+
+impl Drop for SessionLog {
+    /// Waits for queued lines to reach the file before the log goes away.
+    fn drop(&mut self) {
+        self.tx = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_lines(mut file: File, rx: Receiver<String>, error_tx: Sender<io::Error>) {
+    for line in rx {
+        if let Err(err) = file.write_all(line.as_bytes()).and_then(|_| file.flush()) {
+            let _ = error_tx.send(err);
+            return;
+        }
+    }
+}
+
+/// Formats a time as ISO-8601 UTC with milliseconds, e.g.
+/// `2024-03-01T09:15:42.120Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date (Howard Hinnant's
+// `civil_from_days`).
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,109 @@
+use std::ffi::{OsStr, OsString};
+
+// Command lines are split with `shell_words`, which only works on UTF-8.
+// Lines from `.flw` files and stdin may carry paths that are not valid UTF-8
+// (common on Linux), so on Unix those lines go through a byte-level splitter
+// with the same quoting rules. Other platforms fall back to a lossy decode.
+
+#[cfg(unix)]
+pub fn os_from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+pub fn os_from_bytes(bytes: Vec<u8>) -> OsString {
+    OsString::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(unix)]
+pub fn os_bytes(value: &OsStr) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    std::borrow::Cow::Borrowed(value.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn os_bytes(value: &OsStr) -> std::borrow::Cow<'_, [u8]> {
+    std::borrow::Cow::Owned(value.to_string_lossy().into_owned().into_bytes())
+}
+
+pub fn trim_os(value: &OsStr) -> OsString {
+    let bytes = os_bytes(value);
+    os_from_bytes(bytes.trim_ascii().to_vec())
+}
+
+/// Returns what follows `prefix` when `value` starts with it.
+pub fn strip_prefix_os(value: &OsStr, prefix: &str) -> Option<OsString> {
+    let bytes = os_bytes(value);
+    bytes
+        .strip_prefix(prefix.as_bytes())
+        .map(|rest| os_from_bytes(rest.to_vec()))
+}
+
+/// Splits a command line into words using POSIX shell quoting rules.
+pub fn split_os(line: &OsStr) -> Result<Vec<OsString>, String> {
+    if let Some(text) = line.to_str() {
+        let words = shell_words::split(text).map_err(|err| err.to_string())?;
+        return Ok(words.into_iter().map(OsString::from).collect());
+    }
+    split_bytes(&os_bytes(line))
+}
+
+fn split_bytes(line: &[u8]) -> Result<Vec<OsString>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<Vec<u8>> = None;
+    let mut iter = line.iter().copied();
+
+    while let Some(byte) = iter.next() {
+        match byte {
+            b' ' | b'\t' | b'\n' => {
+                if let Some(done) = word.take() {
+                    words.push(os_from_bytes(done));
+                }
+            }
+            b'#' if word.is_none() => break,
+            b'\\' => {
+                if let Some(next) = iter.next() {
+                    if next != b'\n' {
+                        word.get_or_insert_with(Vec::new).push(next);
+                    }
+                }
+            }
+            b'\'' => {
+                let current = word.get_or_insert_with(Vec::new);
+                loop {
+                    match iter.next() {
+                        Some(b'\'') => break,
+                        Some(next) => current.push(next),
+                        None => return Err("missing closing quote".to_string()),
+                    }
+                }
+            }
+            b'"' => {
+                let current = word.get_or_insert_with(Vec::new);
+                loop {
+                    match iter.next() {
+                        Some(b'"') => break,
+                        Some(b'\\') => match iter.next() {
+                            Some(next @ (b'$' | b'`' | b'"' | b'\\')) => current.push(next),
+                            Some(b'\n') => {}
+                            Some(next) => {
+                                current.push(b'\\');
+                                current.push(next);
+                            }
+                            None => return Err("missing closing quote".to_string()),
+                        },
+                        Some(next) => current.push(next),
+                        None => return Err("missing closing quote".to_string()),
+                    }
+                }
+            }
+            other => word.get_or_insert_with(Vec::new).push(other),
+        }
+    }
+
+    if let Some(done) = word {
+        words.push(os_from_bytes(done));
+    }
+    Ok(words)
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+pub const MIN_FACTOR: f64 = 0.25;
+pub const MAX_FACTOR: f64 = 100.0;
+
+/// Video filter that plays the input `factor` times faster.
+pub fn setpts_filter(factor: f64) -> String {
+    format!("setpts=PTS/{factor}")
+}
+
+/// Audio filter chain for the same change. `atempo` only takes 0.5 to 2.0
+/// per instance, so larger changes are split into steps, e.g. 4x becomes
+/// `atempo=2.0,atempo=2.0`.
+pub fn atempo_filters(factor: f64) -> String {
+    let mut steps = Vec::new();
+    let mut rest = factor;
+    while rest > 2.0 {
+        steps.push(2.0);
+        rest /= 2.0;
+    }
+    while rest < 0.5 {
+        steps.push(0.5);
+        rest /= 0.5;
+    }
+    if steps.is_empty() || (rest - 1.0).abs() > 1e-9 {
+        steps.push(rest);
+    }
+    steps
+        .iter()
+        .map(|step| format!("atempo={:?}", (step * 1e6).round() / 1e6))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Length of the output when `input` is played `factor` times faster.
+pub fn scaled_duration(input: Duration, factor: f64) -> Duration {
+    Duration::from_secs_f64(input.as_secs_f64() / factor)
+}
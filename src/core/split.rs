@@ -0,0 +1,100 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::core::sequence::{has_frame_counter, sequence_files};
+
+/// Share of the size limit a `--size` split aims for. Cuts only land on
+/// keyframes and the bitrate varies, so pieces run over the average.
+const SIZE_HEADROOM: f64 = 0.9;
+
+/// Cuts a file into pieces without re-encoding, with the segment muxer.
+#[derive(Debug, Clone)]
+pub struct SplitJob {
+    pub input: PathBuf,
+    /// Output name with a `%03d`-style counter.
+    pub pattern: PathBuf,
+    pub segment_time: Duration,
+}
+
+impl SplitJob {
+    pub fn args(&self) -> Vec<OsString> {
+        vec![
+            "-i".into(),
+            self.input.clone().into_os_string(),
+            "-map".into(),
+            "0".into(),
+            "-c".into(),
+            "copy".into(),
+            "-f".into(),
+            "segment".into(),
+            "-segment_time".into(),
+            format!("{:.3}", self.segment_time.as_secs_f64()).into(),
+            "-reset_timestamps".into(),
+            "1".into(),
+            self.pattern.clone().into_os_string(),
+        ]
+    }
+
+    /// Every piece written so far, in order.
+    pub fn segments(&self) -> Vec<PathBuf> {
+        sequence_files(&self.pattern)
+    }
+}
+
+/// Checks that `output` has exactly one `%03d`-style counter, which the
+/// segment muxer fills in with the piece number.
+pub fn segment_pattern(output: &Path) -> Result<PathBuf, String> {
+    let directives = output.to_string_lossy().replace("%%", "");
+    if directives.matches('%').count() == 1 && has_frame_counter(Path::new(&directives)) {
+        return Ok(output.to_path_buf());
+    }
+    if directives.contains('%') {
+        return Err(format!(
+            "'{}' needs exactly one numeric counter such as %03d, and no other % fields (write %% for a literal %)",
+            output.display()
+        ));
+    }
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let suggestion = match output.extension() {
+        Some(ext) => format!("{stem}_%03d.{}", ext.to_string_lossy()),
+        None => format!("{stem}_%03d"),
+    };
+    Err(format!(
+        "'{}' has no counter for the piece number; try '{}'",
+        output.display(),
+        output.with_file_name(suggestion).display()
+    ))
+}
+
+/// Segment length that keeps pieces under `max_bytes` at this bitrate.
+pub fn segment_time_for_size(max_bytes: u64, bitrate_kbps: f32) -> Option<Duration> {
+    if bitrate_kbps <= 0.0 {
+        return None;
+    }
+    let seconds = max_bytes as f64 * 8.0 * SIZE_HEADROOM / (f64::from(bitrate_kbps) * 1000.0);
+    (seconds >= 1.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+/// Parses a size such as `1.9GB`, `700MB` or `500M` into bytes (powers of
+/// 1000, so a limit is never overshot by reading it as binary units).
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" => 1e3,
+        "M" | "MB" => 1e6,
+        "G" | "GB" => 1e9,
+        "T" | "TB" => 1e12,
+        _ => return None,
+    };
+    let bytes = (number.parse::<f64>().ok()? * multiplier).round();
+    (bytes.is_finite() && bytes >= 1.0).then_some(bytes as u64)
+}
@@ -0,0 +1,102 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::core::command::escape_filter_value;
+
+static NEXT_TRANSFORMS: AtomicUsize = AtomicUsize::new(0);
+
+/// Where pass one leaves the camera motion for pass two. The file is
+/// removed when this is dropped, so keep it until both passes are done.
+#[derive(Debug)]
+pub struct TransformsFile {
+    path: PathBuf,
+}
+
+impl TransformsFile {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "ffflow-vidstab-{}-{}.trf",
+            std::process::id(),
+            NEXT_TRANSFORMS.fetch_add(1, Ordering::Relaxed)
+        ));
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Default for TransformsFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TransformsFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A two-pass vidstab stabilization: detect the shake, then smooth it out.
+#[derive(Debug)]
+pub struct StabilizeJob {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// How shaky the footage is, 1 (a little) to 10 (a lot).
+    pub shakiness: u32,
+    /// Frames on each side averaged into the camera path; more is smoother.
+    pub smoothing: u32,
+    /// Arguments for pass two, e.g. codecs.
+    pub extra_args: Vec<String>,
+    pub transforms: TransformsFile,
+}
+
+impl StabilizeJob {
+    /// Pass one: analyse the motion into the transforms file. Nothing is
+    /// written but that file.
+    pub fn detect_args(&self) -> Vec<OsString> {
+        vec![
+            "-i".into(),
+            self.input.clone().into_os_string(),
+            "-vf".into(),
+            format!(
+                "vidstabdetect=shakiness={}:result={}",
+                self.shakiness,
+                self.transforms_value()
+            )
+            .into(),
+            "-f".into(),
+            "null".into(),
+            "-".into(),
+        ]
+    }
+
+    /// Pass two: apply the smoothed motion, then sharpen a little to make
+    /// up for the resampling. Audio is copied.
+    pub fn transform_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "-i".into(),
+            self.input.clone().into_os_string(),
+            "-vf".into(),
+            format!(
+                "vidstabtransform=input={}:smoothing={},unsharp=5:5:0.8:3:3:0.4",
+                self.transforms_value(),
+                self.smoothing
+            )
+            .into(),
+            "-c:a".into(),
+            "copy".into(),
+        ];
+        args.extend(self.extra_args.iter().map(OsString::from));
+        args.push(self.output.clone().into_os_string());
+        args
+    }
+
+    fn transforms_value(&self) -> String {
+        escape_filter_value(&self.transforms.path().to_string_lossy())
+    }
+}
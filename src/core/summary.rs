@@ -6,10 +6,12 @@ use regex::Regex;
 use crate::core::progress::{parse_bitrate_to_kbps, parse_ffmpeg_time, parse_size_to_bytes};
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct EncodeSummary {
     pub final_size_bytes: u64,
     pub duration: Duration,
     pub avg_bitrate_kbps: f32,
+    pub encode_elapsed: Option<Duration>,
 }
 
 static RE_LSIZE: Lazy<Regex> =
@@ -42,5 +44,90 @@ pub fn parse_summary_line(line: &str) -> Option<EncodeSummary> {
         final_size_bytes: size.unwrap_or(0),
         duration: duration.unwrap_or(Duration::from_secs(0)),
         avg_bitrate_kbps: bitrate.unwrap_or(0.0),
+        encode_elapsed: None,
     })
 }
+
+/// How many times faster than realtime `media` was written in `elapsed`.
+/// `None` when either is zero.
+pub fn average_speed(media: Duration, elapsed: Duration) -> Option<f64> {
+    let elapsed = elapsed.as_secs_f64();
+    (elapsed > 0.0 && !media.is_zero()).then(|| media.as_secs_f64() / elapsed)
+}
+
+/// Running totals over every job of a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Jobs that have ended, whether or not they succeeded.
+    pub jobs: usize,
+    /// What ffmpeg reported writing, summed over its final stats lines.
+    pub bytes_written: u64,
+    /// Media time processed, as of each final stats line.
+    pub media: Duration,
+    /// Wall-clock time spent in jobs, every pass included.
+    pub elapsed: Duration,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one ffmpeg run's final stats. A job with several passes adds
+    /// one per pass.
+    pub fn add_summary(&mut self, summary: &EncodeSummary) {
+        self.bytes_written = self.bytes_written.saturating_add(summary.final_size_bytes);
+        self.media = self.media.saturating_add(summary.duration);
+    }
+
+    /// Counts a job that has ended after running for `elapsed`.
+    pub fn add_job(&mut self, elapsed: Duration) {
+        self.jobs += 1;
+        self.elapsed = self.elapsed.saturating_add(elapsed);
+    }
+
+    /// How many times faster than realtime the session has processed media.
+    pub fn average_speed(&self) -> Option<f64> {
+        average_speed(self.media, self.elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: f64) -> Duration {
+        Duration::from_secs_f64(secs)
+    }
+
+    #[test]
+    fn average_speed_against_realtime() {
+        assert_eq!(average_speed(secs(30.0), secs(60.0)), Some(0.5));
+        assert_eq!(average_speed(secs(60.0), secs(60.0)), Some(1.0));
+        assert_eq!(average_speed(secs(465.0), secs(150.0)), Some(3.1));
+    }
+
+    #[test]
+    fn average_speed_needs_both_times() {
+        assert_eq!(average_speed(secs(60.0), Duration::ZERO), None);
+        assert_eq!(average_speed(Duration::ZERO, secs(60.0)), None);
+    }
+
+    #[test]
+    fn session_speed_sums_every_job() {
+        let mut stats = SessionStats::new();
+        assert_eq!(stats.average_speed(), None);
+        let summary = parse_summary_line(
+            "frame= 1500 fps=50 q=-1.0 Lsize=    2048kB time=00:01:00.00 bitrate= 279.6kbits/s speed=2.0x",
+        )
+        .unwrap();
+        stats.add_summary(&summary);
+        stats.add_job(secs(30.0));
+        stats.add_summary(&summary);
+        stats.add_job(secs(90.0));
+        assert_eq!(stats.jobs, 2);
+        assert_eq!(stats.bytes_written, 2 * 2_048_000);
+        assert_eq!(stats.media, secs(120.0));
+        assert_eq!(stats.average_speed(), Some(1.0));
+    }
+}
@@ -0,0 +1,113 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static RE_TAG_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_.:-]*$").unwrap());
+// `    title           : My Video`; a continuation line has no key.
+static RE_TAG_LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {4}(\S*?)\s*:(?: (.*))?$").unwrap());
+
+/// Containers that take a cover image as an `attached_pic` video stream.
+const COVER_EXTENSIONS: &[&str] = &["mp4", "m4a", "m4v", "mov"];
+
+/// Splits `key=value` at the first `=`, so the value may contain more.
+pub fn parse_tag(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid tag '{value}' (expected key=value)"))?;
+    if !RE_TAG_KEY.is_match(key) {
+        return Err(format!(
+            "invalid tag key '{key}' (letters, digits and _ . : - only)"
+        ));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Rewrites a file's container tags, copying every stream as is.
+#[derive(Debug, Clone)]
+pub struct TagEdit {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub set: Vec<(String, String)>,
+    /// Drop the input's tags before applying `set`.
+    pub clear_all: bool,
+    pub cover: Option<PathBuf>,
+}
+
+impl TagEdit {
+    /// `video_streams` is how many video streams the input has; the cover
+    /// comes after them.
+    pub fn args(&self, video_streams: usize) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["-i".into(), self.input.clone().into_os_string()];
+        if let Some(cover) = &self.cover {
+            args.extend(["-i".into(), cover.clone().into_os_string()]);
+        }
+        args.extend(["-map".into(), "0".into()]);
+        if self.cover.is_some() {
+            args.extend(["-map".into(), "1".into()]);
+        }
+        args.extend(["-c".into(), "copy".into()]);
+        if self.clear_all {
+            args.extend(["-map_metadata".into(), "-1".into()]);
+        }
+        // Each tag is one argument, so quotes, `=` and newlines in the
+        // value reach ffmpeg untouched.
+        for (key, value) in &self.set {
+            args.push("-metadata".into());
+            args.push(format!("{key}={value}").into());
+        }
+        if self.cover.is_some() {
+            args.push(format!("-disposition:v:{video_streams}").into());
+            args.push("attached_pic".into());
+        }
+        args.push(self.output.clone().into_os_string());
+        args
+    }
+}
+
+/// Whether `output`'s container can carry cover art.
+pub fn supports_cover(output: &Path) -> bool {
+    output
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| COVER_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Container tags of the first input in `ffmpeg -i` output, in file order.
+/// Multi-line values are joined with `\n`.
+pub fn parse_tags(stderr: &str) -> Vec<(String, String)> {
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut in_input = false;
+    let mut in_block = false;
+    for line in stderr.lines() {
+        if line.starts_with("Input #") {
+            if in_input {
+                break;
+            }
+            in_input = true;
+            continue;
+        }
+        if !in_input {
+            continue;
+        }
+        if !in_block {
+            in_block = line == "  Metadata:";
+            continue;
+        }
+        let Some(capture) = RE_TAG_LINE.captures(line) else {
+            break;
+        };
+        let value = capture.get(2).map_or("", |value| value.as_str());
+        match (&capture[1], tags.last_mut()) {
+            ("", Some((_, last))) => {
+                last.push('\n');
+                last.push_str(value);
+            }
+            ("", None) => {}
+            (key, _) => tags.push((key.to_string(), value.to_string())),
+        }
+    }
+    tags
+}
@@ -0,0 +1,86 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::core::session_log::format_timestamp;
+
+// Other `{...}` text is left alone, so directories with braces in their
+// names still work.
+static RE_TOKEN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{(stem|ext|dir|date|preset|codec)(?::([^{}]*))?\}").unwrap());
+
+/// What an output template can refer to.
+#[derive(Debug, Clone)]
+pub struct TemplateContext<'a> {
+    /// The first input; `{stem}`, `{ext}` and `{dir}` come from it.
+    pub input: &'a Path,
+    pub preset: Option<&'a str>,
+    pub codec: Option<&'a str>,
+    /// `{date}`, as YYYY-MM-DD (UTC).
+    pub date: SystemTime,
+}
+
+/// Whether `path` has any template token in it.
+pub fn is_template(path: &Path) -> bool {
+    path.to_str().is_some_and(|text| RE_TOKEN.is_match(text))
+}
+
+/// Expands `{stem}`, `{ext}`, `{dir}`, `{date}`, `{preset}` and `{codec}`.
+/// `{name:fallback}` uses the fallback when the value is missing, e.g.
+/// `{ext:mp4}` for an input without an extension.
+pub fn expand_template(template: &Path, context: &TemplateContext) -> Result<PathBuf, String> {
+    let Some(text) = template.to_str() else {
+        return Ok(template.to_path_buf());
+    };
+    let mut expanded = OsString::new();
+    let mut last = 0;
+    for capture in RE_TOKEN.captures_iter(text) {
+        let token = capture.get(0).expect("whole match");
+        expanded.push(&text[last..token.start()]);
+        last = token.end();
+        let value = match &capture[1] {
+            "stem" => context.input.file_stem().map(OsString::from),
+            "ext" => context.input.extension().map(OsString::from),
+            "dir" => Some(match context.input.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.as_os_str().to_owned(),
+                _ => OsString::from("."),
+            }),
+            "date" => Some(format_timestamp(context.date)[..10].into()),
+            "preset" => context.preset.map(OsString::from),
+            _ => context.codec.map(OsString::from),
+        };
+        match (value, capture.get(2)) {
+            (Some(value), _) => expanded.push(value),
+            (None, Some(fallback)) => expanded.push(fallback.as_str()),
+            (None, None) => {
+                return Err(format!(
+                    "output template: nothing to put in {} (add a fallback, e.g. {{{}:x}})",
+                    token.as_str(),
+                    &capture[1]
+                ))
+            }
+        }
+    }
+    expanded.push(&text[last..]);
+    Ok(PathBuf::from(expanded))
+}
+
+/// The first of `name_1.ext`, `name_2.ext`, ... that does not exist yet.
+pub fn next_free_name(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default();
+    (1..)
+        .map(|n| {
+            let mut name = stem.to_os_string();
+            name.push(format!("_{n}"));
+            if let Some(ext) = path.extension() {
+                name.push(".");
+                name.push(ext);
+            }
+            path.with_file_name(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered name is free")
+}
@@ -0,0 +1,222 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::core::formatter::format_duration;
+use crate::core::runner::writes_to_stdout;
+use crate::core::sequence::has_frame_counter;
+use crate::core::summary::parse_summary_line;
+
+/// Least slack allowed on the output's duration.
+const DURATION_TOLERANCE: Duration = Duration::from_secs(1);
+/// Slack as a share of the expected duration, for long outputs.
+const DURATION_TOLERANCE_SHARE: f64 = 0.02;
+/// Decode errors listed one by one; the rest are only counted.
+const MAX_DECODE_ERRORS: usize = 5;
+
+static RE_STREAM_KIND: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*Stream #\d+:\d+.*?: (Video|Audio|Subtitle):").unwrap());
+
+/// How thoroughly a finished job's output is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// Read the header: duration and stream types.
+    Quick,
+    /// Also decode the whole file and report decode errors.
+    Full,
+}
+
+impl FromStr for VerifyMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "quick" => Ok(Self::Quick),
+            "full" => Ok(Self::Full),
+            _ => Err(format!(
+                "invalid verify mode '{value}' (expected quick or full)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+}
+
+impl StreamKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Subtitle => "subtitle",
+        }
+    }
+}
+
+impl FromStr for StreamKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "video" | "v" => Ok(Self::Video),
+            "audio" | "a" => Ok(Self::Audio),
+            "subtitle" | "s" => Ok(Self::Subtitle),
+            _ => Err(format!(
+                "invalid stream type '{value}' (expected video, audio or subtitle)"
+            )),
+        }
+    }
+}
+
+/// How long a file is and which kinds of streams it has.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaLayout {
+    pub duration: Option<Duration>,
+    pub streams: Vec<StreamKind>,
+}
+
+impl MediaLayout {
+    /// What a job says it wrote: the streams of its last `Output #N`
+    /// section (the file at the end of the command) and the time on its
+    /// final stats line.
+    pub fn from_job_log<S: AsRef<str>>(lines: &[S]) -> Self {
+        let start = lines
+            .iter()
+            .rposition(|line| line.as_ref().starts_with("Output #"))
+            .map_or(lines.len(), |index| index + 1);
+        let output = lines[start..].iter().map(AsRef::as_ref);
+        Self {
+            duration: lines
+                .iter()
+                .rev()
+                .find_map(|line| parse_summary_line(line.as_ref()))
+                .map(|summary| summary.duration),
+            streams: stream_kinds(output),
+        }
+    }
+
+    fn count(&self, kind: StreamKind) -> usize {
+        self.streams
+            .iter()
+            .filter(|stream| **stream == kind)
+            .count()
+    }
+
+    /// e.g. `00:01:00, 1 video, 2 audio`.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self
+            .duration
+            .map_or("no duration".to_string(), format_duration)];
+        for kind in [StreamKind::Video, StreamKind::Audio, StreamKind::Subtitle] {
+            match self.count(kind) {
+                0 => {}
+                count => parts.push(format!("{count} {}", kind.name())),
+            }
+        }
+        parts.join(", ")
+    }
+}
+
+/// Kinds of the `Stream #N:M` lines among `lines`, in order.
+pub fn stream_kinds<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<StreamKind> {
+    lines
+        .into_iter()
+        .filter_map(|line| RE_STREAM_KIND.captures(line))
+        .map(|capture| match &capture[1] {
+            "Video" => StreamKind::Video,
+            "Audio" => StreamKind::Audio,
+            _ => StreamKind::Subtitle,
+        })
+        .collect()
+}
+
+/// What is wrong with the file on disk compared to what the job wrote; empty
+/// when it checks out.
+pub fn compare_layouts(expected: &MediaLayout, actual: &MediaLayout) -> Vec<String> {
+    let mut findings = Vec::new();
+    match (expected.duration, actual.duration) {
+        (Some(expected), Some(actual)) => {
+            let tolerance = DURATION_TOLERANCE.max(expected.mul_f64(DURATION_TOLERANCE_SHARE));
+            let off = actual.abs_diff(expected);
+            if off > tolerance {
+                findings.push(format!(
+                    "duration is {}, expected {}",
+                    format_duration(actual),
+                    format_duration(expected)
+                ));
+            }
+        }
+        (Some(_), None) => findings.push("the file reports no duration".to_string()),
+        (None, _) => {}
+    }
+    for kind in [StreamKind::Video, StreamKind::Audio, StreamKind::Subtitle] {
+        let (wanted, found) = (expected.count(kind), actual.count(kind));
+        if found < wanted {
+            findings.push(format!(
+                "expected {wanted} {} stream(s), found {found}",
+                kind.name()
+            ));
+        }
+    }
+    findings
+}
+
+/// The file a job writes, when it is one that can be checked afterwards
+/// (not stdout, a URL or a numbered sequence).
+pub fn verify_target(args: &[OsString]) -> Option<PathBuf> {
+    if writes_to_stdout(args)
+        || args
+            .windows(2)
+            .any(|pair| pair[0] == "-f" && pair[1] == "null")
+    {
+        return None;
+    }
+    let output = PathBuf::from(args.last()?);
+    let url = output.to_str().is_some_and(|text| text.contains("://"));
+    (!url && !has_frame_counter(&output)).then_some(output)
+}
+
+/// Decodes every stream of `path` and throws the frames away; only
+/// decode errors are logged, and progress goes to stdout.
+pub fn decode_args(path: &Path) -> Vec<OsString> {
+    vec![
+        "-v".into(),
+        "error".into(),
+        "-progress".into(),
+        "pipe:1".into(),
+        "-i".into(),
+        path.as_os_str().to_owned(),
+        "-f".into(),
+        "null".into(),
+        "-".into(),
+    ]
+}
+
+/// Findings from a decode run's log, which holds nothing but errors.
+pub fn decode_findings<S: AsRef<str>>(lines: &[S]) -> Vec<String> {
+    let errors: Vec<&str> = lines
+        .iter()
+        .map(|line| line.as_ref().trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let mut findings: Vec<String> = errors
+        .iter()
+        .take(MAX_DECODE_ERRORS)
+        .map(|line| format!("decode error: {line}"))
+        .collect();
+    if errors.len() > MAX_DECODE_ERRORS {
+        findings.push(format!(
+            "... and {} more decode errors",
+            errors.len() - MAX_DECODE_ERRORS
+        ));
+    }
+    findings
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long a file must stay quiet after a change before it counts; an
+/// editor saving a large file writes it in many steps.
+pub const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Reports when a file is modified or replaced. The parent directory is
+/// watched rather than the file, so a file that is deleted and written
+/// again (as many tools save) is still followed.
+pub struct InputWatcher {
+    path: PathBuf,
+    _watcher: RecommendedWatcher,
+    changes: Receiver<Instant>,
+    changed_at: Option<Instant>,
+}
+
+impl InputWatcher {
+    pub fn new(path: &Path) -> Result<Self, String> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("cannot watch '{}'", path.display()))?
+            .to_os_string();
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let (tx, changes) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                let ours = event
+                    .paths
+                    .iter()
+                    .any(|changed| changed.file_name() == Some(name.as_os_str()));
+                if ours && (event.kind.is_create() || event.kind.is_modify()) {
+                    let _ = tx.send(Instant::now());
+                }
+            })
+            .map_err(|err| format!("cannot watch '{}': {err}", path.display()))?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|err| format!("cannot watch '{}': {err}", dir.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            _watcher: watcher,
+            changes,
+            changed_at: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True once the file has changed and then settled for
+    /// `WATCH_DEBOUNCE`. While it is missing (mid-replace), keeps waiting.
+    pub fn poll(&mut self) -> bool {
+        while let Ok(at) = self.changes.try_recv() {
+            self.changed_at = Some(at);
+        }
+        let settled = self
+            .changed_at
+            .is_some_and(|at| at.elapsed() >= WATCH_DEBOUNCE);
+        if settled && self.path.exists() {
+            self.changed_at = None;
+            return true;
+        }
+        false
+    }
+}
+
+impl std::fmt::Debug for InputWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InputWatcher")
+            .field("path", &self.path)
+            .field("changed_at", &self.changed_at)
+            .finish()
+    }
+}
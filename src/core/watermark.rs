@@ -0,0 +1,74 @@
+use std::str::FromStr;
+
+/// Where the watermark sits on the picture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "top-left" => Ok(Position::TopLeft),
+            "top-right" => Ok(Position::TopRight),
+            "bottom-left" => Ok(Position::BottomLeft),
+            "bottom-right" => Ok(Position::BottomRight),
+            "center" => Ok(Position::Center),
+            _ => Err(format!(
+                "unknown position '{value}' (expected top-left, top-right, bottom-left, bottom-right or center)"
+            )),
+        }
+    }
+}
+
+/// How to lay a watermark (input 1) over a video (input 0).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watermark {
+    pub position: Position,
+    /// Gap to the nearest edges, in pixels. Ignored when centered.
+    pub margin: u32,
+    /// 0 is invisible, 1 leaves the image as is.
+    pub opacity: f32,
+    /// Scale the watermark to this width first (height follows).
+    pub width: Option<u32>,
+}
+
+impl Watermark {
+    /// The `-filter_complex` graph; the result is the `[vout]` pad.
+    pub fn filter_graph(&self) -> String {
+        let mut chain = Vec::new();
+        if let Some(width) = self.width {
+            chain.push(format!("scale={width}:-1"));
+        }
+        if self.opacity < 1.0 {
+            chain.push(format!("format=rgba,colorchannelmixer=aa={}", self.opacity));
+        }
+        let (x, y) = self.overlay_position();
+        if chain.is_empty() {
+            return format!("[0:v][1:v]overlay={x}:{y}[vout]");
+        }
+        format!(
+            "[1:v]{}[wm];[0:v][wm]overlay={x}:{y}[vout]",
+            chain.join(",")
+        )
+    }
+
+    /// `overlay` x/y expressions: `W`/`H` are the video's size, `w`/`h`
+    /// the watermark's.
+    fn overlay_position(&self) -> (String, String) {
+        let m = self.margin;
+        match self.position {
+            Position::TopLeft => (format!("{m}"), format!("{m}")),
+            Position::TopRight => (format!("W-w-{m}"), format!("{m}")),
+            Position::BottomLeft => (format!("{m}"), format!("H-h-{m}")),
+            Position::BottomRight => (format!("W-w-{m}"), format!("H-h-{m}")),
+            Position::Center => ("(W-w)/2".to_string(), "(H-h)/2".to_string()),
+        }
+    }
+}
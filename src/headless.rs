@@ -0,0 +1,1239 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::cli;
+use crate::core;
+use crate::core::alias::expand_alias;
+use crate::core::batch::{expand_command, FlwParser};
+use crate::core::checkpoint::Checkpoint;
+use crate::core::depends::{describe_blocker, split_job_header, JobOutcomes, Outcome};
+use crate::core::config::{config_path, Config};
+use crate::core::error::{classify_stderr, FailureReason, FfxError};
+use crate::core::cropdetect::{cropdetect_args, CropDetector};
+use crate::core::event::FfmpegEvent;
+use crate::core::hls::read_playlist;
+use crate::core::loudnorm::{LoudnormJob, LoudnormParser};
+use crate::core::job::JobStatus;
+use crate::core::formatter::{
+    format_input_line, format_mapping_line, format_output_line, format_playlist_stats, format_progress_line, progress_bar_string, update_ratio, format_quality_lines,
+    format_completion_line, format_event_json, format_finish_json, format_message_json, format_rung_result, format_saved_file, format_start_json,
+    format_summary_line,
+};
+use crate::core::progress::{parse_duration_from_args, parse_seek_from_args, InputDuration};
+use crate::core::fs::SystemFs;
+use crate::core::preflight::preflight;
+use crate::core::quality::QualityParser;
+use crate::core::queue::{
+    parse_on_error_directive, split_priority, strip_next_marker, JobQueue, OnError,
+};
+use crate::core::schedule::{
+    format_start_time, parse_scheduled, strict_schedule, strip_at_prefix, Schedule,
+};
+use crate::core::probe::{decode_log, probe_layout};
+use crate::core::runner::{JobHandle, JobTimeout};
+use crate::core::sequence::sequence_total_frames;
+use crate::core::session_log::SessionLog;
+use crate::core::verify::{compare_layouts, decode_findings, verify_target, MediaLayout, VerifyMode};
+
+/// How often a progress line is printed while a job runs.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// Cells inside the brackets of the progress bar.
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// How long ffmpeg gets to finish writing after Ctrl-C before it is killed.
+const INTERRUPT_GRACE: Duration = Duration::from_secs(5);
+
+const INTERRUPT_POLL: Duration = Duration::from_millis(200);
+
+/// Runs queued commands followed by every command read from stdin, printing
+/// the session log to stdout. Returns once stdin hits EOF and the queue is
+/// drained.
+///
+/// Without `read_stdin` only the queue runs (`-c`, or a batch piped in as
+/// `-`), and the log goes to stderr so stdout holds nothing but what the
+/// commands themselves write.
+///
+/// With `json_events` the session is printed as one JSON object per line
+/// instead, on stdout unless a job writes its output there.
+///
+/// Nothing is forwarded to ffmpeg's stdin: jobs get `-n` (never overwrite),
+/// or `-y` with `overwrite`, and any prompt that still shows up is answered
+/// `n`. If a job failed, the
+/// error carries the exit code of the first ffmpeg that did.
+pub fn run(
+    initial_queue: Vec<OsString>,
+    log_file: Option<PathBuf>,
+    checkpoint: Option<Checkpoint>,
+    read_stdin: bool,
+    json_events: bool,
+    overwrite: bool,
+) -> Result<(), FfxError> {
+    let mut out = Output::open(log_file.as_deref())?;
+    out.json = json_events;
+    out.overwrite = overwrite;
+    out.to_stderr = !read_stdin && !json_events;
+    if let Err(err) = core::process::catch_interrupt() {
+        out.record("session", &format!("Ctrl-C will not stop ffmpeg cleanly: {err}"));
+    }
+    run_session(initial_queue, checkpoint, read_stdin, &mut out)
+}
+
+/// `run` once its output is set up.
+fn run_session(
+    initial_queue: Vec<OsString>,
+    mut checkpoint: Option<Checkpoint>,
+    read_stdin: bool,
+    out: &mut Output,
+) -> Result<(), FfxError> {
+    let mut outcomes = JobOutcomes::new();
+    if let Some(checkpoint) = checkpoint.as_ref().filter(|checkpoint| !checkpoint.skipped().is_empty()) {
+        out.line(format!(
+            "Skipped {} job(s) already done according to '{}'",
+            checkpoint.skipped().len(),
+            checkpoint.path().display()
+        ));
+        for line in checkpoint.skipped() {
+            outcomes.record_line(line, Outcome::Succeeded);
+        }
+    }
+    if !initial_queue.is_empty() {
+        out.record("batch", &format!("{} commands queued", initial_queue.len()));
+        for command in &initial_queue {
+            out.record("batch", &command.to_string_lossy());
+        }
+    }
+
+    let aliases = match config_path().map(|path| Config::load(&path)) {
+        Some(Ok(config)) => {
+            let aliases = config.aliases.clone();
+            cli::use_config(config);
+            aliases
+        }
+        Some(Err(err)) => {
+            out.line(format!("warning: {err}; its settings are ignored"));
+            BTreeMap::new()
+        }
+        None => BTreeMap::new(),
+    };
+
+    // Lines from stdin are expanded as they arrive, so a bad one is an
+    // error in its place in the session.
+    let (line_tx, line_rx) = mpsc::channel::<Result<OsString, String>>();
+    let mut queue = JobQueue::from(initial_queue);
+    while let Some(command) = queue.pop_front() {
+        let _ = line_tx.send(Ok(command));
+    }
+
+    if read_stdin {
+        thread::spawn(move || read_commands(io::stdin().lock(), line_tx));
+    } else {
+        drop(line_tx);
+    }
+
+    let mut failed = 0usize;
+    // Lines that one wildcard line fanned out into, run before the next.
+    let mut fanned: VecDeque<OsString> = VecDeque::new();
+    // Lines given an `at` start time, run once it comes even if stdin has
+    // ended by then.
+    let mut schedule = Schedule::new();
+    let mut on_error = OnError::default();
+    // Failures from before the policy was set don't count against it.
+    let mut failed_before_policy = 0;
+    // The line of the last command run and the failure count before it;
+    // it is checkpointed if that count hasn't moved.
+    let mut ran: Option<(OsString, usize)> = None;
+    // The `@job` name of the last line and the failure count before it.
+    let mut named: Option<(String, usize)> = None;
+    loop {
+        if let Some((line, _)) = ran.take().filter(|(_, before)| *before == failed) {
+            record_checkpoint(checkpoint.as_mut(), &line, out);
+        }
+        if let Some((name, before)) = named.take() {
+            let outcome = if failed == before {
+                Outcome::Succeeded
+            } else {
+                Outcome::Failed
+            };
+            outcomes.record(&name, outcome);
+        }
+        // Nobody is there to resume a paused queue, so pause stops too.
+        if failed > failed_before_policy && on_error != OnError::Continue {
+            out.line(format!(
+                "Stopping after the failure (on-error {}); the remaining jobs are skipped",
+                on_error.name()
+            ));
+            break;
+        }
+        for job in schedule.take_due(SystemTime::now()) {
+            out.line(format!(
+                "Start time {} reached: {}",
+                format_start_time(job.at),
+                job.line.to_string_lossy()
+            ));
+            fanned.push_back(job.line);
+        }
+        // Ctrl-C no longer ends the process by itself, so waiting for
+        // stdin has to notice it too.
+        let line = match fanned.pop_front() {
+            Some(line) => line,
+            None => match line_rx.recv_timeout(INTERRUPT_POLL) {
+                Ok(Ok(line)) => line,
+                Ok(Err(err)) => {
+                    out.line(format!("error: {err}"));
+                    failed += 1;
+                    continue;
+                }
+                Err(_) if core::process::interrupted() => break,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) if schedule.is_empty() => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(INTERRUPT_POLL);
+                    continue;
+                }
+            },
+        };
+        if core::process::interrupted() {
+            break;
+        }
+        if checkpoint.as_ref().is_some_and(|checkpoint| checkpoint.is_done(&line)) {
+            out.line(format!("Already done, skipping: {}", line.to_string_lossy()));
+            outcomes.record_line(&line, Outcome::Succeeded);
+            continue;
+        }
+        let queued = line.clone();
+        // Lines run in the order they arrive, so queue markers mean nothing
+        // here beyond being valid.
+        let line = strip_next_marker(&line).unwrap_or(line);
+        if let Some(policy) = line.to_str().and_then(parse_on_error_directive) {
+            out.line(format!(">> {}", line.to_string_lossy()));
+            match policy {
+                Ok(policy) => {
+                    on_error = policy;
+                    failed_before_policy = failed;
+                }
+                Err(err) => {
+                    out.line(format!("error: {err}"));
+                    failed += 1;
+                }
+            }
+            continue;
+        }
+        let line = match split_priority(&line) {
+            Ok((_, line)) => line,
+            Err(err) => {
+                out.line(format!(">> {}", line.to_string_lossy()));
+                out.line(format!("error: {err}"));
+                failed += 1;
+                continue;
+            }
+        };
+        let line = match split_job_header(&line) {
+            Ok(None) => line,
+            Ok(Some((header, command))) => {
+                if let Some((job, outcome)) = outcomes.blocker(&header) {
+                    out.line(format!(">> {}", line.to_string_lossy()));
+                    out.line(format!(
+                        "Skipped job '{}': {}",
+                        header.name,
+                        describe_blocker(job, outcome)
+                    ));
+                    outcomes.record(&header.name, Outcome::Skipped);
+                    continue;
+                }
+                named = Some((header.name, failed));
+                command
+            }
+            Err(err) => {
+                out.line(format!(">> {}", line.to_string_lossy()));
+                out.line(format!("error: {err}"));
+                failed += 1;
+                continue;
+            }
+        };
+        if let Some(rest) = strip_at_prefix(&line) {
+            out.line(format!(">> {}", line.to_string_lossy()));
+            let now = SystemTime::now();
+            match parse_scheduled(&rest, now) {
+                Ok(job) if job.at > now => {
+                    out.line(format!(
+                        "Scheduled for {}: {}",
+                        format_start_time(job.at),
+                        job.line.to_string_lossy()
+                    ));
+                    schedule.add(job);
+                }
+                Ok(job) if strict_schedule() => {
+                    out.line(format!(
+                        "error: start time {} has already passed",
+                        format_start_time(job.at)
+                    ));
+                    failed += 1;
+                }
+                Ok(job) => {
+                    out.line(format!(
+                        "note: start time {} has already passed; running now",
+                        format_start_time(job.at)
+                    ));
+                    fanned.push_front(job.line);
+                }
+                Err(err) => {
+                    out.line(format!("error: {err}"));
+                    failed += 1;
+                }
+            }
+            continue;
+        }
+        let line = match expand_alias(&line, &aliases) {
+            Ok(line) => line,
+            Err(err) => {
+                out.line(format!(">> {}", line.to_string_lossy()));
+                out.line(format!("error: {err}"));
+                failed += 1;
+                continue;
+            }
+        };
+        ran = Some((queued, failed));
+        // Media on stdout must not be mixed with our own lines.
+        out.to_stderr = (!read_stdin && !out.json) || cli::line_writes_stdout(&line);
+        out.line(format!(">> {}", line.to_string_lossy()));
+        // `ffmpeg ...` lines pass through; anything else is parsed once here.
+        let ok = match cli::passthrough_job(&line) {
+            Some(job) => run_line_job(job, out),
+            None => match cli::parse_line(&line) {
+                Err(err) => {
+                    out.line(format!("error: {err}"));
+                    false
+                }
+                Ok(cli::Commands::Encode(args)) => {
+                    let lines = cli::fan_out_encode(&line, &args);
+                    if lines.len() > 1 {
+                        // The line itself is done once all its jobs are.
+                        ran = None;
+                        out.line(format!("Queued {} jobs, one per matching file", lines.len()));
+                        fanned.extend(lines);
+                        true
+                    } else {
+                        run_line_job(cli::job_args_from_command(cli::Commands::Encode(args)), out)
+                    }
+                }
+                Ok(cli::Commands::Cropdetect(args)) => run_cropdetect(args, out),
+                Ok(cli::Commands::Chapters(args)) => print_lines(cli::chapter_lines(&args), out),
+                Ok(cli::Commands::Encoders(args)) => print_lines(cli::encoder_lines(&args), out),
+                Ok(cli::Commands::Formats(args)) => print_lines(cli::format_lines(&args), out),
+                Ok(cli::Commands::Filters(args)) => print_lines(cli::filter_lines(&args), out),
+                Ok(cli::Commands::Doctor) => print_lines(Ok(cli::doctor_lines()), out),
+                Ok(cli::Commands::Version) => print_lines(Ok(cli::version_lines()), out),
+                Ok(cli::Commands::Tag(args)) if args.show => print_lines(cli::tag_lines(&args.input), out),
+                Ok(cli::Commands::Hls(args)) => run_hls(args, out),
+                Ok(cli::Commands::Split(args)) => run_split(args, out),
+                Ok(cli::Commands::Ladder(args)) => {
+                    failed += run_ladder(args, out);
+                    true
+                }
+                Ok(cli::Commands::Compare(args)) => run_compare(args, out),
+                Ok(cli::Commands::Stabilize(args)) => run_stabilize(args, out),
+                Ok(cli::Commands::Normalize(args)) => run_normalize(cli::normalize_args_to_job(args), out),
+                Ok(command) => run_line_job(cli::job_args_from_command(command), out),
+            },
+        };
+        if !ok {
+            failed += 1;
+        }
+    }
+
+    if core::process::interrupted() {
+        out.record("session", "interrupted");
+        return Err(FfxError::Interrupted);
+    }
+    if failed > 0 {
+        let message = format!("{failed} job(s) failed");
+        return Err(match out.failed_exit {
+            Some(exit_code) => FfxError::ProcessFailed {
+                exit_code: Some(exit_code),
+                stderr: message,
+            },
+            None => FfxError::InvalidCommand { message },
+        });
+    }
+    Ok(())
+}
+
+/// Prints the lines of a listing command, or its error. Returns whether
+/// it worked.
+fn print_lines(lines: Result<Vec<String>, String>, out: &mut Output) -> bool {
+    match lines {
+        Ok(lines) => {
+            lines.into_iter().for_each(|line| out.line(line));
+            true
+        }
+        Err(err) => {
+            out.line(format!("error: {err}"));
+            false
+        }
+    }
+}
+
+/// Runs a line that is a single ffmpeg job. Returns whether it finished.
+fn run_line_job(job: Result<cli::JobArgs, String>, out: &mut Output) -> bool {
+    let job = match job {
+        Ok(job) => job,
+        Err(err) => {
+            out.line(format!("error: {err}"));
+            return false;
+        }
+    };
+    for note in &job.notes {
+        out.line(note.clone());
+    }
+    // Nobody is there to confirm, so a short disk is only a warning.
+    if let Some(message) = preflight(&SystemFs, &job.args, job.duration).and_then(|check| check.message()) {
+        out.line(format!("warning: {message}"));
+    }
+    // `job.concat_list` stays alive until ffmpeg is done with it.
+    if run_job_for(job.args, job.duration, job.timeout, job.verify, out).is_none() {
+        return false;
+    }
+    if let Some(path) = job.report_output {
+        match std::fs::metadata(&path) {
+            Ok(meta) => out.line(format_saved_file(&path, meta.len())),
+            Err(err) => out.line(format!("warning: cannot read '{}': {err}", path.display())),
+        }
+    }
+    true
+}
+
+/// Sends each command read from `input` (stdin), expanded, until it ends
+/// or the session stops listening.
+fn read_commands(mut input: impl BufRead, line_tx: mpsc::Sender<Result<OsString, String>>) {
+    let mut parser = FlwParser::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match input.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if let Some(command) = parser.push_line(&line) {
+            if line_tx.send(expand_command(command)).is_err() {
+                return;
+            }
+        }
+    }
+    if let Some(command) = parser.finish() {
+        let _ = line_tx.send(expand_command(command));
+    }
+}
+
+fn record_checkpoint(checkpoint: Option<&mut Checkpoint>, line: &OsStr, out: &mut Output) {
+    let Some(checkpoint) = checkpoint else {
+        return;
+    };
+    if let Err(err) = checkpoint.record(line) {
+        out.line(format!(
+            "warning: cannot write checkpoint '{}': {err}",
+            checkpoint.path().display()
+        ));
+    }
+}
+
+fn run_hls(args: cli::HlsArgs, out: &mut Output) -> bool {
+    let (job, notes) = match cli::hls_args_to_job(args) {
+        Ok(planned) => planned,
+        Err(err) => {
+            out.line(format!("error: {err}"));
+            return false;
+        }
+    };
+    for note in notes {
+        out.line(note);
+    }
+    if run_job(job.args(), out).is_none() {
+        return false;
+    }
+    let playlist = job.media_playlist();
+    match read_playlist(&playlist) {
+        Ok(stats) => out.line(format_playlist_stats(&playlist, &stats)),
+        Err(err) => out.line(format!("warning: cannot read '{}': {err}", playlist.display())),
+    }
+    true
+}
+
+fn run_split(args: cli::SplitArgs, out: &mut Output) -> bool {
+    let (job, notes) = match cli::split_args_to_job(args) {
+        Ok(planned) => planned,
+        Err(err) => {
+            out.line(format!("error: {err}"));
+            return false;
+        }
+    };
+    for note in notes {
+        out.line(note);
+    }
+    if run_job(job.args(), out).is_none() {
+        return false;
+    }
+    let segments = job.segments();
+    for path in &segments {
+        match std::fs::metadata(path) {
+            Ok(meta) => out.line(format_saved_file(path, meta.len())),
+            Err(err) => out.line(format!("warning: cannot read '{}': {err}", path.display())),
+        }
+    }
+    out.line(format!("{} pieces written", segments.len()));
+    true
+}
+
+/// Runs every rung, then lists what each produced. Returns how many failed.
+fn run_ladder(args: cli::LadderArgs, out: &mut Output) -> usize {
+    let plan = match cli::ladder_args_to_plan(args) {
+        Ok(plan) => plan,
+        Err(err) => {
+            out.line(format!("error: {err}"));
+            return 1;
+        }
+    };
+    for note in &plan.notes {
+        out.line(note.clone());
+    }
+    let total = plan.rungs.len();
+    let mut succeeded = Vec::with_capacity(total);
+    for (idx, planned) in plan.rungs.iter().enumerate() {
+        if core::process::interrupted() {
+            break;
+        }
+        out.line(format!("{} {}/{total}", plan.label, idx + 1));
+        out.line(format!(">> {}", planned.line.to_string_lossy()));
+        let ok = match cli::job_args_from_line(&planned.line) {
+            Ok(job) => run_job(job.args, out).is_some(),
+            Err(err) => {
+                out.line(format!("error: {err}"));
+                false
+            }
+        };
+        succeeded.push(ok);
+    }
+
+    out.line(format!("{} done:", plan.label));
+    let mut failed = 0;
+    for (planned, ok) in plan.rungs.iter().zip(succeeded) {
+        match std::fs::metadata(&planned.output).ok().filter(|_| ok) {
+            Some(meta) => out.line(format_rung_result(
+                &planned.rung,
+                &planned.output,
+                meta.len(),
+                plan.duration,
+            )),
+            None => {
+                failed += 1;
+                out.line(format!(
+                    "{:>5}p  {}  failed",
+                    planned.rung.height,
+                    planned.output.display()
+                ));
+            }
+        }
+    }
+    failed
+}
+
+fn run_compare(args: cli::CompareArgs, out: &mut Output) -> bool {
+    let (job, warning) = cli::compare_args_to_job(&args);
+    if let Some(warning) = warning {
+        out.line(warning);
+    }
+    let Some(stderr) = run_job(job.args(), out) else {
+        return false;
+    };
+    let mut parser = QualityParser::new();
+    for line in &stderr {
+        parser.push_line(line);
+    }
+    let scores = parser.scores();
+    if scores.is_empty() {
+        out.line("error: ffmpeg printed no quality scores".to_string());
+        return false;
+    }
+    for line in format_quality_lines(&scores) {
+        out.line(line);
+    }
+    if let Some(path) = args.json {
+        let json = scores.to_json(&job);
+        if let Err(err) = std::fs::write(&path, &json) {
+            out.line(format!("error: cannot write '{}': {err}", path.display()));
+            return false;
+        }
+        out.line(format_saved_file(&path, json.len() as u64));
+    }
+    true
+}
+
+/// Detects the crop and, with `--apply`, runs the cropped encode straight
+/// away. Without it the crop is only remembered for `--crop auto`.
+fn run_cropdetect(args: cli::CropdetectArgs, out: &mut Output) -> bool {
+    let sample = Duration::from_secs(args.sample.into());
+    let Some(stderr) = run_job(cropdetect_args(&args.input, sample), out) else {
+        return false;
+    };
+    let mut detector = CropDetector::new();
+    for line in &stderr {
+        detector.push_line(line);
+    }
+    let Some((crop, count, total)) = detector.best() else {
+        out.line("warning: cropdetect made no suggestions (no video?)".to_string());
+        return true;
+    };
+    cli::remember_crop(crop);
+    out.line(format!("Detected {} ({count} of {total} samples)", crop.filter()));
+    if !args.apply {
+        return true;
+    }
+
+    let line = cli::cropped_encode_line(&args, crop);
+    out.line(format!(">> {}", line.to_string_lossy()));
+    match cli::job_args_from_line(&line) {
+        Ok(job) => {
+            for note in job.notes {
+                out.line(note);
+            }
+            run_job(job.args, out).is_some()
+        }
+        Err(err) => {
+            out.line(format!("error: {err}"));
+            false
+        }
+    }
+}
+
+fn run_normalize(job: LoudnormJob, out: &mut Output) -> bool {
+    out.line("pass 1/2: measuring loudness".to_string());
+    let Some(stderr) = run_job(job.measure_args(), out) else {
+        return false;
+    };
+    let mut parser = LoudnormParser::new();
+    for line in &stderr {
+        parser.push_line(line);
+    }
+    match parser.finish() {
+        Ok(measured) => {
+            out.line("pass 2/2: applying".to_string());
+            run_job(job.apply_args(&measured), out).is_some()
+        }
+        Err(err) => {
+            out.line(format!("error: {err}; skipping pass 2"));
+            false
+        }
+    }
+}
+
+fn run_stabilize(args: cli::StabilizeArgs, out: &mut Output) -> bool {
+    let job = match cli::stabilize_args_to_job(args) {
+        Ok(job) => job,
+        Err(err) => {
+            out.line(format!("error: {err}"));
+            return false;
+        }
+    };
+    out.line("pass 1/2: detecting motion".to_string());
+    if run_job(job.detect_args(), out).is_none() {
+        return false;
+    }
+    out.line("pass 2/2: stabilizing".to_string());
+    if run_job(job.transform_args(), out).is_none() {
+        return false;
+    }
+    match std::fs::metadata(&job.output) {
+        Ok(meta) => out.line(format_saved_file(&job.output, meta.len())),
+        Err(err) => out.line(format!("warning: cannot read '{}': {err}", job.output.display())),
+    }
+    true
+}
+
+/// Runs one ffmpeg job to completion. Returns its stderr log if it
+/// succeeded.
+fn run_job(args: Vec<OsString>, out: &mut Output) -> Option<Vec<String>> {
+    run_job_for(args, None, None, None, out)
+}
+
+/// Puts `-y` (with `overwrite`) or `-n` in front of `args`, unless the
+/// job already says which it wants.
+fn add_overwrite_flag(args: &mut Vec<OsString>, overwrite: bool) {
+    if !args.iter().any(|arg| arg == "-y" || arg == "-n") {
+        args.insert(0, if overwrite { "-y" } else { "-n" }.into());
+    }
+}
+
+/// `run_job` for a job whose output length is known up front; progress is
+/// measured against it instead of what the arguments say. A `timeout`
+/// kills ffmpeg once it runs out, and `verify` checks the output after.
+fn run_job_for(
+    mut args: Vec<OsString>,
+    expected: Option<Duration>,
+    timeout: Option<JobTimeout>,
+    verify: Option<VerifyMode>,
+    out: &mut Output,
+) -> Option<Vec<String>> {
+    let verify = verify.and_then(|mode| Some((mode, verify_target(&args)?)));
+    add_overwrite_flag(&mut args, out.overwrite);
+    let command_line: Vec<String> = args
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let command_line = format!("ffmpeg {}", shell_words::join(&command_line));
+    out.record("job", &format!("start: {command_line}"));
+    if out.json {
+        out.json(format_start_json(&command_line));
+    }
+
+    let duration = expected.or_else(|| parse_duration_from_args(&args));
+    let length = JobLength {
+        // Without a length of its own, the job runs as long as its inputs allow.
+        fixed: duration.is_some(),
+        duration,
+        input_duration: InputDuration::from_args(&args),
+        seek_offset: parse_seek_from_args(&args).unwrap_or_default(),
+        total_frames: sequence_total_frames(&args),
+    };
+    let started = Instant::now();
+    let handle = core::runner::run_args_with_timeout(args, timeout);
+    let JobRun {
+        had_error,
+        stderr,
+        interrupted_at,
+        written,
+        final_time,
+    } = follow_job(&handle, length, out);
+
+    if had_error && out.failed_exit.is_none() {
+        out.failed_exit = handle.exit.get().filter(|code| *code != 0);
+    }
+    if had_error {
+        let reason = classify_stderr(&stderr.join("\n"));
+        if reason != FailureReason::Unknown {
+            out.line(format!("error: {reason}"));
+        }
+    }
+    let media = final_time
+        .or_else(|| handle.progress.take().map(|update| update.time))
+        .or(written);
+    let mut findings = Vec::new();
+    if let (Some((mode, path)), false, None) = (&verify, had_error, interrupted_at) {
+        let mut expected = MediaLayout::from_job_log(&stderr);
+        expected.duration = expected.duration.or(media);
+        findings = verify_output(*mode, path, &expected, out);
+    }
+    let status = if interrupted_at.is_some() {
+        JobStatus::Interrupted
+    } else if had_error {
+        JobStatus::Failed
+    } else if !findings.is_empty() {
+        JobStatus::VerificationFailed
+    } else {
+        JobStatus::Finished
+    };
+    if status == JobStatus::Finished {
+        out.line(format_completion_line(started.elapsed(), media));
+    }
+    out.record("job", &format!("finish: {status:?}"));
+    match out.json {
+        true => out.json(format_finish_json(status)),
+        false => out.line(format!("Job finished: {status:?}")),
+    }
+    (status == JobStatus::Finished).then_some(stderr)
+}
+
+/// What progress of a job is measured against.
+struct JobLength {
+    duration: Option<Duration>,
+    /// `duration` is the job's own; otherwise it follows the inputs.
+    fixed: bool,
+    input_duration: InputDuration,
+    seek_offset: Duration,
+    total_frames: Option<u64>,
+}
+
+/// What `follow_job` saw of a job that ended.
+struct JobRun {
+    had_error: bool,
+    stderr: Vec<String>,
+    interrupted_at: Option<Instant>,
+    /// Output time of the latest progress update, and of the final stats
+    /// line once there is one.
+    written: Option<Duration>,
+    final_time: Option<Duration>,
+}
+
+/// Prints a running job's events until ffmpeg exits. With `--json-events`
+/// every progress update goes out in its place among the other events;
+/// otherwise one progress line is printed per `PROGRESS_INTERVAL`.
+fn follow_job(handle: &JobHandle, length: JobLength, out: &mut Output) -> JobRun {
+    let JobLength {
+        mut duration,
+        fixed,
+        input_duration,
+        seek_offset,
+        total_frames,
+    } = length;
+    let mut inputs = Vec::new();
+    let mut run = JobRun {
+        had_error: false,
+        stderr: Vec::new(),
+        interrupted_at: None,
+        written: None,
+        final_time: None,
+    };
+    let mut segments_opened = 0usize;
+    let mut last_progress = Instant::now();
+    let mut received = 0u64;
+
+    loop {
+        if core::process::interrupted() {
+            // ffmpeg usually got the same Ctrl-C from the terminal; signal
+            // it anyway in case only ffflow was, then kill it if it hangs.
+            match run.interrupted_at {
+                None => {
+                    run.interrupted_at = Some(Instant::now());
+                    if let Some(pid) = handle.pid.get() {
+                        let _ = core::process::interrupt(pid);
+                    }
+                }
+                Some(at) if at.elapsed() >= INTERRUPT_GRACE => handle.cancel.cancel(),
+                Some(_) => {}
+            }
+        }
+
+        let next = handle.events.recv_timeout(PROGRESS_INTERVAL);
+
+        if out.json {
+            // Only updates sent before the event just received go first.
+            if let Some(update) = handle.progress.take_after(received) {
+                run.written = Some(update.time);
+                let ratio = update_ratio(&update, duration, total_frames);
+                if let Some(json) = format_event_json(&FfmpegEvent::Progress(update), ratio) {
+                    out.json(json);
+                }
+            }
+        } else if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            if let Some(update) = handle.progress.take() {
+                run.written = Some(update.time);
+                if let Some(line) = format_progress_line(&update, duration, total_frames) {
+                    let ratio = update_ratio(&update, duration, total_frames);
+                    out.progress(line, ratio);
+                }
+            }
+        }
+
+        let event = match next {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        received += 1;
+        if out.json {
+            if let Some(json) = format_event_json(&event, None) {
+                out.json(json);
+            }
+        }
+
+        match event {
+            FfmpegEvent::Input(info) => {
+                out.text(format_input_line(&info));
+                inputs.push(info);
+                if !fixed {
+                    duration = input_duration
+                        .pick(&inputs)
+                        .map(|duration| duration.saturating_sub(seek_offset));
+                }
+            }
+            // The `Output` line that follows names the file too.
+            FfmpegEvent::OutputStarted(_) => {}
+            FfmpegEvent::Output(info) => out.text(format_output_line(&info)),
+            FfmpegEvent::Mapping(mappings) => out.text(format_mapping_line(&mappings)),
+            FfmpegEvent::Summary(summary) => {
+                run.final_time = Some(summary.duration).filter(|duration| !duration.is_zero());
+                out.text(format_summary_line(&summary));
+            }
+            FfmpegEvent::Warning(message) => out.text(format!("warning: {message}")),
+            FfmpegEvent::Error(message) => {
+                run.had_error = true;
+                out.text(format!("error: {message}"));
+            }
+            FfmpegEvent::SegmentOpened(_) => {
+                segments_opened += 1;
+                if segments_opened > 1 {
+                    out.text(format!("segment {} written", segments_opened - 1));
+                }
+            }
+            FfmpegEvent::Prompt(message) => {
+                out.text(format!("PROMPT: {message}"));
+                out.text(">> Sent: n".to_string());
+                let _ = handle.stdin.send("n\n".to_string());
+            }
+            FfmpegEvent::Noise(line) => out.line(line),
+            FfmpegEvent::Log(line) => {
+                out.record("stderr", &line);
+                run.stderr.push(line);
+            }
+            // Progress comes through `handle.progress`.
+            FfmpegEvent::Progress(_) => {}
+        }
+    }
+    run
+}
+
+/// Checks a finished job's output against what ffmpeg said it wrote and
+/// prints the result. Returns what is wrong with it.
+fn verify_output(mode: VerifyMode, path: &Path, expected: &MediaLayout, out: &mut Output) -> Vec<String> {
+    let actual = match probe_layout(path) {
+        Ok(actual) => actual,
+        Err(err) => {
+            let finding = format!("cannot read '{}': {err}", path.display());
+            out.line(format!("error: verify: {finding}"));
+            return vec![finding];
+        }
+    };
+    let mut findings = compare_layouts(expected, &actual);
+    if mode == VerifyMode::Full && findings.is_empty() {
+        out.line(format!("Decoding '{}' to check it...", path.display()));
+        findings = match decode_log(path) {
+            Ok((lines, exited_cleanly)) => {
+                let mut findings = decode_findings(&lines);
+                if findings.is_empty() && !exited_cleanly {
+                    findings.push("ffmpeg could not decode the file".to_string());
+                }
+                findings
+            }
+            Err(err) => vec![format!("cannot decode '{}': {err}", path.display())],
+        };
+    }
+    if findings.is_empty() {
+        out.line(format!("Verified '{}': {}", path.display(), actual.describe()));
+    }
+    for finding in &findings {
+        out.line(format!("error: verify: {finding}"));
+    }
+    findings
+}
+
+/// Prints session lines to stdout and mirrors them into the `--log-file`.
+struct Output {
+    log: Option<SessionLog>,
+    /// Print to stderr, while the job's output is going to stdout.
+    to_stderr: bool,
+    /// Progress is drawn as one bar on stderr, which is a terminal.
+    live: bool,
+    /// The bar is on screen and has to be cleared before the next line.
+    bar_shown: bool,
+    /// Exit code of the first ffmpeg that failed, for the session's own.
+    failed_exit: Option<i32>,
+    /// `--json-events`: every line is a JSON object.
+    json: bool,
+    /// `--overwrite`: jobs get `-y` instead of `-n`.
+    overwrite: bool,
+    /// Printed lines are kept here instead.
+    #[cfg(test)]
+    captured: Option<Vec<String>>,
+}
+
+impl Output {
+    fn open(path: Option<&Path>) -> Result<Self, FfxError> {
+        let log = path
+            .map(|path| {
+                SessionLog::open(path).map_err(|err| FfxError::InvalidCommand {
+                    message: format!("cannot open log file '{}': {err}", path.display()),
+                })
+            })
+            .transpose()?;
+        Ok(Self {
+            log,
+            to_stderr: false,
+            live: io::stderr().is_terminal(),
+            bar_shown: false,
+            failed_exit: None,
+            json: false,
+            overwrite: false,
+            #[cfg(test)]
+            captured: None,
+        })
+    }
+
+    /// Shows a progress update: redrawn in place on a terminal, one line
+    /// each otherwise.
+    fn progress(&mut self, line: String, ratio: Option<f64>) {
+        if !self.live {
+            self.line(line);
+            return;
+        }
+        let bar = match ratio {
+            Some(ratio) => format!("{} ", progress_bar_string(ratio, PROGRESS_BAR_WIDTH)),
+            None => String::new(),
+        };
+        let text = line.strip_prefix("progress: ").unwrap_or(&line);
+        eprint!("\r{bar}{text}\x1b[K");
+        self.bar_shown = true;
+        self.record("history", &line);
+    }
+
+    fn line(&mut self, line: String) {
+        match self.json {
+            true => self.print(&format_message_json(&line)),
+            false => self.print(&line),
+        }
+        self.record("history", &line);
+    }
+
+    /// A line that `--json-events` replaces with the event it describes.
+    fn text(&mut self, line: String) {
+        if !self.json {
+            self.line(line);
+        }
+    }
+
+    fn json(&mut self, line: String) {
+        self.print(&line);
+        self.record("event", &line);
+    }
+
+    fn print(&mut self, line: &str) {
+        #[cfg(test)]
+        if let Some(captured) = &mut self.captured {
+            captured.push(line.to_string());
+            return;
+        }
+        if std::mem::take(&mut self.bar_shown) {
+            eprint!("\r\x1b[K");
+        }
+        match self.to_stderr {
+            true => eprintln!("{line}"),
+            false => println!("{line}"),
+        }
+    }
+
+    fn record(&mut self, kind: &str, line: &str) {
+        if let Some(err) = self.log.as_ref().and_then(SessionLog::take_error) {
+            self.log = None;
+            self.print(&format!("error: session log disabled: {err}"));
+        }
+        if let Some(log) = &self.log {
+            log.record(kind, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::core::metadata::{InputInfo, OutputInfo};
+    use crate::core::progress::parse_progress_line;
+    use crate::core::runner::{CancelToken, ChildExit, ChildPid, LatestProgress};
+    use crate::core::summary::parse_summary_line;
+
+    fn captured(json: bool) -> Output {
+        let mut out = Output::open(None).unwrap();
+        out.json = json;
+        out.live = false;
+        out.captured = Some(Vec::new());
+        out
+    }
+
+    fn handle(events: mpsc::Receiver<FfmpegEvent>, progress: LatestProgress) -> JobHandle {
+        JobHandle {
+            events,
+            stdin: mpsc::channel().0,
+            progress,
+            cancel: CancelToken::default(),
+            pid: ChildPid::default(),
+            exit: ChildExit::default(),
+        }
+    }
+
+    fn length(duration: Option<Duration>) -> JobLength {
+        JobLength {
+            duration,
+            fixed: duration.is_some(),
+            input_duration: InputDuration::Input(0),
+            seek_offset: Duration::ZERO,
+            total_frames: None,
+        }
+    }
+
+    fn input(duration: Duration) -> InputInfo {
+        InputInfo {
+            index: 0,
+            width: 1920,
+            height: 1080,
+            fps: 25.0,
+            codec: "h264".into(),
+            duration: Some(duration),
+            container: Some("mov,mp4,m4a,3gp,3g2,mj2".into()),
+            path: Some("in.mov".into()),
+            bitrate_kbps: Some(4000.0),
+            chapters: Vec::new(),
+        }
+    }
+
+    const STATS: &str =
+        "frame=  250 fps= 50 q=28.0 size=    1024kB time=00:00:10.00 bitrate= 838.9kbits/s speed=2.00x";
+
+    #[test]
+    fn json_events_keep_ffmpeg_order_and_carry_percent() {
+        let (tx, rx) = mpsc::channel();
+        let progress = LatestProgress::default();
+        tx.send(FfmpegEvent::Log("Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mov':".into())).unwrap();
+        tx.send(FfmpegEvent::Input(input(Duration::from_secs(40)))).unwrap();
+        tx.send(FfmpegEvent::OutputStarted("out.mp4".into())).unwrap();
+        tx.send(FfmpegEvent::Output(OutputInfo {
+            index: 0,
+            container: "mp4".into(),
+            codec: "h264".into(),
+            width: 1280,
+            height: 720,
+            path: "out.mp4".into(),
+        }))
+        .unwrap();
+        progress.set(parse_progress_line(STATS).unwrap(), 4);
+        tx.send(FfmpegEvent::Warning("3 frames dropped".into())).unwrap();
+        let summary = parse_summary_line(
+            "frame= 1000 fps= 50 q=-1.0 Lsize=    4096kB time=00:00:40.00 bitrate= 838.9kbits/s speed=2.00x",
+        );
+        tx.send(FfmpegEvent::Summary(summary.unwrap())).unwrap();
+        drop(tx);
+
+        let mut out = captured(true);
+        let run = follow_job(&handle(rx, progress), length(None), &mut out);
+        assert!(!run.had_error);
+        assert_eq!(run.written, Some(Duration::from_secs(10)));
+        assert_eq!(run.final_time, Some(Duration::from_secs(40)));
+
+        let lines = out.captured.unwrap();
+        let kinds: Vec<&str> = lines
+            .iter()
+            .map(|line| line.split('"').nth(3).unwrap())
+            .collect();
+        assert_eq!(kinds, ["input", "output", "progress", "warning", "summary"]);
+        assert_eq!(
+            lines[2],
+            "{\"event\": \"progress\", \"frame\": 250, \"time_secs\": 10, \"fps\": 50, \
+             \"speed\": 2, \"size_bytes\": 1024000, \"percent\": 25}"
+        );
+    }
+
+    #[test]
+    fn json_progress_has_no_percent_without_a_length() {
+        let (tx, rx) = mpsc::channel();
+        let progress = LatestProgress::default();
+        progress.set(parse_progress_line(STATS).unwrap(), 0);
+        drop(tx);
+
+        let mut out = captured(true);
+        follow_job(&handle(rx, progress), length(None), &mut out);
+        let lines = out.captured.unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("{\"event\": \"progress\""));
+        assert!(!lines[0].contains("percent"));
+    }
+
+    #[test]
+    fn plain_lines_become_messages_in_json_mode() {
+        let mut out = captured(true);
+        out.line("Queued 2 jobs, one per matching file".into());
+        out.text("error: never shown".into());
+        assert_eq!(
+            out.captured.unwrap(),
+            ["{\"event\": \"message\", \"text\": \"Queued 2 jobs, one per matching file\"}"]
+        );
+    }
+
+    #[test]
+    fn overwrite_picks_y_unless_the_job_chose() {
+        let argv = |args: &[&str], overwrite: bool| {
+            let mut args: Vec<OsString> = args.iter().map(Into::into).collect();
+            add_overwrite_flag(&mut args, overwrite);
+            args
+        };
+        assert_eq!(argv(&["-i", "a.mkv", "a.mp4"], false), ["-n", "-i", "a.mkv", "a.mp4"]);
+        assert_eq!(argv(&["-i", "a.mkv", "a.mp4"], true), ["-y", "-i", "a.mkv", "a.mp4"]);
+        assert_eq!(argv(&["-i", "a.mkv", "-n", "a.mp4"], true), ["-i", "a.mkv", "-n", "a.mp4"]);
+        assert_eq!(argv(&["-y", "-i", "a.mkv", "a.mp4"], false), ["-y", "-i", "a.mkv", "a.mp4"]);
+    }
+
+    #[test]
+    fn checkpointed_jobs_are_skipped_and_the_rest_run() {
+        let path = std::env::temp_dir().join(format!("ffflow-checkpoint-{}.txt", std::process::id()));
+        fs::write(&path, "doctor\n").unwrap();
+        let mut checkpoint = Checkpoint::open(&path).unwrap();
+        let queue = checkpoint.filter(vec!["doctor".into(), "version".into()]);
+        assert_eq!(queue, ["version"]);
+
+        let mut out = captured(false);
+        run_session(queue, Some(checkpoint), false, &mut out).unwrap();
+        let printed = out.captured.unwrap();
+        let recorded = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(printed[0].starts_with("Skipped 1 job(s) already done according to"), "{printed:?}");
+        assert!(!printed.iter().any(|line| line == ">> doctor"), "{printed:?}");
+        assert!(printed.iter().any(|line| line == ">> version"), "{printed:?}");
+        // What ran is recorded in turn.
+        assert_eq!(recorded, "doctor\nversion\n");
+    }
+
+    #[test]
+    fn checkpointed_lines_read_later_are_skipped_too() {
+        let path = std::env::temp_dir().join(format!("ffflow-checkpoint-late-{}.txt", std::process::id()));
+        fs::write(&path, "doctor\n").unwrap();
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        let mut out = captured(false);
+        // As if both lines came from stdin, past the up-front filter.
+        run_session(vec!["doctor".into(), "version".into()], Some(checkpoint), false, &mut out).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let printed = out.captured.unwrap();
+        assert_eq!(printed[0], "Already done, skipping: doctor");
+        assert_eq!(printed[1], ">> version");
+    }
+
+    /// What `read_commands` sends for `input`.
+    fn commands_read(input: &str) -> Vec<Result<String, String>> {
+        let (line_tx, line_rx) = mpsc::channel();
+        read_commands(io::Cursor::new(input), line_tx);
+        line_rx
+            .into_iter()
+            .map(|command| command.map(|line| line.into_string().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn stdin_commands_join_continuations_and_skip_comments() {
+        let commands = commands_read(
+            "# transcode the intro\n\
+             encode -i intro.mov \\\n  --vcodec libx264 \\\n  -o intro.mp4\n\
+             \n\
+             \x20 # then check it\n\
+             probe -i intro.mp4\n\
+             remux -i a.mkv -o a.mp4",
+        );
+        let words = |command: &Result<String, String>| {
+            command.as_ref().unwrap().split_whitespace().map(str::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(commands.len(), 3, "{commands:?}");
+        assert_eq!(words(&commands[0]), ["encode", "-i", "intro.mov", "--vcodec", "libx264", "-o", "intro.mp4"]);
+        assert_eq!(words(&commands[1]), ["probe", "-i", "intro.mp4"]);
+        // The last line counts without a newline.
+        assert_eq!(words(&commands[2]), ["remux", "-i", "a.mkv", "-o", "a.mp4"]);
+    }
+
+    #[test]
+    fn continuation_at_end_of_stdin_still_runs() {
+        let commands = commands_read("probe -i a.mp4 \\\n");
+        assert_eq!(commands.len(), 1, "{commands:?}");
+        assert_eq!(commands[0].as_deref().unwrap().trim(), "probe -i a.mp4");
+    }
+}
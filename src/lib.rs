@@ -0,0 +1,111 @@
+//! The engine behind the `ffflow` CLI: spawns ffmpeg and turns its stderr
+//! into typed events (inputs, outputs, progress, summary, errors).
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//!
+//! use ffflow::command::FfmpegCommand;
+//! use ffflow::event::FfmpegEvent;
+//!
+//! let command = FfmpegCommand {
+//!     inputs: vec![PathBuf::from("in.mov")],
+//!     output: PathBuf::from("out.mp4"),
+//!     video_codec: Some("libx264".to_string()),
+//!     audio_codec: None,
+//!     preset: Some("veryfast".to_string()),
+//!     ..FfmpegCommand::default()
+//! };
+//!
+//! for event in ffflow::run(command) {
+//!     if let FfmpegEvent::Progress(progress) = event {
+//!         println!("frame {}", progress.frame);
+//!     }
+//! }
+//! ```
+
+mod core {
+    pub mod batch;
+    pub mod color;
+    pub mod command;
+    pub mod concat;
+    pub mod error;
+    pub mod event;
+    pub mod job;
+    pub mod metadata;
+    pub mod progress;
+    pub mod runner;
+    pub mod summary;
+
+    // Helpers the engine uses only in part; the rest serves the binary.
+    #[allow(dead_code)]
+    pub mod depends;
+    #[allow(dead_code)]
+    pub mod expand;
+    #[allow(dead_code)]
+    pub mod process;
+    #[allow(dead_code)]
+    pub mod queue;
+    #[allow(dead_code)]
+    pub mod shell;
+}
+
+pub use crate::core::runner::EventStream;
+pub use crate::core::{batch, command, error, event, job, metadata, progress, runner, summary};
+
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use crate::core::command::FfmpegCommand;
+use crate::core::error::FfxError;
+use crate::core::job::{Job, JobStatus};
+use crate::core::process;
+
+/// Starts ffmpeg in the background and returns its events as an iterator.
+pub fn run(command: FfmpegCommand) -> EventStream {
+    runner::run_with_events(command).into()
+}
+
+/// Runs a command to completion without streaming any events.
+pub fn run_blocking(command: FfmpegCommand) -> Result<Job, FfxError> {
+    let mut job = Job {
+        id: 1,
+        status: JobStatus::Pending,
+        started_at: None,
+        ended_at: None,
+    };
+
+    job.status = JobStatus::Running;
+    job.started_at = Some(Instant::now());
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(command.to_args()).stderr(Stdio::piped());
+    process::apply_niceness(&mut cmd);
+
+    let child = cmd.spawn().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            FfxError::BinaryNotFound
+        } else {
+            FfxError::ProcessFailed {
+                exit_code: None,
+                stderr: e.to_string(),
+            }
+        }
+    })?;
+
+    let output = child.wait_with_output().map_err(|e| FfxError::ProcessFailed {
+        exit_code: None,
+        stderr: e.to_string(),
+    })?;
+
+    job.ended_at = Some(Instant::now());
+
+    if output.status.success() {
+        job.status = JobStatus::Finished;
+        Ok(job)
+    } else {
+        Err(FfxError::ProcessFailed {
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
@@ -1,16 +1,66 @@
 mod cli;
+mod clipboard;
 mod core;
+mod headless;
 mod tui;
 
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
 use clap::Parser;
 use cli::SystemCli;
 use core::batch;
+use core::checkpoint::Checkpoint;
 
 fn main() {
     let args = SystemCli::parse();
+    if let Some(shell) = args.completions {
+        cli::print_completions(shell);
+        return;
+    }
     let mut queue = Vec::new();
+    if let Some(nice) = args.nice {
+        core::process::set_niceness(nice);
+    }
+    core::schedule::set_strict_schedule(args.strict_schedule);
+    core::runner::set_verbose(args.verbose);
 
-    if let Some(path) = args.file {
+    if !args.commands.is_empty() {
+        queue = args.commands.iter().map(Into::into).collect();
+    }
+    // `-` reads the batch from stdin, which then can't also carry
+    // commands or keypresses.
+    let from_pipe = args.file.as_deref() == Some(Path::new("-"));
+    if from_pipe && args.stdin {
+        eprintln!("Error: '-' already reads the batch from stdin; drop --stdin");
+        std::process::exit(2);
+    }
+    if from_pipe && io::stdin().is_terminal() {
+        eprintln!(
+            "Error: '-' reads a .flw batch from stdin, but stdin is a terminal; \
+             pipe one in (ffflow - < jobs.flw) or pass the file (ffflow jobs.flw)"
+        );
+        std::process::exit(2);
+    }
+    let headless = args.stdin || from_pipe || !args.commands.is_empty();
+    if args.json_events && !headless {
+        eprintln!("Error: --json-events needs -c, --stdin or -; the TUI prints no events");
+        std::process::exit(2);
+    }
+    if args.overwrite && !headless {
+        eprintln!("Error: --overwrite needs -c, --stdin or -; the TUI asks before overwriting");
+        std::process::exit(2);
+    }
+
+    if from_pipe {
+        match batch::parse_flw(io::stdin().lock()) {
+            Ok(cmds) => queue = cmds,
+            Err(e) => {
+                eprintln!("Error reading batch from stdin: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(path) = args.file {
         match batch::parse_flw_file(&path) {
             Ok(cmds) => queue = cmds,
             Err(e) => {
@@ -20,8 +70,89 @@ fn main() {
         }
     }
 
-    if let Err(err) = tui::run(queue) {
-        eprintln!("{err}");
-        std::process::exit(1);
+    let checkpoint = match args.checkpoint.as_deref().map(Checkpoint::open) {
+        Some(Ok(mut checkpoint)) => {
+            queue = checkpoint.filter(queue);
+            Some(checkpoint)
+        }
+        Some(Err(e)) => {
+            eprintln!("Error reading checkpoint file: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    let result = if headless {
+        headless::run(
+            queue,
+            args.log_file,
+            checkpoint,
+            args.stdin,
+            args.json_events,
+            args.overwrite,
+        )
+    } else {
+        tui::run(queue, args.log_file, args.scrollback as usize, checkpoint)
+    };
+
+    if let Err(err) = result {
+        use core::error::FfxError;
+        let code = match &err {
+            // 128 + SIGINT, as shells report a job stopped with Ctrl-C.
+            FfxError::Interrupted => 130,
+            // ffmpeg's own status, as a script running it directly would get.
+            FfxError::ProcessFailed { exit_code: Some(code), .. } => *code,
+            _ => 1,
+        };
+        let message = match err {
+            FfxError::ProcessFailed { stderr, .. } => stderr,
+            err => err.to_string(),
+        };
+        eprintln!("{message}");
+        std::process::exit(code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::*;
+
+    /// The ffmpeg argv for one `-c` command, as the shell handed it over.
+    fn command_argv(command: &str) -> Vec<OsString> {
+        let args = SystemCli::try_parse_from(["ffflow", "-c", command]).unwrap();
+        let line = OsString::from(&args.commands[0]);
+        cli::job_args_from_line(&line).unwrap().args
+    }
+
+    #[test]
+    fn quoted_filter_stays_one_argument() {
+        let argv = command_argv(r#"ffmpeg -i in.mov -vf "scale=1280:-2,fps=30" out.mp4"#);
+        assert_eq!(argv, ["-i", "in.mov", "-vf", "scale=1280:-2,fps=30", "out.mp4"]);
+    }
+
+    #[test]
+    fn filter_graph_labels_stay_one_argument() {
+        let argv = command_argv("ffmpeg -i in.mov -filter_complex [0:v]split=2[a][b] -map [a] a.mp4 -map [b] b.mp4");
+        assert_eq!(argv[3], "[0:v]split=2[a][b]");
+        assert_eq!(argv.len(), 10);
+    }
+
+    #[test]
+    fn commands_take_json_events_and_overwrite() {
+        let args = SystemCli::try_parse_from([
+            "ffflow",
+            "-c",
+            "remux -i a.mkv -o a.mp4",
+            "--json-events",
+            "--overwrite",
+            "-c",
+            "probe -i a.mp4",
+        ])
+        .unwrap();
+        assert_eq!(args.commands, ["remux -i a.mkv -o a.mp4", "probe -i a.mp4"]);
+        assert!(args.json_events);
+        assert!(args.overwrite);
     }
 }
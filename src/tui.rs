@@ -1,28 +1,76 @@
+use std::ffi::{OsStr, OsString};
 use std::io;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime};
 
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode,
+    KeyEvent, KeyModifiers, MouseEvent, MouseEventKind,
+};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::ExecutableCommand;
+use unicode_segmentation::UnicodeSegmentation;
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
 use ratatui::Terminal;
 
-use crate::cli::{self, Commands};
+use crate::cli;
+use crate::clipboard;
 use crate::core;
-use crate::core::error::FfxError;
+use crate::core::error::{classify_stderr, FailureReason, FfxError};
 use crate::core::event::FfmpegEvent;
+use crate::core::fs::SystemFs;
 use crate::core::formatter::{
-    format_duration, format_input_line, format_output_line, format_progress_line,
-    format_summary_line,
+    format_duration, format_frame_stats, format_saved_file, format_input_brief, format_input_line, format_mapping_line, format_speed, format_output_line, format_progress_line, progress_bar_string, update_ratio,
+    format_completion_line, format_playlist_stats, format_quality_lines, format_rung_result, format_session_brief, format_session_stats, format_summary_line, sparkline,
+    display_width, tail_to_width, truncate_to_width,
 };
+use crate::core::alias::{expand_alias, parse_alias_definition};
+use crate::core::capabilities::{detect_version_in_background, known_ffmpeg_version, FfmpegVersion};
+use crate::core::checkpoint::Checkpoint;
+use crate::core::concat::ConcatList;
+use crate::core::config::{config_path, Config};
+use crate::core::live::{is_live_source, LiveStats};
+use crate::core::depends::{describe_blocker, split_job_header, JobOutcomes, Outcome};
+use crate::core::cropdetect::{cropdetect_args, CropDetector};
+use crate::core::hls::{read_playlist, HlsJob};
 use crate::core::job::JobStatus;
+use crate::core::ladder::LadderPlan;
+use crate::core::loudnorm::{LoudnormJob, LoudnormParser};
 use crate::core::metadata::{InputInfo, OutputInfo};
-use crate::core::progress::{parse_ffmpeg_time, FfmpegProgress};
-use crate::core::summary::EncodeSummary;
+use crate::core::progress::{
+    parse_duration_from_args, parse_seek_from_args, FfmpegProgress, FrameCounts, InputDuration,
+    PassTracker, SpeedTrend,
+};
+use crate::core::probe::probe_layout;
+use crate::core::expand::set_expand_passthrough;
+use crate::core::process::{self, set_niceness, MAX_NICENESS};
+use crate::core::queue::{
+    parse_on_error_directive, split_priority, strip_next_marker, JobQueue, OnError,
+};
+use crate::core::schedule::{
+    format_start_time, parse_scheduled, strict_schedule, strip_at_prefix, Schedule,
+};
+use crate::core::quality::{QualityJob, QualityParser};
+use crate::core::split::SplitJob;
+use crate::core::stabilize::StabilizeJob;
+use crate::core::watch::InputWatcher;
+use crate::core::report::{write_reports, JobReport};
+use crate::core::template::next_free_name;
+use crate::core::verify::{
+    compare_layouts, decode_args, decode_findings, verify_target, MediaLayout, VerifyMode,
+};
+use crate::core::preflight::{preflight, SpaceCheck};
+use crate::core::runner::{set_verbose, writes_to_stdout, ChildPid, JobTimeout, LatestProgress};
+use crate::core::sequence::sequence_total_frames;
+use crate::core::session_log::SessionLog;
+use crate::core::shell::{strip_prefix_os, trim_os};
+use crate::core::summary::{EncodeSummary, SessionStats};
 
 struct TerminalGuard;
 
@@ -37,86 +85,934 @@ impl TerminalGuard {
             .map_err(|e| FfxError::InvalidCommand {
                 message: e.to_string(),
             })?;
+        // Pasted text arrives as one event instead of keystrokes, so its
+        // newlines don't submit half a command.
+        let _ = stdout.execute(EnableBracketedPaste);
+        let _ = stdout.execute(EnableMouseCapture);
         Ok(Self)
     }
 }
 
 impl Drop for TerminalGuard {
     fn drop(&mut self) {
-        let _ = disable_raw_mode();
-        let mut stdout = io::stdout();
-        let _ = stdout.execute(LeaveAlternateScreen);
+        leave_terminal();
     }
 }
 
+fn leave_terminal() {
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    let _ = stdout.execute(DisableBracketedPaste);
+    let _ = stdout.execute(DisableMouseCapture);
+    let _ = stdout.execute(LeaveAlternateScreen);
+}
+
+/// A panic message printed on the alternate screen vanishes with it, so
+/// the terminal is restored first and the default hook prints after.
+fn restore_terminal_on_panic() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        leave_terminal();
+        previous(info);
+    }));
+}
+
 #[derive(Debug)]
 struct AppState {
     input: String,
-    history: Vec<String>,
+    history: Vec<HistoryEntry>,
+    /// How many history entries stay on screen; older ones scroll off.
+    history_cap: usize,
+    /// Text of every history line, kept well past `history_cap`.
+    archive: std::collections::VecDeque<String>,
     progress: Option<FfmpegProgress>,
-    input_info: Option<InputInfo>,
-    output_info: Option<OutputInfo>,
+    pass_tracker: Option<PassTracker>,
+    speed_trend: SpeedTrend,
+    /// Set while the job reads a live source (stream URL, capture device).
+    live: Option<LiveStats>,
+    frame_counts: FrameCounts,
+    inputs: Vec<InputInfo>,
+    outputs: Vec<OutputInfo>,
     summary: Option<EncodeSummary>,
+    /// One entry per finished job this session, for `report`.
+    reports: Vec<JobReport>,
+    /// Totals over this session's jobs, for `stats` and the header.
+    stats: SessionStats,
+    job_started_at: Option<Instant>,
     job_status: Option<JobStatus>,
-    last_error: Option<String>,
+    /// Errors since the strip under the header was last dismissed.
+    errors: Vec<String>,
+    /// The strip lists every pinned error instead of the latest.
+    errors_expanded: bool,
+    last_command: Option<String>,
+    /// Every line run this session, oldest first, for `history` and `!N`.
+    commands: Vec<String>,
+    normalize: Option<NormalizeRun>,
+    stabilize: Option<StabilizeRun>,
+    next_pass: Option<Vec<OsString>>,
+    /// Kept until the job that reads it finishes; dropping it deletes it.
+    concat_list: Option<Arc<ConcatList>>,
+    report_output: Option<PathBuf>,
+    /// Timeout for the next job started; taken when it starts.
+    job_timeout: Option<JobTimeout>,
+    /// Check free space before each job (`set preflight on|off`).
+    preflight: bool,
+    /// Job waiting for an answer before it starts.
+    held: Option<HeldJob>,
+    /// Check every job's output once it succeeds (`set verify on|full|off`).
+    verify: Option<VerifyMode>,
+    verifying: Option<VerifyRun>,
+    cropdetect: Option<CropdetectRun>,
+    /// Encode line offered after a cropdetect run, queued with Ctrl+E.
+    crop_offer: Option<OsString>,
+    /// Aliases and other settings from the config file.
+    config: Config,
+    /// Where `config` is saved; `None` when there is nowhere to save it or
+    /// the file could not be read, so it isn't overwritten.
+    config_path: Option<PathBuf>,
+    /// Set while `wizard` asks for the parts of an encode.
+    wizard: Option<Wizard>,
+    compare: Option<CompareRun>,
+    ladder: Option<LadderRun>,
+    hls: Option<HlsJob>,
+    split: Option<SplitJob>,
+    watch: Option<WatchRun>,
+    /// Segment files the muxer has opened during this job.
+    segments_opened: usize,
+    /// The output file ffmpeg opened last during this job.
+    writing: Option<String>,
+    failure: Option<FailureReason>,
     should_quit: bool,
     job_running: bool,
     scroll_offset: usize,
     view_lines: usize,
     tick: u64,
     duration: Option<Duration>,
+    seek_offset: Duration,
+    /// The job set its own length (`-t`, or a re-timed output), so input
+    /// durations don't replace it.
+    duration_fixed: bool,
+    /// Which input's length the job runs to otherwise.
+    input_duration: InputDuration,
+    /// Images the job's sequence input holds; progress is counted in
+    /// frames when known.
+    total_frames: Option<u64>,
     last_progress_line: Option<String>,
     progress_log_counter: u64,
     stdin_tx: Option<mpsc::Sender<String>>,
-    job_queue: std::collections::VecDeque<String>,
+    latest_progress: Option<LatestProgress>,
+    job_pid: Option<ChildPid>,
+    log_pane: LogPane,
+    session_log: Option<SessionLog>,
+    job_queue: JobQueue,
+    /// Jobs waiting for a start time given with `at`.
+    schedule: Schedule,
+    on_error: OnError,
+    /// A job failed under `on-error pause`; the queue waits for `resume`.
+    queue_paused: bool,
+    /// Jobs that failed this session.
+    failed_jobs: usize,
+    /// Where finished queued jobs are recorded (`--checkpoint`).
+    checkpoint: Option<Checkpoint>,
+    /// The queued line behind the running or held job, recorded in the
+    /// checkpoint if it succeeds.
+    checkpoint_line: Option<OsString>,
+    /// How the `@job` lines run so far ended.
+    job_outcomes: JobOutcomes,
+    /// The `@job` name of the line being run, and how many errors had been
+    /// shown when it started.
+    named_job: Option<(String, usize)>,
+}
+
+/// One line in the session pane. The kind decides how it is styled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HistoryEntry {
+    UserInput(String),
+    Info(String),
+    Progress(String),
+    /// An ffmpeg line normally hidden as noise, shown with `set verbose on`.
+    Noise(String),
+    Warning(String),
+    Error(String),
+    Prompt(String),
+    Divider,
+}
+
+impl HistoryEntry {
+    fn text(&self) -> Option<&str> {
+        match self {
+            Self::UserInput(text)
+            | Self::Info(text)
+            | Self::Progress(text)
+            | Self::Noise(text)
+            | Self::Warning(text)
+            | Self::Error(text)
+            | Self::Prompt(text) => Some(text),
+            Self::Divider => None,
+        }
+    }
+
+    fn style(&self) -> Style {
+        match self {
+            Self::UserInput(_) => Style::default().add_modifier(Modifier::BOLD),
+            Self::Info(_) => Style::default(),
+            Self::Progress(_) | Self::Noise(_) | Self::Divider => {
+                Style::default().add_modifier(Modifier::DIM)
+            }
+            Self::Warning(_) => Style::default().fg(Color::Yellow),
+            Self::Error(_) => Style::default().fg(Color::Red),
+            Self::Prompt(_) => Style::default().fg(Color::Cyan),
+        }
+    }
+}
+
+/// A job line held back until the user answers a prompt about it.
+#[derive(Debug)]
+enum HeldJob {
+    /// The output may not fit on disk.
+    LowSpace(cli::JobArgs),
+    /// The output file exists; `rename` is the first free numbered name.
+    OutputExists { job: cli::JobArgs, rename: PathBuf },
+    /// Several pasted lines: queue each, or join them into one.
+    Paste(Vec<String>),
+}
+
+/// An encode put together one answer at a time by `wizard`.
+#[derive(Debug, Default)]
+struct Wizard {
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    codec: Option<String>,
+}
+
+impl Wizard {
+    /// What the next answer is for; the preset comes last.
+    fn label(&self) -> &'static str {
+        match (&self.input, &self.output, &self.codec) {
+            (None, _, _) => "Input file",
+            (_, None, _) => "Output file",
+            (_, _, None) => "Video codec",
+            _ => "Preset",
+        }
+    }
+
+    /// What an empty answer stands for. The input has none unless a file
+    /// was already opened this session.
+    fn default_answer(&self, current_input: Option<&str>) -> Option<String> {
+        match (&self.input, &self.output, &self.codec) {
+            (None, _, _) => current_input.map(str::to_string),
+            (Some(input), None, _) => {
+                let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+                Some(input.with_file_name(format!("{stem}_encoded.mp4")).display().to_string())
+            }
+            (_, _, None) => Some("libx264".to_string()),
+            _ => Some("medium".to_string()),
+        }
+    }
+
+    /// The `encode` line the answers make, ending with `preset`.
+    fn encode_line(&self, preset: &str) -> OsString {
+        let words = [
+            "encode".to_string(),
+            "-i".to_string(),
+            self.input.as_deref().unwrap_or(Path::new("")).display().to_string(),
+            "-o".to_string(),
+            self.output.as_deref().unwrap_or(Path::new("")).display().to_string(),
+            "--vcodec".to_string(),
+            self.codec.clone().unwrap_or_default(),
+            "--preset".to_string(),
+            preset.to_string(),
+        ];
+        shell_words::join(words).into()
+    }
+}
+
+/// Output check of the running job, done once ffmpeg succeeds. In `full`
+/// mode a decode pass follows the job.
+#[derive(Debug)]
+struct VerifyRun {
+    mode: VerifyMode,
+    output: PathBuf,
+    decoding: bool,
+    /// Length of the output, for the decode pass's progress.
+    duration: Option<Duration>,
+    findings: Vec<String>,
+    /// The job's own report, kept while the decode pass runs.
+    report: Option<JobReport>,
+}
+
+/// A `normalize` job: pass one measures loudness, pass two applies it.
+#[derive(Debug)]
+struct NormalizeRun {
+    job: LoudnormJob,
+    parser: LoudnormParser,
+    applying: bool,
+}
+
+/// A `stabilize` job: pass one detects the shake, pass two removes it.
+/// Dropping it deletes the transforms file between the two.
+#[derive(Debug)]
+struct StabilizeRun {
+    job: StabilizeJob,
+    transforming: bool,
+}
+
+/// A `watch` command: the job line re-run whenever its input changes.
+#[derive(Debug)]
+struct WatchRun {
+    line: OsString,
+    input: PathBuf,
+    /// Set once the first run has succeeded.
+    watcher: Option<InputWatcher>,
+    /// A run of this line is in progress.
+    running: bool,
+}
+
+#[derive(Debug)]
+struct CropdetectRun {
+    args: cli::CropdetectArgs,
+    detector: CropDetector,
+}
+
+#[derive(Debug)]
+struct CompareRun {
+    job: QualityJob,
+    json: Option<PathBuf>,
+    parser: QualityParser,
+}
+
+/// Rungs of a `ladder` waiting in or moving through the queue.
+#[derive(Debug)]
+struct LadderRun {
+    plan: LadderPlan,
+    results: Vec<Option<JobStatus>>,
+    /// Index of the rung that is running now.
+    current: Option<usize>,
+}
+
+impl LadderRun {
+    /// e.g. `ladder movie 2/4`, shown in the header while a rung runs.
+    fn label(&self) -> Option<String> {
+        self.current
+            .map(|idx| format!("{} {}/{}", self.plan.label, idx + 1, self.plan.rungs.len()))
+    }
+}
+
+/// History lines kept for `save`, however small the on-screen scrollback.
+const HISTORY_ARCHIVE_MAX_LINES: usize = 100_000;
+
+/// Raw stderr lines kept for the log pane; the oldest are dropped first.
+const LOG_PANE_MAX_LINES: usize = 5000;
+
+/// Raw ffmpeg stderr of the current job. The buffer outlives the pane being
+/// closed and is only cleared when the next job starts.
+#[derive(Debug)]
+struct LogPane {
+    lines: std::collections::VecDeque<String>,
+    visible: bool,
+    scroll_offset: usize,
+    view_lines: usize,
+}
+
+impl Default for LogPane {
+    fn default() -> Self {
+        Self {
+            lines: std::collections::VecDeque::new(),
+            visible: false,
+            scroll_offset: 0,
+            view_lines: 1,
+        }
+    }
 }
 
-const DIVIDER_MARKER: &str = "<divider>";
+impl LogPane {
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= LOG_PANE_MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        // Scrolled up: keep the same lines in view instead of following.
+        if self.scroll_offset > 0 {
+            self.scroll_offset = (self.scroll_offset + 1).min(self.max_scroll());
+        }
+    }
+
+    fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll_offset = 0;
+    }
+
+    fn set_view_lines(&mut self, lines: usize) {
+        self.view_lines = lines.max(1);
+        self.scroll_offset = self.scroll_offset.min(self.max_scroll());
+    }
+
+    fn scroll_up(&mut self, lines: usize) {
+        self.scroll_offset = (self.scroll_offset + lines).min(self.max_scroll());
+    }
+
+    fn scroll_down(&mut self, lines: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.view_lines)
+    }
+}
 
 impl AppState {
-    fn new(queue: Vec<String>) -> Self {
+    fn new(queue: Vec<OsString>) -> Self {
         let mut history = Vec::new();
-        history.push("Welcome to ffflow. Type 'help' for commands.".to_string());
+        history.push(HistoryEntry::Info(
+            "Welcome to ffflow. Type 'help' for commands.".to_string(),
+        ));
         if !queue.is_empty() {
-            history.push(format!("Loaded {} jobs from batch file.", queue.len()));
+            history.push(HistoryEntry::Info(format!(
+                "Loaded {} jobs from batch file.",
+                queue.len()
+            )));
         }
+        let archive = history
+            .iter()
+            .filter_map(|entry| entry.text().map(str::to_string))
+            .collect();
         Self {
             input: String::new(),
             history,
+            history_cap: 500,
+            archive,
             progress: None,
-            input_info: None,
-            output_info: None,
+            pass_tracker: None,
+            speed_trend: SpeedTrend::default(),
+            live: None,
+            frame_counts: FrameCounts::default(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
             summary: None,
+            reports: Vec::new(),
+            stats: SessionStats::new(),
+            job_started_at: None,
             job_status: None,
-            last_error: None,
+            errors: Vec::new(),
+            errors_expanded: false,
+            last_command: None,
+            commands: Vec::new(),
+            normalize: None,
+            stabilize: None,
+            next_pass: None,
+            concat_list: None,
+            report_output: None,
+            job_timeout: None,
+            preflight: true,
+            held: None,
+            verify: None,
+            verifying: None,
+            cropdetect: None,
+            crop_offer: None,
+            config: Config::default(),
+            config_path: None,
+            wizard: None,
+            compare: None,
+            ladder: None,
+            hls: None,
+            split: None,
+            watch: None,
+            segments_opened: 0,
+            writing: None,
+            failure: None,
             should_quit: false,
             job_running: false,
             scroll_offset: 0,
             view_lines: 1,
             tick: 0,
             duration: None,
+            seek_offset: Duration::ZERO,
+            duration_fixed: false,
+            input_duration: InputDuration::Input(0),
+            total_frames: None,
             last_progress_line: None,
             progress_log_counter: 0,
             stdin_tx: None,
-            job_queue: std::collections::VecDeque::from(queue),
+            latest_progress: None,
+            job_pid: None,
+            log_pane: LogPane::default(),
+            session_log: None,
+            job_queue: JobQueue::from(queue),
+            schedule: Schedule::new(),
+            on_error: OnError::default(),
+            queue_paused: false,
+            failed_jobs: 0,
+            checkpoint: None,
+            checkpoint_line: None,
+            job_outcomes: JobOutcomes::new(),
+            named_job: None,
+        }
+    }
+
+    fn load_config(&mut self) {
+        let Some(path) = config_path() else {
+            return;
+        };
+        match Config::load(&path) {
+            Ok(config) => {
+                cli::use_config(config.clone());
+                self.config = config;
+                self.config_path = Some(path);
+            }
+            Err(err) => self.push_history(HistoryEntry::Warning(format!(
+                "warning: {err}; its settings are ignored and changes won't be saved"
+            ))),
+        }
+    }
+
+    fn save_config(&mut self) {
+        let saved = match &self.config_path {
+            Some(path) => self.config.save(path),
+            None => Err("no config file to save to".to_string()),
+        };
+        if let Err(err) = saved {
+            self.push_history(HistoryEntry::Warning(format!(
+                "warning: kept for this session only: {err}"
+            )));
+        }
+    }
+
+    fn push_history(&mut self, entry: HistoryEntry) {
+        if let HistoryEntry::Error(text) = &entry {
+            self.errors.push(text.clone());
+        }
+        if let Some(text) = entry.text() {
+            self.log_record("history", text);
+            if self.archive.len() >= HISTORY_ARCHIVE_MAX_LINES {
+                self.archive.pop_front();
+            }
+            self.archive.push_back(text.to_string());
         }
+        self.history.push(entry);
+        self.trim_history();
+    }
+
+    fn set_history_cap(&mut self, cap: usize) {
+        self.history_cap = cap.max(1);
+        self.trim_history();
     }
 
-    fn push_history(&mut self, line: impl Into<String>) {
-        const MAX_LINES: usize = 500;
-        if self.history.len() >= MAX_LINES {
-            let drain_count = self.history.len().saturating_sub(MAX_LINES - 1);
+    fn trim_history(&mut self) {
+        if self.history.len() > self.history_cap {
+            let drain_count = self.history.len() - self.history_cap;
             self.history.drain(0..drain_count);
         }
-        self.history.push(line.into());
         self.clamp_scroll();
     }
 
-    fn update_job(&mut self, status: JobStatus) {
+    fn log_record(&self, kind: &str, line: &str) {
+        if let Some(log) = &self.session_log {
+            log.record(kind, line);
+        }
+    }
+
+    /// Turns logging off after the first write failure and says so once.
+    fn check_session_log(&mut self) {
+        let Some(err) = self.session_log.as_ref().and_then(SessionLog::take_error) else {
+            return;
+        };
+        self.session_log = None;
+        self.push_history(HistoryEntry::Error(format!(
+            "error: session log disabled: {err}"
+        )));
+    }
+
+    fn open_session_log(&mut self, path: &Path) {
+        match SessionLog::open(path) {
+            Ok(log) => {
+                self.session_log = Some(log);
+                self.log_record("session", "log opened");
+                if !self.job_queue.is_empty() {
+                    let queued: Vec<String> = self
+                        .job_queue
+                        .iter()
+                        .map(|job| job.line.to_string_lossy().into_owned())
+                        .collect();
+                    self.log_batch(&queued);
+                }
+            }
+            Err(err) => {
+                self.push_history(HistoryEntry::Error(format!(
+                    "error: cannot open log file '{}': {err}",
+                    path.display()
+                )));
+            }
+        }
+    }
+
+    fn log_batch(&mut self, commands: &[String]) {
+        self.log_record("batch", &format!("{} commands queued", commands.len()));
+        for command in commands {
+            self.log_record("batch", command);
+        }
+    }
+
+    fn update_job(&mut self, mut status: JobStatus) {
+        if let Some(run) = self.normalize.as_mut().filter(|run| !run.applying) {
+            if status == JobStatus::Finished {
+                match run.parser.finish() {
+                    Ok(measured) => {
+                        run.applying = true;
+                        self.next_pass = Some(run.job.apply_args(&measured));
+                        if let Some(tracker) = &self.pass_tracker {
+                            let (pass, elapsed) = tracker.finish();
+                            self.push_history(HistoryEntry::Info(format!(
+                                "pass {pass} complete in {}",
+                                format_duration(elapsed)
+                            )));
+                        }
+                        return;
+                    }
+                    Err(err) => {
+                        self.push_history(HistoryEntry::Error(format!(
+                            "error: {err}; skipping pass 2"
+                        )));
+                        status = JobStatus::Failed;
+                    }
+                }
+            }
+        }
+        self.normalize = None;
+        if let Some(run) = self.stabilize.as_mut().filter(|run| !run.transforming) {
+            if status == JobStatus::Finished {
+                run.transforming = true;
+                self.next_pass = Some(run.job.transform_args());
+                if let Some(tracker) = &self.pass_tracker {
+                    let (pass, elapsed) = tracker.finish();
+                    self.push_history(HistoryEntry::Info(format!(
+                        "pass {pass} complete in {}",
+                        format_duration(elapsed)
+                    )));
+                }
+                return;
+            }
+        }
+        self.stabilize = None;
+        let mut verified = self.verifying.take();
+        if let Some(run) = verified.as_mut() {
+            if run.decoding {
+                let log: Vec<&str> = self.log_pane.lines.iter().map(String::as_str).collect();
+                run.findings = decode_findings(&log);
+                if run.findings.is_empty() && status != JobStatus::Finished {
+                    run.findings.push("ffmpeg could not decode the file".into());
+                }
+                status = JobStatus::Finished;
+            } else if status == JobStatus::Finished {
+                let layout = self.check_output(run);
+                if run.mode == VerifyMode::Full && run.findings.is_empty() {
+                    self.push_history(HistoryEntry::Info(format!(
+                        "Decoding '{}' to check it...",
+                        run.output.display()
+                    )));
+                    run.decoding = true;
+                    run.duration = layout.and_then(|layout| layout.duration);
+                    run.report = Some(self.job_report(status));
+                    self.next_pass = Some(decode_args(&run.output));
+                    self.verifying = verified;
+                    return;
+                }
+                if let Some(layout) = layout.filter(|_| run.findings.is_empty()) {
+                    self.push_history(HistoryEntry::Info(format!(
+                        "Verified '{}': {}",
+                        run.output.display(),
+                        layout.describe()
+                    )));
+                }
+            }
+            if status == JobStatus::Finished && !run.findings.is_empty() {
+                for finding in &run.findings {
+                    self.push_history(HistoryEntry::Error(format!("error: verify: {finding}")));
+                }
+                status = JobStatus::VerificationFailed;
+            } else if run.decoding {
+                self.push_history(HistoryEntry::Info(format!(
+                    "Verified '{}': decoded without errors",
+                    run.output.display()
+                )));
+            }
+        }
+        self.concat_list = None;
+        if let Some(run) = self.cropdetect.take().filter(|_| status == JobStatus::Finished) {
+            self.finish_cropdetect(run);
+        }
+        if let Some(run) = self.compare.take().filter(|_| status == JobStatus::Finished) {
+            self.finish_compare(run);
+        }
+        if let Some(job) = self.hls.take().filter(|_| status == JobStatus::Finished) {
+            let playlist = job.media_playlist();
+            let entry = match read_playlist(&playlist) {
+                Ok(stats) => HistoryEntry::Info(format_playlist_stats(&playlist, &stats)),
+                Err(err) => HistoryEntry::Warning(format!(
+                    "warning: cannot read '{}': {err}",
+                    playlist.display()
+                )),
+            };
+            self.push_history(entry);
+        }
+        if let Some(job) = self.split.take().filter(|_| status == JobStatus::Finished) {
+            let segments = job.segments();
+            for path in &segments {
+                let entry = match std::fs::metadata(path) {
+                    Ok(meta) => HistoryEntry::Info(format_saved_file(path, meta.len())),
+                    Err(err) => HistoryEntry::Warning(format!(
+                        "warning: cannot read '{}': {err}",
+                        path.display()
+                    )),
+                };
+                self.push_history(entry);
+            }
+            self.push_history(HistoryEntry::Info(format!("{} pieces written", segments.len())));
+        }
+        if let Some(ladder) = &mut self.ladder {
+            if let Some(idx) = ladder.current.take() {
+                ladder.results[idx] = Some(status);
+            }
+            if ladder.results.iter().all(Option::is_some) {
+                let ladder = self.ladder.take().expect("checked above");
+                self.finish_ladder(ladder);
+            }
+        }
+        if self.watch.as_ref().is_some_and(|watch| watch.running) {
+            let mut watch = self.watch.take().expect("checked above");
+            watch.running = false;
+            if watch.watcher.is_none() && status != JobStatus::Finished {
+                self.push_history(HistoryEntry::Warning(
+                    "Not watching: the first run failed.".into(),
+                ));
+            } else if watch.watcher.is_none() {
+                match InputWatcher::new(&watch.input) {
+                    Ok(watcher) => {
+                        self.push_history(HistoryEntry::Info(format!(
+                            "Watching '{}' for changes (Ctrl+W to stop).",
+                            watch.input.display()
+                        )));
+                        watch.watcher = Some(watcher);
+                        self.watch = Some(watch);
+                    }
+                    Err(err) => self.push_history(HistoryEntry::Error(format!("error: {err}"))),
+                }
+            } else {
+                self.watch = Some(watch);
+            }
+        }
+        let report_output = self.report_output.take();
+        if status == JobStatus::Finished {
+            if let Some(tracker) = &self.pass_tracker {
+                let (pass, elapsed) = tracker.finish();
+                self.push_history(HistoryEntry::Info(format!(
+                    "pass {pass} complete in {}",
+                    format_duration(elapsed)
+                )));
+            }
+            if let Some(started) = self.job_started_at {
+                let line = format_completion_line(started.elapsed(), self.media_written());
+                self.push_history(HistoryEntry::Info(line));
+            }
+            if let Some(path) = report_output {
+                let entry = match std::fs::metadata(&path) {
+                    Ok(meta) => HistoryEntry::Info(format_saved_file(&path, meta.len())),
+                    Err(err) => HistoryEntry::Warning(format!(
+                        "warning: cannot read '{}': {err}",
+                        path.display()
+                    )),
+                };
+                self.push_history(entry);
+            }
+        }
+        if status == JobStatus::Failed {
+            let lines: Vec<&str> = self.log_pane.lines.iter().map(String::as_str).collect();
+            let stderr = lines.join("\n");
+            let reason = classify_stderr(&stderr);
+            if reason != FailureReason::Unknown {
+                self.push_history(HistoryEntry::Error(format!("error: {reason}")));
+                self.failure = Some(reason);
+            }
+        }
+        let mut report = verified
+            .as_mut()
+            .and_then(|run| run.report.take())
+            .unwrap_or_else(|| self.job_report(status));
+        report.status = status;
+        report.verification = verified.map(|run| run.findings).unwrap_or_default();
+        self.stats.add_job(report.elapsed);
+        self.reports.push(report);
         self.job_running = false;
         self.job_status = Some(status);
         self.stdin_tx = None;
-        self.push_history(format!("Job finished: {status:?}"));
+        self.log_record("job", &format!("finish: {status:?}"));
+        self.push_history(HistoryEntry::Info(format!("Job finished: {status:?}")));
+        if matches!(status, JobStatus::Failed | JobStatus::VerificationFailed) {
+            self.failed_jobs += 1;
+            self.apply_on_error();
+        }
+        if let Some((name, _)) = self.named_job.take() {
+            let outcome = if status == JobStatus::Finished {
+                Outcome::Succeeded
+            } else {
+                Outcome::Failed
+            };
+            self.job_outcomes.record(&name, outcome);
+        }
+        let line = self.checkpoint_line.take();
+        if let Some((checkpoint, line)) = self.checkpoint.as_mut().zip(line) {
+            if status == JobStatus::Finished {
+                if let Err(err) = checkpoint.record(&line) {
+                    let message = format!(
+                        "warning: cannot write checkpoint '{}': {err}",
+                        checkpoint.path().display()
+                    );
+                    self.push_history(HistoryEntry::Warning(message));
+                }
+            }
+        }
+    }
+
+    fn apply_on_error(&mut self) {
+        if self.job_queue.is_empty() {
+            return;
+        }
+        match self.on_error {
+            OnError::Continue => {}
+            OnError::Stop => {
+                let skipped = self.job_queue.len();
+                self.job_queue.clear();
+                self.push_history(HistoryEntry::Error(format!(
+                    "error: stopping after the failure; {skipped} queued job(s) skipped"
+                )));
+            }
+            OnError::Pause => {
+                self.queue_paused = true;
+                self.push_history(HistoryEntry::Warning(
+                    "Queue paused after the failure. Type 'resume' to go on, or 'skip' to drop the next job first.".into(),
+                ));
+            }
+        }
+    }
+
+    fn finish_cropdetect(&mut self, run: CropdetectRun) {
+        let Some((crop, count, total)) = run.detector.best() else {
+            self.push_history(HistoryEntry::Warning(
+                "warning: cropdetect made no suggestions (no video?)".into(),
+            ));
+            return;
+        };
+        cli::remember_crop(crop);
+        self.push_history(HistoryEntry::Info(format!(
+            "Detected {} ({count} of {total} samples); --crop auto will use it",
+            crop.filter()
+        )));
+        let line = cli::cropped_encode_line(&run.args, crop);
+        if run.args.apply {
+            self.push_history(HistoryEntry::Info(format!(
+                "Queued: {}",
+                line.to_string_lossy()
+            )));
+            self.job_queue.push_front(line);
+        } else {
+            self.push_history(HistoryEntry::Prompt(format!(
+                "Press Ctrl+E to queue: {}",
+                line.to_string_lossy()
+            )));
+            self.crop_offer = Some(line);
+        }
+    }
+
+    /// Compares the finished job's output on disk with what ffmpeg said it
+    /// wrote, noting problems in `run`. Returns the layout it read.
+    fn check_output(&self, run: &mut VerifyRun) -> Option<MediaLayout> {
+        let log: Vec<&str> = self.log_pane.lines.iter().map(String::as_str).collect();
+        let mut expected = MediaLayout::from_job_log(&log);
+        expected.duration = expected
+            .duration
+            .or(self.summary.as_ref().map(|summary| summary.duration))
+            .or(self.progress.as_ref().map(|update| update.time));
+        match probe_layout(&run.output) {
+            Ok(actual) => {
+                run.findings = compare_layouts(&expected, &actual);
+                Some(actual)
+            }
+            Err(err) => {
+                run.findings = vec![format!("cannot read '{}': {err}", run.output.display())];
+                None
+            }
+        }
+    }
+
+    /// Media time the job got through: the final stats line, else the
+    /// last progress update.
+    fn media_written(&self) -> Option<Duration> {
+        self.summary
+            .as_ref()
+            .map(|summary| summary.duration)
+            .filter(|duration| !duration.is_zero())
+            .or_else(|| self.progress.as_ref().map(|update| update.time))
+    }
+
+    fn job_report(&self, status: JobStatus) -> JobReport {
+        let summary = self.summary.as_ref();
+        JobReport {
+            command: self.last_command.clone().unwrap_or_default(),
+            status,
+            input: self.inputs.first().and_then(|info| info.path.clone()),
+            output: self.outputs.first().map(|info| info.path.clone()),
+            size_bytes: summary.map(|summary| summary.final_size_bytes),
+            avg_bitrate_kbps: summary.map(|summary| summary.avg_bitrate_kbps),
+            duration: summary.map(|summary| summary.duration),
+            elapsed: self
+                .job_started_at
+                .map(|started| started.elapsed())
+                .unwrap_or_default(),
+            verification: Vec::new(),
+        }
+    }
+
+    fn finish_ladder(&mut self, ladder: LadderRun) {
+        self.push_history(HistoryEntry::Info(format!("{} done:", ladder.plan.label)));
+        for (planned, status) in ladder.plan.rungs.iter().zip(&ladder.results) {
+            let entry = match (status, std::fs::metadata(&planned.output)) {
+                (Some(JobStatus::Finished), Ok(meta)) => HistoryEntry::Info(format_rung_result(
+                    &planned.rung,
+                    &planned.output,
+                    meta.len(),
+                    ladder.plan.duration,
+                )),
+                _ => HistoryEntry::Error(format!(
+                    "{:>5}p  {}  failed",
+                    planned.rung.height,
+                    planned.output.display()
+                )),
+            };
+            self.push_history(entry);
+        }
+    }
+
+    fn finish_compare(&mut self, run: CompareRun) {
+        let scores = run.parser.scores();
+        if scores.is_empty() {
+            self.push_history(HistoryEntry::Error(
+                "error: ffmpeg printed no quality scores".into(),
+            ));
+            return;
+        }
+        for line in format_quality_lines(&scores) {
+            self.push_history(HistoryEntry::Info(line));
+        }
+        if let Some(path) = run.json {
+            let json = scores.to_json(&run.job);
+            let entry = match std::fs::write(&path, &json) {
+                Ok(()) => HistoryEntry::Info(format_saved_file(&path, json.len() as u64)),
+                Err(err) => HistoryEntry::Error(format!(
+                    "error: cannot write '{}': {err}",
+                    path.display()
+                )),
+            };
+            self.push_history(entry);
+        }
     }
 
     fn set_view_lines(&mut self, lines: usize) {
@@ -153,109 +1049,249 @@ impl AppState {
     }
 }
 
-pub fn run(initial_queue: Vec<String>) -> Result<(), FfxError> {
+pub fn run(
+    initial_queue: Vec<OsString>,
+    log_file: Option<PathBuf>,
+    scrollback: usize,
+    checkpoint: Option<Checkpoint>,
+) -> Result<(), FfxError> {
+    detect_version_in_background();
     let _guard = TerminalGuard::enter()?;
+    restore_terminal_on_panic();
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend).map_err(|e| FfxError::InvalidCommand {
         message: e.to_string(),
     })?;
 
+    let mut app = AppState::new(initial_queue);
+    app.set_history_cap(scrollback);
+    app.load_config();
+    if let Some(path) = log_file {
+        app.open_session_log(&path);
+    }
+    if let Some(checkpoint) = checkpoint.as_ref().filter(|checkpoint| !checkpoint.skipped().is_empty()) {
+        app.push_history(HistoryEntry::Info(format!(
+            "Skipped {} job(s) already done according to '{}'.",
+            checkpoint.skipped().len(),
+            checkpoint.path().display()
+        )));
+        for line in checkpoint.skipped() {
+            app.job_outcomes.record_line(line, Outcome::Succeeded);
+        }
+    }
+    app.checkpoint = checkpoint;
+
+    // The hook has already put the terminal back and printed the panic;
+    // what is left is the job, which must not outlive us.
+    panic::catch_unwind(AssertUnwindSafe(|| event_loop(&mut terminal, &mut app))).unwrap_or_else(|_| {
+        stop_job(&app);
+        Err(FfxError::Panicked)
+    })
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut AppState,
+) -> Result<(), FfxError> {
     let (event_tx, event_rx) = mpsc::channel::<FfmpegEvent>();
     let (job_tx, job_rx) = mpsc::channel::<JobStatus>();
 
-    let mut app = AppState::new(initial_queue);
-
     loop {
+        app.check_session_log();
+
         while let Ok(event) = event_rx.try_recv() {
             match event {
-                FfmpegEvent::Progress(update) => {
-                    app.progress = Some(update.clone());
-                    if let Some(line) = format_progress_line(&update, app.duration) {
-                        app.last_progress_line = Some(line.clone());
-                        app.progress_log_counter = app.progress_log_counter.wrapping_add(1);
-                        if app.progress_log_counter % 25 == 0 {
-                            app.push_history(line);
-                        }
-                    }
-                }
+                FfmpegEvent::Progress(update) => apply_progress(app, update),
                 FfmpegEvent::Input(info) => {
-                    app.input_info = Some(info.clone());
-                    if let Some(duration) = info.duration {
-                        app.duration = Some(duration);
+                    app.push_history(HistoryEntry::Info(format_input_line(&info)));
+                    app.inputs.push(info);
+                    if let Some(duration) = app
+                        .input_duration
+                        .pick(&app.inputs)
+                        .filter(|_| !app.duration_fixed)
+                    {
+                        app.duration = Some(duration.saturating_sub(app.seek_offset));
                     }
-                    app.push_history(format_input_line(&info));
+                }
+                FfmpegEvent::OutputStarted(path) => {
+                    app.writing = Some(path);
                 }
                 FfmpegEvent::Output(info) => {
-                    app.output_info = Some(info.clone());
-                    app.push_history(format_output_line(&info));
+                    app.push_history(HistoryEntry::Info(format_output_line(&info)));
+                    app.outputs.push(info);
+                }
+                FfmpegEvent::Mapping(mappings) => {
+                    app.push_history(HistoryEntry::Info(format_mapping_line(&mappings)));
                 }
                 FfmpegEvent::Summary(summary) => {
-                    app.summary = Some(summary.clone());
-                    app.push_history(format_summary_line(&summary));
+                    // ffmpeg's final size covers every output it wrote.
+                    let line = match app.outputs.len() {
+                        0 | 1 => format_summary_line(&summary),
+                        n => format!("{} across {n} outputs", format_summary_line(&summary)),
+                    };
+                    app.stats.add_summary(&summary);
+                    app.summary = Some(summary);
+                    app.push_history(HistoryEntry::Info(line));
+                }
+                FfmpegEvent::Log(line) => {
+                    app.log_record("stderr", &line);
+                    if let Some(run) = &mut app.normalize {
+                        run.parser.push_line(&line);
+                    }
+                    if let Some(run) = &mut app.cropdetect {
+                        run.detector.push_line(&line);
+                    }
+                    if let Some(run) = &mut app.compare {
+                        run.parser.push_line(&line);
+                    }
+                    app.log_pane.push(line);
+                }
+                FfmpegEvent::SegmentOpened(_) => {
+                    app.segments_opened += 1;
+                }
+                FfmpegEvent::Noise(line) => {
+                    app.push_history(HistoryEntry::Noise(line));
+                }
+                FfmpegEvent::Warning(message) => {
+                    app.push_history(HistoryEntry::Warning(format!("warning: {message}")));
                 }
                 FfmpegEvent::Error(message) => {
-                    app.last_error = Some(message.clone());
                     app.job_status = Some(JobStatus::Failed);
-                    app.push_history(format!("error: {message}"));
+                    app.push_history(HistoryEntry::Error(format!("error: {message}")));
                 }
                 FfmpegEvent::Prompt(message) => {
                     app.job_status = Some(JobStatus::AwaitingConfirmation);
-                    app.push_history(format!("PROMPT: {message}"));
-                    app.push_history(">> Press 'y' to confirm or 'n' to abort.");
+                    app.push_history(HistoryEntry::Prompt(format!("PROMPT: {message}")));
+                    app.push_history(HistoryEntry::Prompt(
+                        ">> Press 'y' to confirm or 'n' to abort.".into(),
+                    ));
                 }
             }
         }
 
+        if let Some(update) = app.latest_progress.as_ref().and_then(LatestProgress::take) {
+            apply_progress(app, update);
+        }
+
         while let Ok(status) = job_rx.try_recv() {
             app.update_job(status);
         }
 
-        if !app.job_running && app.job_status != Some(JobStatus::AwaitingConfirmation) {
+        if let Some(args) = app.next_pass.take() {
+            // Elapsed time covers every pass of the job.
+            let started = app.job_started_at;
+            start_job(app, args, event_tx.clone(), job_tx.clone());
+            app.job_started_at = started;
+            match app.verifying.as_ref().filter(|run| run.decoding).map(|run| run.duration) {
+                // A decode pass only logs errors, so no input header gives
+                // its length.
+                Some(duration) => {
+                    app.duration = duration;
+                    app.duration_fixed = duration.is_some();
+                }
+                None => app.pass_tracker = Some(PassTracker::new(2, 2)),
+            }
+        }
+
+        for job in app.schedule.take_due(SystemTime::now()) {
+            app.push_history(HistoryEntry::Info(format!(
+                "Start time {} reached; queued: {}",
+                format_start_time(job.at),
+                job.line.to_string_lossy()
+            )));
+            app.job_queue.push_back(job.line);
+        }
+
+        // A named line that started no ffmpeg job (a built-in, or one that
+        // couldn't start) has ended by now.
+        if !app.job_running && app.held.is_none() {
+            if let Some((name, errors)) = app.named_job.take() {
+                let outcome = if app.errors.len() > errors {
+                    Outcome::Failed
+                } else {
+                    Outcome::Succeeded
+                };
+                app.job_outcomes.record(&name, outcome);
+            }
+        }
+
+        if !app.job_running
+            && !app.queue_paused
+            && app.job_status != Some(JobStatus::AwaitingConfirmation)
+        {
             if let Some(next_cmd) = app.job_queue.pop_front() {
-                handle_line(&mut app, next_cmd, event_tx.clone(), job_tx.clone());
+                if app.checkpoint.as_ref().is_some_and(|checkpoint| checkpoint.is_done(&next_cmd)) {
+                    app.job_outcomes.record_line(&next_cmd, Outcome::Succeeded);
+                    app.push_history(HistoryEntry::Info(format!(
+                        "Already done, skipping: {}",
+                        next_cmd.to_string_lossy()
+                    )));
+                } else {
+                    app.checkpoint_line = app.checkpoint.is_some().then(|| next_cmd.clone());
+                    handle_line(app, next_cmd, event_tx.clone(), job_tx.clone());
+                    if !app.job_running && app.held.is_none() {
+                        app.checkpoint_line = None;
+                    }
+                }
+            } else {
+                rerun_watch(app, event_tx.clone(), job_tx.clone());
             }
         }
 
         let size = terminal.size().map_err(|e| FfxError::InvalidCommand {
             message: e.to_string(),
         })?;
-        let history_height = size.height.saturating_sub(7).max(3) as usize;
-        let view_lines = history_height.saturating_sub(2).max(1);
-        app.set_view_lines(view_lines);
+        // Also re-clamps both scroll offsets after a resize.
+        if let Some(areas) = screen_layout(size, app.log_pane.visible, error_strip_height(app)) {
+            app.set_view_lines(areas.session.height.saturating_sub(2).max(1) as usize);
+            if let Some(log_area) = areas.log {
+                app.log_pane
+                    .set_view_lines(log_area.height.saturating_sub(2).max(1) as usize);
+            }
+        }
 
         app.tick = app.tick.wrapping_add(1);
 
         terminal
             .draw(|frame| {
-                let layout = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Length(4),
-                        Constraint::Min(3),
-                        Constraint::Length(3),
-                    ])
-                    .split(frame.size());
-
-                let header = render_header(&app, layout[0].width as usize);
-                frame.render_widget(header, layout[0]);
-
-                let history = render_history(&app, layout[1].height as usize, layout[1].width as usize);
-                frame.render_widget(history, layout[1]);
-
-                let input_text = if app.job_status == Some(JobStatus::AwaitingConfirmation) {
-                    format!("{} (y/n)", app.input)
-                } else {
-                    app.input.clone()
+                let Some(layout) = screen_layout(frame.size(), app.log_pane.visible, error_strip_height(app)) else {
+                    let message = format!("terminal too small (need at least {MIN_WIDTH}x{MIN_HEIGHT})");
+                    frame.render_widget(Paragraph::new(message).wrap(Wrap { trim: true }), frame.size());
+                    return;
                 };
 
-                let input = Paragraph::new(input_text.as_str())
-                    .block(Block::default().title("Input").borders(Borders::ALL))
-                    .wrap(Wrap { trim: false });
-                frame.render_widget(input, layout[2]);
+                let header = render_header(app, layout.header.width as usize, layout.compact);
+                frame.render_widget(header, layout.header);
+
+                if let Some(errors_area) = layout.errors {
+                    frame.render_widget(render_error_strip(app), errors_area);
+                }
+
+                let history = render_history(app, layout.session.height as usize, layout.session.width as usize);
+                frame.render_widget(history, layout.session);
+
+                if let Some(log_area) = layout.log {
+                    frame.render_widget(render_log_pane(&app.log_pane, app.failure.as_ref()), log_area);
+                }
+
+                let suffix = match app.job_status {
+                    Some(JobStatus::AwaitingConfirmation) => " (y/n)",
+                    _ => "",
+                };
+                // A long line scrolls sideways so its end, where the cursor
+                // is, stays in view.
+                let room = (layout.input.width as usize)
+                    .saturating_sub(3 + display_width(suffix))
+                    .max(1);
+                let shown = tail_to_width(&app.input, room);
+                let title = app.wizard.as_ref().map_or("Input", Wizard::label);
+                let input = Paragraph::new(format!("{shown}{suffix}"))
+                    .block(Block::default().title(title).borders(Borders::ALL));
+                frame.render_widget(input, layout.input);
                 frame.set_cursor(
-                    layout[2].x + 1 + app.input.len() as u16,
-                    layout[2].y + 1,
+                    layout.input.x + 1 + display_width(shown) as u16,
+                    layout.input.y + 1,
                 );
             })
             .map_err(|e| FfxError::InvalidCommand {
@@ -265,328 +1301,1863 @@ pub fn run(initial_queue: Vec<String>) -> Result<(), FfxError> {
         if event::poll(Duration::from_millis(50)).map_err(|e| FfxError::InvalidCommand {
             message: e.to_string(),
         })? {
-            if let Event::Key(key) = event::read().map_err(|e| FfxError::InvalidCommand {
+            let event = event::read().map_err(|e| FfxError::InvalidCommand {
                 message: e.to_string(),
-            })? {
-                if let Some(JobStatus::AwaitingConfirmation) = app.job_status {
-                    match key.code {
-                         KeyCode::Char('y') | KeyCode::Char('Y') => {
-                            if let Some(tx) = &app.stdin_tx {
-                                let _ = tx.send("y\n".to_string());
-                            }
-                            app.job_status = Some(JobStatus::Running);
-                            app.push_history(">> Sent: y");
-                        }
-                        KeyCode::Char('n') | KeyCode::Char('N') => {
-                            if let Some(tx) = &app.stdin_tx {
-                                let _ = tx.send("n\n".to_string());
-                            }
-                            app.job_status = Some(JobStatus::Running);
-                             app.push_history(">> Sent: n");
-                        }
-                        KeyCode::Esc => {
-                            app.should_quit = true;
-                        }
-                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                            app.should_quit = true;
-                        }
-                        _ => {}
-                    }
-                } else {
-                    match key.code {
-                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                            app.should_quit = true;
-                        }
-                        KeyCode::Char(ch) => {
-                            app.input.push(ch);
-                        }
-                        KeyCode::Backspace => {
-                            app.input.pop();
-                        }
-                        KeyCode::Enter => {
-                            let line = app.input.trim().to_string();
-                            app.input.clear();
-                            if !line.is_empty() {
-                                handle_line(&mut app, line, event_tx.clone(), job_tx.clone());
-                            }
-                        }
-                        KeyCode::PageUp => {
-                            let step = app.view_lines.saturating_sub(1).max(1);
-                            app.scroll_up(step);
-                        }
-                        KeyCode::PageDown => {
-                            let step = app.view_lines.saturating_sub(1).max(1);
-                            app.scroll_down(step);
-                        }
-                        KeyCode::Up => {
-                            app.scroll_up(1);
-                        }
-                        KeyCode::Down => {
-                            app.scroll_down(1);
-                        }
-                        KeyCode::Home => {
-                            app.scroll_top();
-                        }
-                        KeyCode::End => {
-                            app.scroll_bottom();
-                        }
-                        KeyCode::Esc => {
-                            app.should_quit = true;
+            })?;
+            handle_event(app, event, &event_tx, &job_tx);
+        }
+
+        if app.should_quit {
+            // Never leave a stopped ffmpeg behind.
+            if app.job_status == Some(JobStatus::Paused) {
+                toggle_pause(app);
+            }
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_event(
+    app: &mut AppState,
+    event: Event,
+    event_tx: &mpsc::Sender<FfmpegEvent>,
+    job_tx: &mpsc::Sender<JobStatus>,
+) {
+    match event {
+        Event::Key(key) => handle_key(app, key, event_tx, job_tx),
+        Event::Paste(text) => handle_paste(app, &text),
+        Event::Mouse(mouse) => handle_mouse(app, mouse),
+        _ => {}
+    }
+}
+
+/// Lines moved per wheel notch.
+const WHEEL_LINES: usize = 3;
+
+/// The wheel scrolls like Up/Down, a few lines per notch: the log pane
+/// while it is open, the history otherwise.
+fn handle_mouse(app: &mut AppState, mouse: MouseEvent) {
+    match (mouse.kind, app.log_pane.visible) {
+        (MouseEventKind::ScrollUp, true) => app.log_pane.scroll_up(WHEEL_LINES),
+        (MouseEventKind::ScrollDown, true) => app.log_pane.scroll_down(WHEEL_LINES),
+        (MouseEventKind::ScrollUp, false) => app.scroll_up(WHEEL_LINES),
+        (MouseEventKind::ScrollDown, false) => app.scroll_down(WHEEL_LINES),
+        _ => {}
+    }
+}
+
+/// Pasted text goes into the input line. Several complete lines can be
+/// queued one job each instead, after asking; while a job runs they are
+/// queued straight away, since new lines can't start until it ends.
+fn handle_paste(app: &mut AppState, text: &str) {
+    if app.job_status == Some(JobStatus::AwaitingConfirmation) {
+        return;
+    }
+    let mut lines = pasted_lines(text);
+    match lines.len() {
+        0 => {}
+        1 => app.input.push_str(&lines.remove(0)),
+        count if app.job_running => {
+            app.push_history(HistoryEntry::Info(format!(
+                "Queued {count} pasted commands; they start after the current job."
+            )));
+            app.job_queue.extend(lines.into_iter().map(OsString::from));
+        }
+        count => {
+            app.push_history(HistoryEntry::Prompt(format!("PROMPT: Pasted {count} commands.")));
+            app.push_history(HistoryEntry::Prompt(
+                ">> Press 'y' to queue each as a job or 'n' to join them into the input line.".into(),
+            ));
+            app.job_status = Some(JobStatus::AwaitingConfirmation);
+            app.held = Some(HeldJob::Paste(lines));
+        }
+    }
+}
+
+/// Splits pasted text into command lines. A line ending in `\` carries on
+/// the next, as in a shell; tabs become spaces and other control characters
+/// are dropped so they can't garble the input box.
+fn pasted_lines(text: &str) -> Vec<String> {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in text.split('\n') {
+        let line: String = line
+            .chars()
+            .filter_map(|ch| match ch {
+                '\t' => Some(' '),
+                ch if ch.is_control() => None,
+                ch => Some(ch),
+            })
+            .collect();
+        match line.trim_end().strip_suffix('\\') {
+            Some(start) => {
+                current.push_str(start);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(&line);
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    lines.push(current);
+    lines
+        .into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn handle_key(
+    app: &mut AppState,
+    key: KeyEvent,
+    event_tx: &mpsc::Sender<FfmpegEvent>,
+    job_tx: &mpsc::Sender<JobStatus>,
+) {
+    if let (Some(JobStatus::AwaitingConfirmation), Some(_)) =
+        (app.job_status, app.held.as_ref())
+    {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                app.job_status = None;
+                match app.held.take() {
+                    Some(HeldJob::LowSpace(job)) => {
+                        app.push_history(HistoryEntry::UserInput(">> Starting anyway".into()));
+                        launch_job(app, job, event_tx.clone(), job_tx.clone());
+                    }
+                    Some(HeldJob::OutputExists { mut job, .. }) => {
+                        app.push_history(HistoryEntry::UserInput(">> Overwriting".into()));
+                        job.args.insert(0, "-y".into());
+                        start_job_args(app, job, event_tx.clone(), job_tx.clone());
+                    }
+                    Some(HeldJob::Paste(lines)) => {
+                        app.push_history(HistoryEntry::UserInput(format!(">> Queued {} commands", lines.len())));
+                        app.job_queue.extend(lines.into_iter().map(OsString::from));
+                    }
+                    None => {}
+                }
+            }
+            KeyCode::Char('r') | KeyCode::Char('R')
+                if matches!(app.held, Some(HeldJob::OutputExists { .. })) =>
+            {
+                app.job_status = None;
+                if let Some(HeldJob::OutputExists { mut job, rename }) = app.held.take() {
+                    app.push_history(HistoryEntry::UserInput(format!(
+                        ">> Saving as {}",
+                        rename.display()
+                    )));
+                    let old = job.args.pop().map(PathBuf::from);
+                    if job.report_output.is_some() && job.report_output == old {
+                        job.report_output = Some(rename.clone());
+                    }
+                    job.args.push(rename.into_os_string());
+                    start_job_args(app, job, event_tx.clone(), job_tx.clone());
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                app.job_status = None;
+                match app.held.take() {
+                    Some(HeldJob::Paste(lines)) => {
+                        app.input.push_str(&lines.join(" "));
+                        app.push_history(HistoryEntry::UserInput(">> Joined into one line".into()));
+                    }
+                    _ => {
+                        app.checkpoint_line = None;
+                        if let Some((name, _)) = app.named_job.take() {
+                            app.job_outcomes.record(&name, Outcome::Skipped);
                         }
-                        _ => {}
+                        app.push_history(HistoryEntry::UserInput(">> Skipped".into()));
                     }
                 }
             }
+            KeyCode::Esc => {
+                app.should_quit = true;
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.should_quit = true;
+            }
+            _ => {}
+        }
+    } else if let Some(JobStatus::AwaitingConfirmation) = app.job_status {
+        match key.code {
+             KeyCode::Char('y') | KeyCode::Char('Y') => {
+                if let Some(tx) = &app.stdin_tx {
+                    let _ = tx.send("y\n".to_string());
+                }
+                app.job_status = Some(JobStatus::Running);
+                app.push_history(HistoryEntry::UserInput(">> Sent: y".into()));
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                if let Some(tx) = &app.stdin_tx {
+                    let _ = tx.send("n\n".to_string());
+                }
+                app.job_status = Some(JobStatus::Running);
+                app.push_history(HistoryEntry::UserInput(">> Sent: n".into()));
+            }
+            KeyCode::Esc => {
+                app.should_quit = true;
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.should_quit = true;
+            }
+            _ => {}
+        }
+    } else {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.should_quit = true;
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                toggle_pause(app);
+            }
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                copy_last_command(app);
+            }
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let message = match app.watch.take() {
+                    Some(watch) => {
+                        format!("Stopped watching '{}'.", watch.input.display())
+                    }
+                    None => "Not watching anything.".to_string(),
+                };
+                app.push_history(HistoryEntry::Info(message));
+            }
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                match app.crop_offer.take() {
+                    Some(line) => {
+                        app.push_history(HistoryEntry::Info(format!(
+                            "Queued: {}",
+                            line.to_string_lossy()
+                        )));
+                        app.job_queue.push_back(line);
+                    }
+                    None => app.push_history(HistoryEntry::Info(
+                        "No crop to apply; run cropdetect first.".into(),
+                    )),
+                }
+            }
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.errors.clear();
+                app.errors_expanded = false;
+            }
+            KeyCode::Char(ch) => {
+                app.input.push(ch);
+            }
+            KeyCode::Backspace => {
+                // A whole character as seen, e.g. an accented letter made of
+                // two code points, or an emoji with a skin tone.
+                if let Some((start, _)) = app.input.grapheme_indices(true).next_back() {
+                    app.input.truncate(start);
+                }
+            }
+            KeyCode::Esc if app.wizard.is_some() => {
+                app.wizard = None;
+                app.input.clear();
+                app.push_history(HistoryEntry::UserInput(">> Wizard cancelled".into()));
+            }
+            KeyCode::Enter if app.wizard.is_some() => {
+                let answer = app.input.trim().to_string();
+                app.input.clear();
+                answer_wizard(app, answer, event_tx, job_tx);
+            }
+            KeyCode::Enter => {
+                let line = app.input.trim().to_string();
+                app.input.clear();
+                if !line.is_empty() {
+                    handle_line(app, line.into(), event_tx.clone(), job_tx.clone());
+                }
+            }
+            KeyCode::F(2) => {
+                app.log_pane.visible = !app.log_pane.visible;
+            }
+            KeyCode::F(3) if !app.errors.is_empty() => {
+                app.errors_expanded = !app.errors_expanded;
+            }
+            // With the log pane open, scrolling moves the log.
+            KeyCode::PageUp if app.log_pane.visible => {
+                let step = app.log_pane.view_lines.saturating_sub(1).max(1);
+                app.log_pane.scroll_up(step);
+            }
+            KeyCode::PageDown if app.log_pane.visible => {
+                let step = app.log_pane.view_lines.saturating_sub(1).max(1);
+                app.log_pane.scroll_down(step);
+            }
+            KeyCode::Up if app.log_pane.visible => {
+                app.log_pane.scroll_up(1);
+            }
+            KeyCode::Down if app.log_pane.visible => {
+                app.log_pane.scroll_down(1);
+            }
+            KeyCode::Home if app.log_pane.visible => {
+                app.log_pane.scroll_up(usize::MAX);
+            }
+            KeyCode::End if app.log_pane.visible => {
+                app.log_pane.scroll_offset = 0;
+            }
+            KeyCode::PageUp => {
+                let step = app.view_lines.saturating_sub(1).max(1);
+                app.scroll_up(step);
+            }
+            KeyCode::PageDown => {
+                let step = app.view_lines.saturating_sub(1).max(1);
+                app.scroll_down(step);
+            }
+            KeyCode::Up => {
+                app.scroll_up(1);
+            }
+            KeyCode::Down => {
+                app.scroll_down(1);
+            }
+            KeyCode::Home => {
+                app.scroll_top();
+            }
+            KeyCode::End => {
+                app.scroll_bottom();
+            }
+            KeyCode::Esc => {
+                app.should_quit = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A line that points back at an earlier command, as in a shell.
+#[derive(Debug, PartialEq, Eq)]
+enum Recall {
+    /// Run the command again.
+    Run(String),
+    /// Put the command on the input line to edit first.
+    Edit(String),
+}
+
+/// Resolves `!!`, `!N` (from 1), `!?text` and `!e` against `commands`,
+/// oldest first; `redo` and `redo --edit` are the spelled-out forms of `!!`
+/// and `!e`. `None` for an ordinary line.
+fn recall(line: &str, commands: &[String]) -> Option<Result<Recall, String>> {
+    let last = || {
+        commands
+            .last()
+            .cloned()
+            .ok_or_else(|| "no previous command".to_string())
+    };
+    let recalled = match line {
+        "!!" | "redo" => last().map(Recall::Run),
+        "!e" | "redo --edit" => last().map(Recall::Edit),
+        _ => {
+            let reference = line.strip_prefix('!')?;
+            if let Some(pattern) = reference.strip_prefix('?') {
+                let pattern = pattern.strip_suffix('?').unwrap_or(pattern);
+                commands
+                    .iter()
+                    .rev()
+                    .find(|command| command.contains(pattern))
+                    .cloned()
+                    .map(Recall::Run)
+                    .ok_or_else(|| format!("no command containing '{pattern}'"))
+            } else if let Ok(number) = reference.parse::<usize>() {
+                number
+                    .checked_sub(1)
+                    .and_then(|index| commands.get(index))
+                    .cloned()
+                    .map(Recall::Run)
+                    .ok_or_else(|| format!("no command {number} in the history"))
+            } else {
+                Err(format!(
+                    "unknown history reference '{line}' (expected !!, !<n>, !?<text> or !e)"
+                ))
+            }
+        }
+    };
+    Some(recalled)
+}
+
+/// Copies the last submitted line. Without a clipboard the line is printed
+/// on its own so it can be selected with the mouse instead.
+fn copy_last_command(app: &mut AppState) {
+    let Some(command) = app.last_command.clone() else {
+        app.push_history(HistoryEntry::Info("Nothing to copy yet.".into()));
+        return;
+    };
+    match clipboard::copy(&command) {
+        Ok(()) => {
+            app.push_history(HistoryEntry::Info(format!("Copied to clipboard: {command}")));
+        }
+        Err(err) => {
+            app.push_history(HistoryEntry::Warning(format!(
+                "warning: clipboard unavailable ({err})"
+            )));
+            app.push_history(HistoryEntry::Info(command));
+        }
+    }
+}
+
+fn prompt_wizard(app: &mut AppState) {
+    let Some(wizard) = &app.wizard else {
+        return;
+    };
+    let current_input = app.inputs.first().and_then(|info| info.path.as_deref());
+    let prompt = match wizard.default_answer(current_input) {
+        Some(default) => format!("{} [{default}]:", wizard.label()),
+        None => format!("{}:", wizard.label()),
+    };
+    app.push_history(HistoryEntry::Prompt(prompt));
+}
+
+/// Takes the answer to the current `wizard` question. After the last one
+/// the encode line runs as if typed, or is queued behind a running job.
+fn answer_wizard(
+    app: &mut AppState,
+    answer: String,
+    event_tx: &mpsc::Sender<FfmpegEvent>,
+    job_tx: &mpsc::Sender<JobStatus>,
+) {
+    let Some(mut wizard) = app.wizard.take() else {
+        return;
+    };
+    let current_input = app.inputs.first().and_then(|info| info.path.as_deref());
+    let Some(answer) = Some(answer)
+        .filter(|answer| !answer.is_empty())
+        .or_else(|| wizard.default_answer(current_input))
+    else {
+        app.push_history(HistoryEntry::Warning("An input file is needed.".into()));
+        app.wizard = Some(wizard);
+        prompt_wizard(app);
+        return;
+    };
+    app.push_history(HistoryEntry::UserInput(format!(">> {answer}")));
+    if wizard.input.is_none() {
+        if !Path::new(&answer).exists() {
+            app.push_history(HistoryEntry::Warning(format!("'{answer}' does not exist.")));
+        } else {
+            wizard.input = Some(answer.into());
+        }
+    } else if wizard.output.is_none() {
+        wizard.output = Some(answer.into());
+    } else if wizard.codec.is_none() {
+        wizard.codec = Some(answer);
+    } else {
+        let line = wizard.encode_line(&answer);
+        if app.job_running {
+            app.push_history(HistoryEntry::Info(format!("Queued: {}", line.to_string_lossy())));
+            app.job_queue.push_back(line);
+        } else {
+            handle_line(app, line, event_tx.clone(), job_tx.clone());
+        }
+        return;
+    }
+    app.wizard = Some(wizard);
+    prompt_wizard(app);
+}
+
+/// Stops a running ffmpeg the way Ctrl-C would, waking it first if it
+/// is paused.
+fn stop_job(app: &AppState) {
+    if let Some(pid) = app.job_pid.as_ref().and_then(ChildPid::get) {
+        let _ = process::resume(pid);
+        let _ = process::interrupt(pid);
+    }
+}
+
+fn toggle_pause(app: &mut AppState) {
+    let Some(pid) = app.job_pid.as_ref().and_then(ChildPid::get) else {
+        app.push_history(HistoryEntry::Info("No running job to pause.".into()));
+        return;
+    };
+    let pausing = app.job_status != Some(JobStatus::Paused);
+    let result = if pausing {
+        process::pause(pid)
+    } else {
+        process::resume(pid)
+    };
+    match result {
+        Ok(()) if pausing => {
+            app.job_status = Some(JobStatus::Paused);
+            app.push_history(HistoryEntry::Info(">> Paused (Ctrl+P to resume)".into()));
+        }
+        Ok(()) => {
+            app.job_status = Some(JobStatus::Running);
+            app.push_history(HistoryEntry::Info(">> Resumed".into()));
+        }
+        Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+    }
+}
+
+fn apply_progress(app: &mut AppState, update: FfmpegProgress) {
+    if let Some((pass, elapsed)) = app
+        .pass_tracker
+        .as_mut()
+        .and_then(|tracker| tracker.observe(&update))
+    {
+        app.push_history(HistoryEntry::Info(format!(
+            "pass {pass} complete in {}",
+            format_duration(elapsed)
+        )));
+        app.push_history(HistoryEntry::Divider);
+    }
+    if let Some(line) = format_progress_line(&update, app.duration, app.total_frames) {
+        app.last_progress_line = Some(line.clone());
+        app.progress_log_counter = app.progress_log_counter.wrapping_add(1);
+        if app.progress_log_counter.is_multiple_of(25) {
+            app.push_history(HistoryEntry::Progress(line));
+        }
+    }
+    if let Some(speed) = update.speed {
+        app.speed_trend.push(speed);
+    }
+    if let Some(live) = &mut app.live {
+        live.push(&update);
+    }
+    app.frame_counts.push(&update);
+    app.progress = Some(update);
+}
+
+/// Words a TUI line can start with besides the subcommands.
+const TUI_COMMANDS: &[&str] = &[
+    "ffmpeg", "alias", "at", "batch", "clear", "exit", "history", "info", "logs", "queue", "quit", "redo", "report", "resume", "save", "set", "skip", "stats", "unalias", "watch", "wizard",
+];
+
+/// The 0-based index of job `number` as listed by `queue`; out of range
+/// (or not a number) is reported as an error line.
+fn queue_index(app: &mut AppState, number: &str) -> Option<usize> {
+    let number = number.trim();
+    let index = number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_sub(1))
+        .filter(|index| *index < app.job_queue.len());
+    if index.is_none() {
+        app.push_history(HistoryEntry::Error(format!(
+            "error: no job {number} in the queue (see 'queue')"
+        )));
+    }
+    index
+}
+
+/// `at <time> <command>` (or `@at` from a `.flw` file) and
+/// `at cancel <n>`.
+fn handle_at(app: &mut AppState, rest: &OsStr, priority: i32) {
+    let text = rest.to_string_lossy();
+    if let Some(number) = text.strip_prefix("cancel ") {
+        let cancelled = number
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| app.schedule.cancel(number.checked_sub(1)?));
+        match cancelled {
+            Some(job) => app.push_history(HistoryEntry::Info(format!(
+                "Cancelled: {}",
+                job.line.to_string_lossy()
+            ))),
+            None => app.push_history(HistoryEntry::Error(format!(
+                "error: no scheduled job {} (see 'queue')",
+                number.trim()
+            ))),
+        }
+        return;
+    }
+    let now = SystemTime::now();
+    let mut job = match parse_scheduled(rest, now) {
+        Ok(job) => job,
+        Err(err) => {
+            app.push_history(HistoryEntry::Error(format!("error: {err}")));
+            return;
+        }
+    };
+    if priority != 0 {
+        let mut line = OsString::from(format!("@priority {priority} "));
+        line.push(&job.line);
+        job.line = line;
+    }
+    let when = format_start_time(job.at);
+    if job.at > now {
+        let left = job.at.duration_since(now).unwrap_or_default();
+        app.push_history(HistoryEntry::Info(format!(
+            "Scheduled for {when} (in {}): {}",
+            format_duration(left),
+            job.line.to_string_lossy()
+        )));
+        app.schedule.add(job);
+    } else if strict_schedule() {
+        app.push_history(HistoryEntry::Error(format!(
+            "error: start time {when} has already passed"
+        )));
+    } else {
+        app.push_history(HistoryEntry::Info(format!(
+            "note: start time {when} has already passed; queued to run now"
+        )));
+        app.job_queue.push_back(job.line);
+    }
+}
+
+fn handle_line(
+    app: &mut AppState,
+    line: OsString,
+    event_tx: mpsc::Sender<FfmpegEvent>,
+    job_tx: mpsc::Sender<JobStatus>,
+) {
+    // Queued lines may come from a .flw file with non-UTF-8 paths: built-in
+    // commands are matched on a lossy copy, jobs get the raw bytes.
+    let raw = trim_os(&line);
+    if !app.history.is_empty() {
+        app.push_history(HistoryEntry::Divider);
+    }
+    let typed = raw.to_string_lossy().into_owned();
+    let raw = match recall(&typed, &app.commands) {
+        None => {
+            app.push_history(HistoryEntry::UserInput(format!(">> {typed}")));
+            raw
+        }
+        Some(Ok(Recall::Run(command))) => {
+            app.push_history(HistoryEntry::UserInput(format!(">> (expanded) {command}")));
+            OsString::from(command)
+        }
+        Some(Ok(Recall::Edit(command))) => {
+            app.push_history(HistoryEntry::UserInput(format!(">> {typed}")));
+            app.input = command;
+            return;
+        }
+        Some(Err(err)) => {
+            app.push_history(HistoryEntry::UserInput(format!(">> {typed}")));
+            app.push_history(HistoryEntry::Error(format!("error: {err}")));
+            return;
+        }
+    };
+    app.commands.push(raw.to_string_lossy().into_owned());
+    let raw = match split_job_header(&raw) {
+        Ok(None) => raw,
+        Ok(Some((header, command))) => {
+            if let Some((job, outcome)) = app.job_outcomes.blocker(&header) {
+                let reason = describe_blocker(job, outcome);
+                app.job_outcomes.record(&header.name, Outcome::Skipped);
+                app.job_status = Some(JobStatus::Skipped);
+                app.push_history(HistoryEntry::Warning(format!(
+                    "Skipped job '{}': {reason}",
+                    header.name
+                )));
+                return;
+            }
+            app.named_job = Some((header.name, app.errors.len()));
+            command
+        }
+        Err(err) => {
+            app.push_history(HistoryEntry::Error(format!("error: {err}")));
+            return;
+        }
+    };
+    let next = strip_next_marker(&raw);
+    let to_front = next.is_some();
+    let (priority, raw) = match split_priority(&next.unwrap_or(raw)) {
+        Ok(split) => split,
+        Err(err) => {
+            app.push_history(HistoryEntry::Error(format!("error: {err}")));
+            return;
+        }
+    };
+    if let Some(rest) = strip_at_prefix(&raw) {
+        handle_at(app, &rest, priority);
+        return;
+    }
+    let raw = match expand_alias(&raw, &app.config.aliases) {
+        Ok(raw) => raw,
+        Err(err) => {
+            app.push_history(HistoryEntry::Error(format!("error: {err}")));
+            return;
+        }
+    };
+    let text = raw.to_string_lossy().into_owned();
+    let trimmed = text.as_str();
+    app.last_command = Some(trimmed.to_string());
+    if let Some(ladder) = &mut app.ladder {
+        ladder.current = ladder.plan.rungs.iter().position(|planned| planned.line == raw);
+    }
+
+    if trimmed.eq_ignore_ascii_case("quit") || trimmed.eq_ignore_ascii_case("exit") {
+        app.should_quit = true;
+        return;
+    }
+
+    if trimmed.eq_ignore_ascii_case("clear") {
+        app.history.clear();
+        app.scroll_bottom();
+        return;
+    }
+
+    if trimmed.eq_ignore_ascii_case("help") {
+        app.push_history(HistoryEntry::Info("Commands:".into()));
+        app.push_history(HistoryEntry::Info("  encode -i <input> -o <output> [--vcodec ...] [--acodec ...] [--preset ...] [--profile ...]".into()));
+        app.push_history(HistoryEntry::Info(
+            "  profile <name> -i <input> -o <output>".into(),
+        ));
+        app.push_history(HistoryEntry::Info("  probe -i <input>".into()));
+        app.push_history(HistoryEntry::Info(
+            "  normalize -i <input> -o <output> [--target -16] [--lra 11] [--tp -1.5]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  split -i <input> -o <name_%03d.ext> (--every|--segment-time <time> | --size <size>)".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  stabilize -i <input> -o <output> [--shakiness 5] [--smoothing 10]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  thumbnail -i <input> -o <image> [--at <time>]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  gif -i <input> -o <out.gif> [--fps <n>] [--width <px>]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  cropdetect -i <input> [--sample 60] [--apply -o <output>]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  extract-audio -i <input> -o <output> [--acodec <codec>]".into(),
+        ));
+        app.push_history(HistoryEntry::Info("  remux -i <input> -o <output>".into()));
+        app.push_history(HistoryEntry::Info("  chapters -i <input> [--export <file>]".into()));
+        app.push_history(HistoryEntry::Info(
+            "  tag -i <input> (-o <output> [--set key=value] [--clear-all] [--cover <image>] | --show)".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  loop -i <input> -o <output> [--times <n>] [--reverse | --boomerang]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  speed -i <input> -o <output> --factor <0.25-100> [--mute]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  record --from <url|device> [-f <format>] --for <time> -o <output>".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  watermark -i <input> -w <logo> -o <output> [--position <corner>] [--opacity <0-1>] [--scale <fraction>]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  compare --ref <original> --dist <encode> [--json <path>]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  ladder -i <input> --out-dir <dir> [--rungs 1080p:5000k,720p:3000k,...]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  hls -i <input> --out-dir <dir> [--segment-time 6] [--variant 720p:3000k,...]".into(),
+        ));
+        app.push_history(HistoryEntry::Info("  info [-i <input>]".into()));
+        app.push_history(HistoryEntry::Info("  presets".into()));
+        app.push_history(HistoryEntry::Info(
+            "  wizard - build an encode by answering a few questions".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  encoders [text] [--type video|audio|subtitle] / formats [text] / filters [text]".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  doctor - check ffmpeg, ffprobe and the optional codecs ffflow can use".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  version - ffflow and ffmpeg versions, and how ffmpeg was built".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  <command>! or <command> --next - run it right after the current job".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  queue / queue promote <n> - list the waiting jobs, or move one to the front".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  queue remove <n> / queue move <from> <to> - drop or reorder a waiting job".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  at <HH:MM | YYYY-MM-DDTHH:MM> <command> / at cancel <n> - run a command later".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  resume / skip - restart a paused queue, skip also drops the next job".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  history / !! / !<n> / !?<text> - list or re-run earlier commands".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  !e (or redo --edit) - put the last command on the input line to edit".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  alias [<name> = <command>] / unalias <name> - short names for command lines".into(),
+        ));
+        app.push_history(HistoryEntry::Info("  ffmpeg <args...>".into()));
+        app.push_history(HistoryEntry::Info("  batch <file.flw>".into()));
+        app.push_history(HistoryEntry::Info(
+            "  watch <job> - re-run the job whenever its input changes (Ctrl+W stops)".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  logs (or F2) - toggle the raw ffmpeg log".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  Ctrl+P - pause/resume the running job".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  Ctrl+Y - copy the last command line".into(),
+        ));
+        app.push_history(HistoryEntry::Info("  set logfile <path|off>".into()));
+        app.push_history(HistoryEntry::Info(
+            "  set preflight <on|off> - check free space before each job".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  set expand <on|off> - expand ~ and $VARIABLES in ffmpeg lines too".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  set verbose <on|off> - also show ffmpeg's banner and encoder stats lines".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  set verify <on|full|off> - check each job's output after it succeeds".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  set scrollback <n> - history lines kept on screen".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  set nice <0-19> - run ffmpeg at lower CPU priority".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  set mouse <on|off> - wheel scrolling, or off for the terminal's own selection".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  set on-error <continue|stop|pause> - what the queue does when a job fails".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  save <path> - write the full history to a file".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  report <path.json|path.csv> - save this session's job results".into(),
+        ));
+        app.push_history(HistoryEntry::Info(
+            "  stats - bytes written, media processed and time spent this session".into(),
+        ));
+        app.push_history(HistoryEntry::Info("  clear / exit".into()));
+        app.push_history(HistoryEntry::Info("Profiles:".into()));
+        for profile in &core::profile::PROFILES {
+            app.push_history(HistoryEntry::Info(format!(
+                "  {:<10} {}",
+                profile.name, profile.description
+            )));
+        }
+        return;
+    }
+
+    if trimmed == "stats" {
+        for line in format_session_stats(&app.stats) {
+            app.push_history(HistoryEntry::Info(line));
+        }
+        return;
+    }
+
+    if trimmed == "history" {
+        let lines: Vec<String> = app
+            .commands
+            .iter()
+            .enumerate()
+            .map(|(index, command)| format!("{:>5}  {command}", index + 1))
+            .collect();
+        for line in lines {
+            app.push_history(HistoryEntry::Info(line));
+        }
+        return;
+    }
+
+    if trimmed == "queue" || trimmed == "queue list" {
+        if app.job_queue.is_empty() && app.schedule.is_empty() {
+            app.push_history(HistoryEntry::Info("The queue is empty.".into()));
+        }
+        let mut lines: Vec<String> = app
+            .job_queue
+            .iter()
+            .enumerate()
+            .map(|(index, job)| {
+                // Jobs after one that failed will be skipped when they come up.
+                let blocked = split_job_header(&job.line)
+                    .ok()
+                    .flatten()
+                    .and_then(|(header, _)| match app.job_outcomes.blocker(&header) {
+                        Some((job, Some(outcome))) => {
+                            Some(format!("  [skipped: {}]", describe_blocker(job, Some(outcome))))
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                format!(
+                    "{:>5}  [priority {}]  {}{blocked}",
+                    index + 1,
+                    job.priority,
+                    job.line.to_string_lossy()
+                )
+            })
+            .collect();
+        if !app.schedule.is_empty() {
+            lines.push("Scheduled (at cancel <n> removes one):".into());
+            let now = SystemTime::now();
+            lines.extend(app.schedule.iter().enumerate().map(|(index, job)| {
+                let left = job.at.duration_since(now).unwrap_or_default();
+                format!(
+                    "{:>5}  at {} (in {})  {}",
+                    index + 1,
+                    format_start_time(job.at),
+                    format_duration(left),
+                    job.line.to_string_lossy()
+                )
+            }));
+        }
+        for line in lines {
+            app.push_history(HistoryEntry::Info(line));
+        }
+        return;
+    }
+
+    if let Some(number) = trimmed.strip_prefix("queue promote ") {
+        let promoted = queue_index(app, number)
+            .and_then(|index| app.job_queue.promote(index))
+            .map(|job| job.line.to_string_lossy().into_owned());
+        if let Some(line) = promoted {
+            app.push_history(HistoryEntry::Info(format!("Next up: {line}")));
+        }
+        return;
+    }
+
+    if let Some(number) = trimmed.strip_prefix("queue remove ") {
+        let removed = queue_index(app, number).and_then(|index| app.job_queue.remove(index));
+        if let Some(job) = removed {
+            app.push_history(HistoryEntry::Info(format!(
+                "Removed: {}",
+                job.line.to_string_lossy()
+            )));
+        }
+        return;
+    }
+
+    if let Some(numbers) = trimmed.strip_prefix("queue move ") {
+        let Some((from, to)) = numbers.trim().split_once(char::is_whitespace) else {
+            app.push_history(HistoryEntry::Error(
+                "error: expected queue move <from> <to>".into(),
+            ));
+            return;
+        };
+        let moved = queue_index(app, from)
+            .and_then(|from| Some((from, queue_index(app, to)?)))
+            .and_then(|(from, to)| app.job_queue.move_to(from, to))
+            .map(|job| job.line.to_string_lossy().into_owned());
+        if let Some(line) = moved {
+            app.push_history(HistoryEntry::Info(format!(
+                "Moved to {}: {line}",
+                to.trim()
+            )));
+        }
+        return;
+    }
+
+    if trimmed == "alias" {
+        if app.config.aliases.is_empty() {
+            app.push_history(HistoryEntry::Info(
+                "No aliases yet; define one with alias <name> = <command>.".into(),
+            ));
+        }
+        let lines: Vec<String> = app
+            .config
+            .aliases
+            .iter()
+            .map(|(name, expansion)| format!("  {name} = {expansion}"))
+            .collect();
+        for line in lines {
+            app.push_history(HistoryEntry::Info(line));
+        }
+        return;
+    }
+
+    if let Some(definition) = trimmed.strip_prefix("alias ") {
+        let defined = parse_alias_definition(definition)
+            .and_then(|(name, expansion)| {
+                cli::check_alias_name(&name, TUI_COMMANDS)?;
+                Ok((name, expansion))
+            });
+        match defined {
+            Ok((name, expansion)) => {
+                app.push_history(HistoryEntry::Info(format!("Alias {name} = {expansion}")));
+                app.config.aliases.insert(name, expansion);
+                app.save_config();
+            }
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+        }
+        return;
+    }
+
+    if let Some(name) = trimmed.strip_prefix("unalias ") {
+        let name = name.trim();
+        if app.config.aliases.remove(name).is_some() {
+            app.push_history(HistoryEntry::Info(format!("Removed alias {name}.")));
+            app.save_config();
+        } else {
+            app.push_history(HistoryEntry::Error(format!("error: no alias named '{name}'")));
+        }
+        return;
+    }
+
+    if let Some(path_str) = strip_prefix_os(&raw, "batch ") {
+        let path = PathBuf::from(trim_os(&path_str));
+        match core::batch::parse_flw_file(&path) {
+            Ok(commands) => {
+                let count = commands.len();
+                let listed: Vec<String> = commands
+                    .iter()
+                    .map(|command| command.to_string_lossy().into_owned())
+                    .collect();
+                app.log_batch(&listed);
+                app.job_queue.extend(commands);
+                app.push_history(HistoryEntry::Info(format!(
+                    "Loaded {} jobs from '{}'.",
+                    count,
+                    path.display()
+                )));
+            }
+            Err(e) => {
+                app.push_history(HistoryEntry::Error(format!(
+                    "error reading batch file: {}",
+                    e
+                )));
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = strip_prefix_os(&raw, "set logfile ") {
+        let path = trim_os(&path);
+        if path == "off" {
+            app.session_log = None;
+            app.push_history(HistoryEntry::Info("Session log closed.".into()));
+        } else {
+            let path = PathBuf::from(path);
+            app.session_log = None;
+            app.open_session_log(&path);
+            if app.session_log.is_some() {
+                app.push_history(HistoryEntry::Info(format!(
+                    "Logging session to '{}'.",
+                    path.display()
+                )));
+            }
         }
+        return;
+    }
+
+    if let Some(value) = trimmed.strip_prefix("set preflight ") {
+        match value.trim() {
+            "on" => {
+                app.preflight = true;
+                app.push_history(HistoryEntry::Info("Checking free space before each job.".into()));
+            }
+            "off" => {
+                app.preflight = false;
+                app.push_history(HistoryEntry::Info("Free-space check turned off.".into()));
+            }
+            other => app.push_history(HistoryEntry::Error(format!(
+                "error: invalid preflight setting '{other}' (expected on or off)"
+            ))),
+        }
+        return;
+    }
+
+    if let Some(value) = trimmed.strip_prefix("set verbose ") {
+        match value.trim() {
+            "on" => {
+                set_verbose(true);
+                app.push_history(HistoryEntry::Info(
+                    "Showing every ffmpeg line, banners and encoder stats included, from the next job.".into(),
+                ));
+            }
+            "off" => {
+                set_verbose(false);
+                app.push_history(HistoryEntry::Info("Hiding ffmpeg's noise lines again.".into()));
+            }
+            other => app.push_history(HistoryEntry::Error(format!(
+                "error: invalid verbose setting '{other}' (expected on or off)"
+            ))),
+        }
+        return;
+    }
+
+    if let Some(value) = trimmed.strip_prefix("set expand ") {
+        match value.trim() {
+            "on" => {
+                set_expand_passthrough(true);
+                app.push_history(HistoryEntry::Info(
+                    "Expanding ~ and $VARIABLES in ffmpeg lines too.".into(),
+                ));
+            }
+            "off" => {
+                set_expand_passthrough(false);
+                app.push_history(HistoryEntry::Info("ffmpeg lines are passed as typed.".into()));
+            }
+            other => app.push_history(HistoryEntry::Error(format!(
+                "error: invalid expand setting '{other}' (expected on or off)"
+            ))),
+        }
+        return;
+    }
+
+    let on_error = trimmed
+        .strip_prefix("set on-error ")
+        .map(|value| value.trim().parse::<OnError>())
+        .or_else(|| parse_on_error_directive(trimmed));
+    if let Some(on_error) = on_error {
+        match on_error {
+            Ok(on_error) => {
+                app.on_error = on_error;
+                app.push_history(HistoryEntry::Info(format!(
+                    "When a job fails, the queue will {}.",
+                    match on_error {
+                        OnError::Continue => "go on with the next job",
+                        OnError::Stop => "be dropped",
+                        OnError::Pause => "pause until you type 'resume'",
+                    }
+                )));
+            }
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+        }
+        return;
+    }
+
+    if trimmed == "resume" || trimmed == "skip" {
+        if !app.queue_paused {
+            app.push_history(HistoryEntry::Info("The queue is not paused.".into()));
+            return;
+        }
+        if trimmed == "skip" {
+            if let Some(line) = app.job_queue.pop_front() {
+                app.push_history(HistoryEntry::Info(format!(
+                    "Skipped: {}",
+                    line.to_string_lossy()
+                )));
+            }
+        }
+        app.queue_paused = false;
+        app.push_history(HistoryEntry::Info(format!(
+            "Resuming the queue ({} waiting).",
+            app.job_queue.len()
+        )));
+        return;
+    }
+
+    if let Some(value) = trimmed.strip_prefix("set verify ") {
+        let (verify, message) = match value.trim() {
+            "on" | "quick" => (Some(VerifyMode::Quick), "Checking each job's output after it succeeds."),
+            "full" => (Some(VerifyMode::Full), "Checking and fully decoding each job's output after it succeeds."),
+            "off" => (None, "Output check turned off."),
+            other => {
+                app.push_history(HistoryEntry::Error(format!(
+                    "error: invalid verify setting '{other}' (expected on, full or off)"
+                )));
+                return;
+            }
+        };
+        app.verify = verify;
+        app.push_history(HistoryEntry::Info(message.into()));
+        return;
+    }
+
+    if let Some(value) = trimmed.strip_prefix("set nice ") {
+        match value.trim().parse::<i32>() {
+            Ok(nice) if (0..=MAX_NICENESS).contains(&nice) => {
+                set_niceness(nice);
+                let message = match nice {
+                    0 => "Running ffmpeg at normal priority.".to_string(),
+                    nice => format!("Running ffmpeg at niceness {nice} from the next job."),
+                };
+                app.push_history(HistoryEntry::Info(message));
+            }
+            _ => app.push_history(HistoryEntry::Error(format!(
+                "error: invalid niceness '{}' (expected 0 to {MAX_NICENESS})",
+                value.trim()
+            ))),
+        }
+        return;
+    }
+
+    if let Some(value) = trimmed.strip_prefix("set mouse ") {
+        let mut stdout = io::stdout();
+        let (result, message) = match value.trim() {
+            "on" => (stdout.execute(EnableMouseCapture).map(drop), "Mouse wheel scrolls the history."),
+            "off" => (
+                stdout.execute(DisableMouseCapture).map(drop),
+                "Mouse capture off; the terminal selects text again.",
+            ),
+            other => {
+                app.push_history(HistoryEntry::Error(format!(
+                    "error: invalid mouse setting '{other}' (expected on or off)"
+                )));
+                return;
+            }
+        };
+        app.push_history(match result {
+            Ok(()) => HistoryEntry::Info(message.into()),
+            Err(err) => HistoryEntry::Error(format!("error: cannot change mouse capture: {err}")),
+        });
+        return;
+    }
+
+    if let Some(value) = trimmed.strip_prefix("set scrollback ") {
+        match value.trim().parse::<usize>() {
+            Ok(lines) if lines > 0 => {
+                app.set_history_cap(lines);
+                app.push_history(HistoryEntry::Info(format!(
+                    "Keeping the last {lines} history lines on screen."
+                )));
+            }
+            _ => app.push_history(HistoryEntry::Error(format!(
+                "error: invalid scrollback '{}' (expected a line count)",
+                value.trim()
+            ))),
+        }
+        return;
+    }
+
+    if let Some(path) = strip_prefix_os(&raw, "save ") {
+        let path = PathBuf::from(trim_os(&path));
+        let lines: Vec<&str> = app.archive.iter().map(String::as_str).collect();
+        let text = format!("{}\n", lines.join("\n"));
+        let entry = match std::fs::write(&path, text) {
+            Ok(()) => HistoryEntry::Info(format!(
+                "Saved {} history lines to '{}'.",
+                app.archive.len(),
+                path.display()
+            )),
+            Err(err) => HistoryEntry::Error(format!(
+                "error: cannot write '{}': {err}",
+                path.display()
+            )),
+        };
+        app.push_history(entry);
+        return;
+    }
+
+    if let Some(path) = strip_prefix_os(&raw, "report ") {
+        let path = PathBuf::from(trim_os(&path));
+        let entry = match write_reports(&path, &app.reports) {
+            Ok(()) => HistoryEntry::Info(format!(
+                "Wrote {} job(s) to '{}'.",
+                app.reports.len(),
+                path.display()
+            )),
+            Err(err) => HistoryEntry::Error(format!(
+                "error: cannot write '{}': {err}",
+                path.display()
+            )),
+        };
+        app.push_history(entry);
+        return;
+    }
+
+    if trimmed.eq_ignore_ascii_case("logs") {
+        app.log_pane.visible = !app.log_pane.visible;
+        return;
+    }
 
-        if app.should_quit {
-            break;
-        }
+    if trimmed.eq_ignore_ascii_case("wizard") {
+        app.wizard = Some(Wizard::default());
+        app.push_history(HistoryEntry::Info(
+            "Building an encode. Enter takes the [default], Esc cancels.".into(),
+        ));
+        prompt_wizard(app);
+        return;
     }
 
-    Ok(())
-}
+    if trimmed.eq_ignore_ascii_case("presets") {
+        for preset in cli::PRESETS {
+            app.push_history(HistoryEntry::Info(preset.to_string()));
+        }
+        return;
+    }
 
-fn handle_line(
-    app: &mut AppState,
-    line: String,
-    event_tx: mpsc::Sender<FfmpegEvent>,
-    job_tx: mpsc::Sender<JobStatus>,
-) {
-    let trimmed = line.trim();
-    if !app.history.is_empty() {
-        app.push_history(DIVIDER_MARKER);
+    if app.job_running {
+        if to_front {
+            app.push_history(HistoryEntry::Info(format!("Queued next: {trimmed}")));
+            app.job_queue.push_front(raw);
+        } else if priority != 0 {
+            app.push_history(HistoryEntry::Info(format!(
+                "Queued at priority {priority}: {trimmed}"
+            )));
+            app.job_queue.push_with_priority(raw, priority);
+        } else {
+            app.push_history(HistoryEntry::Info(
+                "A job is already running. Add --next to run this one after it.".into(),
+            ));
+        }
+        return;
     }
-    app.push_history(format!(">> {trimmed}"));
 
-    if trimmed.eq_ignore_ascii_case("quit") || trimmed.eq_ignore_ascii_case("exit") {
-        app.should_quit = true;
+    let lines = cli::fan_out_line(&raw);
+    if lines.len() > 1 {
+        app.push_history(HistoryEntry::Info(format!(
+            "Queued {} jobs, one per matching file.",
+            lines.len()
+        )));
+        for line in lines.into_iter().rev() {
+            app.job_queue.push_front(line);
+        }
         return;
     }
 
-    if trimmed.eq_ignore_ascii_case("clear") {
-        app.history.clear();
-        app.scroll_bottom();
+    if trimmed.eq_ignore_ascii_case("info") {
+        match app.inputs.first().and_then(|info| info.path.clone()) {
+            Some(path) => {
+                let args = cli::info_args_to_argv(cli::InfoArgs { input: path.into() });
+                start_job(app, args, event_tx, job_tx);
+            }
+            None => app.push_history(HistoryEntry::Error(
+                "error: no current input; use info -i <input>".into(),
+            )),
+        }
         return;
     }
 
-    if trimmed.eq_ignore_ascii_case("help") {
-        app.push_history("Commands:".to_string());
-        app.push_history("  encode -i <input> -o <output> [--vcodec ...] [--acodec ...] [--preset ...]".to_string());
-        app.push_history("  probe -i <input>".to_string());
-        app.push_history("  presets".to_string());
-        app.push_history("  presets".to_string());
-        app.push_history("  ffmpeg <args...>".to_string());
-        app.push_history("  batch <file.flw>".to_string());
-        app.push_history("  clear / exit".to_string());
+    if let Ok(cli::Commands::Chapters(args)) = cli::parse_line(&raw) {
+        match cli::chapter_lines(&args) {
+            Ok(lines) => {
+                for line in lines {
+                    app.push_history(HistoryEntry::Info(line));
+                }
+            }
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+        }
         return;
     }
 
-    if let Some(path_str) = trimmed.strip_prefix("batch ") {
-        let path = std::path::Path::new(path_str.trim());
-        match core::batch::parse_flw_file(path) {
-            Ok(commands) => {
-                let count = commands.len();
-                app.job_queue.extend(commands);
-                app.push_history(format!("Loaded {} jobs from '{}'.", count, path.display()));
+    let listing = match cli::parse_line(&raw) {
+        Ok(cli::Commands::Encoders(args)) => Some(cli::encoder_lines(&args)),
+        Ok(cli::Commands::Formats(args)) => Some(cli::format_lines(&args)),
+        Ok(cli::Commands::Filters(args)) => Some(cli::filter_lines(&args)),
+        Ok(cli::Commands::Doctor) => Some(Ok(cli::doctor_lines())),
+        Ok(cli::Commands::Version) => Some(Ok(cli::version_lines())),
+        _ => None,
+    };
+    if let Some(listing) = listing {
+        match listing {
+            Ok(lines) => {
+                for line in lines {
+                    app.push_history(HistoryEntry::Info(line));
+                }
             }
-            Err(e) => {
-                app.push_history(format!("error reading batch file: {}", e));
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+        }
+        return;
+    }
+
+    if let Ok(cli::Commands::Tag(args)) = cli::parse_line(&raw) {
+        if args.show {
+            match cli::tag_lines(&args.input) {
+                Ok(lines) => {
+                    for line in lines {
+                        app.push_history(HistoryEntry::Info(line));
+                    }
+                }
+                Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
             }
+            return;
         }
+    }
+
+    if let Ok(cli::Commands::Cropdetect(args)) = cli::parse_line(&raw) {
+        let sample = Duration::from_secs(args.sample.into());
+        start_job(app, cropdetect_args(&args.input, sample), event_tx, job_tx);
+        app.cropdetect = Some(CropdetectRun {
+            args,
+            detector: CropDetector::new(),
+        });
         return;
     }
 
-    if trimmed.eq_ignore_ascii_case("presets") {
-        for preset in cli::PRESETS {
-            app.push_history(preset);
+    if let Ok(cli::Commands::Compare(args)) = cli::parse_line(&raw) {
+        let (job, warning) = cli::compare_args_to_job(&args);
+        if let Some(warning) = warning {
+            app.push_history(HistoryEntry::Warning(warning));
         }
+        start_job(app, job.args(), event_tx, job_tx);
+        app.compare = Some(CompareRun {
+            job,
+            json: args.json,
+            parser: QualityParser::new(),
+        });
         return;
     }
 
-    if app.job_running {
-        app.push_history("A job is already running. Please wait for it to finish.".to_string());
+    if let Ok(cli::Commands::Hls(args)) = cli::parse_line(&raw) {
+        match cli::hls_args_to_job(args) {
+            Ok((job, notes)) => {
+                for note in notes {
+                    app.push_history(HistoryEntry::Warning(note));
+                }
+                start_job(app, job.args(), event_tx, job_tx);
+                app.hls = Some(job);
+            }
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+        }
         return;
     }
 
-    if let Some(rest) = trimmed.strip_prefix("ffmpeg ") {
-        match shell_words::split(rest) {
-            Ok(args) => {
-                if args.is_empty() {
-                    app.push_history("error: ffmpeg requires arguments".to_string());
-                    return;
+    if let Ok(cli::Commands::Split(args)) = cli::parse_line(&raw) {
+        match cli::split_args_to_job(args) {
+            Ok((job, notes)) => {
+                for note in notes {
+                    app.push_history(HistoryEntry::Warning(note));
                 }
-                app.duration = parse_duration_from_args(&args);
-                app.job_running = true;
-                app.job_status = Some(JobStatus::Running);
-                app.progress = None;
-                app.last_progress_line = None;
-                app.last_error = None;
-
-                let (rx, tx) = core::runner::run_args_with_events(args);
-                app.stdin_tx = Some(tx);
-
-                std::thread::spawn(move || {
-                    let mut had_error = false;
-                    for event in rx {
-                        if matches!(event, FfmpegEvent::Error(_)) {
-                            had_error = true;
-                        }
-                        let _ = event_tx.send(event);
-                    }
-                    let status = if had_error {
-                        JobStatus::Failed
-                    } else {
-                        JobStatus::Finished
-                    };
-                    let _ = job_tx.send(status);
+                start_job(app, job.args(), event_tx, job_tx);
+                app.split = Some(job);
+            }
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+        }
+        return;
+    }
+
+    if let Ok(cli::Commands::Ladder(args)) = cli::parse_line(&raw) {
+        if app.ladder.is_some() {
+            app.push_history(HistoryEntry::Error(
+                "error: a ladder is already queued".into(),
+            ));
+            return;
+        }
+        match cli::ladder_args_to_plan(args) {
+            Ok(plan) => {
+                for note in &plan.notes {
+                    app.push_history(HistoryEntry::Warning(note.clone()));
+                }
+                let lines: Vec<OsString> = plan.rungs.iter().map(|planned| planned.line.clone()).collect();
+                let listed: Vec<String> = lines
+                    .iter()
+                    .map(|line| line.to_string_lossy().into_owned())
+                    .collect();
+                app.log_batch(&listed);
+                app.push_history(HistoryEntry::Info(format!(
+                    "{}: queued {} rungs",
+                    plan.label,
+                    lines.len()
+                )));
+                app.job_queue.extend(lines);
+                app.ladder = Some(LadderRun {
+                    results: vec![None; plan.rungs.len()],
+                    current: None,
+                    plan,
                 });
             }
-            Err(err) => {
-                app.push_history(format!("error: {err}"));
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
+        }
+        return;
+    }
+
+    if let Ok(cli::Commands::Normalize(args)) = cli::parse_line(&raw) {
+        let job = cli::normalize_args_to_job(args);
+        start_job(app, job.measure_args(), event_tx, job_tx);
+        app.pass_tracker = Some(PassTracker::new(1, 2));
+        app.normalize = Some(NormalizeRun {
+            job,
+            parser: LoudnormParser::new(),
+            applying: false,
+        });
+        return;
+    }
+
+    if let Ok(cli::Commands::Stabilize(args)) = cli::parse_line(&raw) {
+        match cli::stabilize_args_to_job(args) {
+            Ok(job) => {
+                start_job(app, job.detect_args(), event_tx, job_tx);
+                app.pass_tracker = Some(PassTracker::new(1, 2));
+                app.report_output = Some(job.output.clone());
+                app.stabilize = Some(StabilizeRun {
+                    job,
+                    transforming: false,
+                });
             }
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
         }
         return;
     }
 
-    match cli::parse_line(trimmed) {
-        Ok(Commands::Encode(args)) => {
-            let cmd = cli::encode_args_to_command(args);
-            app.duration = parse_duration_from_args(&cmd.extra_args);
-            app.job_running = true;
-            app.job_status = Some(JobStatus::Running);
-            app.progress = None;
-            app.last_progress_line = None;
-            app.last_error = None;
-            
-            let (rx, tx) = core::run_with_events(cmd);
-            app.stdin_tx = Some(tx);
-
-            std::thread::spawn(move || {
-                let mut had_error = false;
-                for event in rx {
-                    if matches!(event, FfmpegEvent::Error(_)) {
-                        had_error = true;
+    if let Some(rest) = strip_prefix_os(&raw, "watch ") {
+        let line = trim_os(&rest);
+        match cli::job_args_from_line(&line) {
+            Ok(job) => {
+                let input = job
+                    .args
+                    .windows(2)
+                    .find(|pair| pair[0] == "-i")
+                    .map(|pair| PathBuf::from(&pair[1]));
+                match input.filter(|_| job.concat_list.is_none()) {
+                    Some(input) => {
+                        // Replaces any earlier watch.
+                        app.watch = Some(WatchRun {
+                            line,
+                            input,
+                            watcher: None,
+                            running: true,
+                        });
+                        start_job_args(app, job, event_tx, job_tx);
                     }
-                    let _ = event_tx.send(event);
+                    None => app.push_history(HistoryEntry::Error(
+                        "error: watch needs a job with a single input file".into(),
+                    )),
                 }
-                let status = if had_error {
-                    JobStatus::Failed
-                } else {
-                    JobStatus::Finished
-                };
-                let _ = job_tx.send(status);
-            });
+            }
+            Err(err) => app.push_history(HistoryEntry::Error(format!("error: {err}"))),
         }
-        Ok(Commands::Probe(args)) => {
-            let cmd = cli::probe_args_to_command(args);
-            app.duration = parse_duration_from_args(&cmd.extra_args);
-            app.job_running = true;
-            app.job_status = Some(JobStatus::Running);
-            app.progress = None;
-            app.last_progress_line = None;
-            app.last_error = None;
-
-            let (rx, tx) = core::run_with_events(cmd);
-            app.stdin_tx = Some(tx);
-
-            std::thread::spawn(move || {
-                let mut had_error = false;
-                for event in rx {
-                    if matches!(event, FfmpegEvent::Error(_)) {
-                        had_error = true;
-                    }
-                    let _ = event_tx.send(event);
-                }
-                let status = if had_error {
-                    JobStatus::Failed
-                } else {
-                    JobStatus::Finished
-                };
-                let _ = job_tx.send(status);
-            });
+        return;
+    }
+
+    if let Some(message) = cli::unknown_command(&raw, TUI_COMMANDS) {
+        app.push_history(HistoryEntry::Error(format!("error: {message}")));
+        return;
+    }
+
+    match cli::job_args_from_line(&raw) {
+        Ok(job) => start_job_args(app, job, event_tx, job_tx),
+        Err(err) => {
+            app.push_history(HistoryEntry::Error(format!("error: {err}")));
+        }
+    }
+}
+
+/// Starts a job line's ffmpeg run, after showing the notes made while
+/// building it.
+fn start_job_args(
+    app: &mut AppState,
+    mut job: cli::JobArgs,
+    event_tx: mpsc::Sender<FfmpegEvent>,
+    job_tx: mpsc::Sender<JobStatus>,
+) {
+    if writes_to_stdout(&job.args) {
+        app.push_history(HistoryEntry::Error(
+            "error: output to stdout only works with --stdin, not in the TUI".into(),
+        ));
+        return;
+    }
+    for note in std::mem::take(&mut job.notes) {
+        app.push_history(HistoryEntry::Warning(note));
+    }
+    if let Some(existing) = existing_output(&job.args) {
+        let rename = next_free_name(&existing);
+        app.push_history(HistoryEntry::Prompt(format!(
+            "PROMPT: '{}' already exists.",
+            existing.display()
+        )));
+        app.push_history(HistoryEntry::Prompt(format!(
+            ">> Press 'y' to overwrite, 'n' to skip, or 'r' to save as '{}'.",
+            rename.display()
+        )));
+        app.job_status = Some(JobStatus::AwaitingConfirmation);
+        app.held = Some(HeldJob::OutputExists { job, rename });
+        return;
+    }
+    let check = app
+        .preflight
+        .then(|| preflight(&SystemFs, &job.args, job.duration))
+        .flatten();
+    match check {
+        Some(check @ SpaceCheck::Low { .. }) => {
+            if let Some(message) = check.message() {
+                app.push_history(HistoryEntry::Warning(format!("warning: {message}")));
+            }
+        }
+        Some(check @ SpaceCheck::Short { .. }) => {
+            if let Some(message) = check.message() {
+                app.push_history(HistoryEntry::Prompt(format!("PROMPT: {message}")));
+            }
+            app.push_history(HistoryEntry::Prompt(
+                ">> Press 'y' to start anyway or 'n' to skip it.".into(),
+            ));
+            app.job_status = Some(JobStatus::AwaitingConfirmation);
+            app.held = Some(HeldJob::LowSpace(job));
+            return;
         }
-        Ok(Commands::Presets) => {
-            for preset in cli::PRESETS {
-                app.push_history(preset);
+        Some(SpaceCheck::Enough) | None => {}
+    }
+    launch_job(app, job, event_tx, job_tx);
+}
+
+/// The output file of `args` when it is already on disk and ffmpeg would
+/// stop to ask about it (no `-y` or `-n`).
+fn existing_output(args: &[OsString]) -> Option<PathBuf> {
+    if args.iter().any(|a| a == "-y" || a == "-n") {
+        return None;
+    }
+    let output = PathBuf::from(args.last()?);
+    let protocol = output.to_str().is_some_and(|s| s.contains("://"));
+    (!protocol && output.is_file()).then_some(output)
+}
+
+/// `start_job_args` once any notes are shown and the disk check passed.
+fn launch_job(
+    app: &mut AppState,
+    job: cli::JobArgs,
+    event_tx: mpsc::Sender<FfmpegEvent>,
+    job_tx: mpsc::Sender<JobStatus>,
+) {
+    app.concat_list = job.concat_list;
+    app.report_output = job.report_output;
+    app.job_timeout = job.timeout;
+    app.verifying = job.verify.or(app.verify).and_then(|mode| {
+        Some(VerifyRun {
+            mode,
+            output: verify_target(&job.args)?,
+            decoding: false,
+            duration: None,
+            findings: Vec::new(),
+            report: None,
+        })
+    });
+    start_job(app, job.args, event_tx, job_tx);
+    if let Some(duration) = job.duration {
+        app.duration = Some(duration);
+        app.duration_fixed = true;
+    }
+}
+
+/// Re-runs the watched job once its input has changed and settled. The
+/// output from the last run is replaced without asking.
+fn rerun_watch(
+    app: &mut AppState,
+    event_tx: mpsc::Sender<FfmpegEvent>,
+    job_tx: mpsc::Sender<JobStatus>,
+) {
+    let Some(watch) = app.watch.as_mut() else {
+        return;
+    };
+    if !watch.watcher.as_mut().is_some_and(InputWatcher::poll) {
+        return;
+    }
+    watch.running = true;
+    let line = watch.line.clone();
+    let input = watch.input.clone();
+    app.push_history(HistoryEntry::Divider);
+    app.push_history(HistoryEntry::Info(format!(
+        "'{}' changed; re-running.",
+        input.display()
+    )));
+    match cli::job_args_from_line(&line) {
+        Ok(mut job) => {
+            if !job.args.iter().any(|arg| arg == "-y" || arg == "-n") {
+                job.args.insert(0, "-y".into());
             }
+            start_job_args(app, job, event_tx, job_tx);
         }
         Err(err) => {
-            app.push_history(format!("error: {err}"));
+            app.watch = None;
+            app.push_history(HistoryEntry::Error(format!("error: {err}; stopped watching")));
         }
     }
 }
 
-fn render_header(app: &AppState, width: usize) -> Paragraph<'static> {
+fn start_job(
+    app: &mut AppState,
+    args: Vec<OsString>,
+    event_tx: mpsc::Sender<FfmpegEvent>,
+    job_tx: mpsc::Sender<JobStatus>,
+) {
+    app.duration = parse_duration_from_args(&args);
+    app.duration_fixed = app.duration.is_some();
+    app.input_duration = InputDuration::from_args(&args);
+    app.total_frames = sequence_total_frames(&args);
+    app.seek_offset = parse_seek_from_args(&args).unwrap_or_default();
+    app.pass_tracker = PassTracker::from_args(&args);
+    app.speed_trend.clear();
+    app.live = is_live_source(&args).then(LiveStats::default);
+    app.frame_counts.clear();
+    app.summary = None;
+    app.job_started_at = Some(Instant::now());
+    app.segments_opened = 0;
+    app.writing = None;
+    app.job_running = true;
+    app.job_status = Some(JobStatus::Running);
+    app.progress = None;
+    app.inputs.clear();
+    app.outputs.clear();
+    app.log_pane.clear();
+    app.last_progress_line = None;
+    app.failure = None;
+
+    let command_line: Vec<String> = args
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    app.log_record("job", &format!("start: ffmpeg {}", shell_words::join(&command_line)));
+
+    let handle = core::runner::run_args_with_timeout(args, app.job_timeout.take());
+    app.stdin_tx = Some(handle.stdin);
+    app.latest_progress = Some(handle.progress);
+    app.job_pid = Some(handle.pid);
+    let events = handle.events;
+
+    std::thread::spawn(move || {
+        let mut had_error = false;
+        for event in events {
+            if matches!(event, FfmpegEvent::Error(_)) {
+                had_error = true;
+            }
+            let _ = event_tx.send(event);
+        }
+        let status = if had_error {
+            JobStatus::Failed
+        } else {
+            JobStatus::Finished
+        };
+        let _ = job_tx.send(status);
+    });
+}
+
+fn render_header(app: &AppState, width: usize, compact: bool) -> Paragraph<'static> {
     let status = match app.job_status {
         Some(JobStatus::Pending) => "Pending",
         Some(JobStatus::Running) => "Running",
+        Some(JobStatus::Paused) => "Paused",
         Some(JobStatus::Finished) => "Finished",
         Some(JobStatus::Failed) => "Failed",
+        Some(JobStatus::VerificationFailed) => "Verification Failed",
+        Some(JobStatus::Interrupted) => "Interrupted",
+        Some(JobStatus::Skipped) => "Skipped",
         Some(JobStatus::AwaitingConfirmation) => "Awaiting Confirmation",
         None => "Idle",
     };
+    let status = if app.queue_paused {
+        format!("{status}, paused ({} failed)", app.failed_jobs)
+    } else {
+        status.to_string()
+    };
 
     let progress = match &app.progress {
-        Some(update) => format!(
-            "time={} frame={} speed={}x",
-            format_duration(update.time),
-            update.frame,
-            update.speed
-        ),
+        Some(update) => {
+            let trend = match update.speed {
+                Some(_) if app.job_running => format!(" {}", app.speed_trend.trend().arrow()),
+                _ => String::new(),
+            };
+            format!(
+                "time={} frame={} speed={}{trend}{}",
+                format_duration(update.time),
+                update.frame,
+                format_speed(update.speed),
+                format_frame_stats(update)
+            )
+        }
         None => "time=--:--:-- frame= speed=".to_string(),
     };
+    let progress = match &app.pass_tracker {
+        Some(tracker) => format!("{} {progress}", tracker.label(pass_ratio(app))),
+        None => progress,
+    };
+    let progress = match app.ladder.as_ref().and_then(LadderRun::label) {
+        Some(label) => format!("{label} {progress}"),
+        None => progress,
+    };
 
     let bar_width = width.saturating_sub(30).clamp(10, 40);
-    let progress_bar = render_progress_bar(app, bar_width);
+    // A live source has no length to measure against: show how long it
+    // has run, the bitrate lately and whether frames are being dropped.
+    let (progress_bar, progress) = match (&app.live, pass_ratio(app)) {
+        (Some(live), None) if app.job_running => {
+            let elapsed = app.job_started_at.map(|start| start.elapsed()).unwrap_or_default();
+            let mut progress = progress;
+            if let Some(kbps) = live.current_bitrate() {
+                progress.push_str(&format!(
+                    " {} {kbps:.0}kbits/s",
+                    sparkline(&live.bitrates(), bar_width)
+                ));
+            }
+            (format!("LIVE {}", format_duration(elapsed)), progress)
+        }
+        _ if width < MIN_BAR_WIDTH as usize => match overall_ratio(app).filter(|_| app.job_running) {
+            Some(ratio) => (format!("{:.0}%", ratio * 100.0), progress),
+            None => (String::new(), progress),
+        },
+        _ => (render_progress_bar(app, bar_width), progress),
+    };
+
+    // Counters that are still going up point at a timing problem now,
+    // not one that happened earlier in the job.
+    let mut growing = Vec::new();
+    if app.job_running && app.frame_counts.recent_drops() > 0 {
+        growing.push("dropping frames");
+    }
+    if app.job_running && app.frame_counts.recent_dups() > 0 {
+        growing.push("duplicating frames");
+    }
+    let warning = growing.join(", ");
+
+    // Live jobs already show it in place of the bar.
+    let elapsed = app
+        .job_started_at
+        .filter(|_| app.job_running && app.live.is_none())
+        .map(|started| format!("elapsed {}", format_duration(started.elapsed())));
+
+    if compact {
+        let elapsed = elapsed.unwrap_or_default();
+        let line = [status.as_str(), elapsed.as_str(), progress_bar.as_str(), progress.as_str(), warning.as_str()]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        return Paragraph::new(truncate_to_width(&line, width));
+    }
+
+    let mut status_line = vec![Span::raw("Status: "), Span::raw(status)];
+    if let Some(elapsed) = elapsed {
+        status_line.push(Span::raw(format!("  {elapsed}")));
+    }
+    if let Some(info) = app.inputs.first() {
+        status_line.push(Span::raw(format!("  {}", format_input_brief(info))));
+    }
+    if let Some(watcher) = app.watch.as_ref().and_then(|watch| watch.watcher.as_ref()) {
+        status_line.push(Span::raw(format!("  watching {}…", watcher.path().display())));
+    }
+    if let Some(path) = app.writing.as_ref().filter(|_| app.job_running) {
+        status_line.push(Span::raw(format!("  writing {path}…")));
+    }
+    if app.segments_opened > 1 {
+        status_line.push(Span::raw(format!(
+            "  segment {} written",
+            app.segments_opened - 1
+        )));
+    }
+    if app.stats.jobs > 0 {
+        status_line.push(Span::styled(
+            format!("  {}", format_session_brief(&app.stats)),
+            Style::default().add_modifier(Modifier::DIM),
+        ));
+    }
+
+    let mut progress_line = vec![Span::raw(progress_bar), Span::raw(" "), Span::raw(progress)];
+    if !warning.is_empty() {
+        progress_line.push(Span::styled(
+            format!("  {warning}"),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
 
-    let text = vec![
-        Line::from(vec![Span::raw("Status: "), Span::raw(status)]),
-        Line::from(vec![
-            Span::raw(progress_bar),
-            Span::raw(" "),
-            Span::raw(progress),
-        ]),
-    ];
+    let text = vec![Line::from(status_line), Line::from(progress_line)];
 
     Paragraph::new(text)
-        .block(Block::default().title("ffflow").borders(Borders::ALL))
+        .block(Block::default().title(header_title()).borders(Borders::ALL))
         .wrap(Wrap { trim: true })
 }
 
+/// `ffflow — ffmpeg 6.1.1` once the ffmpeg version is known.
+fn header_title() -> String {
+    match known_ffmpeg_version() {
+        Some(text) => {
+            let version = FfmpegVersion::parse(text).map_or_else(|| text.to_string(), |release| release.to_string());
+            format!("ffflow — ffmpeg {version}")
+        }
+        None => "ffflow".to_string(),
+    }
+}
+
 fn render_progress_bar(app: &AppState, width: usize) -> String {
     let width = width.max(10);
-    let mut bar = String::with_capacity(width + 2);
-    bar.push('[');
-
     if !app.job_running {
-        for _ in 0..width {
-            bar.push(' ');
-        }
-        bar.push(']');
-        return bar;
-    }
-
-    if let (Some(update), Some(total)) = (&app.progress, app.duration) {
-        let elapsed = update.time.as_secs_f64();
-        let total = total.as_secs_f64();
-        if total > 0.0 {
-            let ratio = (elapsed / total).clamp(0.0, 1.0);
-            let filled = ((ratio * width as f64).round() as usize).min(width);
-            for idx in 0..width {
-                if idx < filled {
-                    bar.push('=');
-                } else if idx == filled && filled < width {
-                    bar.push('>');
-                } else {
-                    bar.push(' ');
-                }
-            }
-            bar.push(']');
-            return bar;
-        }
+        return format!("[{}]", " ".repeat(width));
     }
 
+    if let Some(ratio) = overall_ratio(app) {
+        return progress_bar_string(ratio, width);
+    }
+
+    let mut bar = String::with_capacity(width + 2);
+    bar.push('[');
+
     let pos = (app.tick as usize) % width;
     for idx in 0..width {
         if idx == pos {
@@ -601,51 +3172,322 @@ fn render_progress_bar(app: &AppState, width: usize) -> String {
     bar
 }
 
+/// How far along the whole job is, across passes.
+fn overall_ratio(app: &AppState) -> Option<f64> {
+    let ratio = pass_ratio(app)?;
+    Some(match &app.pass_tracker {
+        Some(tracker) => tracker.overall_ratio(ratio),
+        None => ratio,
+    })
+}
+
+fn pass_ratio(app: &AppState) -> Option<f64> {
+    update_ratio(app.progress.as_ref()?, app.duration, app.total_frames)
+}
+
+/// Errors listed when the strip is expanded; older ones are only counted.
+const MAX_STRIP_ERRORS: usize = 5;
+
+/// Smallest screen anything is drawn on.
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 8;
+/// Below this many rows the header shrinks to one line and the log pane
+/// stays closed.
+const COMPACT_HEIGHT: u16 = 16;
+/// Narrower than this, the header shows a percentage instead of a bar.
+const MIN_BAR_WIDTH: u16 = 40;
+
+#[derive(Debug, PartialEq, Eq)]
+struct ScreenAreas {
+    header: Rect,
+    /// One unboxed header line instead of two in a box.
+    compact: bool,
+    /// Only while errors are pinned.
+    errors: Option<Rect>,
+    session: Rect,
+    input: Rect,
+    log: Option<Rect>,
+}
+
+/// Splits the screen into header, error strip (when there are errors),
+/// session, input and, when open, the log pane below the session. None
+/// when the screen is too small to draw on.
+fn screen_layout(area: Rect, log_visible: bool, error_lines: u16) -> Option<ScreenAreas> {
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        return None;
+    }
+    let compact = area.height < COMPACT_HEIGHT;
+    let error_lines = if compact { error_lines.min(1) } else { error_lines };
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(if compact { 1 } else { 4 }),
+            Constraint::Length(error_lines),
+            Constraint::Min(3),
+            Constraint::Length(3),
+        ])
+        .split(area);
+    let mut areas = ScreenAreas {
+        header: layout[0],
+        compact,
+        errors: (error_lines > 0).then_some(layout[1]),
+        session: layout[2],
+        input: layout[3],
+        log: None,
+    };
+    if log_visible && !compact {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(layout[2]);
+        areas.session = split[0];
+        areas.log = Some(split[1]);
+    }
+    Some(areas)
+}
+
+/// Lines the error strip needs: none without errors, one for the latest,
+/// or a list of them when expanded.
+fn error_strip_height(app: &AppState) -> u16 {
+    match (app.errors.len(), app.errors_expanded) {
+        (0, _) => 0,
+        (count, true) => (count.min(MAX_STRIP_ERRORS) + usize::from(count > MAX_STRIP_ERRORS) + 1) as u16,
+        (_, false) => 1,
+    }
+}
+
+fn render_error_strip(app: &AppState) -> Paragraph<'static> {
+    let style = Style::default().fg(Color::Red);
+    let count = app.errors.len();
+    let hint = match (count, app.errors_expanded) {
+        (1, _) => "Ctrl+D to dismiss".to_string(),
+        (_, true) => format!("{count} errors - F3 to collapse, Ctrl+D to dismiss"),
+        (_, false) => format!("{count} errors - F3 to view, Ctrl+D to dismiss"),
+    };
+    let hint = Span::styled(format!("  ({hint})"), style.add_modifier(Modifier::DIM));
+    let latest = app.errors.last().cloned().unwrap_or_default();
+    if !app.errors_expanded || count == 1 {
+        return Paragraph::new(Line::from(vec![Span::styled(latest, style), hint]));
+    }
+    let shown = &app.errors[count.saturating_sub(MAX_STRIP_ERRORS)..];
+    let mut lines = Vec::with_capacity(shown.len() + 2);
+    if count > shown.len() {
+        lines.push(Line::from(Span::styled(
+            format!("... {} earlier", count - shown.len()),
+            style.add_modifier(Modifier::DIM),
+        )));
+    }
+    lines.extend(shown.iter().map(|error| Line::from(Span::styled(error.clone(), style))));
+    lines.push(Line::from(hint));
+    Paragraph::new(lines)
+}
+
+fn render_log_pane(pane: &LogPane, failure: Option<&FailureReason>) -> Paragraph<'static> {
+    let end = pane.lines.len().saturating_sub(pane.scroll_offset);
+    let start = end.saturating_sub(pane.view_lines);
+    let lines: Vec<Line> = pane
+        .lines
+        .range(start..end)
+        .map(|line| Line::from(line.clone()))
+        .collect();
+    let mut title = if pane.scroll_offset > 0 {
+        "ffmpeg log (scrolled, End to follow)".to_string()
+    } else {
+        "ffmpeg log".to_string()
+    };
+    if let Some(reason) = failure {
+        title.push_str(&format!(" - {reason}"));
+    }
+
+    Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL))
+}
+
 fn render_history(app: &AppState, height: usize, width: usize) -> Paragraph<'static> {
     let max_lines = height.saturating_sub(2).max(1);
     let end = app.history.len().saturating_sub(app.scroll_offset);
     let start = end.saturating_sub(max_lines);
     let divider_width = width.saturating_sub(2).max(1);
     let divider = "─".repeat(divider_width);
+    // One row per entry, cut to the pane's width, so scrolling by entries
+    // lines up with what is on screen; `save` keeps the full text.
     let lines: Vec<Line> = app.history[start..end]
         .iter()
-        .map(|line| {
-            if line == DIVIDER_MARKER {
-                Line::from(Span::raw(divider.clone()))
-            } else {
-                Line::from(line.clone())
-            }
+        .map(|entry| match entry.text() {
+            Some(text) => Line::from(Span::styled(truncate_to_width(text, divider_width), entry.style())),
+            None => Line::from(Span::styled(divider.clone(), entry.style())),
         })
         .collect();
 
-    Paragraph::new(lines)
-        .block(Block::default().title("Session").borders(Borders::ALL))
-        .wrap(Wrap { trim: false })
+    Paragraph::new(lines).block(Block::default().title("Session").borders(Borders::ALL))
 }
 
-fn parse_duration_from_args(args: &[String]) -> Option<Duration> {
-    let mut idx = 0;
-    while idx < args.len() {
-        if args[idx] == "-t" {
-            if let Some(value) = args.get(idx + 1) {
-                if let Ok(seconds) = value.parse::<f64>() {
-                    let micros = (seconds * 1_000_000.0).round().max(0.0) as u64;
-                    return Some(Duration::from_micros(micros));
-                }
-                if let Some(duration) = parse_ffmpeg_time(value) {
-                    return Some(duration);
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paste(app: &mut AppState, text: &str) {
+        let (event_tx, _event_rx) = mpsc::channel();
+        let (job_tx, _job_rx) = mpsc::channel();
+        handle_event(app, Event::Paste(text.to_string()), &event_tx, &job_tx);
+    }
+
+    #[test]
+    fn pasted_lines_split_on_any_line_ending() {
+        let lines = pasted_lines("probe a.mov\r\nprobe b.mov\rprobe c.mov\n\n");
+        assert_eq!(lines, ["probe a.mov", "probe b.mov", "probe c.mov"]);
+    }
+
+    #[test]
+    fn pasted_lines_join_backslash_continuations() {
+        let lines = pasted_lines("encode -i a.mov \\\n  --vcodec libx264 \\\n  -o a.mp4\nprobe a.mp4\n");
+        assert_eq!(lines.len(), 2);
+        let words: Vec<&str> = lines[0].split_whitespace().collect();
+        assert_eq!(words, ["encode", "-i", "a.mov", "--vcodec", "libx264", "-o", "a.mp4"]);
+        assert_eq!(lines[1], "probe a.mp4");
+    }
+
+    #[test]
+    fn pasted_lines_strip_control_characters() {
+        assert_eq!(pasted_lines("probe\u{1b}\u{7} a\0.mov\tnow"), ["probe a.mov now"]);
+    }
+
+    #[test]
+    fn single_line_paste_goes_into_the_input() {
+        let mut app = AppState::new(Vec::new());
+        app.input.push_str("encode ");
+        paste(&mut app, "-i a.mov -o a.mp4\n");
+        assert_eq!(app.input, "encode -i a.mov -o a.mp4");
+        assert!(app.held.is_none());
+    }
+
+    #[test]
+    fn multi_line_paste_asks_before_queueing() {
+        let mut app = AppState::new(Vec::new());
+        paste(&mut app, "probe a.mov\nprobe b.mov\n");
+        assert!(app.input.is_empty());
+        assert_eq!(app.job_status, Some(JobStatus::AwaitingConfirmation));
+        assert!(matches!(&app.held, Some(HeldJob::Paste(lines)) if lines.len() == 2));
+    }
+
+    #[test]
+    fn multi_line_paste_queues_while_a_job_runs() {
+        let mut app = AppState::new(Vec::new());
+        app.job_running = true;
+        paste(&mut app, "probe a.mov\nprobe b.mov\nprobe c.mov");
+        assert_eq!(app.job_queue.len(), 3);
+        assert!(app.held.is_none());
+    }
+
+    fn run_line(app: &mut AppState, line: &str) {
+        let (event_tx, _event_rx) = mpsc::channel();
+        let (job_tx, _job_rx) = mpsc::channel();
+        handle_line(app, line.into(), event_tx, job_tx);
+    }
+
+    fn waiting(app: &AppState) -> Vec<String> {
+        app.job_queue
+            .iter()
+            .map(|job| job.line.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn queue_commands_count_from_one() {
+        let mut app = AppState::new(Vec::new());
+        app.job_running = true;
+        app.job_queue.extend(["probe a.mov", "probe b.mov", "probe c.mov"].map(OsString::from));
+        run_line(&mut app, "queue move 3 1");
+        assert_eq!(waiting(&app), ["probe c.mov", "probe a.mov", "probe b.mov"]);
+        run_line(&mut app, "queue remove 2");
+        assert_eq!(waiting(&app), ["probe c.mov", "probe b.mov"]);
+        run_line(&mut app, "queue promote 2");
+        assert_eq!(waiting(&app), ["probe b.mov", "probe c.mov"]);
+    }
+
+    #[test]
+    fn queue_commands_reject_jobs_that_are_not_there() {
+        let mut app = AppState::new(Vec::new());
+        app.job_running = true;
+        app.job_queue.extend(["probe a.mov", "probe b.mov"].map(OsString::from));
+        for line in ["queue remove 3", "queue remove 0", "queue move 1 x", "queue promote two"] {
+            run_line(&mut app, line);
+            assert!(
+                matches!(app.history.last(), Some(HistoryEntry::Error(message)) if message.contains("no job")),
+                "{line}"
+            );
         }
-        if let Some(pos) = args[idx].find("duration=") {
-            let value = &args[idx][pos + "duration=".len()..];
-            let value = value.split(':').next().unwrap_or(value);
-            if let Ok(seconds) = value.parse::<f64>() {
-                let micros = (seconds * 1_000_000.0).round().max(0.0) as u64;
-                return Some(Duration::from_micros(micros));
-            }
+        run_line(&mut app, "queue move 1");
+        assert!(matches!(app.history.last(), Some(HistoryEntry::Error(_))));
+        assert_eq!(waiting(&app), ["probe a.mov", "probe b.mov"]);
+    }
+
+    fn layout(width: u16, height: u16, log_visible: bool, error_lines: u16) -> Option<ScreenAreas> {
+        screen_layout(Rect::new(0, 0, width, height), log_visible, error_lines)
+    }
+
+    /// Every row of the screen belongs to exactly one area, top to bottom.
+    fn assert_stacked(areas: &ScreenAreas, height: u16) {
+        let mut rows = vec![areas.header];
+        rows.extend(areas.errors);
+        rows.push(areas.session);
+        rows.extend(areas.log);
+        rows.push(areas.input);
+        let mut y = 0;
+        for rect in rows {
+            assert_eq!(rect.y, y, "{areas:?}");
+            y += rect.height;
+        }
+        assert_eq!(y, height, "{areas:?}");
+    }
+
+    #[test]
+    fn layout_is_none_below_the_minimum() {
+        assert_eq!(layout(MIN_WIDTH - 1, 24, false, 0), None);
+        assert_eq!(layout(80, MIN_HEIGHT - 1, false, 0), None);
+        assert_eq!(layout(19, 7, true, 2), None);
+        assert_eq!(layout(0, 0, false, 0), None);
+    }
+
+    #[test]
+    fn layout_at_the_minimum_is_compact() {
+        let areas = layout(20, 8, false, 0).unwrap();
+        assert!(areas.compact);
+        assert_eq!(areas.header.height, 1);
+        assert_eq!(areas.session.height, 4);
+        assert_eq!(areas.input.height, 3);
+        assert_stacked(&areas, 8);
+
+        let areas = layout(20, 8, true, 5).unwrap();
+        assert_eq!(areas.errors.map(|rect| rect.height), Some(1));
+        assert_eq!(areas.log, None);
+        assert_eq!(areas.session.height, 3);
+        assert_stacked(&areas, 8);
+    }
+
+    #[test]
+    fn layout_80x15_keeps_the_log_closed() {
+        let areas = layout(80, 15, true, 0).unwrap();
+        assert!(areas.compact);
+        assert_eq!(areas.log, None);
+        assert_eq!(areas.session.height, 11);
+        assert_eq!(areas.input.width, 80);
+        assert_stacked(&areas, 15);
+    }
+
+    #[test]
+    fn layout_full_size_splits_session_and_log() {
+        for (width, height) in [(80, 16), (80, 24), (120, 40), (200, 60)] {
+            let areas = layout(width, height, false, 0).unwrap();
+            assert!(!areas.compact);
+            assert_eq!(areas.header.height, 4);
+            assert_eq!(areas.errors, None);
+            assert_stacked(&areas, height);
+
+            let areas = layout(width, height, true, 3).unwrap();
+            assert_eq!(areas.errors.map(|rect| rect.height), Some(3));
+            let log = areas.log.expect("log pane");
+            assert!(log.height.abs_diff(areas.session.height) <= 1, "{areas:?}");
+            assert_stacked(&areas, height);
         }
-        idx += 1;
     }
-    None
 }